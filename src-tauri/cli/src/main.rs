@@ -0,0 +1,334 @@
+//! Headless companion of the GUI: builds the combination matrix of a saved
+//! project configuration with the same header placement or `.cproject`
+//! symbols, CubeIDE invocation, failure criteria and artifact layout,
+//! printing the build output to the console.
+//!
+//! Git and version macros, analysis gates, simulation and history are GUI
+//! features and are not run here.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{ExitCode, Stdio};
+use stm32_builder_core::build_combinations::{generate_build_combinations, missing_required};
+use stm32_builder_core::builder::{build_target, new_run_id};
+use stm32_builder_core::config::BuildSettingsConfig;
+use stm32_builder_core::cproject::{header_placement, read_cproject, DEFAULT_HEADER_DIR};
+use stm32_builder_core::events::EventSink;
+use stm32_builder_core::matrix::{build_matrix, Combination, Matrix, MatrixEvent, MatrixHooks, Proceed};
+use stm32_builder_core::paths;
+use stm32_builder_core::pipeline::{DefineMode, FailurePolicy, MacroTarget};
+use stm32_builder_core::project::{project_name, read_description, resolve_project_path, PROJECT_CONFIG_FILE};
+use stm32_builder_core::runner::{ProcessHandle, ProcessRunner, ProcessSpec, RunningProcess};
+use stm32_builder_core::selection::{selection_combinations, Selection};
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+const USAGE: &str = "Usage: stm32-gui-builder-cli <project dir | config.json> [--settings <build_settings.yaml>] [--clean]";
+
+/// The part of the GUI's `BuildConfig` the CLI uses; other fields of the
+/// file are ignored.
+#[derive(Debug, Deserialize)]
+struct Profile {
+    #[serde(rename = "projectPath")]
+    project_path: String,
+    #[serde(rename = "buildDir")]
+    build_dir: String,
+    #[serde(rename = "cubeIdeExePath")]
+    cube_ide_exe_path: String,
+    #[serde(rename = "workspacePath")]
+    workspace_path: String,
+    #[serde(rename = "projectName")]
+    project_name: Option<String>,
+    #[serde(rename = "configName")]
+    config_name: Option<String>,
+    #[serde(rename = "cleanBuild", default)]
+    clean_build: bool,
+    #[serde(rename = "customConsoleArgs")]
+    custom_console_args: Option<String>,
+    #[serde(default)]
+    settings: serde_json::Map<String, serde_json::Value>,
+    #[serde(rename = "maxWarnings")]
+    max_warnings: Option<usize>,
+    #[serde(rename = "selectedCombinations")]
+    selected_combinations: Option<Vec<Selection>>,
+    #[serde(rename = "errorWarnings")]
+    error_warnings: Option<Vec<String>>,
+    #[serde(rename = "defineMode")]
+    define_mode: Option<DefineMode>,
+    #[serde(rename = "timeoutSecs")]
+    timeout_secs: Option<u64>,
+}
+
+struct Args {
+    profile: PathBuf,
+    settings: Option<PathBuf>,
+    clean: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut profile = None;
+    let mut settings = None;
+    let mut clean = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--settings" => settings = Some(PathBuf::from(args.next().ok_or(USAGE)?)),
+            "--clean" => clean = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if profile.is_none() => profile = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument '{}'\n{}", arg, USAGE)),
+        }
+    }
+    Ok(Args { profile: profile.ok_or(USAGE)?, settings, clean })
+}
+
+/// Reads the configuration file, or the one the GUI saved in a project directory.
+fn load_profile(path: &Path) -> Result<Profile, String> {
+    let file = if path.is_dir() { path.join(PROJECT_CONFIG_FILE) } else { path.to_path_buf() };
+    let content = fs::read_to_string(&file).map_err(|e| format!("Error reading '{}': {}", file.display(), e))?;
+    let mut profile: Profile =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid configuration '{}': {}", file.display(), e))?;
+    if path.is_dir() {
+        // The project may have been moved since the file was written
+        profile.project_path = path.display().to_string();
+    }
+    Ok(profile)
+}
+
+/// Starts the builds plainly, at the console's priority.
+struct ConsoleRunner;
+
+struct ConsoleProcess(Child);
+
+#[async_trait]
+impl ProcessHandle for ConsoleProcess {
+    fn id(&self) -> Option<u32> {
+        self.0.id()
+    }
+
+    async fn wait(&mut self) -> io::Result<i32> {
+        Ok(self.0.wait().await?.code().unwrap_or(-1))
+    }
+
+    async fn kill(&mut self) -> io::Result<()> {
+        self.0.kill().await
+    }
+}
+
+impl ProcessRunner for ConsoleRunner {
+    fn spawn(&self, spec: &ProcessSpec) -> io::Result<RunningProcess> {
+        let mut child = Command::new(&spec.program)
+            .args(&spec.args)
+            .current_dir(&spec.cwd)
+            .envs(spec.envs.iter().map(|(k, v)| (k, v)))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        Ok(RunningProcess {
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+            handle: Box::new(ConsoleProcess(child)),
+            warnings: Vec::new(),
+        })
+    }
+}
+
+/// Echoes the build output to the console's matching stream.
+struct Console;
+
+impl EventSink for Console {
+    fn emit_value(&self, event: &str, payload: Value) {
+        if event != "build-output" {
+            return;
+        }
+        let line = payload["line"].as_str().unwrap_or_default();
+        match payload["stream"].as_str() {
+            Some("stderr") => eprintln!("{}", line),
+            _ => println!("{}", line),
+        }
+    }
+}
+
+/// Prints what the loop does around the builds.
+#[derive(Default)]
+struct ConsoleHooks {
+    exit_code: i32,
+}
+
+#[async_trait]
+impl MatrixHooks for ConsoleHooks {
+    type Error = String;
+
+    async fn before_combination(&mut self, combination: &Combination<'_>) -> Result<Proceed, String> {
+        println!("\n[{}/{}] {}", combination.index + 1, combination.total, combination.name);
+        Ok(Proceed::Build)
+    }
+
+    async fn after_build(&mut self, combination: &Combination<'_>, _header: &str) -> Result<(), String> {
+        println!("{}", combination.artifact.display());
+        Ok(())
+    }
+
+    fn report(&mut self, _combination: &Combination<'_>, event: MatrixEvent<'_>) {
+        match event {
+            MatrixEvent::WriteFailed { path, error } => eprintln!("Error writing '{}': {}", path.display(), error),
+            MatrixEvent::Exited { exit_code, .. } => self.exit_code = exit_code,
+            MatrixEvent::Judged(outcome) => {
+                println!("Exit code {}, {} error(s), {} warning(s)", self.exit_code, outcome.errors, outcome.warnings);
+                if let Some(hint) = &outcome.failure_hint {
+                    eprintln!("{} ({:?}). {}", hint.evidence, hint.code, hint.hint);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Builds every combination in order and stops at the first failure, like
+/// the GUI. Returns the run's output directory.
+async fn build(args: &Args) -> Result<PathBuf, String> {
+    let profile = load_profile(&args.profile)?;
+    let settings_config = match &args.settings {
+        Some(path) => BuildSettingsConfig::from_file(path)?,
+        None => BuildSettingsConfig::load()?,
+    };
+    // An explicit selection does not depend on the values in the form
+    let missing = missing_required(&settings_config, &profile.settings);
+    if !missing.is_empty() && profile.selected_combinations.is_none() {
+        return Err(format!("Required settings have no value: {}", missing.join(", ")));
+    }
+    let combinations = match &profile.selected_combinations {
+        Some(selection) => selection_combinations(&settings_config, selection)?,
+        None => generate_build_combinations(&settings_config, &profile.settings),
+    };
+    if combinations.is_empty() {
+        return Err("No combinations to build".to_string());
+    }
+
+    let project_path = paths::canonicalize(Path::new(&profile.project_path))
+        .map_err(|e| format!("Project directory '{}' not found: {}", profile.project_path, e))?;
+    let project_name = match &profile.project_name {
+        Some(name) => name.clone(),
+        None => project_name(&project_path)?,
+    };
+    let config_name = profile.config_name.as_deref().unwrap_or("Debug");
+    let configurations = read_cproject(&project_path)?;
+    if !configurations.iter().any(|c| c.name == config_name) {
+        return Err(format!("Configuration '{}' not found in .cproject", config_name));
+    }
+    let target = build_target(&project_name, profile.config_name.as_deref());
+    let failure_policy = FailurePolicy::new(&settings_config, profile.error_warnings.as_deref(), profile.max_warnings);
+
+    // build_config.h has to be in a folder the compiler searches, as in the GUI
+    let header_dir = match header_placement(&project_path, &project_name, config_name) {
+        Ok(placement) => {
+            if placement.default_unreachable {
+                eprintln!(
+                    "Warning: {}/ is not on the include path of {}, writing build_config.h to {}/",
+                    DEFAULT_HEADER_DIR, config_name, placement.dir
+                );
+            }
+            placement.dir
+        }
+        Err(e) => {
+            eprintln!("Warning: cannot read the include paths ({}), writing build_config.h to {}/", e, DEFAULT_HEADER_DIR);
+            DEFAULT_HEADER_DIR.to_string()
+        }
+    };
+    // The folder may be a linked resource living outside the project
+    let linked_resources = read_description(&project_path).map(|d| d.linked_resources).unwrap_or_default();
+    let header_file = resolve_project_path(&project_path, &linked_resources, &Path::new(&header_dir).join("build_config.h"));
+    // In .cproject mode the original file is written back when this is dropped
+    let (macro_target, restored_stale) = MacroTarget::new(profile.define_mode.unwrap_or_default(), &project_path, header_file)
+        .map_err(|e| e.to_string())?;
+    if restored_stale {
+        eprintln!("Warning: restored .cproject from the backup of an interrupted run");
+    }
+
+    let run_id = new_run_id();
+    let output_dir = Path::new(&profile.build_dir).join(&run_id);
+    println!("Run {}: {} combination(s) of {}", run_id, combinations.len(), project_name);
+
+    let build_dir = project_path.join(config_name);
+    let matrix = Matrix {
+        run_id: &run_id,
+        settings_config: &settings_config,
+        project_path: &project_path,
+        project_name: &project_name,
+        config_name,
+        build_dir: &build_dir,
+        output_dir: &output_dir,
+        macro_target: &macro_target,
+        extra_defines: "",
+        version_label: None,
+        failure_policy: &failure_policy,
+        cube_ide_exe: &profile.cube_ide_exe_path,
+        target: &target,
+        workspace: &profile.workspace_path,
+        clean: profile.clean_build || args.clean,
+        custom_args: profile.custom_console_args.as_deref(),
+        timeout_secs: profile.timeout_secs,
+    };
+    // Ctrl+C kills the running build; the `.cproject` is restored on the way out
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt.cancel();
+        }
+    });
+    build_matrix(&matrix, &combinations, &ConsoleRunner, &Console, &cancel, &mut ConsoleHooks::default())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(output_dir)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let result = match parse_args() {
+        Ok(args) => build(&args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(output_dir) => {
+            println!("\nAll combinations built into {}", output_dir.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_reads_the_fields_the_gui_saves() {
+        let profile: Profile = serde_json::from_value(serde_json::json!({
+            "projectPath": "/fw/blinky",
+            "buildDir": "/fw/out",
+            "cubeIdeExePath": "/opt/st/stm32cubeide",
+            "workspacePath": "/fw/ws",
+            "defineMode": "cproject",
+            "maxWarnings": 3,
+            "timeoutSecs": 600,
+            "errorWarnings": ["-Wimplicit-function-declaration"],
+            "theme": "dark"
+        }))
+        .unwrap();
+        assert_eq!(profile.define_mode, Some(DefineMode::Cproject));
+        assert_eq!(profile.max_warnings, Some(3));
+        assert_eq!(profile.timeout_secs, Some(600));
+        assert!(!profile.clean_build);
+    }
+}
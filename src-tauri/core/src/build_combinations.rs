@@ -63,4 +63,44 @@ pub fn generate_build_combinations(
     }
 
     build_combinations
-}
\ No newline at end of file
+}
+/// Ids of the required settings without a value in `settings`: selects
+/// always need one, range and checkbox groups `min_selected`.
+pub fn missing_required(
+    settings_config: &BuildSettingsConfig,
+    settings: &serde_json::Map<String, Value>
+) -> Vec<String> {
+    settings_config.build_settings.iter()
+        .filter_map(|setting| {
+            let value = settings.get(&setting.id);
+            let values_count = match setting.field_type.as_str() {
+                "range" | "checkbox_group" => value
+                    .and_then(|v| v.as_array())
+                    .map(|arr| arr.iter().filter(|v| {
+                        // Ignore empty strings in array
+                        if let Some(s) = v.as_str() {
+                            !s.trim().is_empty()
+                        } else {
+                            v.is_number()
+                        }
+                    }).count())
+                    .unwrap_or(0),
+                "select" => value
+                    .and_then(|v| v.as_str())
+                    .map(|s| if s.trim().is_empty() { 0 } else { 1 })
+                    .unwrap_or(0),
+                _ => 1,
+            };
+            let min_required: usize = if setting.field_type == "select" {
+                1
+            } else {
+                setting.min_selected.unwrap_or(0) as usize
+            };
+            if values_count < min_required {
+                Some(setting.id.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+use crate::config::BuildSettingsConfig;
+
+/// Contents of `build_config.h` for a combination. `extra_defines` (git,
+/// version and timestamp macros rendered by the caller) is appended after
+/// the settings' own defines.
+pub fn generate_build_config_h(
+    settings_config: &BuildSettingsConfig,
+    combination: &[(String, String)],
+    extra_defines: &str,
+) -> Result<String, String> {
+    let mut build_config_content = String::new();
+    build_config_content.push_str("#ifndef BUILD_CONFIG_H_\n#define BUILD_CONFIG_H_\n\n");
+
+    for setting in &settings_config.build_settings {
+        let id = &setting.id;
+        let value_opt = combination.iter().find(|(s_id, _)| s_id == id).map(|(_, v)| v.clone());
+
+        match setting.field_type.as_str() {
+            "range" => {
+                if let Some(value) = value_opt
+                    && let Some(validation) = &setting.validation
+                    && let Ok(numbers) = crate::config::parse_range_string(&value, validation.min, validation.max)
+                    && let Some(last_num) = numbers.last()
+                    && let Some(define) = &setting.define
+                {
+                    build_config_content.push_str(&format!(
+                        "#ifndef {}\n#define {} {}\n#endif\n",
+                        define, define, last_num
+                    ));
+                }
+            }
+            "select" | "checkbox_group" => {
+                if let Some(options) = &setting.options {
+                    for opt in options {
+                        let is_selected = if let Some(v) = &value_opt {
+                            v == &opt.value
+                        } else {
+                            false
+                        };
+
+                        if let Some(define) = &opt.define {
+                            if is_selected {
+                                build_config_content.push_str(&format!("#define {}\n", define));
+                            } else {
+                                build_config_content.push_str(&format!("#undef {}\n", define));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    build_config_content.push_str(extra_defines);
+
+    build_config_content.push_str("#undef DEBUG_SET\n");
+    build_config_content.push_str("\n#endif // BUILD_CONFIG_H_\n");
+
+    Ok(build_config_content)
+}
+/// Macros a generated `build_config.h` leaves defined, as `NAME` or
+/// `NAME=VALUE` compiler symbols, in the order they are defined.
+pub fn header_defines(header: &str) -> Vec<String> {
+    let mut defines: Vec<(String, Option<String>)> = Vec::new();
+    for line in header.lines() {
+        let mut words = line.trim().splitn(3, char::is_whitespace);
+        match (words.next(), words.next()) {
+            (Some("#define"), Some(name)) if name != "BUILD_CONFIG_H_" => {
+                let value = words.next().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+                defines.retain(|(n, _)| n != name);
+                defines.push((name.to_string(), value));
+            }
+            (Some("#undef"), Some(name)) => defines.retain(|(n, _)| n != name),
+            _ => {}
+        }
+    }
+    defines
+        .into_iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!("{}={}", name, value),
+            None => name,
+        })
+        .collect()
+}
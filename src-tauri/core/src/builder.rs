@@ -0,0 +1,209 @@
+use crate::command_line;
+use crate::config::BuildSettingsConfig;
+use chrono::Local;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Eclipse application CubeIDE runs for command-line builds.
+pub const HEADLESS_APPLICATION: &str = "org.eclipse.cdt.managedbuilder.core.headlessbuild";
+/// Header every combination's macros are written to, relative to the project.
+pub const GENERATED_HEADER: &str = "Inc/build_config.h";
+
+/// One headless CubeIDE build of a project configuration.
+#[derive(Clone, Debug, Default)]
+pub struct HeadlessBuild<'a> {
+    /// `<project>/<configuration>`, see [`build_target`].
+    pub target: &'a str,
+    pub workspace: &'a str,
+    pub clean: bool,
+    /// Project to import first; a fresh workspace does not know it yet.
+    pub import: Option<&'a Path>,
+    /// Header every compiler run force-includes, through the headless
+    /// builder's `-include` (`-I` is the one taking include folders). The
+    /// compiler looks for it from the configuration's build folder, so pass
+    /// the full path; [`GENERATED_HEADER`] when not given.
+    pub header: Option<&'a Path>,
+    /// Include no header, the macros being in the project's symbol list.
+    pub skip_header: bool,
+    /// Further arguments, split on whitespace outside quotes.
+    pub custom_args: Option<&'a str>,
+}
+
+impl HeadlessBuild<'_> {
+    /// Arguments for the CubeIDE executable. Paths are passed as they are,
+    /// one argument each, so spaces and non-ASCII names need no quoting.
+    pub fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = [
+            "-nosplash",
+            "-application",
+            HEADLESS_APPLICATION,
+            if self.clean { "-cleanBuild" } else { "-build" },
+            self.target,
+            "-data",
+            self.workspace,
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        if !self.skip_header {
+            let header = self.header.unwrap_or(Path::new(GENERATED_HEADER));
+            args.extend(["-include".into(), header.as_os_str().to_os_string()]);
+        }
+        if let Some(project) = self.import {
+            args.extend(["-import".into(), project.as_os_str().to_os_string()]);
+        }
+        if let Some(custom_args) = self.custom_args {
+            args.extend(command_line::split(custom_args).into_iter().map(OsString::from));
+        }
+        args
+    }
+}
+
+/// Rebuild of a configuration with the makefiles an earlier headless build
+/// generated in its folder, sparing the CubeIDE start-up. Only valid while
+/// the project settings are unchanged; `make` picks up edited sources and
+/// headers through the generated dependency files.
+#[derive(Clone, Debug)]
+pub struct MakeBuild<'a> {
+    /// The configuration's folder, holding the generated `makefile`.
+    pub build_dir: &'a Path,
+    pub clean: bool,
+    pub jobs: usize,
+}
+
+impl MakeBuild<'_> {
+    /// Arguments for `make`.
+    pub fn args(&self) -> Vec<OsString> {
+        let mut args = vec!["-C".into(), self.build_dir.as_os_str().to_os_string(), format!("-j{}", self.jobs).into()];
+        // Unlike a `clean` goal, this cannot race `all` under `-j`
+        if self.clean {
+            args.push("-B".into());
+        }
+        args.push("all".into());
+        args
+    }
+}
+
+/// `-build` target: the project, or one of its configurations.
+pub fn build_target(project_name: &str, config_name: Option<&str>) -> String {
+    match config_name {
+        Some(config_name) => format!("{}/{}", project_name, config_name),
+        None => project_name.to_string(),
+    }
+}
+
+/// Generates a run id that sorts chronologically: `YYYYmmdd-HHMMSS-<8 hex>`.
+pub fn new_run_id() -> String {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", Local::now().format("%Y%m%d-%H%M%S"), &suffix[..8])
+}
+
+/// Folder name of a combination's artifacts, e.g. `type_4_mode_GPIO`.
+pub fn combination_name(settings_config: &BuildSettingsConfig, combination: &[(String, String)]) -> String {
+    combination
+        .iter()
+        .filter_map(|(id, value)| {
+            settings_config
+                .build_settings
+                .iter()
+                .find(|s| &s.id == id)
+                .map(|s| format!("{}_{}", s.value, value))
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// File name, without extension, of a combination's firmware and log:
+/// `<project[..6]>_<setting>-<value>..._<config[..5]>[_<version>]`.
+pub fn artifact_stem(
+    settings_config: &BuildSettingsConfig,
+    combination: &[(String, String)],
+    project_name: &str,
+    config_name: &str,
+    version_label: Option<&str>,
+) -> String {
+    let mut name_parts = Vec::new();
+
+    // 1. First 6 characters of project name
+    name_parts.push(project_name.chars().take(6).collect::<String>());
+
+    // 2. Value from higher blocks + used lower ones
+    for (setting_id, value) in combination {
+        if let Some(setting) = settings_config.build_settings.iter().find(|s| &s.id == setting_id)
+            && !value.is_empty()
+        {
+            name_parts.push(format!("{}-{}", setting.value, value));
+        }
+    }
+
+    // 3. Build configuration first 5 symbols
+    name_parts.push(config_name.chars().take(5).collect::<String>());
+    if let Some(label) = version_label {
+        name_parts.push(label.to_string());
+    }
+    name_parts.join("_")
+}
+
+/// Linker output (`elf`, `map`) of the project; CubeIDE keeps the
+/// project's case for these, unlike for the .bin.
+pub fn find_output(build_dir: &Path, project_name: &str, extension: &str) -> Option<PathBuf> {
+    [project_name.to_string(), project_name.to_lowercase()]
+        .iter()
+        .map(|name| build_dir.join(format!("{}.{}", name, extension)))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> BuildSettingsConfig {
+        serde_yaml::from_str(
+            r#"
+version: "1"
+build_settings:
+  - id: board
+    label: Board
+    value: brd
+    description: ""
+    field_type: select
+    format: ""
+    define: BOARD
+    options: null
+    validation: null
+    exclusive: null
+    min_selected: null
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn artifact_stem_shortens_project_and_configuration() {
+        let combination = vec![("board".to_string(), "4".to_string())];
+        let stem = artifact_stem(&settings(), &combination, "Blinky_F4", "Release", Some("1.2.3"));
+        assert_eq!(stem, "Blinky_brd-4_Relea_1.2.3");
+    }
+
+    #[test]
+    fn artifact_stem_shortens_multibyte_names_by_characters() {
+        let combination = vec![("board".to_string(), "4".to_string())];
+        let stem = artifact_stem(&settings(), &combination, "Прошивка", "Отладка", None);
+        assert_eq!(stem, "Прошив_brd-4_Отлад");
+        // The cut would fall inside a character
+        let stem = artifact_stem(&settings(), &[], "ab€defgh", "xyzé€", None);
+        assert_eq!(stem, "ab€def_xyzé€");
+    }
+
+    #[test]
+    fn headless_args_include_the_header_unless_skipped() {
+        let header = Path::new("/fw/blinky/Core/Inc/build_config.h");
+        let build = HeadlessBuild { target: "blinky/Debug", workspace: "/ws", header: Some(header), ..Default::default() };
+        let args = build.args();
+        let include = args.iter().position(|a| a == "-include").unwrap();
+        assert_eq!(args[include + 1], header.as_os_str());
+
+        let args = HeadlessBuild { skip_header: true, ..build }.args();
+        assert!(!args.iter().any(|a| a == "-include"));
+    }
+}
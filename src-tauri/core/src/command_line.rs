@@ -0,0 +1,89 @@
+use std::ffi::OsStr;
+
+/// Splits user-supplied extra arguments on whitespace, keeping text in
+/// double or single quotes together, so `-D NAME="a b"` or
+/// `-I "C:/Program Files/x"` survive. Backslashes are literal, as they are
+/// in Windows paths.
+pub fn split(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_part = false;
+    let mut quote = None;
+    for c in args.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_part = true;
+            }
+            None if c.is_whitespace() => {
+                if in_part {
+                    parts.push(std::mem::take(&mut current));
+                    in_part = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_part = true;
+            }
+        }
+    }
+    if in_part {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Command line for logs: arguments that are empty or contain whitespace
+/// or quotes are put in double quotes. Only for display; processes get
+/// their arguments one by one.
+pub fn display<I, S>(parts: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    parts
+        .into_iter()
+        .map(|part| {
+            let part = part.as_ref().to_string_lossy();
+            if part.is_empty() || part.contains(|c: char| c.is_whitespace() || c == '"') {
+                format!("\"{}\"", part.replace('"', "\\\""))
+            } else {
+                part.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_on_whitespace() {
+        assert_eq!(split("  -j8\t-v  "), vec!["-j8", "-v"]);
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn split_keeps_quoted_text_together() {
+        assert_eq!(split(r#"-D NAME="a b" -I 'C:/Program Files/x'"#), vec!["-D", "NAME=a b", "-I", "C:/Program Files/x"]);
+        assert_eq!(split(r#"-D "say 'hi'""#), vec!["-D", "say 'hi'"]);
+        assert_eq!(split(r#"-D EMPTY="""#), vec!["-D", "EMPTY="]);
+        assert_eq!(split(r#""""#), vec![""]);
+    }
+
+    #[test]
+    fn split_keeps_backslashes_and_non_ascii() {
+        assert_eq!(split(r#"-I C:\Проекты\inc "D:\Мой проект""#), vec!["-I", r"C:\Проекты\inc", r"D:\Мой проект"]);
+    }
+
+    #[test]
+    fn display_quotes_only_where_needed() {
+        assert_eq!(display(["stm32cubeide", "-data", "C:/My Workspace", ""]), r#"stm32cubeide -data "C:/My Workspace" """#);
+        assert_eq!(display(["-D", r#"NAME="x""#]), r#"-D "NAME=\"x\"""#);
+        assert_eq!(display(["Проект/Debug"]), "Проект/Debug");
+    }
+}
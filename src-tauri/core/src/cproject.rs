@@ -0,0 +1,346 @@
+//! Configurations of a CDT managed-build `.cproject` and where
+//! `build_config.h` has to go so the compiler finds it.
+
+use crate::paths;
+use crate::project::{read_description, LinkedResource};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One `-build` configuration of a `.cproject`, as CDT's managed build
+/// stores it.
+#[derive(Clone, Debug, Default)]
+pub struct CprojectConfiguration {
+    pub name: String,
+    /// Usually `${ProjName}`.
+    pub artifact_name: Option<String>,
+    pub artifact_extension: Option<String>,
+    /// `buildPath` of the builder, e.g. `${workspace_loc:/blinky}/Debug`.
+    pub build_path: Option<String>,
+    /// Options of the project's root folder; per-folder and per-file
+    /// overrides are left out.
+    pub options: Vec<CprojectOption>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CprojectOption {
+    /// `superClass` of the option, e.g. `com.st.stm32cube.ide.mcu.gnu.managedbuild.option.fpu`.
+    pub id: String,
+    pub value: Option<String>,
+    /// Entries of list options such as defines and include paths.
+    pub values: Vec<String>,
+}
+
+impl CprojectConfiguration {
+    /// First option whose id ends with `suffix`.
+    pub fn option(&self, suffix: &str) -> Option<&CprojectOption> {
+        self.options.iter().find(|o| o.id.ends_with(suffix))
+    }
+
+    /// Part of an enumerated option's value after `.value.`, e.g. `hard`
+    /// for `...floatabi.value.hard`.
+    pub fn enum_value(&self, suffix: &str) -> Option<String> {
+        let value = self.option(suffix)?.value.as_deref()?;
+        Some(value.rsplit_once(".value.").map(|(_, v)| v).unwrap_or(value).to_string())
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// Reads every configuration of the project's `.cproject` with its options.
+pub fn read_cproject(project_path: &Path) -> Result<Vec<CprojectConfiguration>, String> {
+    let cproject_file = project_path.join(".cproject");
+    let xml_content = fs::read_to_string(&cproject_file)
+        .map_err(|e| format!("Error reading '{}': {}", cproject_file.display(), e))?;
+    let mut reader = Reader::from_str(&xml_content);
+    reader.config_mut().trim_text(true);
+
+    let mut configs: Vec<CprojectConfiguration> = Vec::new();
+    let mut in_configuration = false;
+    let mut in_root_folder = false;
+    let mut option: Option<CprojectOption> = None;
+    loop {
+        let (element, has_children) = match reader.read_event() {
+            Ok(Event::Start(e)) => (e, true),
+            Ok(Event::Empty(e)) => (e, false),
+            Ok(Event::End(e)) => {
+                match e.name().as_ref() {
+                    b"configuration" => in_configuration = false,
+                    b"folderInfo" => in_root_folder = false,
+                    b"option" => {
+                        if let (Some(done), Some(config)) = (option.take(), configs.last_mut()) {
+                            config.options.push(done);
+                        }
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Error parsing .cproject: {}", e)),
+            _ => continue,
+        };
+        match element.name().as_ref() {
+            // Other storage modules refer to configurations by `configurationName`
+            b"configuration" => {
+                if let Some(name) = attribute(&element, b"name") {
+                    configs.push(CprojectConfiguration {
+                        name,
+                        artifact_name: attribute(&element, b"artifactName"),
+                        artifact_extension: attribute(&element, b"artifactExtension"),
+                        ..Default::default()
+                    });
+                    in_configuration = has_children;
+                }
+            }
+            b"builder" if in_root_folder => {
+                if let Some(config) = configs.last_mut() {
+                    config.build_path = attribute(&element, b"buildPath");
+                }
+            }
+            b"folderInfo" if in_configuration => {
+                in_root_folder = has_children && attribute(&element, b"resourcePath").unwrap_or_default().is_empty();
+            }
+            b"option" if in_root_folder => {
+                let parsed = CprojectOption {
+                    id: attribute(&element, b"superClass").unwrap_or_default(),
+                    value: attribute(&element, b"value"),
+                    values: Vec::new(),
+                };
+                if has_children {
+                    option = Some(parsed);
+                } else if let Some(config) = configs.last_mut() {
+                    config.options.push(parsed);
+                }
+            }
+            b"listOptionValue" => {
+                if let (Some(option), Some(value)) = (option.as_mut(), attribute(&element, b"value")) {
+                    option.values.push(value);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(configs)
+}
+
+/// What a configuration produces and how its C sources are compiled.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigurationDetails {
+    pub name: String,
+    /// File name of the linked image, e.g. `blinky.elf`.
+    pub artifact: String,
+    pub artifact_extension: String,
+    /// Folder the build runs in and writes the artifact to.
+    pub build_dir: String,
+    /// Symbols defined for the C compiler, as `NAME` or `NAME=value`.
+    pub defines: Vec<String>,
+    /// C include paths as written in the project, relative to `build_dir`
+    /// unless absolute or given through a variable.
+    pub include_paths: Vec<String>,
+}
+
+/// Replaces the Eclipse variables referring to the project folder.
+fn expand_project_vars(value: &str, project_name: &str, project_path: &Path) -> String {
+    let project = project_path.display().to_string();
+    value
+        .replace("${ProjName}", project_name)
+        .replace(&format!("${{workspace_loc:/{}}}", project_name), &project)
+        .replace("${ProjDirPath}", &project)
+}
+
+impl ConfigurationDetails {
+    pub fn new(config: &CprojectConfiguration, project_name: &str, project_path: &Path) -> Self {
+        let extension = config.artifact_extension.clone().unwrap_or_else(|| "elf".to_string());
+        let artifact_name = config.artifact_name.as_deref().unwrap_or("${ProjName}");
+        let build_dir = match &config.build_path {
+            Some(path) => PathBuf::from(expand_project_vars(path, project_name, project_path)),
+            None => project_path.join(&config.name),
+        };
+        let list = |suffix: &str| config.option(suffix).map(|o| o.values.clone()).unwrap_or_default();
+        ConfigurationDetails {
+            name: config.name.clone(),
+            artifact: format!("{}.{}", expand_project_vars(artifact_name, project_name, project_path), extension),
+            artifact_extension: extension,
+            build_dir: build_dir.display().to_string(),
+            defines: list("c.compiler.option.definedsymbols"),
+            include_paths: list("c.compiler.option.includepaths"),
+        }
+    }
+}
+
+/// Where `build_config.h` goes when the project does not say otherwise.
+pub const DEFAULT_HEADER_DIR: &str = "Inc";
+
+// Vendor code, never the place for the project's own header
+const VENDOR_DIRS: &[&str] = &["Drivers", "Middlewares"];
+
+/// Removes `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Folder an include path of the configuration points to, relative to the
+/// project; folders reached through a linked resource map to their path
+/// inside the project. `None` for other folders outside the project.
+fn project_include_dir(
+    include: &str,
+    project_name: &str,
+    project_path: &Path,
+    build_dir: &Path,
+    linked_resources: &[LinkedResource],
+) -> Option<PathBuf> {
+    let include = include.trim_matches('"').replace("${ProjName}", project_name);
+    let absolute = if let Some(inner) = include.strip_prefix("${workspace_loc:/").and_then(|i| i.strip_suffix('}')) {
+        let (project, rest) = inner.split_once('/').unwrap_or((inner, ""));
+        if project != project_name {
+            return None;
+        }
+        project_path.join(rest)
+    } else if let Some(rest) = include.strip_prefix("${ProjDirPath}") {
+        project_path.join(rest.trim_start_matches(['/', '\\']))
+    } else {
+        build_dir.join(&include)
+    };
+    // Linked resources may spell a share or drive differently than the project
+    let canonical = |path: &Path| paths::canonicalize(path).unwrap_or_else(|_| normalize(path));
+    let absolute = canonical(&absolute);
+    if let Ok(relative) = absolute.strip_prefix(canonical(project_path)) {
+        return Some(relative.to_path_buf());
+    }
+    linked_resources.iter().find_map(|link| {
+        let rest = absolute.strip_prefix(canonical(link.resolved.as_ref()?)).ok()?;
+        Some(Path::new(&link.name).join(rest))
+    })
+}
+
+/// Folder of the project `build_config.h` is written to.
+#[derive(Clone, Debug, Serialize)]
+pub struct HeaderPlacement {
+    /// Relative to the project, e.g. `Core/Inc`.
+    pub dir: String,
+    /// Project folders on the C include path of the configuration.
+    pub include_dirs: Vec<String>,
+    /// Set when `Inc/` is not on the include path, so a header placed there
+    /// would never be found by the compiler.
+    pub default_unreachable: bool,
+}
+
+/// Picks the folder for `build_config.h`: `Inc/` when the configuration
+/// includes it, otherwise the first of the project's own include folders.
+/// Falls back to `Inc/` when the `.cproject` lists no include paths.
+pub fn header_placement(project_path: &Path, project_name: &str, config_name: &str) -> Result<HeaderPlacement, String> {
+    let configs = read_cproject(project_path)?;
+    let linked_resources = read_description(project_path).map(|d| d.linked_resources).unwrap_or_default();
+    let include_dirs: Vec<PathBuf> = configs
+        .iter()
+        .find(|c| c.name == config_name)
+        .or(configs.first())
+        .map(|config| {
+            let details = ConfigurationDetails::new(config, project_name, project_path);
+            let build_dir = PathBuf::from(&details.build_dir);
+            details
+                .include_paths
+                .iter()
+                .filter_map(|include| project_include_dir(include, project_name, project_path, &build_dir, &linked_resources))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let default_dir = Path::new(DEFAULT_HEADER_DIR);
+    let default_unreachable = !include_dirs.is_empty() && !include_dirs.iter().any(|d| d == default_dir);
+    let dir = if default_unreachable {
+        include_dirs
+            .iter()
+            .find(|d| {
+                !d.as_os_str().is_empty()
+                    && !d.components().next().is_some_and(|c| VENDOR_DIRS.iter().any(|v| c.as_os_str() == *v))
+            })
+            .map(PathBuf::as_path)
+            .unwrap_or(default_dir)
+    } else {
+        default_dir
+    };
+    Ok(HeaderPlacement {
+        dir: dir.to_string_lossy().replace('\\', "/"),
+        include_dirs: include_dirs.iter().map(|d| d.to_string_lossy().replace('\\', "/")).collect(),
+        default_unreachable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(include_paths: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let values: String = include_paths
+            .iter()
+            .map(|p| format!(r#"<listOptionValue builtIn="false" value="{}"/>"#, p))
+            .collect();
+        fs::write(dir.path().join(".project"), "<projectDescription><name>blinky</name></projectDescription>").unwrap();
+        fs::write(
+            dir.path().join(".cproject"),
+            format!(
+                r#"<cproject><storageModule><cconfiguration><storageModule>
+                <configuration artifactName="${{ProjName}}" name="Debug">
+                <folderInfo resourcePath=""><toolChain>
+                <option superClass="com.st.stm32cube.ide.mcu.gnu.managedbuild.tool.c.compiler.option.includepaths">{}</option>
+                </toolChain></folderInfo>
+                </configuration></storageModule></cconfiguration></storageModule></cproject>"#,
+                values
+            ),
+        )
+        .unwrap();
+        for include in include_paths {
+            fs::create_dir_all(dir.path().join("Debug").join(include)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn reads_include_paths_of_the_configuration() {
+        let dir = project(&["../Core/Inc", "../Drivers/CMSIS/Include"]);
+        let configs = read_cproject(dir.path()).unwrap();
+        let details = ConfigurationDetails::new(&configs[0], "blinky", dir.path());
+        assert_eq!(details.name, "Debug");
+        assert_eq!(details.artifact, "blinky.elf");
+        assert_eq!(details.include_paths, vec!["../Core/Inc", "../Drivers/CMSIS/Include"]);
+    }
+
+    #[test]
+    fn header_goes_to_inc_when_it_is_included() {
+        let dir = project(&["../Drivers/CMSIS/Include", "../Inc"]);
+        let placement = header_placement(&paths::canonicalize(dir.path()).unwrap(), "blinky", "Debug").unwrap();
+        assert_eq!(placement.dir, DEFAULT_HEADER_DIR);
+        assert!(!placement.default_unreachable);
+    }
+
+    #[test]
+    fn header_avoids_vendor_folders() {
+        let dir = project(&["../Drivers/CMSIS/Include", "../Core/Inc"]);
+        let placement = header_placement(&paths::canonicalize(dir.path()).unwrap(), "blinky", "Debug").unwrap();
+        assert_eq!(placement.dir, "Core/Inc");
+        assert_eq!(placement.include_dirs, vec!["Drivers/CMSIS/Include", "Core/Inc"]);
+        assert!(placement.default_unreachable);
+    }
+}
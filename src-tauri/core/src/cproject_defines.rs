@@ -0,0 +1,163 @@
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Appended to `.cproject` for its backup, kept next to the original so a
+/// run that crashed can be undone by the next one.
+pub const BACKUP_SUFFIX: &str = ".stm32guibuilder.bak";
+
+// C, C++ and assembler tools all name their symbol list this way
+const DEFINED_SYMBOLS_SUFFIX: &str = ".option.definedsymbols";
+
+/// Why the symbols could not be written to `.cproject`.
+#[derive(Debug)]
+pub enum DefinesError {
+    Parse(String),
+    /// The configuration has no symbol list to add to.
+    NoSymbols(String),
+    Read(PathBuf, io::Error),
+    Write(PathBuf, io::Error),
+}
+
+impl fmt::Display for DefinesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinesError::Parse(e) => write!(f, "Error parsing .cproject: {}", e),
+            DefinesError::NoSymbols(config) => write!(
+                f,
+                "Configuration {} has no preprocessor symbol list in .cproject; add a symbol in the project settings or use the header mode",
+                config
+            ),
+            DefinesError::Read(path, e) => write!(f, "Error reading '{}': {}", path.display(), e),
+            DefinesError::Write(path, e) => write!(f, "Error writing '{}': {}", path.display(), e),
+        }
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+fn symbol_name(symbol: &str) -> &str {
+    symbol.split_once('=').map(|(name, _)| name).unwrap_or(symbol)
+}
+
+/// `.cproject` content with `defines` added to every preprocessor symbol
+/// list of the configuration. Symbols of the same name already in a list
+/// are replaced.
+pub fn inject_defines(cproject: &str, config_name: &str, defines: &[String]) -> Result<String, DefinesError> {
+    let mut reader = Reader::from_str(cproject);
+    // Byte ranges to drop and insertions, in file order
+    let mut removed: Vec<(usize, usize)> = Vec::new();
+    let mut inserted: Vec<usize> = Vec::new();
+    // Depth inside the configuration being built
+    let mut config_depth: Option<usize> = None;
+    let mut in_symbols = false;
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader.read_event().map_err(|e| DefinesError::Parse(e.to_string()))?;
+        match &event {
+            Event::Start(e) => {
+                if let Some(depth) = config_depth.as_mut() {
+                    *depth += 1;
+                    if e.name().as_ref() == b"option"
+                        && attribute(e, b"superClass").is_some_and(|s| s.ends_with(DEFINED_SYMBOLS_SUFFIX))
+                    {
+                        in_symbols = true;
+                    }
+                } else if e.name().as_ref() == b"configuration" && attribute(e, b"name").as_deref() == Some(config_name) {
+                    config_depth = Some(0);
+                }
+            }
+            Event::Empty(e) if in_symbols && e.name().as_ref() == b"listOptionValue" => {
+                let existing = attribute(e, b"value").unwrap_or_default();
+                if defines.iter().any(|d| symbol_name(d) == symbol_name(&existing)) {
+                    removed.push((start, reader.buffer_position() as usize));
+                }
+            }
+            Event::End(e) => match config_depth {
+                Some(0) => config_depth = None,
+                Some(ref mut depth) => {
+                    *depth -= 1;
+                    if in_symbols && e.name().as_ref() == b"option" {
+                        inserted.push(start);
+                        in_symbols = false;
+                    }
+                }
+                None => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if inserted.is_empty() {
+        return Err(DefinesError::NoSymbols(config_name.to_string()));
+    }
+
+    let values: String = defines
+        .iter()
+        .map(|d| format!("<listOptionValue builtIn=\"false\" value=\"{}\"/>", escape(d.as_str())))
+        .collect();
+    let mut edited = String::with_capacity(cproject.len() + values.len() * inserted.len());
+    let mut position = 0;
+    let mut removed = removed.into_iter().peekable();
+    for insert_at in inserted {
+        while let Some((from, to)) = removed.next_if(|(from, _)| *from < insert_at) {
+            edited.push_str(&cproject[position..from]);
+            position = to;
+        }
+        edited.push_str(&cproject[position..insert_at]);
+        edited.push_str(&values);
+        position = insert_at;
+    }
+    edited.push_str(&cproject[position..]);
+    Ok(edited)
+}
+
+/// Original `.cproject` of a project whose symbol lists the run edits. It
+/// is written back when the backup is dropped, however the run ends.
+pub struct CprojectBackup {
+    path: PathBuf,
+    backup: PathBuf,
+    original: String,
+}
+
+impl CprojectBackup {
+    /// Saves the `.cproject`. A backup left by a run that did not get to
+    /// restore it is put back first, which the returned flag reports.
+    pub fn new(project_path: &Path) -> Result<(Self, bool), DefinesError> {
+        let path = project_path.join(".cproject");
+        let backup = project_path.join(format!(".cproject{}", BACKUP_SUFFIX));
+        let restored_stale = backup.is_file();
+        if restored_stale {
+            fs::copy(&backup, &path).map_err(|e| DefinesError::Write(path.clone(), e))?;
+        }
+        let original = fs::read_to_string(&path).map_err(|e| DefinesError::Read(path.clone(), e))?;
+        fs::write(&backup, &original).map_err(|e| DefinesError::Write(backup.clone(), e))?;
+        Ok((CprojectBackup { path, backup, original }, restored_stale))
+    }
+
+    /// Writes the original `.cproject` with `defines` added for `config_name`.
+    pub async fn apply(&self, config_name: &str, defines: &[String]) -> Result<(), DefinesError> {
+        let edited = inject_defines(&self.original, config_name, defines)?;
+        tokio::fs::write(&self.path, edited).await.map_err(|e| DefinesError::Write(self.path.clone(), e))
+    }
+}
+
+impl Drop for CprojectBackup {
+    fn drop(&mut self) {
+        // Keep the backup file if the original cannot be written back
+        if fs::write(&self.path, &self.original).is_ok() {
+            fs::remove_file(&self.backup).ok();
+        }
+    }
+}
@@ -1,6 +1,12 @@
 pub const DEFAULT_BUILD_SETTINGS: &str = r#"# build_settings.yaml
 version: "1.0"
 
+# Warnings that fail a combination as if they were errors: gcc option names
+# (with or without -W) or text of the message
+# error_warnings:
+#   - implicit-function-declaration
+#   - -Wreturn-type
+
 # build_settings sample file
 build_settings:
   # range sample
@@ -0,0 +1,390 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A compiler or linker message attributed to a source location where possible.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// Warning option that triggered the message, e.g. `-Wunused-variable`.
+    pub option: Option<String>,
+    pub fixits: Vec<FixIt>,
+}
+
+impl Diagnostic {
+    /// Analysis tool that reported it (`cppcheck`, `clang-tidy`, a hook's
+    /// name), or `None` for compiler and linker messages.
+    pub fn tool(&self) -> Option<&str> {
+        let option = self.option.as_deref()?;
+        if option.starts_with('-') {
+            return None;
+        }
+        Some(option.split(':').next().unwrap_or(option))
+    }
+}
+
+/// Replacement suggested by gcc; only available from JSON diagnostics.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FixIt {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub replacement: String,
+}
+
+// Subset of the gcc `-fdiagnostics-format=json` schema we care about
+#[derive(Deserialize)]
+struct JsonDiagnostic {
+    kind: String,
+    message: String,
+    option: Option<String>,
+    #[serde(default)]
+    locations: Vec<JsonLocation>,
+    #[serde(default)]
+    children: Vec<JsonDiagnostic>,
+    #[serde(default)]
+    fixits: Vec<JsonFixIt>,
+}
+
+#[derive(Deserialize)]
+struct JsonLocation {
+    caret: JsonPosition,
+}
+
+#[derive(Deserialize)]
+struct JsonPosition {
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonFixIt {
+    start: JsonPosition,
+    next: JsonPosition,
+    string: String,
+}
+
+lazy_static! {
+    // main.c:12:5: error: 'foo' undeclared
+    // C:\proj\Core\Src\main.c:12: warning: ...
+    static ref GCC_DIAGNOSTIC: Regex = Regex::new(
+        r"^(?P<file>.+?):(?P<line>\d+):(?:(?P<column>\d+):)?\s+(?P<severity>fatal error|error|warning|note):\s+(?P<message>.*)$"
+    ).unwrap();
+    // main.o: in function `main': / main.c:(.text.main+0x8): undefined reference to `foo'
+    static ref LINKER_REFERENCE: Regex = Regex::new(
+        r"^(?P<file>.+?):\(.*?\):\s+(?P<message>undefined reference to .*|multiple definition of .*)$"
+    ).unwrap();
+    // arm-none-eabi-ld.exe: region `FLASH' overflowed by 1024 bytes
+    static ref LINKER_MESSAGE: Regex = Regex::new(
+        r"(?:^|[/\\])(?:arm-none-eabi-)?ld(?:\.exe)?:\s+(?P<warning>warning:\s+)?(?P<message>.*)$"
+    ).unwrap();
+    // trailing " [-Wunused-variable]" / " [-Werror=format=]"
+    static ref WARNING_OPTION: Regex = Regex::new(r"\s+\[(?P<option>-W[^\]]+)\]$").unwrap();
+}
+
+/// Parses one line of gcc/ld output; returns `None` for ordinary output.
+pub fn parse_line(line: &str) -> Option<Diagnostic> {
+    let line = line.trim();
+
+    if let Some(caps) = GCC_DIAGNOSTIC.captures(line) {
+        let severity = match &caps["severity"] {
+            "warning" => Severity::Warning,
+            "note" => Severity::Note,
+            _ => Severity::Error,
+        };
+        let (message, option) = split_option(&caps["message"]);
+        return Some(Diagnostic {
+            severity,
+            message,
+            file: Some(caps["file"].to_string()),
+            line: caps["line"].parse().ok(),
+            column: caps.name("column").and_then(|c| c.as_str().parse().ok()),
+            option,
+            fixits: Vec::new(),
+        });
+    }
+
+    if let Some(caps) = LINKER_REFERENCE.captures(line) {
+        return Some(Diagnostic {
+            severity: Severity::Error,
+            message: caps["message"].to_string(),
+            file: Some(caps["file"].to_string()),
+            line: None,
+            column: None,
+            option: None,
+            fixits: Vec::new(),
+        });
+    }
+
+    if let Some(caps) = LINKER_MESSAGE.captures(line) {
+        let message = caps["message"].to_string();
+        // "ld: <file>: in function ..." precedes the actual reference error
+        if message.ends_with("in function") || message.contains(": in function `") {
+            return None;
+        }
+        let severity = if caps.name("warning").is_some() {
+            Severity::Warning
+        } else {
+            Severity::Error
+        };
+        return Some(Diagnostic {
+            severity,
+            message,
+            file: None,
+            line: None,
+            column: None,
+            option: None,
+            fixits: Vec::new(),
+        });
+    }
+
+    None
+}
+
+fn split_option(message: &str) -> (String, Option<String>) {
+    match WARNING_OPTION.captures(message) {
+        Some(caps) => (
+            message[..caps.get(0).unwrap().start()].to_string(),
+            Some(caps["option"].to_string()),
+        ),
+        None => (message.to_string(), None),
+    }
+}
+
+/// Parses a line produced by `-fdiagnostics-format=json`: gcc prints one JSON
+/// array per translation unit. Child notes are flattened after their parent.
+pub fn parse_json_line(line: &str) -> Option<Vec<Diagnostic>> {
+    let line = line.trim();
+    if !line.starts_with('[') {
+        return None;
+    }
+    let parsed: Vec<JsonDiagnostic> = serde_json::from_str(line).ok()?;
+    let mut diagnostics = Vec::new();
+    for diagnostic in parsed {
+        flatten_json(diagnostic, &mut diagnostics);
+    }
+    Some(diagnostics)
+}
+
+fn flatten_json(diagnostic: JsonDiagnostic, out: &mut Vec<Diagnostic>) {
+    let severity = match diagnostic.kind.as_str() {
+        "warning" => Severity::Warning,
+        "note" => Severity::Note,
+        _ => Severity::Error,
+    };
+    let caret = diagnostic.locations.first().map(|l| &l.caret);
+    out.push(Diagnostic {
+        severity,
+        message: diagnostic.message,
+        file: caret.map(|c| c.file.clone()),
+        line: caret.map(|c| c.line),
+        column: caret.map(|c| c.column),
+        option: diagnostic.option,
+        fixits: diagnostic
+            .fixits
+            .into_iter()
+            .map(|f| FixIt {
+                file: f.start.file,
+                line: f.start.line,
+                column: f.start.column,
+                end_line: f.next.line,
+                end_column: f.next.column,
+                replacement: f.string,
+            })
+            .collect(),
+    });
+    for child in diagnostic.children {
+        flatten_json(child, out);
+    }
+}
+
+/// Collects diagnostics from build output, dropping repeats of the same
+/// message at the same location (CDT echoes some messages on both stdout
+/// and stderr).
+///
+/// JSON diagnostics are picked up automatically when the project's compiler
+/// flags include `-fdiagnostics-format=json`; text and JSON output can be mixed.
+pub fn parse_output<'a, I>(lines: I) -> Vec<Diagnostic>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut seen = HashSet::new();
+    for line in lines {
+        let parsed = match parse_json_line(line) {
+            Some(json) => json,
+            None => parse_line(line).into_iter().collect(),
+        };
+        for diagnostic in parsed {
+            let key = (diagnostic.file.clone(), diagnostic.line, diagnostic.column, diagnostic.message.clone());
+            if seen.insert(key) {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Turns warnings matching one of `patterns` into errors. A pattern matches
+/// the warning option with or without its `-W` (`implicit-function-declaration`
+/// matches `-Wimplicit-function-declaration` and `-Werror=...`) or is a
+/// substring of the message. Returns how many warnings were promoted.
+pub fn promote_warnings(diagnostics: &mut [Diagnostic], patterns: &[String]) -> usize {
+    let mut promoted = 0;
+    for diagnostic in diagnostics.iter_mut().filter(|d| d.severity == Severity::Warning) {
+        let option = diagnostic
+            .option
+            .as_deref()
+            .map(|o| o.trim_start_matches("-W").trim_start_matches("error=").trim_end_matches('='));
+        let matches = patterns.iter().any(|pattern| {
+            let name = pattern.trim_start_matches("-W");
+            option == Some(name) || diagnostic.message.contains(pattern.as_str())
+        });
+        if matches {
+            diagnostic.severity = Severity::Error;
+            promoted += 1;
+        }
+    }
+    promoted
+}
+
+pub fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics.iter().filter(|d| d.severity == severity).count()
+}
+
+/// Formats the first error as `file:line:col: message`, the way gcc would print it.
+pub fn first_error(diagnostics: &[Diagnostic]) -> Option<String> {
+    diagnostics
+        .iter()
+        .find(|d| d.severity == Severity::Error)
+        .map(|d| {
+            let mut location = String::new();
+            if let Some(file) = &d.file {
+                location.push_str(file);
+                if let Some(line) = d.line {
+                    location.push_str(&format!(":{}", line));
+                    if let Some(column) = d.column {
+                        location.push_str(&format!(":{}", column));
+                    }
+                }
+                location.push_str(": ");
+            }
+            format!("{}{}", location, d.message)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcc_text_lines() {
+        let warning = parse_line("../Core/Src/main.c:12:9: warning: unused variable 'x' [-Wunused-variable]").unwrap();
+        assert_eq!(warning.severity, Severity::Warning);
+        assert_eq!(warning.file.as_deref(), Some("../Core/Src/main.c"));
+        assert_eq!((warning.line, warning.column), (Some(12), Some(9)));
+        assert_eq!(warning.message, "unused variable 'x'");
+        assert_eq!(warning.option.as_deref(), Some("-Wunused-variable"));
+
+        let error = parse_line(r"C:\proj\Core\Src\led.c:40: fatal error: led.h: No such file or directory").unwrap();
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(error.file.as_deref(), Some(r"C:\proj\Core\Src\led.c"));
+        assert_eq!((error.line, error.column), (Some(40), None));
+
+        assert_eq!(parse_line("arm-none-eabi-gcc -mcpu=cortex-m4 -c main.c -o main.o"), None);
+        assert_eq!(parse_line("Finished building: ../Core/Src/main.c"), None);
+    }
+
+    #[test]
+    fn linker_undefined_reference() {
+        assert_eq!(parse_line("/opt/st/ld: ./Core/Src/main.o: in function `main':"), None);
+        let error = parse_line("../Core/Src/main.c:(.text.main+0x8): undefined reference to `led_init'").unwrap();
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(error.file.as_deref(), Some("../Core/Src/main.c"));
+        assert_eq!(error.message, "undefined reference to `led_init'");
+    }
+
+    #[test]
+    fn linker_region_overflow() {
+        let error = parse_line(
+            r"c:\st\stm32cubeide\plugins\gnu-tools\bin\arm-none-eabi-ld.exe: blinky.elf section `.text' will not fit in region `FLASH'",
+        )
+        .unwrap();
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(error.file, None);
+        let overflow = parse_line("arm-none-eabi-ld: region `FLASH' overflowed by 1024 bytes").unwrap();
+        assert_eq!(overflow.message, "region `FLASH' overflowed by 1024 bytes");
+        let warning = parse_line("arm-none-eabi-ld: warning: blinky.elf has a LOAD segment with RWX permissions").unwrap();
+        assert_eq!(warning.severity, Severity::Warning);
+    }
+
+    // gcc 12 with -fdiagnostics-format=json, one array per translation unit
+    const JSON: &str = r#"[{"kind": "error", "message": "expected ';' before '}' token", "children": [{"kind": "note", "message": "to match this '('", "locations": [{"caret": {"file": "../Core/Src/main.c", "line": 20, "display-column": 7, "byte-column": 7, "column": 7}}], "escape-source": false}], "column-origin": 1, "locations": [{"caret": {"file": "../Core/Src/main.c", "line": 21, "display-column": 12, "byte-column": 12, "column": 12}, "finish": {"file": "../Core/Src/main.c", "line": 21, "display-column": 12, "byte-column": 12, "column": 12}}], "fixits": [{"start": {"file": "../Core/Src/main.c", "line": 21, "display-column": 12, "byte-column": 12, "column": 12}, "next": {"file": "../Core/Src/main.c", "line": 21, "display-column": 12, "byte-column": 12, "column": 12}, "string": ";"}], "escape-source": false}, {"kind": "warning", "message": "unused variable 'x'", "option": "-Wunused-variable", "option_url": "https://gcc.gnu.org/onlinedocs/gcc/Warning-Options.html#index-Wunused-variable", "children": [], "column-origin": 1, "locations": [{"caret": {"file": "../Core/Src/main.c", "line": 12, "display-column": 9, "byte-column": 9, "column": 9}}], "escape-source": false}]"#;
+
+    #[test]
+    fn gcc_json_with_children_and_fixits() {
+        let diagnostics = parse_json_line(JSON).unwrap();
+        let summary: Vec<(Severity, Option<u32>, &str)> =
+            diagnostics.iter().map(|d| (d.severity, d.line, d.message.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Error, Some(21), "expected ';' before '}' token"),
+                (Severity::Note, Some(20), "to match this '('"),
+                (Severity::Warning, Some(12), "unused variable 'x'"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].fixits,
+            vec![FixIt {
+                file: "../Core/Src/main.c".to_string(),
+                line: 21,
+                column: 12,
+                end_line: 21,
+                end_column: 12,
+                replacement: ";".to_string(),
+            }]
+        );
+        assert_eq!(diagnostics[2].option.as_deref(), Some("-Wunused-variable"));
+        assert!(diagnostics[2].fixits.is_empty());
+    }
+
+    #[test]
+    fn malformed_json_falls_back_to_text() {
+        let truncated = &JSON[..JSON.len() / 2];
+        assert_eq!(parse_json_line(truncated), None);
+        assert_eq!(parse_json_line("[ 45%] Building C object CMakeFiles/blinky.dir/main.c.obj"), None);
+
+        let text = "../Core/Src/main.c:12:9: warning: unused variable 'x' [-Wunused-variable]";
+        let diagnostics = parse_output([truncated, text, JSON]);
+        // The text warning and the JSON one are the same message
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn repeated_messages_are_reported_once() {
+        let line = "../Core/Src/main.c:12:9: warning: unused variable 'x' [-Wunused-variable]";
+        let other = "../Core/Src/main.c:13:9: warning: unused variable 'x' [-Wunused-variable]";
+        let diagnostics = parse_output([line, "make: *** [all] Error 2", line, other, line]);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].line, Some(13));
+    }
+}
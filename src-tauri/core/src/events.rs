@@ -0,0 +1,63 @@
+use crate::progress::BuildProgress;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Receiver of the events a build reports while it runs (`build-log`,
+/// `build-stage`, ...): the GUI's window, a terminal, a web socket.
+pub trait EventSink: Send + Sync {
+    fn emit_value(&self, event: &str, payload: Value);
+}
+
+impl dyn EventSink + '_ {
+    /// Serializes `payload` and emits it; events that fail to serialize are dropped.
+    pub fn emit<T: Serialize + ?Sized>(&self, event: &str, payload: &T) {
+        if let Ok(value) = serde_json::to_value(payload) {
+            self.emit_value(event, value);
+        }
+    }
+}
+
+/// Discards every event.
+pub struct NoEvents;
+
+impl EventSink for NoEvents {
+    fn emit_value(&self, _event: &str, _payload: Value) {}
+}
+
+/// Payload of the `build-output` event: one raw line of CubeIDE output.
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildOutputLine {
+    pub run_id: String,
+    pub combination: String,
+    pub stream: String,
+    pub line: String,
+}
+
+/// Payload of the `build-progress` event, emitted as the CubeIDE output of
+/// a combination moves on; `index` counts combinations from 0.
+#[derive(Clone, Debug, Serialize)]
+pub struct CombinationProgress {
+    pub run_id: String,
+    pub combination: String,
+    pub index: usize,
+    pub total: usize,
+    #[serde(flatten)]
+    pub progress: BuildProgress,
+}
+
+/// Builds a combination-scoped event name such as
+/// `build-log:20250101-120000-ab12cd34:type_4_mode_GPIO`.
+///
+/// Tauri only accepts alphanumerics, `-`, `/`, `:` and `_` in event names, so
+/// everything else in the ids is replaced with `_`.
+pub fn scoped_channel(base: &str, run_id: Option<&str>, combination: &str) -> String {
+    let sanitize = |id: &str| -> String {
+        id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect()
+    };
+    match run_id {
+        Some(run_id) => format!("{}:{}:{}", base, sanitize(run_id), sanitize(combination)),
+        None => format!("{}:{}", base, sanitize(combination)),
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// Well-known ways the Eclipse/CDT headless builder fails.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCode {
+    ProjectNotFound,
+    WorkspaceInUse,
+    ToolchainNotFound,
+    JvmOutOfMemory,
+    JvmStartFailed,
+}
+
+/// A recognized failure with a short explanation of how to fix it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailureHint {
+    pub code: FailureCode,
+    /// English from [`detect`], see [`FailureCode::message_key`].
+    pub hint: String,
+    /// The output line that matched.
+    pub evidence: String,
+}
+
+// (code, case-insensitive needles); checked in order, first match wins
+const PATTERNS: &[(FailureCode, &[&str])] = &[
+    (
+        FailureCode::WorkspaceInUse,
+        &["workspace is currently in use", "workspace in use", "could not be locked", ".metadata/.lock", ".metadata\\.lock"],
+    ),
+    (
+        FailureCode::ProjectNotFound,
+        &["project not found", "project: ", "does not exist in the workspace"],
+    ),
+    (
+        FailureCode::ToolchainNotFound,
+        &["cannot run program \"arm-none-eabi-gcc", "arm-none-eabi-gcc: not found", "'arm-none-eabi-gcc' is not recognized", "arm-none-eabi-gcc: command not found", "program \"make\" not found"],
+    ),
+    (
+        FailureCode::JvmOutOfMemory,
+        &["java.lang.outofmemoryerror", "gc overhead limit exceeded"],
+    ),
+    (
+        FailureCode::JvmStartFailed,
+        &["could not create the java virtual machine", "failed to create the java virtual machine", "jvm terminated. exit code"],
+    ),
+];
+
+impl FailureCode {
+    /// Key of the hint in the GUI's message table.
+    pub fn message_key(self) -> &'static str {
+        match self {
+            FailureCode::ProjectNotFound => "hint.project_not_found",
+            FailureCode::WorkspaceInUse => "hint.workspace_in_use",
+            FailureCode::ToolchainNotFound => "hint.toolchain_not_found",
+            FailureCode::JvmOutOfMemory => "hint.jvm_out_of_memory",
+            FailureCode::JvmStartFailed => "hint.jvm_start_failed",
+        }
+    }
+
+    /// How to fix it, in English.
+    pub fn hint(self) -> &'static str {
+        match self {
+            FailureCode::ProjectNotFound => "The project is not imported into the selected workspace. Open the workspace in STM32CubeIDE once and import the project, or check the project name and workspace path.",
+            FailureCode::WorkspaceInUse => "The workspace is locked by another Eclipse instance. Close STM32CubeIDE (and any other running build) that uses this workspace, or select a separate workspace for headless builds.",
+            FailureCode::ToolchainNotFound => "The GNU Arm toolchain could not be started. Check the toolchain settings of the project (Properties > C/C++ Build > Settings > MCU Toolchain) and that STM32CubeIDE's bundled tools are installed.",
+            FailureCode::JvmOutOfMemory => "STM32CubeIDE ran out of Java heap. Increase -Xmx in stm32cubeide.ini next to the executable, or build fewer combinations at once.",
+            FailureCode::JvmStartFailed => "The Java VM of STM32CubeIDE failed to start. Check the -vm and -Xmx entries in stm32cubeide.ini and that enough memory is free.",
+        }
+    }
+}
+
+fn matches(code: FailureCode, needle: &str, line: &str) -> bool {
+    if !line.contains(needle) {
+        return false;
+    }
+    // "Project: <name> not found" — avoid firing on every "Project: ..." line
+    if code == FailureCode::ProjectNotFound && needle == "project: " {
+        return line.contains("not found") || line.contains("does not exist");
+    }
+    true
+}
+
+/// Looks for a canonical failure string in the CubeIDE output.
+pub fn detect<'a, I>(lines: I) -> Option<FailureHint>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let lowered: Vec<(String, &str)> = lines
+        .into_iter()
+        .map(|l| (l.to_lowercase(), l))
+        .collect();
+
+    for (code, needles) in PATTERNS {
+        for (lower, original) in &lowered {
+            if needles.iter().any(|n| matches(*code, n, lower)) {
+                return Some(FailureHint {
+                    code: *code,
+                    hint: code.hint().to_string(),
+                    evidence: original.trim().to_string(),
+                });
+            }
+        }
+    }
+    None
+}
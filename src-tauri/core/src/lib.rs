@@ -0,0 +1,23 @@
+//! Build engine of the STM32 GUI builder without the GUI: the settings
+//! schema, the combination matrix, `build_config.h` generation, the
+//! headless CubeIDE invocation and the parsing of its output, and the loop
+//! building a run's combinations with them.
+
+pub mod build_combinations;
+pub mod build_config_gen;
+pub mod builder;
+pub mod command_line;
+pub mod config;
+pub mod cproject;
+pub mod cproject_defines;
+pub mod defaults;
+pub mod diagnostics;
+pub mod events;
+pub mod failure_hints;
+pub mod matrix;
+pub mod paths;
+pub mod pipeline;
+pub mod progress;
+pub mod project;
+pub mod runner;
+pub mod selection;
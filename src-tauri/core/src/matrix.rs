@@ -0,0 +1,520 @@
+//! The loop over a run's combinations that the GUI and the CLI share: every
+//! combination gets its `build_config.h`, a CubeIDE (or `make`) build with
+//! its output streamed and logged, a verdict on that output and its image
+//! moved next to the log. What a frontend does around these steps goes
+//! through [`MatrixHooks`].
+
+use crate::build_config_gen::generate_build_config_h;
+use crate::builder::{artifact_stem, combination_name, HeadlessBuild};
+use crate::config::BuildSettingsConfig;
+use crate::cproject_defines::DefinesError;
+use crate::events::{scoped_channel, BuildOutputLine, CombinationProgress, EventSink};
+use crate::pipeline::{bin_file, Failure, FailurePolicy, MacroTarget, Outcome};
+use crate::progress::ProgressParser;
+use crate::runner::{ProcessRunner, ProcessSpec};
+use async_trait::async_trait;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::time::{self, Duration};
+use tokio_util::sync::CancellationToken;
+
+/// What a run builds its combinations from and where it puts them.
+pub struct Matrix<'a> {
+    pub run_id: &'a str,
+    pub settings_config: &'a BuildSettingsConfig,
+    pub project_path: &'a Path,
+    pub project_name: &'a str,
+    pub config_name: &'a str,
+    /// Folder the build leaves the configuration's output in, normally
+    /// `<project>/<configuration>`.
+    pub build_dir: &'a Path,
+    /// The run's own folder; each combination gets a subfolder.
+    pub output_dir: &'a Path,
+    pub macro_target: &'a MacroTarget,
+    /// Appended to every combination's `build_config.h`.
+    pub extra_defines: &'a str,
+    pub version_label: Option<&'a str>,
+    pub failure_policy: &'a FailurePolicy,
+    pub cube_ide_exe: &'a str,
+    /// CubeIDE's `-build` target, see [`crate::builder::build_target`].
+    pub target: &'a str,
+    pub workspace: &'a str,
+    pub clean: bool,
+    pub custom_args: Option<&'a str>,
+    /// Each build is killed once it runs this long.
+    pub timeout_secs: Option<u64>,
+}
+
+/// One combination of the run, as the hooks see it.
+pub struct Combination<'a> {
+    /// Position in the run, from 0.
+    pub index: usize,
+    pub total: usize,
+    pub settings: &'a [(String, String)],
+    /// Folder name, see [`combination_name`].
+    pub name: &'a str,
+    /// `<output_dir>/<name>`.
+    pub dir: &'a Path,
+    /// Where the image ends up.
+    pub artifact: &'a Path,
+    /// Where the build output is written.
+    pub log_file: &'a Path,
+}
+
+/// Whether a combination is built after [`MatrixHooks::before_combination`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Proceed {
+    Build,
+    Skip,
+}
+
+/// Steps of a combination, reported as they start.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatrixStep {
+    RemoveArtifact,
+    GenerateHeader,
+    Build,
+    CheckOutput,
+    MoveArtifact,
+}
+
+/// What the loop reports while building a combination.
+pub enum MatrixEvent<'a> {
+    Step(MatrixStep),
+    /// The build process started; `pid` is `None` for a dry run.
+    Started { pid: Option<u32>, warnings: &'a [String] },
+    /// The build process is gone, because it exited or was killed.
+    Stopped,
+    /// The build exited with its output collected.
+    Exited { exit_code: i32, stdout: &'a [String], stderr: &'a [String] },
+    /// A copy or log that could not be written; the build goes on.
+    WriteFailed { path: &'a Path, error: &'a io::Error },
+    /// The verdict on the output; a failure ends the run right after.
+    Judged(&'a Outcome),
+}
+
+/// A frontend's part of the loop. Hook errors of type `Error` end the run
+/// and come back as [`MatrixError::Hook`].
+#[async_trait]
+pub trait MatrixHooks: Send {
+    type Error: Send;
+
+    /// Before anything of `combination` is done.
+    async fn before_combination(&mut self, _combination: &Combination<'_>) -> Result<Proceed, Self::Error> {
+        Ok(Proceed::Build)
+    }
+
+    /// With the macros in place, before `spec` is started; `spec` may be
+    /// changed, e.g. to a warm `make` build.
+    async fn before_build(
+        &mut self,
+        _combination: &Combination<'_>,
+        _header: &str,
+        _spec: &mut ProcessSpec,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Once the image is in [`Combination::artifact`].
+    async fn after_build(&mut self, _combination: &Combination<'_>, _header: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn report(&mut self, _combination: &Combination<'_>, _event: MatrixEvent<'_>) {}
+}
+
+/// Why a run stopped before building all its combinations.
+#[derive(Debug)]
+pub enum MatrixError<E> {
+    /// `running` when a build was killed, not just not started.
+    Cancelled { combination: String, running: bool },
+    Timeout { combination: String, seconds: u64 },
+    CreateDir(PathBuf, io::Error),
+    Remove(PathBuf, io::Error),
+    Header(String),
+    Defines(DefinesError),
+    Spawn(io::Error),
+    Wait { combination: String, error: io::Error },
+    /// The build failed by the [`FailurePolicy`].
+    Failed { combination: String, failure: Failure },
+    /// The build succeeded without leaving its image.
+    NotBuilt(PathBuf),
+    Move { from: PathBuf, to: PathBuf, error: io::Error },
+    Hook(E),
+}
+
+impl<E: fmt::Display> fmt::Display for MatrixError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::Cancelled { combination, .. } => write!(f, "Cancelled at combination {}", combination),
+            MatrixError::Timeout { combination, seconds } => {
+                write!(f, "Combination {} took longer than {} s", combination, seconds)
+            }
+            MatrixError::CreateDir(path, e) => write!(f, "Error creating directory '{}': {}", path.display(), e),
+            MatrixError::Remove(path, e) => write!(f, "Error removing '{}': {}", path.display(), e),
+            MatrixError::Header(e) => f.write_str(e),
+            MatrixError::Defines(e) => e.fmt(f),
+            MatrixError::Spawn(e) => write!(f, "Failed to start the build: {}", e),
+            MatrixError::Wait { combination, error } => {
+                write!(f, "Failed to wait for the build of {}: {}", combination, error)
+            }
+            MatrixError::Failed { combination, failure } => write!(f, "Combination {} failed: {}", combination, failure),
+            MatrixError::NotBuilt(path) => write!(f, "'{}' was not built", path.display()),
+            MatrixError::Move { from, to, error } => {
+                write!(f, "Error moving '{}' to '{}': {}", from.display(), to.display(), error)
+            }
+            MatrixError::Hook(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Completes after `secs`, or never when there is no limit.
+async fn time_limit(secs: Option<u64>) {
+    match secs {
+        Some(secs) => time::sleep(Duration::from_secs(secs)).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// How a build process ended.
+enum Ended {
+    Exited(io::Result<i32>, Vec<String>, Vec<String>),
+    TimedOut,
+    Cancelled,
+}
+
+/// Streams one output of the build to `events` and collects its lines;
+/// stdout also drives the progress events.
+async fn read_output(
+    output: Box<dyn AsyncRead + Send + Unpin>,
+    stream: &str,
+    events: &dyn EventSink,
+    run_id: &str,
+    combination: &Combination<'_>,
+    mut progress: Option<ProgressParser>,
+) -> Vec<String> {
+    let channel = scoped_channel("build-output", Some(run_id), combination.name);
+    let mut lines = BufReader::new(output).lines();
+    let mut collected = Vec::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let payload = BuildOutputLine {
+            run_id: run_id.to_string(),
+            combination: combination.name.to_string(),
+            stream: stream.to_string(),
+            line,
+        };
+        events.emit("build-output", &payload);
+        events.emit(&channel, &payload);
+        if let Some(progress) = progress.as_mut().and_then(|p| p.feed(&payload.line)) {
+            let payload = CombinationProgress {
+                run_id: run_id.to_string(),
+                combination: combination.name.to_string(),
+                index: combination.index,
+                total: combination.total,
+                progress,
+            };
+            events.emit("build-progress", &payload);
+        }
+        collected.push(payload.line);
+    }
+    collected
+}
+
+/// Builds `combinations` in order and stops at the first that fails or
+/// at cancellation; the build output goes to `events` as it comes.
+pub async fn build_matrix<H: MatrixHooks>(
+    matrix: &Matrix<'_>,
+    combinations: &[Vec<(String, String)>],
+    runner: &dyn ProcessRunner,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+    hooks: &mut H,
+) -> Result<(), MatrixError<H::Error>> {
+    for (index, settings) in combinations.iter().enumerate() {
+        let name = combination_name(matrix.settings_config, settings);
+        if cancel.is_cancelled() {
+            return Err(MatrixError::Cancelled { combination: name, running: false });
+        }
+        let dir = matrix.output_dir.join(&name);
+        let stem = artifact_stem(
+            matrix.settings_config,
+            settings,
+            matrix.project_name,
+            matrix.config_name,
+            matrix.version_label,
+        );
+        let artifact = dir.join(format!("{}.bin", stem));
+        let log_file = dir.join(format!("{}.txt", stem));
+        let combination = Combination {
+            index,
+            total: combinations.len(),
+            settings,
+            name: &name,
+            dir: &dir,
+            artifact: &artifact,
+            log_file: &log_file,
+        };
+        if hooks.before_combination(&combination).await.map_err(MatrixError::Hook)? == Proceed::Skip {
+            continue;
+        }
+        build_combination(matrix, &combination, runner, events, cancel, hooks).await?;
+    }
+    Ok(())
+}
+
+async fn build_combination<H: MatrixHooks>(
+    matrix: &Matrix<'_>,
+    combination: &Combination<'_>,
+    runner: &dyn ProcessRunner,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+    hooks: &mut H,
+) -> Result<(), MatrixError<H::Error>> {
+    fs::create_dir_all(combination.dir)
+        .await
+        .map_err(|e| MatrixError::CreateDir(combination.dir.to_path_buf(), e))?;
+
+    hooks.report(combination, MatrixEvent::Step(MatrixStep::RemoveArtifact));
+    if fs::try_exists(combination.artifact).await.unwrap_or(false) {
+        fs::remove_file(combination.artifact)
+            .await
+            .map_err(|e| MatrixError::Remove(combination.artifact.to_path_buf(), e))?;
+    }
+
+    hooks.report(combination, MatrixEvent::Step(MatrixStep::GenerateHeader));
+    let header = generate_build_config_h(matrix.settings_config, combination.settings, matrix.extra_defines)
+        .map_err(MatrixError::Header)?;
+    // Keep a copy next to the artifacts; the project's header is overwritten by the next combination
+    let header_copy = combination.dir.join("build_config.h");
+    if let Err(error) = fs::write(&header_copy, &header).await {
+        hooks.report(combination, MatrixEvent::WriteFailed { path: &header_copy, error: &error });
+    }
+    matrix.macro_target.write(matrix.config_name, &header).await.map_err(MatrixError::Defines)?;
+
+    let mut spec = ProcessSpec {
+        program: matrix.cube_ide_exe.into(),
+        args: HeadlessBuild {
+            target: matrix.target,
+            workspace: matrix.workspace,
+            clean: matrix.clean,
+            // A fresh workspace (clean export, verification builds) does not know the project yet
+            import: (!Path::new(matrix.workspace).join(".metadata").exists()).then_some(matrix.project_path),
+            header: matrix.macro_target.header(),
+            skip_header: matrix.macro_target.header().is_none(),
+            custom_args: matrix.custom_args,
+        }
+        .args(),
+        cwd: matrix.project_path.to_path_buf(),
+        envs: Vec::new(),
+    };
+    hooks.before_build(combination, &header, &mut spec).await.map_err(MatrixError::Hook)?;
+
+    hooks.report(combination, MatrixEvent::Step(MatrixStep::Build));
+    let process = runner.spawn(&spec).map_err(MatrixError::Spawn)?;
+    let (stdout, stderr, mut handle) = (process.stdout, process.stderr, process.handle);
+    hooks.report(combination, MatrixEvent::Started { pid: handle.id(), warnings: &process.warnings });
+
+    let ended = tokio::select! {
+        ended = async {
+            // Both pipes are drained before waiting, so a full pipe cannot stall the build
+            let (stdout, stderr) = tokio::join!(
+                read_output(stdout, "stdout", events, matrix.run_id, combination, Some(ProgressParser::new(matrix.build_dir))),
+                read_output(stderr, "stderr", events, matrix.run_id, combination, None),
+            );
+            Ended::Exited(handle.wait().await, stdout, stderr)
+        } => ended,
+        _ = time_limit(matrix.timeout_secs) => Ended::TimedOut,
+        _ = cancel.cancelled() => Ended::Cancelled,
+    };
+    let (exit_code, stdout, stderr) = match ended {
+        Ended::Exited(status, stdout, stderr) => {
+            hooks.report(combination, MatrixEvent::Stopped);
+            let exit_code = status
+                .map_err(|error| MatrixError::Wait { combination: combination.name.to_string(), error })?;
+            (exit_code, stdout, stderr)
+        }
+        Ended::TimedOut => {
+            handle.kill().await.ok();
+            hooks.report(combination, MatrixEvent::Stopped);
+            let seconds = matrix.timeout_secs.unwrap_or_default();
+            return Err(MatrixError::Timeout { combination: combination.name.to_string(), seconds });
+        }
+        Ended::Cancelled => {
+            handle.kill().await.ok();
+            hooks.report(combination, MatrixEvent::Stopped);
+            return Err(MatrixError::Cancelled { combination: combination.name.to_string(), running: true });
+        }
+    };
+
+    let mut log = format!("# run_id: {}\n", matrix.run_id);
+    for line in &stdout {
+        log.push_str(line);
+        log.push('\n');
+    }
+    for line in &stderr {
+        log.push_str("[STDERR] ");
+        log.push_str(line.trim());
+        log.push('\n');
+    }
+    if let Err(error) = fs::write(combination.log_file, log).await {
+        hooks.report(combination, MatrixEvent::WriteFailed { path: combination.log_file, error: &error });
+    }
+    hooks.report(combination, MatrixEvent::Exited { exit_code, stdout: &stdout, stderr: &stderr });
+
+    // Diagnostics, known Eclipse failures and the warning limits decide
+    let output: Vec<&str> = stdout.iter().chain(&stderr).map(String::as_str).collect();
+    let outcome = matrix.failure_policy.judge(exit_code, &output);
+    hooks.report(combination, MatrixEvent::Judged(&outcome));
+    if let Some(failure) = outcome.failure {
+        return Err(MatrixError::Failed { combination: combination.name.to_string(), failure });
+    }
+
+    // The process has exited and released the image by now
+    hooks.report(combination, MatrixEvent::Step(MatrixStep::CheckOutput));
+    let image = bin_file(matrix.build_dir, matrix.project_name);
+    if !fs::try_exists(&image).await.unwrap_or(false) {
+        return Err(MatrixError::NotBuilt(image));
+    }
+    hooks.report(combination, MatrixEvent::Step(MatrixStep::MoveArtifact));
+    fs::rename(&image, combination.artifact).await.map_err(|error| MatrixError::Move {
+        from: image.clone(),
+        to: combination.artifact.to_path_buf(),
+        error,
+    })?;
+    hooks.after_build(combination, &header).await.map_err(MatrixError::Hook)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::DryRunRunner;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    fn settings() -> BuildSettingsConfig {
+        serde_yaml::from_str(
+            r#"
+version: "1"
+build_settings:
+  - id: board
+    label: Board
+    value: brd
+    description: ""
+    field_type: select
+    format: ""
+    define: null
+    options:
+      - label: F4
+        value: "4"
+        define: BOARD_F4
+        description: null
+    validation: null
+    exclusive: null
+    min_selected: null
+"#,
+        )
+        .unwrap()
+    }
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<String>>);
+
+    impl EventSink for Recorded {
+        fn emit_value(&self, event: &str, payload: Value) {
+            let line = payload["line"].as_str().unwrap_or_default();
+            self.0.lock().unwrap().push(format!("{} {}", event, line));
+        }
+    }
+
+    #[derive(Default)]
+    struct Built(Vec<PathBuf>);
+
+    #[async_trait]
+    impl MatrixHooks for Built {
+        type Error = String;
+
+        async fn after_build(&mut self, combination: &Combination<'_>, _header: &str) -> Result<(), String> {
+            self.0.push(combination.artifact.to_path_buf());
+            Ok(())
+        }
+    }
+
+    async fn dry_run(runner: &DryRunRunner, project: &Path, output_dir: &Path, build_dir: &Path) -> (Result<(), MatrixError<String>>, Recorded, Built) {
+        let settings_config = settings();
+        let header_file = project.join("Core").join("Inc").join("build_config.h");
+        let (macro_target, _) = MacroTarget::new(Default::default(), project, header_file).unwrap();
+        let matrix = Matrix {
+            run_id: "run1",
+            settings_config: &settings_config,
+            project_path: project,
+            project_name: "Blinky",
+            config_name: "Debug",
+            build_dir,
+            output_dir,
+            macro_target: &macro_target,
+            extra_defines: "",
+            version_label: None,
+            failure_policy: &FailurePolicy::default(),
+            cube_ide_exe: "stm32cubeide",
+            target: "Blinky/Debug",
+            workspace: "/ws",
+            clean: false,
+            custom_args: None,
+            timeout_secs: None,
+        };
+        let combinations = vec![vec![("board".to_string(), "4".to_string())]];
+        let (events, mut hooks) = (Recorded::default(), Built::default());
+        let result = build_matrix(&matrix, &combinations, runner, &events, &CancellationToken::new(), &mut hooks).await;
+        (result, events, hooks)
+    }
+
+    #[tokio::test]
+    async fn dry_run_builds_a_combination_without_touching_the_project_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("blinky");
+        std::fs::create_dir_all(project.join("Debug")).unwrap();
+        std::fs::write(project.join("Debug").join("blinky.bin"), b"firmware").unwrap();
+        let output_dir = dir.path().join("out").join("run1");
+        let build_dir = output_dir.join(".dry-run").join("Debug");
+        let runner = DryRunRunner { output: vec!["Finished building: blinky.elf".to_string()], ..DryRunRunner::for_build(&build_dir, "Blinky") };
+
+        let (result, events, hooks) = dry_run(&runner, &project, &output_dir, &build_dir).await;
+        result.unwrap();
+
+        let combo_dir = output_dir.join("brd_4");
+        assert_eq!(hooks.0, vec![combo_dir.join("Blinky_brd-4_Debug.bin")]);
+        assert!(combo_dir.join("Blinky_brd-4_Debug.bin").is_file());
+        let log = std::fs::read_to_string(combo_dir.join("Blinky_brd-4_Debug.txt")).unwrap();
+        assert!(log.starts_with("# run_id: run1\n[DRY RUN] stm32cubeide "), "{}", log);
+        assert!(log.contains("Finished building: blinky.elf\n"));
+        let header = std::fs::read_to_string(project.join("Core").join("Inc").join("build_config.h")).unwrap();
+        assert!(header.contains("#define BOARD_F4\n"));
+        assert_eq!(std::fs::read_to_string(combo_dir.join("build_config.h")).unwrap(), header);
+        assert_eq!(std::fs::read(project.join("Debug").join("blinky.bin")).unwrap(), b"firmware");
+        assert!(events.0.lock().unwrap().contains(&"build-output Finished building: blinky.elf".to_string()));
+    }
+
+    #[tokio::test]
+    async fn dry_run_stops_at_a_failed_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("blinky");
+        let output_dir = dir.path().join("out").join("run1");
+        let build_dir = output_dir.join(".dry-run").join("Debug");
+        let runner = DryRunRunner {
+            exit_code: 2,
+            output: vec!["../Core/Src/main.c:3:1: error: expected ';'".to_string()],
+            ..DryRunRunner::for_build(&build_dir, "Blinky")
+        };
+
+        let (result, _, hooks) = dry_run(&runner, &project, &output_dir, &build_dir).await;
+        match result {
+            Err(MatrixError::Failed { combination, failure: Failure::Build { exit_code: 2, .. } }) => assert_eq!(combination, "brd_4"),
+            other => panic!("{:?}", other.err()),
+        }
+        assert!(hooks.0.is_empty());
+        assert!(!output_dir.join("brd_4").join("Blinky_brd-4_Debug.bin").exists());
+    }
+}
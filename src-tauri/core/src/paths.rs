@@ -0,0 +1,58 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Drops the `\\?\` prefix `canonicalize` adds on Windows: `\\?\C:\fw`
+/// becomes `C:\fw` and `\\?\UNC\server\share` becomes `\\server\share`.
+/// Eclipse, `make` and `cmd` do not understand verbatim paths, while the
+/// standard library adds the prefix back by itself for file operations on
+/// paths longer than `MAX_PATH`. Other paths are returned unchanged.
+pub fn simplify(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if let Some(share) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", share));
+    }
+    match text.strip_prefix(r"\\?\") {
+        // `\\?\Volume{...}` has no other spelling
+        Some(drive_path) if drive_path.as_bytes().get(1) == Some(&b':') => PathBuf::from(drive_path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `canonicalize` in the form other programs accept, see [`simplify`].
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    path.canonicalize().map(|p| simplify(&p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_verbatim_unc() {
+        assert_eq!(simplify(Path::new(r"\\?\UNC\server\share\fw")), PathBuf::from(r"\\server\share\fw"));
+    }
+
+    #[test]
+    fn simplify_verbatim_drive() {
+        assert_eq!(simplify(Path::new(r"\\?\C:\Users\dev\fw")), PathBuf::from(r"C:\Users\dev\fw"));
+        // Nothing else names a volume GUID path
+        let volume = r"\\?\Volume{0b2c4f1e-0000-0000-0000-100000000000}\fw";
+        assert_eq!(simplify(Path::new(volume)), PathBuf::from(volume));
+    }
+
+    #[test]
+    fn simplify_passes_other_paths_through() {
+        for path in [r"C:\Users\dev\fw", r"\\server\share\fw", "/home/dev/fw", "fw/Debug"] {
+            assert_eq!(simplify(Path::new(path)), PathBuf::from(path));
+        }
+    }
+
+    #[test]
+    fn canonicalize_has_no_verbatim_prefix() {
+        let canonical = canonicalize(&std::env::temp_dir()).unwrap();
+        assert!(canonical.is_absolute());
+        assert!(!canonical.to_string_lossy().starts_with(r"\\?\"));
+    }
+}
@@ -0,0 +1,226 @@
+//! What building one combination involves besides starting CubeIDE:
+//! getting its macros to the compiler, deciding from the output whether it
+//! failed and where the image ends up. The GUI and the CLI both build
+//! their combinations through these steps.
+
+use crate::build_config_gen::header_defines;
+use crate::config::BuildSettingsConfig;
+use crate::cproject_defines::{CprojectBackup, DefinesError};
+use crate::diagnostics::{self, Diagnostic, Severity};
+use crate::failure_hints::{self, FailureHint};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefineMode {
+    /// `build_config.h`, force-included into every file.
+    #[default]
+    Header,
+    /// The preprocessor symbols of the configuration in `.cproject`, for
+    /// projects where some files are built without the forced include.
+    /// The original `.cproject` is restored after the run.
+    Cproject,
+}
+
+/// Where the combinations' macros go during a run.
+pub enum MacroTarget {
+    /// The project's `build_config.h`.
+    Header(PathBuf),
+    /// The configuration's symbols; the original `.cproject` is written
+    /// back when this is dropped, however the run ends.
+    Cproject(CprojectBackup),
+}
+
+impl MacroTarget {
+    /// Also tells whether a `.cproject` left edited by a run that did not
+    /// get to restore it was put back first.
+    pub fn new(mode: DefineMode, project_path: &Path, header_file: PathBuf) -> Result<(Self, bool), DefinesError> {
+        match mode {
+            DefineMode::Header => Ok((MacroTarget::Header(header_file), false)),
+            DefineMode::Cproject => {
+                CprojectBackup::new(project_path).map(|(backup, restored_stale)| (MacroTarget::Cproject(backup), restored_stale))
+            }
+        }
+    }
+
+    /// Header for CubeIDE's `-include`; `None` when the macros are symbols.
+    pub fn header(&self) -> Option<&Path> {
+        match self {
+            MacroTarget::Header(file) => Some(file),
+            MacroTarget::Cproject(_) => None,
+        }
+    }
+
+    /// Hands one combination's `build_config.h` to the compiler.
+    pub async fn write(&self, config_name: &str, header: &str) -> Result<(), DefinesError> {
+        match self {
+            MacroTarget::Header(file) => {
+                if let Some(parent) = file.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(|e| DefinesError::Write(parent.to_path_buf(), e))?;
+                }
+                tokio::fs::write(file, header).await.map_err(|e| DefinesError::Write(file.clone(), e))
+            }
+            MacroTarget::Cproject(backup) => backup.apply(config_name, &header_defines(header)).await,
+        }
+    }
+}
+
+/// What fails a combination besides CubeIDE's exit code.
+#[derive(Clone, Debug, Default)]
+pub struct FailurePolicy {
+    /// Warnings treated as errors, see [`diagnostics::promote_warnings`].
+    pub error_warnings: Vec<String>,
+    pub max_warnings: Option<usize>,
+}
+
+impl FailurePolicy {
+    /// The schema's error warnings followed by the run's own.
+    pub fn new(settings_config: &BuildSettingsConfig, error_warnings: Option<&[String]>, max_warnings: Option<usize>) -> Self {
+        FailurePolicy {
+            error_warnings: settings_config.error_warnings.iter().chain(error_warnings.into_iter().flatten()).cloned().collect(),
+            max_warnings,
+        }
+    }
+
+    /// Judges a finished CubeIDE run by its exit code and output.
+    pub fn judge(&self, exit_code: i32, output: &[&str]) -> Outcome {
+        let mut diagnostics = diagnostics::parse_output(output.iter().copied());
+        let promoted = diagnostics::promote_warnings(&mut diagnostics, &self.error_warnings);
+        let errors = diagnostics::count(&diagnostics, Severity::Error);
+        let warnings = diagnostics::count(&diagnostics, Severity::Warning);
+        let warnings_exceeded = self.max_warnings.is_some_and(|max| warnings > max);
+        // Eclipse reports some fatal problems (e.g. unknown project) with exit code 0
+        let failure_hint = failure_hints::detect(output.iter().copied());
+        let failure = if exit_code != 0 || failure_hint.is_some() {
+            Some(Failure::Build {
+                exit_code,
+                reason: diagnostics::first_error(&diagnostics).or_else(|| failure_hint.as_ref().map(|h| h.evidence.clone())),
+            })
+        } else if promoted > 0 {
+            Some(Failure::PromotedWarnings { count: promoted, first: diagnostics::first_error(&diagnostics).unwrap_or_default() })
+        } else if warnings_exceeded {
+            Some(Failure::TooManyWarnings { warnings, max: self.max_warnings.unwrap_or_default() })
+        } else {
+            None
+        };
+        Outcome { diagnostics, errors, warnings, warnings_exceeded, failure_hint, failure }
+    }
+}
+
+/// Verdict on one combination's CubeIDE run.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub diagnostics: Vec<Diagnostic>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub warnings_exceeded: bool,
+    pub failure_hint: Option<FailureHint>,
+    pub failure: Option<Failure>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Failure {
+    /// CubeIDE exited with an error or printed a known failure; `reason`
+    /// is the first error, else the matching line.
+    Build { exit_code: i32, reason: Option<String> },
+    /// Warnings promoted to errors, with the first of them.
+    PromotedWarnings { count: usize, first: String },
+    TooManyWarnings { warnings: usize, max: usize },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Build { reason: Some(reason), .. } => f.write_str(reason),
+            Failure::Build { exit_code, reason: None } => write!(f, "CubeIDE exited with code {}", exit_code),
+            Failure::PromotedWarnings { count, first } => write!(f, "{} warning(s) treated as errors: {}", count, first),
+            Failure::TooManyWarnings { warnings, max } => write!(f, "{} warnings, at most {} allowed", warnings, max),
+        }
+    }
+}
+
+/// Image a successful headless build leaves in the configuration's folder.
+pub fn bin_file(build_dir: &Path, project_name: &str) -> PathBuf {
+    build_dir.join(format!("{}.bin", project_name.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failure_hints::FailureCode;
+    use std::fs;
+
+    const WARNING: &str = "../Core/Src/main.c:12:9: warning: unused variable 'x' [-Wunused-variable]";
+    const ERROR: &str = "../Core/Src/main.c:20:5: error: 'y' undeclared (first use in this function)";
+
+    fn policy(error_warnings: &[&str], max_warnings: Option<usize>) -> FailurePolicy {
+        FailurePolicy { error_warnings: error_warnings.iter().map(|w| w.to_string()).collect(), max_warnings }
+    }
+
+    #[test]
+    fn clean_build_passes() {
+        let outcome = policy(&[], Some(1)).judge(0, &["Building file: ../Core/Src/main.c", WARNING]);
+        assert_eq!(outcome.failure, None);
+        assert_eq!((outcome.errors, outcome.warnings), (0, 1));
+    }
+
+    #[test]
+    fn exit_code_fails_with_the_first_error() {
+        let outcome = policy(&[], None).judge(2, &[WARNING, ERROR]);
+        assert_eq!(
+            outcome.failure,
+            Some(Failure::Build { exit_code: 2, reason: Some("../Core/Src/main.c:20:5: 'y' undeclared (first use in this function)".into()) })
+        );
+        assert_eq!(policy(&[], None).judge(1, &[]).failure.unwrap().to_string(), "CubeIDE exited with code 1");
+    }
+
+    #[test]
+    fn known_failure_fails_despite_exit_code_zero() {
+        let lock = "Workspace is currently in use by another Eclipse instance";
+        let outcome = policy(&[], None).judge(0, &[lock]);
+        assert_eq!(outcome.failure_hint.map(|h| h.code), Some(FailureCode::WorkspaceInUse));
+        assert_eq!(outcome.failure, Some(Failure::Build { exit_code: 0, reason: Some(lock.into()) }));
+    }
+
+    #[test]
+    fn warnings_fail_when_promoted_or_too_many() {
+        let outcome = policy(&["unused-variable"], None).judge(0, &[WARNING]);
+        assert!(matches!(outcome.failure, Some(Failure::PromotedWarnings { count: 1, .. })));
+
+        let outcome = policy(&[], Some(0)).judge(0, &[WARNING]);
+        assert!(outcome.warnings_exceeded);
+        assert_eq!(outcome.failure, Some(Failure::TooManyWarnings { warnings: 1, max: 0 }));
+    }
+
+    #[tokio::test]
+    async fn header_target_creates_the_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("Core/Inc/build_config.h");
+        let (target, restored_stale) = MacroTarget::new(DefineMode::Header, dir.path(), file.clone()).unwrap();
+        assert!(!restored_stale);
+        assert_eq!(target.header(), Some(file.as_path()));
+        target.write("Debug", "#define BOARD 4\n").await.unwrap();
+        assert_eq!(fs::read_to_string(file).unwrap(), "#define BOARD 4\n");
+    }
+
+    #[tokio::test]
+    async fn cproject_target_edits_symbols_and_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let cproject = r#"<cproject><configuration name="Debug"><folderInfo resourcePath="">
+            <option superClass="com.st.stm32cube.ide.mcu.gnu.managedbuild.tool.c.compiler.option.definedsymbols">
+            <listOptionValue builtIn="false" value="DEBUG"/></option>
+            </folderInfo></configuration></cproject>"#;
+        fs::write(dir.path().join(".cproject"), cproject).unwrap();
+        {
+            let (target, _) = MacroTarget::new(DefineMode::Cproject, dir.path(), PathBuf::new()).unwrap();
+            assert_eq!(target.header(), None);
+            target.write("Debug", "#define BOARD 4\n").await.unwrap();
+            let edited = fs::read_to_string(dir.path().join(".cproject")).unwrap();
+            assert!(edited.contains(r#"value="BOARD=4""#));
+            assert!(edited.contains(r#"value="DEBUG""#));
+        }
+        assert_eq!(fs::read_to_string(dir.path().join(".cproject")).unwrap(), cproject);
+    }
+}
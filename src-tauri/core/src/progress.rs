@@ -0,0 +1,223 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    // Classic CDT managed build
+    static ref BUILDING_FILE: Regex = Regex::new(r"^Building file: (.+)$").unwrap();
+    // CubeIDE 1.4+ prints the compiler command instead
+    static ref COMPILE_COMMAND: Regex = Regex::new(r#"^arm-none-eabi-gcc(?:\.exe)? "([^"]+\.[cCsS])" .* -c "#).unwrap();
+    static ref BUILDING_TARGET: Regex = Regex::new(r"^Building target: (.+)$").unwrap();
+    static ref LINK_COMMAND: Regex = Regex::new(r#"^arm-none-eabi-g(?:cc|\+\+)(?:\.exe)? -o "([^"]+\.elf)""#).unwrap();
+    // make/CMake style "[ 45%] Building C object ..."
+    static ref PERCENT: Regex = Regex::new(r"^\[\s*(\d{1,3})%\]").unwrap();
+    // Ninja "[12/48] Building C object ..."
+    static ref STEPS: Regex = Regex::new(r"^\[(\d+)/(\d+)\]").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildPhase {
+    Compiling,
+    Linking,
+}
+
+/// How far the compiler has got through one combination.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BuildProgress {
+    pub phase: BuildPhase,
+    /// Source being compiled, or the target being linked.
+    pub file: Option<String>,
+    pub files_built: usize,
+    /// Objects of the configuration, from the generated makefiles.
+    pub files_total: Option<usize>,
+    /// 0-100; `None` while there is nothing to estimate it from.
+    pub percent: Option<u8>,
+}
+
+/// Counts the objects listed under `OBJS +=` in the `subdir.mk` files CDT
+/// generates in a configuration's build folder.
+pub fn count_objects(build_dir: &Path) -> usize {
+    let mut count = 0;
+    let mut dirs = vec![build_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.file_name().is_some_and(|n| n == "subdir.mk") {
+                count += fs::read_to_string(&path).map(|mk| objects_in(&mk)).unwrap_or(0);
+            }
+        }
+    }
+    count
+}
+
+fn objects_in(makefile: &str) -> usize {
+    let mut count = 0;
+    let mut in_objs = false;
+    for line in makefile.lines() {
+        let line = line.trim();
+        if line.starts_with("OBJS +=") {
+            in_objs = true;
+            continue;
+        }
+        if in_objs {
+            let item = line.trim_end_matches('\\').trim();
+            if item.ends_with(".o") {
+                count += 1;
+            }
+            // The list ends at the first line without a continuation
+            in_objs = line.ends_with('\\');
+        }
+    }
+    count
+}
+
+/// Follows the headless build output of one combination line by line.
+pub struct ProgressParser {
+    build_dir: PathBuf,
+    progress: Option<BuildProgress>,
+}
+
+impl ProgressParser {
+    /// `build_dir` is the configuration's folder (`<project>/Debug`), where
+    /// CDT writes the makefiles before compiling.
+    pub fn new(build_dir: &Path) -> Self {
+        ProgressParser { build_dir: build_dir.to_path_buf(), progress: None }
+    }
+
+    /// Progress after `line`, when the line moved it.
+    pub fn feed(&mut self, line: &str) -> Option<BuildProgress> {
+        let line = line.trim();
+        if let Some(percent) = PERCENT.captures(line).and_then(|c| c[1].parse::<u8>().ok()) {
+            let progress = self.current();
+            progress.percent = Some(percent.min(100));
+            return Some(progress.clone());
+        }
+        if let Some((done, total)) = STEPS
+            .captures(line)
+            .and_then(|c| Some((c[1].parse::<usize>().ok()?, c[2].parse::<usize>().ok()?)))
+            .filter(|(_, total)| *total > 0)
+        {
+            let progress = self.current();
+            progress.files_built = done;
+            progress.files_total = Some(total);
+            progress.percent = Some((done * 100 / total).min(100) as u8);
+            return Some(progress.clone());
+        }
+        if let Some(file) = BUILDING_FILE
+            .captures(line)
+            .or_else(|| COMPILE_COMMAND.captures(line))
+            .map(|c| c[1].to_string())
+        {
+            let progress = self.current();
+            progress.phase = BuildPhase::Compiling;
+            progress.files_built += 1;
+            progress.file = Some(file);
+            if let Some(total) = progress.files_total.filter(|total| *total > 0) {
+                // Linking is left for the last percent
+                progress.percent = Some((progress.files_built * 99 / total).min(99) as u8);
+            }
+            return Some(progress.clone());
+        }
+        if let Some(target) = BUILDING_TARGET
+            .captures(line)
+            .or_else(|| LINK_COMMAND.captures(line))
+            .map(|c| c[1].to_string())
+        {
+            let progress = self.current();
+            progress.phase = BuildPhase::Linking;
+            progress.file = Some(target);
+            progress.percent = Some(progress.percent.unwrap_or(0).max(99));
+            return Some(progress.clone());
+        }
+        None
+    }
+
+    fn current(&mut self) -> &mut BuildProgress {
+        let build_dir = &self.build_dir;
+        self.progress.get_or_insert_with(|| {
+            // The makefiles are complete once the first compiler line shows up
+            let total = count_objects(build_dir);
+            BuildProgress {
+                phase: BuildPhase::Compiling,
+                file: None,
+                files_built: 0,
+                files_total: (total > 0).then_some(total),
+                percent: None,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(parser: &mut ProgressParser, output: &str) -> Vec<Option<BuildProgress>> {
+        output.lines().map(|line| parser.feed(line)).collect()
+    }
+
+    #[test]
+    fn make_percent_lines() {
+        let mut parser = ProgressParser::new(Path::new("/nonexistent"));
+        let seen = feed_all(
+            &mut parser,
+            "-- Configuring done\n\
+             [  4%] Building C object CMakeFiles/blinky.elf.dir/Core/Src/main.c.obj\n\
+             /src/Core/Src/main.c:12:5: warning: unused variable 'x' [-Wunused-variable]\n\
+             [100%] Linking C executable blinky.elf\n\
+             make[2]: Leaving directory '/build'",
+        );
+        let percents: Vec<Option<u8>> = seen.iter().map(|p| p.as_ref().and_then(|p| p.percent)).collect();
+        assert_eq!(percents, vec![None, Some(4), None, Some(100), None]);
+    }
+
+    #[test]
+    fn ninja_step_lines() {
+        let mut parser = ProgressParser::new(Path::new("/nonexistent"));
+        let seen = feed_all(
+            &mut parser,
+            "ninja: Entering directory `build/Debug'\n\
+             [1/4] Building C object CMakeFiles/blinky.elf.dir/Core/Src/main.c.obj\n\
+             [2/4] Building ASM object CMakeFiles/blinky.elf.dir/startup_stm32f407xx.s.obj\n\
+             [4/4] Linking C executable blinky.elf",
+        );
+        assert_eq!(seen[0], None);
+        let steps: Vec<(usize, Option<usize>, Option<u8>)> = seen[1..]
+            .iter()
+            .flatten()
+            .map(|p| (p.files_built, p.files_total, p.percent))
+            .collect();
+        assert_eq!(steps, vec![(1, Some(4), Some(25)), (2, Some(4), Some(50)), (4, Some(4), Some(100))]);
+    }
+
+    #[test]
+    fn cdt_lines_count_against_the_makefiles() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("subdir.mk"),
+            "OBJS += \\\n./Core/Src/main.o \\\n./Core/Src/led.o \n\nC_DEPS += \\\n./Core/Src/main.d\n",
+        )
+        .unwrap();
+        let mut parser = ProgressParser::new(dir.path());
+        let seen = feed_all(
+            &mut parser,
+            "make -j8 all\n\
+             arm-none-eabi-gcc \"../Core/Src/main.c\" -mcpu=cortex-m4 -std=gnu11 -g3 -c -o \"Core/Src/main.o\"\n\
+             Finished building: ../Core/Src/main.c\n\
+             arm-none-eabi-gcc -o \"blinky.elf\" @\"objects.list\" -mcpu=cortex-m4",
+        );
+        assert_eq!(seen[0], None);
+        assert_eq!(seen[2], None);
+        let compiled = seen[1].clone().unwrap();
+        assert_eq!(compiled.file.as_deref(), Some("../Core/Src/main.c"));
+        assert_eq!((compiled.files_built, compiled.files_total, compiled.percent), (1, Some(2), Some(49)));
+        let linked = seen[3].clone().unwrap();
+        assert_eq!((linked.phase, linked.file.as_deref(), linked.percent), (BuildPhase::Linking, Some("blinky.elf"), Some(99)));
+    }
+}
@@ -0,0 +1,215 @@
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Build configuration saved in the project directory by the GUI; the CLI
+/// reads the same file.
+pub const PROJECT_CONFIG_FILE: &str = ".stm32guibuilder.json";
+
+/// A resource of the project that lives elsewhere, from the
+/// `<linkedResources>` of its `.project`.
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkedResource {
+    /// Path inside the project, e.g. `Drivers/CMSIS`.
+    pub name: String,
+    pub folder: bool,
+    /// Location as written, e.g. `PARENT-1-PROJECT_LOC/Drivers/CMSIS`.
+    pub location: String,
+    /// Where it is on disk; `None` for virtual folders and locations using
+    /// variables the project does not define.
+    pub resolved: Option<PathBuf>,
+}
+
+/// Name and linked resources of a `.project` file.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectDescription {
+    pub name: String,
+    pub linked_resources: Vec<LinkedResource>,
+}
+
+// Eclipse writes `${` and `}` in location URIs percent-encoded
+fn unescape_uri(value: &str) -> String {
+    value.replace("%7B", "{").replace("%7D", "}").replace("%20", " ")
+}
+
+/// Resolves a linked resource location: `PROJECT_LOC`, `PARENT-<n>-<var>`,
+/// `${var}`, the project's own path variables and `file:` URIs.
+fn resolve_location(location: &str, project_path: &Path, variables: &HashMap<String, String>, depth: usize) -> Option<PathBuf> {
+    // Variables defined in terms of each other must end somewhere
+    if depth > 8 {
+        return None;
+    }
+    let location = unescape_uri(location);
+    if location.starts_with("virtual:") {
+        return None;
+    }
+    if let Some(path) = location.strip_prefix("file:") {
+        // `file:/C:/fw` on Windows, `file:/home/fw` elsewhere
+        let path = match path.as_bytes() {
+            [b'/', _, b':', ..] => &path[1..],
+            _ => path,
+        };
+        return Some(PathBuf::from(path));
+    }
+    let (head, rest) = match location.strip_prefix("${").and_then(|l| l.split_once('}')) {
+        Some((variable, rest)) => (variable.to_string(), rest.trim_start_matches(['/', '\\']).to_string()),
+        None => match location.split_once(['/', '\\']) {
+            Some((head, rest)) => (head.to_string(), rest.to_string()),
+            None => (location.clone(), String::new()),
+        },
+    };
+    let base = if head == "PROJECT_LOC" {
+        project_path.to_path_buf()
+    } else if let Some((levels, variable)) = head.strip_prefix("PARENT-").and_then(|h| h.split_once('-')) {
+        let mut base = resolve_location(variable, project_path, variables, depth + 1)?;
+        for _ in 0..levels.parse::<usize>().ok()? {
+            base = base.parent()?.to_path_buf();
+        }
+        base
+    } else if let Some(value) = variables.get(&head) {
+        resolve_location(value, project_path, variables, depth + 1)?
+    } else if Path::new(&location).is_absolute() {
+        return Some(PathBuf::from(location));
+    } else {
+        return None;
+    };
+    Some(if rest.is_empty() { base } else { base.join(rest) })
+}
+
+/// Reads the project's `.project` file.
+pub fn read_description(project_path: &Path) -> Result<ProjectDescription, String> {
+    let project_file = project_path.join(".project");
+    if !project_file.exists() {
+        return Err(".project file not found".to_string());
+    }
+    let xml_content = fs::read_to_string(&project_file).map_err(|e| e.to_string())?;
+    let mut reader = Reader::from_str(&xml_content);
+    reader.config_mut().trim_text(true);
+
+    let mut description = ProjectDescription::default();
+    let mut variables = HashMap::new();
+    // Element names from the root down to the current one
+    let mut path: Vec<String> = Vec::new();
+    let mut link = (String::new(), false, String::new());
+    let mut variable = (String::new(), String::new());
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => path.push(String::from_utf8_lossy(e.name().as_ref()).into_owned()),
+            Ok(Event::End(e)) => {
+                match e.name().as_ref() {
+                    b"link" => {
+                        let (name, folder, location) = std::mem::take(&mut link);
+                        description.linked_resources.push(LinkedResource { name, folder, location, resolved: None });
+                    }
+                    b"variable" => {
+                        let (name, value) = std::mem::take(&mut variable);
+                        variables.insert(name, value);
+                    }
+                    _ => (),
+                }
+                path.pop();
+            }
+            Ok(Event::Text(text)) => {
+                let text = text.unescape().map_err(|e| e.to_string())?.into_owned();
+                let parents: Vec<&str> = path.iter().map(String::as_str).collect();
+                match parents.as_slice() {
+                    // `<name>` also appears in build commands and links
+                    ["projectDescription", "name"] => description.name = text,
+                    [.., "link", "name"] => link.0 = text,
+                    // 1 is a file, 2 a folder
+                    [.., "link", "type"] => link.1 = text == "2",
+                    [.., "link", "location" | "locationURI"] => link.2 = text,
+                    [.., "variable", "name"] => variable.0 = text,
+                    [.., "variable", "value"] => variable.1 = text,
+                    _ => (),
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(e.to_string()),
+            _ => (),
+        }
+    }
+    if description.name.is_empty() {
+        return Err("Project name not found in .project file".to_string());
+    }
+    for link in &mut description.linked_resources {
+        link.resolved = resolve_location(&link.location, project_path, &variables, 0);
+    }
+    Ok(description)
+}
+
+/// Name of the Eclipse project, from its `.project` file.
+pub fn project_name(project_path: &Path) -> Result<String, String> {
+    read_description(project_path).map(|description| description.name)
+}
+
+/// Where a path inside the project is on disk, following the linked
+/// resource it falls under, if any.
+pub fn resolve_project_path(project_path: &Path, linked_resources: &[LinkedResource], relative: &Path) -> PathBuf {
+    linked_resources
+        .iter()
+        .filter_map(|link| {
+            let rest = relative.strip_prefix(&link.name).ok()?;
+            Some((link.name.len(), link.resolved.as_ref()?.join(rest)))
+        })
+        // The innermost link wins
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, path)| path)
+        .unwrap_or_else(|| project_path.join(relative))
+}
+
+// Deeper trees are vendor SDKs rather than project folders
+const MAX_SEARCH_DEPTH: usize = 8;
+
+// Never hold a project of their own
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "Drivers", "Middlewares"];
+
+/// Eclipse project found under a folder.
+#[derive(Clone, Debug, Serialize)]
+pub struct FoundProject {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Whether `dir` is a configuration's build folder: CDT writes `objects.mk`
+/// into each of them, whatever the configuration is called.
+pub fn is_build_output(dir: &Path) -> bool {
+    dir.join("objects.mk").is_file()
+}
+
+/// Folders under `root` (included) holding both a `.project` and a
+/// `.cproject`, sorted by path. Hidden folders, build output and symlinks
+/// are not searched; nested projects (e.g. the cores of a dual-core MCU)
+/// are all returned.
+pub fn find_projects(root: &Path) -> Vec<FoundProject> {
+    let mut projects = Vec::new();
+    let mut dirs = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        if dir.join(".cproject").is_file()
+            && let Ok(name) = project_name(&dir)
+        {
+            projects.push(FoundProject { name, path: dir.clone() });
+        }
+        if depth == MAX_SEARCH_DEPTH {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let path = entry.path();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) || is_build_output(&path) {
+                continue;
+            }
+            dirs.push((path, depth + 1));
+        }
+    }
+    projects.sort_by(|a, b| a.path.cmp(&b.path));
+    projects
+}
@@ -0,0 +1,168 @@
+//! How the builds' processes are started: CubeIDE, or `make` for warm
+//! builds. The GUI starts them with its own priority and process tree
+//! handling, the CLI plainly, tests and dry runs not at all.
+
+use crate::command_line;
+use async_trait::async_trait;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncRead;
+
+/// Process the builder wants started.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessSpec {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    pub cwd: PathBuf,
+    pub envs: Vec<(String, String)>,
+}
+
+impl ProcessSpec {
+    /// Command line as logged; arguments with spaces are quoted.
+    pub fn command_line(&self) -> String {
+        command_line::display(std::iter::once(&self.program).chain(&self.args))
+    }
+}
+
+/// Handle of a started process.
+#[async_trait]
+pub trait ProcessHandle: Send + Sync {
+    fn id(&self) -> Option<u32>;
+    /// Exit code; -1 if the process was ended by a signal.
+    async fn wait(&mut self) -> io::Result<i32>;
+    async fn kill(&mut self) -> io::Result<()>;
+}
+
+pub struct RunningProcess {
+    pub stdout: Box<dyn AsyncRead + Send + Unpin>,
+    pub stderr: Box<dyn AsyncRead + Send + Unpin>,
+    pub handle: Box<dyn ProcessHandle>,
+    /// Scheduling that could not be applied; the process runs regardless.
+    pub warnings: Vec<String>,
+}
+
+/// Starts the processes of a build.
+pub trait ProcessRunner: Send + Sync {
+    fn spawn(&self, spec: &ProcessSpec) -> io::Result<RunningProcess>;
+}
+
+/// Starts nothing: every process prints its command line and `output`,
+/// then exits with `exit_code`. `files` are created empty first, unless
+/// they exist, so the stages looking for artifacts find them.
+#[derive(Clone, Debug, Default)]
+pub struct DryRunRunner {
+    pub exit_code: i32,
+    pub output: Vec<String>,
+    pub files: Vec<PathBuf>,
+}
+
+impl DryRunRunner {
+    /// Dry run of a build whose output goes to `build_dir`: the build
+    /// "produces" the `.bin` the builder expects there. Keep `build_dir`
+    /// out of the project, or the dry run's image replaces the real one.
+    pub fn for_build(build_dir: &Path, project_name: &str) -> Self {
+        DryRunRunner { files: vec![build_dir.join(format!("{}.bin", project_name.to_lowercase()))], ..Default::default() }
+    }
+}
+
+struct DryRunHandle(i32);
+
+#[async_trait]
+impl ProcessHandle for DryRunHandle {
+    fn id(&self) -> Option<u32> {
+        None
+    }
+
+    async fn wait(&mut self) -> io::Result<i32> {
+        Ok(self.0)
+    }
+
+    async fn kill(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ProcessRunner for DryRunRunner {
+    fn spawn(&self, spec: &ProcessSpec) -> io::Result<RunningProcess> {
+        for file in &self.files {
+            // Relative to the working directory like the process's own files
+            let path = spec.cwd.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            OpenOptions::new().create(true).append(true).open(&path)?;
+        }
+        let mut stdout = format!("[DRY RUN] {}\n", spec.command_line());
+        for line in &self.output {
+            stdout.push_str(line);
+            stdout.push('\n');
+        }
+        Ok(RunningProcess {
+            stdout: Box::new(Cursor::new(stdout.into_bytes())),
+            stderr: Box::new(tokio::io::empty()),
+            handle: Box::new(DryRunHandle(self.exit_code)),
+            warnings: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn spec(cwd: &Path) -> ProcessSpec {
+        ProcessSpec {
+            program: "stm32cubeide".into(),
+            args: vec!["-build".into(), "My Project/Debug".into()],
+            cwd: cwd.to_path_buf(),
+            envs: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_prints_the_command_line_and_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = DryRunRunner { exit_code: 2, output: vec!["main.c:1:1: error: boom".to_string()], files: Vec::new() };
+        let mut process = runner.spawn(&spec(dir.path())).unwrap();
+
+        let mut stdout = String::new();
+        process.stdout.read_to_string(&mut stdout).await.unwrap();
+        assert_eq!(stdout, "[DRY RUN] stm32cubeide -build \"My Project/Debug\"\nmain.c:1:1: error: boom\n");
+        let mut stderr = String::new();
+        process.stderr.read_to_string(&mut stderr).await.unwrap();
+        assert!(stderr.is_empty());
+        assert_eq!(process.handle.id(), None);
+        assert_eq!(process.handle.wait().await.unwrap(), 2);
+        process.handle.kill().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dry_run_creates_the_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = DryRunRunner { files: vec![Path::new("Debug").join("blinky.bin")], ..Default::default() };
+        let mut process = runner.spawn(&spec(dir.path())).unwrap();
+
+        assert_eq!(process.handle.wait().await.unwrap(), 0);
+        assert!(dir.path().join("Debug").join("blinky.bin").is_file());
+    }
+
+    #[test]
+    fn dry_run_keeps_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("Debug")).unwrap();
+        std::fs::write(dir.path().join("Debug").join("blinky.bin"), b"firmware").unwrap();
+        let runner = DryRunRunner::for_build(&dir.path().join("Debug"), "Blinky");
+        runner.spawn(&spec(dir.path())).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("Debug").join("blinky.bin")).unwrap(), b"firmware");
+    }
+
+    #[test]
+    fn dry_run_for_build_expects_the_lowercase_bin() {
+        let runner = DryRunRunner::for_build(Path::new("/out/.dry-run/Release"), "Blinky");
+        assert_eq!(runner.files, vec![Path::new("/out/.dry-run/Release").join("blinky.bin")]);
+        assert_eq!(runner.exit_code, 0);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::config::{parse_range_string, BuildSetting, BuildSettingsConfig};
+use std::collections::HashMap;
+
+/// One combination picked explicitly, as setting id -> value.
+pub type Selection = HashMap<String, String>;
+
+/// Finds the setting a column/key refers to: its id, its short `value`
+/// (as used in output folder names) or its label.
+fn find_setting<'a>(settings_config: &'a BuildSettingsConfig, name: &str) -> Option<&'a BuildSetting> {
+    let name = name.trim();
+    settings_config
+        .build_settings
+        .iter()
+        .find(|s| s.id == name)
+        .or_else(|| settings_config.build_settings.iter().find(|s| s.value == name))
+        .or_else(|| {
+            settings_config
+                .build_settings
+                .iter()
+                .find(|s| s.label.eq_ignore_ascii_case(name))
+        })
+}
+
+fn validate_value(setting: &BuildSetting, value: &str) -> Result<(), String> {
+    match setting.field_type.as_str() {
+        "range" => {
+            if let Some(validation) = &setting.validation {
+                let numbers = parse_range_string(value, validation.min, validation.max)?;
+                if numbers.len() != 1 {
+                    return Err(format!("Expected a single value for {}, got '{}'", setting.id, value));
+                }
+            }
+        }
+        "select" | "checkbox_group" => {
+            if let Some(options) = &setting.options
+                && !options.iter().any(|o| o.value == value)
+            {
+                return Err(format!(
+                    "Invalid value '{}' for {}. Valid options: {:?}",
+                    value,
+                    setting.id,
+                    options.iter().map(|o| &o.value).collect::<Vec<_>>()
+                ));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Maps keys to setting ids and checks every value against the schema.
+/// Empty values mean the setting is not part of the combination; rows
+/// without any value (blank spreadsheet lines) are dropped.
+pub fn normalize_selection(
+    settings_config: &BuildSettingsConfig,
+    rows: Vec<HashMap<String, String>>,
+) -> Result<Vec<Selection>, String> {
+    let mut selections = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        let mut selection = Selection::new();
+        for (key, value) in row {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let setting = find_setting(settings_config, &key)
+                .ok_or_else(|| format!("Row {}: unknown build setting '{}'", index + 1, key))?;
+            validate_value(setting, value).map_err(|e| format!("Row {}: {}", index + 1, e))?;
+            selection.insert(setting.id.clone(), value.to_string());
+        }
+        if !selection.is_empty() {
+            selections.push(selection);
+        }
+    }
+    Ok(selections)
+}
+
+/// Turns explicit selections into build combinations, ordered like the
+/// schema so folder and file names match those of a full matrix build.
+pub fn selection_combinations(
+    settings_config: &BuildSettingsConfig,
+    selections: &[Selection],
+) -> Result<Vec<Vec<(String, String)>>, String> {
+    let mut combinations = Vec::new();
+    for (index, selection) in selections.iter().enumerate() {
+        let mut combination = Vec::new();
+        for setting in &settings_config.build_settings {
+            match selection.get(&setting.id) {
+                Some(value) => {
+                    validate_value(setting, value).map_err(|e| format!("Combination {}: {}", index + 1, e))?;
+                    combination.push((setting.id.clone(), value.clone()));
+                }
+                None if setting.field_type == "select" || setting.min_selected.unwrap_or(0) > 0 => {
+                    return Err(format!("Combination {}: no value for required setting '{}'", index + 1, setting.id));
+                }
+                None => {}
+            }
+        }
+        if let Some(unknown) = selection
+            .keys()
+            .find(|id| !settings_config.build_settings.iter().any(|s| &s.id == *id))
+        {
+            return Err(format!("Combination {}: unknown build setting '{}'", index + 1, unknown));
+        }
+        if !combinations.contains(&combination) {
+            combinations.push(combination);
+        }
+    }
+    Ok(combinations)
+}
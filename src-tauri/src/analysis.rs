@@ -0,0 +1,96 @@
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use stm32_builder_core::build_config_gen::header_defines;
+use tokio::process::Command;
+
+/// What a post-build gate does with a combination that does not pass it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GateAction {
+    /// Report the findings and keep the combination.
+    #[default]
+    Warn,
+    Fail,
+}
+
+/// A combination that has just been built, as seen by the post-build stages.
+pub struct CombinationBuild<'a> {
+    pub project_path: &'a Path,
+    /// CubeIDE's output folder, e.g. `<project>/Debug`.
+    pub build_dir: &'a Path,
+    /// Folder of the combination in the run's output directory.
+    pub combo_dir: &'a Path,
+    /// Linked image, if CubeIDE produced one.
+    pub elf: Option<&'a Path>,
+    /// Linker map, if the project writes one.
+    pub map: Option<&'a Path>,
+    /// Generated `build_config.h`.
+    pub header: &'a str,
+}
+
+impl CombinationBuild<'_> {
+    /// `-D` flags for the macros the generated header leaves defined, as
+    /// the compiler saw them, and `-U` for those it undefines for good.
+    pub fn define_flags(&self) -> Vec<String> {
+        let defines = header_defines(self.header);
+        let defined = |name: &str| defines.iter().any(|d| d.split_once('=').map_or(d.as_str(), |(n, _)| n) == name);
+        let mut undefined: Vec<&str> = Vec::new();
+        for line in self.header.lines() {
+            if let Some(name) = line.trim().strip_prefix("#undef ").map(str::trim)
+                && !defined(name)
+                && !undefined.contains(&name)
+            {
+                undefined.push(name);
+            }
+        }
+        defines
+            .iter()
+            .map(|define| format!("-D{}", define))
+            .chain(undefined.into_iter().map(|name| format!("-U{}", name)))
+            .collect()
+    }
+}
+
+/// Runs an analysis tool in the project directory and returns its exit
+/// code with stdout and stderr lines.
+pub async fn run_tool(program: &str, args: &[String], dir: &Path) -> Result<(i32, Vec<String>, Vec<String>), String> {
+    let mut command = Command::new(program);
+    command.args(args).current_dir(dir).kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command
+        .output()
+        .await
+        .map_err(|e| tr!("tool.start_failed", program, e))?;
+    let lines = |bytes: &[u8]| String::from_utf8_lossy(bytes).lines().map(str::to_string).collect::<Vec<_>>();
+    Ok((output.status.code().unwrap_or(-1), lines(&output.stdout), lines(&output.stderr)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_follow_the_header_order() {
+        let header = "#ifndef BUILD_CONFIG_H_\n#define BUILD_CONFIG_H_\n\n\
+                      #define BOARD_F4\n#undef BOARD_H7\n\
+                      #ifndef CLOCK_MHZ\n#define CLOCK_MHZ 168\n#endif\n\
+                      #define FW_VERSION \"1.2\"\n#define DEBUG_SET\n#undef DEBUG_SET\n\
+                      #undef BOARD_F4\n#define BOARD_F4 2\n\n#endif // BUILD_CONFIG_H_\n";
+        let build = CombinationBuild {
+            project_path: Path::new("."),
+            build_dir: Path::new("Debug"),
+            combo_dir: Path::new("out"),
+            elf: None,
+            map: None,
+            header,
+        };
+        assert_eq!(
+            build.define_flags(),
+            vec!["-DCLOCK_MHZ=168", "-DFW_VERSION=\"1.2\"", "-DBOARD_F4=2", "-UBOARD_H7", "-UDEBUG_SET"]
+        );
+    }
+}
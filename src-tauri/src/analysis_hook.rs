@@ -0,0 +1,177 @@
+use crate::analysis::{run_tool, CombinationBuild};
+use crate::compile_db::{split_command, COMPILE_COMMANDS_FILE};
+use stm32_builder_core::diagnostics::{self, Diagnostic, Severity};
+use crate::i18n::tr;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+/// How the output of an analysis hook is read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFormat {
+    /// `file:line:col: warning: message`, optionally ending in ` [id]`.
+    #[default]
+    Gcc,
+    /// One finding per line matching `pattern`.
+    Regex,
+    /// A SARIF 2.1 log.
+    Sarif,
+}
+
+/// External checker (PC-lint, Coverity, a MISRA checker...) run after every
+/// successful combination.
+///
+/// `command` is split into arguments like a shell would and may use
+/// `{project}`, `{build_dir}`, `{combo_dir}`, `{header}` and
+/// `{compile_commands}`; an argument that is exactly `{defines}` expands to
+/// the combination's `-D`/`-U` flags.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnalysisHook {
+    /// Shown in the log and prefixed to finding ids, e.g. `pclint:9003`.
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub format: HookFormat,
+    /// For `regex`: named groups `message` and optionally `file`, `line`,
+    /// `column`, `severity` and `id`.
+    pub pattern: Option<String>,
+    /// File the tool writes its results to (same placeholders as
+    /// `command`); stdout is read otherwise.
+    pub output: Option<String>,
+}
+
+fn expand(template: &str, build: &CombinationBuild<'_>) -> String {
+    template
+        .replace("{project}", &build.project_path.display().to_string())
+        .replace("{build_dir}", &build.build_dir.display().to_string())
+        .replace("{combo_dir}", &build.combo_dir.display().to_string())
+        .replace("{header}", &build.combo_dir.join("build_config.h").display().to_string())
+        .replace("{compile_commands}", &build.combo_dir.join(COMPILE_COMMANDS_FILE).display().to_string())
+}
+
+fn severity(text: &str) -> Severity {
+    match text.to_lowercase().as_str() {
+        "error" | "fatal" | "fatal error" | "high" => Severity::Error,
+        "note" | "info" | "information" | "none" | "low" => Severity::Note,
+        _ => Severity::Warning,
+    }
+}
+
+fn tagged(hook: &AnalysisHook, id: Option<&str>) -> Option<String> {
+    Some(match id {
+        Some(id) => format!("{}:{}", hook.name, id),
+        None => hook.name.clone(),
+    })
+}
+
+fn parse_gcc(hook: &AnalysisHook, lines: &[String]) -> Vec<Diagnostic> {
+    lines
+        .iter()
+        .filter_map(|line| diagnostics::parse_line(line))
+        .map(|mut diagnostic| {
+            // Compiler-style tools put their own id where gcc puts the warning option
+            let id = match diagnostic.message.rfind(" [") {
+                Some(start) if diagnostic.message.ends_with(']') => {
+                    let id = diagnostic.message[start + 2..diagnostic.message.len() - 1].to_string();
+                    diagnostic.message.truncate(start);
+                    Some(id)
+                }
+                _ => diagnostic.option.take(),
+            };
+            diagnostic.option = tagged(hook, id.as_deref());
+            diagnostic
+        })
+        .collect()
+}
+
+fn parse_regex(hook: &AnalysisHook, lines: &[String]) -> Result<Vec<Diagnostic>, String> {
+    let pattern = hook.pattern.as_deref().ok_or_else(|| tr!("hook.pattern_missing", hook.name))?;
+    let regex = Regex::new(pattern).map_err(|e| tr!("hook.pattern_invalid", hook.name, e))?;
+    Ok(lines
+        .iter()
+        .filter_map(|line| regex.captures(line.trim()))
+        .filter_map(|caps| {
+            let group = |name: &str| caps.name(name).map(|m| m.as_str());
+            Some(Diagnostic {
+                severity: group("severity").map(severity).unwrap_or(Severity::Warning),
+                message: group("message")?.to_string(),
+                file: group("file").map(str::to_string),
+                line: group("line").and_then(|l| l.parse().ok()),
+                column: group("column").and_then(|c| c.parse().ok()),
+                option: tagged(hook, group("id")),
+                fixits: Vec::new(),
+            })
+        })
+        .collect())
+}
+
+fn parse_sarif(hook: &AnalysisHook, text: &str) -> Result<Vec<Diagnostic>, String> {
+    let log: Value = serde_json::from_str(text).map_err(|e| tr!("hook.sarif_invalid", hook.name, e))?;
+    let results = log["runs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|run| run["results"].as_array().into_iter().flatten());
+    Ok(results
+        .map(|result| {
+            let location = &result["locations"][0]["physicalLocation"];
+            let region = &location["region"];
+            Diagnostic {
+                severity: result["level"].as_str().map(severity).unwrap_or(Severity::Warning),
+                message: result["message"]["text"].as_str().unwrap_or_default().to_string(),
+                file: location["artifactLocation"]["uri"]
+                    .as_str()
+                    .map(|uri| uri.trim_start_matches("file://").to_string()),
+                line: region["startLine"].as_u64().map(|l| l as u32),
+                column: region["startColumn"].as_u64().map(|c| c as u32),
+                option: tagged(hook, result["ruleId"].as_str()),
+                fixits: Vec::new(),
+            }
+        })
+        .collect())
+}
+
+/// Runs the hook for a combination and returns its findings. The tool's
+/// exit code is ignored: most checkers exit non-zero when they find anything.
+pub async fn run(hook: &AnalysisHook, build: &CombinationBuild<'_>) -> Result<Vec<Diagnostic>, String> {
+    let mut words = split_command(&hook.command).into_iter();
+    let program = words.next().map(|p| expand(&p, build)).ok_or_else(|| tr!("hook.command_empty", hook.name))?;
+    let mut args = Vec::new();
+    for word in words {
+        if word == "{defines}" {
+            args.extend(build.define_flags());
+        } else {
+            args.push(expand(&word, build));
+        }
+    }
+
+    let output_file = hook.output.as_deref().map(|path| expand(path, build));
+    if let Some(path) = &output_file {
+        // Do not pick up the results of an earlier combination
+        fs::remove_file(path).ok();
+    }
+    let (_, stdout, stderr) = run_tool(&program, &args, build.project_path).await?;
+    let lines = match &output_file {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| tr!("fs.read", path, e))?
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        None => stdout.into_iter().chain(stderr).collect::<Vec<_>>(),
+    };
+
+    let parsed = match hook.format {
+        HookFormat::Gcc => parse_gcc(hook, &lines),
+        HookFormat::Regex => parse_regex(hook, &lines)?,
+        HookFormat::Sarif => parse_sarif(hook, &lines.join("\n"))?,
+    };
+    let mut findings: Vec<Diagnostic> = Vec::new();
+    for finding in parsed {
+        if !findings.contains(&finding) {
+            findings.push(finding);
+        }
+    }
+    Ok(findings)
+}
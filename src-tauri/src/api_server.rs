@@ -0,0 +1,314 @@
+use crate::builder::run_project;
+use crate::cancel::cancel_build;
+use crate::history;
+use crate::i18n::tr;
+use crate::logging::emit_to_window;
+use crate::models::BuildResult;
+use crate::project_config::load_project_config;
+use crate::recent::{self, RecentKind};
+use axum::extract::{Path as UrlPath, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use stm32_builder_core::builder::new_run_id;
+use stm32_builder_core::paths;
+use stm32_builder_core::selection::Selection;
+use tauri::{command, Manager, Window};
+use tokio::sync::oneshot;
+
+const DEFAULT_PORT: u16 = 8765;
+
+/// The REST API, registered with `app.manage()`: address, token and stop
+/// signal of the running server, and the run started through it or the
+/// outcome of the last one.
+#[derive(Default)]
+pub struct ApiServer {
+    server: Mutex<ServerState>,
+    run: Mutex<ApiRunStatus>,
+}
+
+#[derive(Default)]
+enum ServerState {
+    #[default]
+    Stopped,
+    /// Reserved by a start that is still binding, so a second start fails
+    /// instead of racing it.
+    Starting,
+    Running(ApiServerInfo, oneshot::Sender<()>),
+}
+
+/// Where the server listens and the token clients send as
+/// `Authorization: Bearer <token>`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ApiServerInfo {
+    pub address: String,
+    pub token: String,
+}
+
+/// Outcome of one combination, as reported by `GET /api/status`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ApiCombinationStatus {
+    pub combination: String,
+    pub success: bool,
+    pub failure_reason: Option<String>,
+    pub artifact: Option<String>,
+}
+
+/// Body of `GET /api/status`: the run started through the API.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApiRunStatus {
+    pub running: bool,
+    pub run_id: Option<String>,
+    /// Unset while the run is in progress.
+    pub success: Option<bool>,
+    pub cancelled: bool,
+    pub result: Option<String>,
+    pub combinations: Vec<ApiCombinationStatus>,
+}
+
+impl ApiRunStatus {
+    fn finished(run_id: String, result: &BuildResult) -> Self {
+        ApiRunStatus {
+            running: false,
+            run_id: Some(run_id),
+            success: Some(result.success),
+            cancelled: result.cancelled,
+            result: Some(result.result.clone()),
+            combinations: result
+                .combinations
+                .iter()
+                .map(|c| ApiCombinationStatus {
+                    combination: c.combination.clone(),
+                    success: c.success,
+                    failure_reason: c.failure_reason.clone(),
+                    artifact: c.artifact.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Body of `POST /api/builds`. Only configurations saved from the GUI are
+/// built, so a client cannot choose the executable, scripts or plugins a
+/// build runs; unknown fields are rejected rather than ignored.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiBuildRequest {
+    /// Project folder of the saved configuration; one of the recent projects.
+    #[serde(rename = "configId")]
+    pub config_id: String,
+    /// Combinations to build instead of the saved form values.
+    pub selection: Option<Vec<Selection>>,
+}
+
+/// File of a run's output directory, relative to it.
+#[derive(Debug, Serialize)]
+pub struct ApiArtifact {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    window: Window,
+    token: String,
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+// Takes as long for every token of the right length, so the response time
+// does not tell how much of a guess matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && std::hint::black_box(a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y))) == 0
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| constant_time_eq(token.as_bytes(), state.token.as_bytes())) {
+        return error(StatusCode::UNAUTHORIZED, tr!("api.unauthorized"));
+    }
+    next.run(request).await
+}
+
+async fn status(State(state): State<ApiState>) -> Json<ApiRunStatus> {
+    Json(state.window.state::<ApiServer>().run.lock().unwrap().clone())
+}
+
+/// Starts a build of a saved configuration and answers with its run id
+/// right away; progress is polled through `GET /api/status`.
+async fn start_build(State(state): State<ApiState>, Json(request): Json<ApiBuildRequest>) -> Response {
+    let window = state.window.clone();
+    // Only projects opened in the GUI, with the configuration saved there
+    let known = paths::canonicalize(Path::new(&request.config_id)).ok().filter(|wanted| {
+        recent::entries(&window, RecentKind::Project)
+            .unwrap_or_default()
+            .iter()
+            .any(|project| paths::canonicalize(Path::new(project)).ok().as_ref() == Some(wanted))
+    });
+    let saved = match known {
+        Some(_) => load_project_config(request.config_id.clone()).await,
+        None => Ok(None),
+    };
+    let mut config = match saved {
+        Ok(Some(config)) => config,
+        Ok(None) => return error(StatusCode::NOT_FOUND, tr!("api.unknown_config", request.config_id)),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+    if let Some(selection) = request.selection {
+        config.selected_combinations = Some(selection);
+    }
+
+    let run_id = new_run_id();
+    {
+        let api = window.state::<ApiServer>();
+        let mut run = api.run.lock().unwrap();
+        if run.running {
+            return error(StatusCode::CONFLICT, tr!("api.busy", run.run_id.clone().unwrap_or_default()));
+        }
+        *run = ApiRunStatus { running: true, run_id: Some(run_id.clone()), ..Default::default() };
+    }
+    let id = run_id.clone();
+    tokio::spawn(async move {
+        let outcome = run_project(window.clone(), config, id.clone()).await;
+        *window.state::<ApiServer>().run.lock().unwrap() = match outcome {
+            Ok(result) => ApiRunStatus::finished(id, &result),
+            Err(e) => ApiRunStatus {
+                run_id: Some(id),
+                success: Some(false),
+                result: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+    });
+    (StatusCode::ACCEPTED, Json(json!({ "run_id": run_id }))).into_response()
+}
+
+async fn cancel(State(state): State<ApiState>) -> Response {
+    let run_id = {
+        let api = state.window.state::<ApiServer>();
+        let run = api.run.lock().unwrap();
+        if !run.running {
+            return error(StatusCode::CONFLICT, tr!("api.idle"));
+        }
+        run.run_id.clone()
+    };
+    match cancel_build(state.window.clone(), run_id).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+fn list_files(dir: &Path) -> Vec<ApiArtifact> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&current) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            files.push(ApiArtifact {
+                path: path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().replace('\\', "/"),
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Files in the output directory of a recorded run.
+async fn artifacts(State(state): State<ApiState>, UrlPath(run_id): UrlPath<String>) -> Response {
+    match history::get_run(state.window.clone(), run_id).await {
+        Ok(run) => Json(list_files(Path::new(&run.summary.output_dir))).into_response(),
+        Err(e) => error(StatusCode::NOT_FOUND, e),
+    }
+}
+
+/// Starts the local REST API on `127.0.0.1:<port>` (8765 by default):
+///
+/// - `POST /api/builds` with an [`ApiBuildRequest`] body starts a build
+/// - `GET /api/status` reports the build started through the API
+/// - `POST /api/cancel` cancels it
+/// - `GET /api/runs/<run_id>/artifacts` lists a run's output files
+///
+/// Every request must carry the returned token; a random one is generated
+/// unless `token` is given.
+#[command]
+pub async fn start_api_server(window: Window, port: Option<u16>, token: Option<String>) -> Result<ApiServerInfo, String> {
+    {
+        let api = window.state::<ApiServer>();
+        let mut server = api.server.lock().unwrap();
+        match &*server {
+            ServerState::Running(info, _) => return Err(tr!("api.already_running", info.address)),
+            ServerState::Starting => return Err(tr!("api.starting")),
+            ServerState::Stopped => *server = ServerState::Starting,
+        }
+    }
+    let address = format!("127.0.0.1:{}", port.unwrap_or(DEFAULT_PORT));
+    let listener = match tokio::net::TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            *window.state::<ApiServer>().server.lock().unwrap() = ServerState::Stopped;
+            return Err(tr!("api.bind_failed", address, e));
+        }
+    };
+    let token = token.filter(|t| !t.is_empty()).unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+
+    let state = ApiState { window: window.clone(), token: token.clone() };
+    let app = Router::new()
+        .route("/api/builds", post(start_build))
+        .route("/api/status", get(status))
+        .route("/api/cancel", post(cancel))
+        .route("/api/runs/{run_id}/artifacts", get(artifacts))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state);
+
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server_window = window.clone();
+    tokio::spawn(async move {
+        let server = axum::serve(listener, app).with_graceful_shutdown(async {
+            stopped.await.ok();
+        });
+        if let Err(e) = server.await {
+            // The server is gone; let the window know and allow a restart
+            *server_window.state::<ApiServer>().server.lock().unwrap() = ServerState::Stopped;
+            emit_to_window(&server_window, "api-server-error", tr!("api.server_failed", e));
+        }
+    });
+
+    let info = ApiServerInfo { address, token };
+    *window.state::<ApiServer>().server.lock().unwrap() = ServerState::Running(info.clone(), stop);
+    Ok(info)
+}
+
+#[command]
+pub async fn stop_api_server(window: Window) -> Result<(), String> {
+    let api = window.state::<ApiServer>();
+    let mut server = api.server.lock().unwrap();
+    let stop = match std::mem::take(&mut *server) {
+        ServerState::Running(_, stop) => stop,
+        // A start in progress keeps its reservation
+        other => {
+            *server = other;
+            return Err(tr!("api.not_running"));
+        }
+    };
+    stop.send(()).ok();
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use tauri::command;
+
+/// Last bytes of a `.bin` carrying a footer.
+pub const FOOTER_MAGIC: &[u8; 8] = b"SGBFOOT1";
+
+// JSON length (u32, little-endian) and the magic
+const TRAILER_LEN: usize = 4 + FOOTER_MAGIC.len();
+
+/// Build settings appended to a `.bin`, so an image read back from a
+/// device can be traced to its combination without the run manifest.
+///
+/// Laid out as the JSON record, its length as a little-endian `u32`, then
+/// [`FOOTER_MAGIC`]. Nothing in it changes between identical builds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BinFooter {
+    pub project: String,
+    pub configuration: String,
+    /// Setting ids and the values of the combination.
+    pub combination: BTreeMap<String, String>,
+    pub git_hash: Option<String>,
+    pub git_dirty: Option<bool>,
+    /// Version label, e.g. `v1.2.3-b45`.
+    pub version: Option<String>,
+}
+
+impl BinFooter {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = serde_json::to_vec(self).unwrap_or_default();
+        let len = bytes.len() as u32;
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(FOOTER_MAGIC);
+        bytes
+    }
+
+    /// Footer at the end of `image`, if it has one.
+    pub fn decode(image: &[u8]) -> Option<BinFooter> {
+        let trailer = image.len().checked_sub(TRAILER_LEN)?;
+        if &image[trailer + 4..] != FOOTER_MAGIC {
+            return None;
+        }
+        let len = u32::from_le_bytes(image[trailer..trailer + 4].try_into().ok()?) as usize;
+        let json = image.get(trailer.checked_sub(len)?..trailer)?;
+        serde_json::from_slice(json).ok()
+    }
+
+    pub fn append_to(&self, bin: &Path) -> Result<(), String> {
+        let mut file = OpenOptions::new().append(true).open(bin).map_err(|e| tr!("fs.write", bin.display(), e))?;
+        file.write_all(&self.encode()).map_err(|e| tr!("fs.write", bin.display(), e))
+    }
+}
+
+/// Build settings recorded at the end of a `.bin`; `None` when it was
+/// built without a footer.
+#[command]
+pub async fn read_bin_footer(path: String) -> Result<Option<BinFooter>, String> {
+    let image = fs::read(&path).map_err(|e| tr!("fs.read", path, e))?;
+    Ok(BinFooter::decode(&image))
+}
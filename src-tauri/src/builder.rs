@@ -1,48 +1,435 @@
 use crate::{
-    build_combinations::generate_build_combinations,
-    build_config_gen::generate_build_config_h,
-    models::{BuildConfig, BuildResult},
-    process::{BUILD_CANCEL_NOTIFY, BUILD_CONFIG, BUILD_CHILD},
-    utils::{/* log_with_timestamp, */ get_project_name, get_cproject_configurations, LogLevel, validate_project_file, validate_cproject_file},
-    config::{BuildSettingsConfig, parse_range_string, load_build_settings_schema},
-    logging::Logger
+    models::{BuildConfig, BuildResult, CombinationResult, DirtyTreePolicy, StageStatus},
+    failure_hints::{self, FailureCode},
+    error::BuildError,
+    elf_size,
+    process::{BuildManager, TrackedChild},
+    utils::{get_project_name, get_cproject_configurations, LogLevel, validate_project_file, validate_cproject_file},
+    config::load_build_settings_schema,
+    logging::{emit_to_window, Logger, WindowEvents},
+    log_storage::{prune_runs_async, rotate_log_async, LogRetention},
+    log_index,
+    history,
+    recent::{self, RecentKind},
+    project_config,
+    report,
+    provenance,
+    git_info::{self, GitInfo, TagSettings},
+    versioning::{self, FirmwareVersion},
+    reproducible,
+    analysis::{CombinationBuild, GateAction},
+    cppcheck,
+    clang_tidy,
+    analysis_hook,
+    symbol_gate,
+    elf_check,
+    renode,
+    qemu,
+    simulation::SimulationResult,
+    size_gate,
+    map_file::{self, UNUSED_CODE_FILE},
+    bin_footer::BinFooter,
+    signing,
+    warm_build::MakeTools,
+    unit_tests::{self, TestStage, UnitTestReport, UnitTestSettings},
+    compile_db::{self, COMPILE_COMMANDS_FILE},
+    manifest::RunManifest,
+    resources,
+    webhook,
+    notifications::{self, BudgetTimer},
+    plugins::{self, PluginManifest, PluginStage, StepContext},
+    scripting::{self, HookOutcome, RunScript},
+    runner::SystemRunner,
+    preflight::{self, CheckStatus, PreflightReport},
+    i18n::tr
 };
-use serde_json;
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::Path;
-use tauri::{command, Window, Emitter};
-use tokio::process::Command;
-use tokio::time::{self, Duration};
-use tokio::sync::Notify;
-use std::sync::Arc;
-
-// Add platform-specific imports
-#[cfg(unix)]
-use std::os::unix::process::CommandExt;
-
-// Helper function for formatting setting messages
+use async_trait::async_trait;
+use chrono::Local;
+use tokio::fs;
+use std::path::{Path, PathBuf};
+use stm32_builder_core::{
+    build_combinations::{generate_build_combinations, missing_required},
+    build_config_gen::header_defines,
+    builder::{build_target, find_output, new_run_id},
+    config::{BuildSettingsConfig, parse_range_string},
+    cproject::{header_placement, DEFAULT_HEADER_DIR},
+    cproject_defines::DefinesError,
+    matrix::{build_matrix, Combination, Matrix, MatrixError, MatrixEvent, MatrixHooks, MatrixStep, Proceed},
+    paths,
+    pipeline::{Failure, FailurePolicy, MacroTarget},
+    project::{read_description, resolve_project_path},
+    runner::{DryRunRunner, ProcessRunner, ProcessSpec},
+    selection::selection_combinations,
+};
+use tauri::{command, Window, Emitter, Manager};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio::time::Duration;
+
+/// Emitted with the `CombinationResult` of each combination as soon as it
+/// is done, successful or not.
+pub const COMBINATION_FINISHED: &str = "combination-finished";
+
+/// Every run writes into its own `build_dir/<run_id>/`, so successive runs
+/// never overwrite each other's artifacts.
+fn run_output_dir(build_dir: &Path, run_id: &str) -> PathBuf {
+    build_dir.join(run_id)
+}
+
+/// Subfolder of the run's folder a dry run "builds" into; removed after the run.
+const DRY_RUN_DIR: &str = ".dry-run";
+
+/// Marks the combination that was just built as failed by a post-build
+/// gate and ends the run.
+fn fail_combination(
+    logger: &mut Logger<'_>,
+    combinations: &mut [CombinationResult],
+    combo_name: &str,
+    reason: String,
+) -> BuildResult {
+    let msg = logger.error(&tr!("combination.failed", combo_name, reason));
+    if let Some(last) = combinations.last_mut() {
+        last.success = false;
+        last.failure_reason = Some(reason.clone());
+    }
+    BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, failure_reason: Some(reason), ..Default::default() }
+}
+
+/// Ends the run with `error`, logged as its result.
+fn fail_run(logger: &mut Logger<'_>, error: BuildError) -> BuildResult {
+    let msg = logger.error(&error.to_string());
+    BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, error: Some(error), ..Default::default() }
+}
+
+/// Runs the unit test stage; returns the run's result if the tests fail it.
+async fn unit_test_stage(
+    logger: &mut Logger<'_>,
+    settings: &UnitTestSettings,
+    project_path: &Path,
+    report: &mut Option<UnitTestReport>,
+) -> Option<BuildResult> {
+    logger.set_stage("unit_tests");
+    logger.begin_step("unit_tests", &tr!("step.unit_tests"));
+    let reason = match unit_tests::run(settings, project_path).await {
+        Ok(tests) => {
+            let passed = tests.passed();
+            let summary = tr!("tests.summary", tests.tests, tests.failures, tests.errors, tests.skipped);
+            *report = Some(tests);
+            if passed {
+                logger.info(&summary);
+                return None;
+            }
+            summary
+        }
+        Err(e) => e,
+    };
+    if settings.action == GateAction::Fail {
+        let msg = logger.error(&tr!("tests.failed", reason));
+        return Some(BuildResult { result: msg.clone(), logs: logger.get_logs().clone(), success: false, failure_reason: Some(msg), ..Default::default() });
+    }
+    logger.warning(&tr!("tests.failed", reason));
+    None
+}
+
+/// Calls a hook of the run script and logs what it printed.
+fn script_hook(logger: &mut Logger<'_>, script: &mut RunScript, hook: &str, context: rhai::Map) -> Result<HookOutcome, String> {
+    logger.set_stage("script");
+    let outcome = script.call(hook, context);
+    for line in script.take_output() {
+        logger.info(&line);
+    }
+    outcome
+}
+
+/// Runs a plugin step; returns the reason if it fails the combination.
+async fn plugin_step(
+    logger: &mut Logger<'_>,
+    plugin: &PluginManifest,
+    build: &CombinationBuild<'_>,
+    context: &StepContext<'_>,
+) -> Result<(), String> {
+    logger.set_stage("plugins");
+    logger.begin_step("plugin", &tr!("step.plugin", plugin.name, context.combination));
+    match plugins::run(plugin, build, context).await {
+        Ok(output) => {
+            for line in &output {
+                logger.debug(line);
+            }
+            logger.info(&tr!("plugin.done", plugin.name));
+            Ok(())
+        }
+        Err(reason) if plugin.action == GateAction::Fail => Err(reason),
+        Err(reason) => {
+            logger.warning(&reason);
+            Ok(())
+        }
+    }
+}
+
 fn format_setting_message(setting_id: &str, value: &serde_json::Value) -> String {
     format!("Setting '{}' with value '{}'", setting_id, value)
 }
 
+/// A `.cproject` or header error in the user's language.
+fn defines_error(error: DefinesError) -> String {
+    match error {
+        DefinesError::Parse(e) => tr!("cproject.parse", e),
+        DefinesError::NoSymbols(config_name) => tr!("cproject.no_symbols", config_name),
+        DefinesError::Read(path, e) => tr!("fs.read", path.display(), e),
+        DefinesError::Write(path, e) => tr!("fs.write", path.display(), e),
+    }
+}
+
+/// Why a combination failed, in the user's language.
+fn failure_reason(failure: &Failure) -> String {
+    match failure {
+        Failure::Build { reason: Some(reason), .. } => reason.clone(),
+        Failure::Build { exit_code, reason: None } => tr!("combination.exit_code", exit_code),
+        Failure::PromotedWarnings { count, first } => tr!("warnings.promoted", count, first),
+        Failure::TooManyWarnings { warnings, max } => tr!("warnings.exceeded", warnings, max),
+    }
+}
+
 #[command]
-pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildResult, tauri::Error> {
+pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildResult, BuildError> {
+    run_project(window, config, new_run_id()).await
+}
+
+/// Adds the run's paths to the recent lists and keeps its configuration as
+/// the one restored on the next start.
+fn remember_config(logger: &mut Logger<'_>, window: &Window, config: &BuildConfig) {
+    for (kind, value) in [
+        (RecentKind::Project, &config.project_path),
+        (RecentKind::Workspace, &config.workspace_path),
+        (RecentKind::CubeIde, &config.cube_ide_exe_path),
+        (RecentKind::OutputDir, &config.build_dir),
+    ] {
+        if let Err(e) = recent::add_entry(window, kind, value) {
+            logger.warning(&e);
+        }
+    }
+    if let Err(e) = project_config::save_last_config(window, config) {
+        logger.warning(&e);
+    }
+}
+
+/// `build_project` under a run id chosen by the caller, e.g. the REST API,
+/// which hands it out before the run finishes.
+pub async fn run_project(window: Window, config: BuildConfig, run_id: String) -> Result<BuildResult, BuildError> {
+    let started_at = Local::now().to_rfc3339();
     let mut logger = Logger::new(&window);
-    let mut stages = Vec::new();
+    logger.set_run_id(&run_id);
+    let mut record = RunRecord::default();
+    // Dry runs swap in their own runner once the run's folder is known
+    let runner = SystemRunner { priority: window.state::<BuildManager>().priority() };
+    let mut git = git_info::detect(Path::new(&config.project_path));
+    if config.clean_export.unwrap_or(false) {
+        // Local edits are not part of a build from a clean export
+        if let Some(git) = git.as_mut() {
+            git.dirty = false;
+        }
+    }
+    let version = if config.versioning.unwrap_or(false) {
+        match versioning::next_build(&window, &config.project_path) {
+            Ok(version) => Some(version),
+            Err(e) => {
+                logger.warning(&e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let cancel = window.state::<BuildManager>().start_run(&run_id, window.label());
+    let context = RunContext { run_id: &run_id, git: git.as_ref(), version: version.as_ref(), runner: &runner, cancel: &cancel };
+    let outcome = run_build(&mut logger, &window, config.clone(), context, &mut record).await;
+    window.state::<BuildManager>().end_run(&run_id);
+    let mut result = outcome?;
+    let RunRecord { combinations, preflight, unit_tests: unit_test_report, script: mut run_script } = record;
+    // A failed combination ends the run before the loop reports it
+    if let Some(failed) = combinations.last().filter(|c| !c.success) {
+        emit_to_window(&window, COMBINATION_FINISHED, failed);
+    }
+    logger.finish_stage();
+    logger.end_step(if result.cancelled {
+        StageStatus::Cancelled
+    } else if result.success {
+        StageStatus::Completed
+    } else {
+        StageStatus::Failed
+    });
+    result.stages = logger.get_steps().clone();
+    result.preflight = preflight;
+    result.unit_tests = unit_test_report;
+    result.run_id = run_id.clone();
+    result.stage_timings = logger.get_stage_timings().clone();
+    result.total_errors = combinations.iter().map(|c| c.errors).sum();
+    result.total_warnings = combinations.iter().map(|c| c.warnings).sum();
+    result.combinations = combinations;
+
+    // Record what this run produced next to its artifacts
+    let output_dir = run_output_dir(Path::new(&config.build_dir), &run_id);
+    let mut manifest = RunManifest::new(&config, &result, &output_dir, started_at);
+    manifest.provenance = Some(provenance::collect(&window, &config));
+    manifest.git = git;
+    manifest.version = version;
+    if config.reproducible.unwrap_or(false) {
+        let report = reproducible::ReproducibilityReport {
+            source_date_epoch: reproducible::source_date_epoch(manifest.git.as_ref()),
+            prefix_map_flags: reproducible::prefix_map_flags(Path::new(&config.project_path)),
+            ..Default::default()
+        };
+        manifest.reproducibility = Some(report);
+        if result.success {
+            let previous = history::history_path(&window).and_then(|path| {
+                history::previous_runs(&path, &config.project_path, reproducible::PREVIOUS_RUNS_SEARCHED)
+            });
+            match previous {
+                Ok(previous) => {
+                    let report = reproducible::compare_with_previous(&window, &manifest, &previous);
+                    if !report.differing.is_empty() {
+                        logger.warning(&tr!("reproducible.differs", report.compared_run.clone().unwrap_or_default(), report.differing.join(", ")));
+                    } else if let Some(run) = &report.compared_run {
+                        logger.info(&tr!("reproducible.identical", report.identical.len(), run));
+                    }
+                    manifest.reproducibility = Some(report);
+                }
+                Err(e) => {
+                    logger.warning(&e);
+                }
+            }
+        }
+    }
+    if let (Some(version), true) = (version, result.success)
+        && let Err(e) = versioning::save_version(&window, &config.project_path, version)
+    {
+        logger.warning(&e);
+    }
+    if output_dir.is_dir() {
+        if let Err(e) = manifest.write(&output_dir) {
+            logger.warning(&e);
+        }
+        if let Err(e) = report::write_html(&manifest, &output_dir) {
+            logger.warning(&e);
+        }
+    }
+    if let Err(e) = manifest.archive(&window) {
+        logger.warning(&e);
+    }
+    if let Err(e) = history::history_path(&window).and_then(|path| history::record_run(&path, &manifest)) {
+        logger.warning(&e);
+    }
+    if let (Some(settings), true) = (&config.git_tag, result.success) {
+        tag_commit(&window, &mut logger, settings, &manifest);
+    }
+    if let Some(script) = run_script.as_mut() {
+        let context = scripting::run_context(&run_id, Path::new(&config.project_path), &output_dir, config.config_name.as_deref().unwrap_or("Debug"), result.combinations.len());
+        if let Err(e) = script_hook(&mut logger, script, scripting::AFTER_RUN, scripting::with_run_result(context, &result)) {
+            logger.warning(&e);
+        }
+    }
+    if let Some(hooks) = &config.webhooks {
+        let payload = webhook::WebhookPayload::new(&manifest, result.failure_reason.clone());
+        for e in webhook::notify(hooks, payload).await {
+            logger.warning(&e);
+        }
+    }
+    if let Some(settings) = &config.notifications
+        && let Err(e) = notifications::run_finished(&window, settings, &result)
+    {
+        logger.warning(&e);
+    }
+
+    // Make this run's log searchable later on
+    match log_index::index_path(&window) {
+        Ok(path) => {
+            if let Err(e) = log_index::index_entries(&path, &run_id, &result.logs) {
+                logger.warning(&e);
+            }
+        }
+        Err(e) => {
+            logger.warning(&e);
+        }
+    }
+    Ok(result)
+}
+
+/// Tags the commit a successful run was built from and labels the run with
+/// the same name, so the next release's changelog starts from it.
+fn tag_commit(window: &Window, logger: &mut Logger<'_>, settings: &TagSettings, manifest: &RunManifest) {
+    let Some(git) = &manifest.git else {
+        logger.warning(&tr!("git.not_a_repository", manifest.project_path));
+        return;
+    };
+    if git.dirty {
+        logger.warning(&tr!("git.tag_dirty"));
+        return;
+    }
+    let name = format!(
+        "{}{}",
+        settings.prefix.as_deref().unwrap_or_default(),
+        manifest.version.map(|v| v.label()).unwrap_or_else(|| manifest.run_id.clone())
+    );
+    let project_path = Path::new(&manifest.project_path);
+    let message = format!("Release build of run {}", manifest.run_id);
+    if let Err(e) = git_info::create_tag(project_path, &name, &git.hash, &message) {
+        logger.warning(&e);
+        return;
+    }
+    logger.info(&tr!("git.tagged", name, git.hash));
+    if settings.push {
+        let remote = settings.remote.as_deref().unwrap_or("origin");
+        match git_info::push_tag(project_path, remote, &name) {
+            Ok(()) => logger.info(&tr!("git.pushed", name, remote)),
+            Err(e) => logger.warning(&e),
+        };
+    }
+    if let Err(e) = history::history_path(window).and_then(|path| history::tag_run(&path, &manifest.run_id, &name)) {
+        logger.warning(&e);
+    }
+}
+
+/// What a run is built from and with.
+struct RunContext<'a> {
+    run_id: &'a str,
+    git: Option<&'a GitInfo>,
+    version: Option<&'a FirmwareVersion>,
+    /// Starts CubeIDE.
+    runner: &'a dyn ProcessRunner,
+    /// Stops the run once cancelled.
+    cancel: &'a CancellationToken,
+}
+
+/// What a run collected, kept however it ends.
+#[derive(Default)]
+struct RunRecord {
+    combinations: Vec<CombinationResult>,
+    preflight: Option<PreflightReport>,
+    unit_tests: Option<UnitTestReport>,
+    /// Left loaded for its `after_run` hook.
+    script: Option<RunScript>,
+}
+
+/// Runs the whole matrix; per-combination outcomes, the preflight and unit
+/// test reports and the run script are collected into `record` so they
+/// survive early returns, stage timings into `logger`.
+async fn run_build(
+    logger: &mut Logger<'_>,
+    window: &Window,
+    config: BuildConfig,
+    context: RunContext<'_>,
+    record: &mut RunRecord,
+) -> Result<BuildResult, BuildError> {
+    let RunContext { run_id, git, version, runner, cancel } = context;
+    let RunRecord { combinations, preflight: preflight_report, unit_tests: unit_test_report, script: run_script } = record;
+    logger.set_stage("validation");
     let mut success = true;
 
     // Load and validate settings configuration
     let settings_config = match BuildSettingsConfig::load() {
         Ok(cfg) => cfg,
         Err(e) => {
-            let msg = logger.error(&format!("Configuration error: {}", e));
-            return Ok(BuildResult { 
-                result: msg, 
-                logs: logger.get_logs().clone(), 
-                stages, 
-                success: false 
-            });
+            return Ok(fail_run(logger, BuildError::config(tr!("config.error", e))));
         }
     };
 
@@ -59,26 +446,22 @@ pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildR
     // Validate all settings
     for setting in &settings_config.build_settings {
         if let Some(value) = config.settings.get(&setting.id) {
-            let msg = logger.debug(&format!("{}", format_setting_message(&setting.id, value)));
+            logger.debug(&format_setting_message(&setting.id, value));
 
             // Explicitly log if array is empty (for checkbox_group/range)
             if (setting.field_type == "checkbox_group" || setting.field_type == "range")
                 && value.is_array() && value.as_array().map(|arr| arr.is_empty()).unwrap_or(false)
             {
-                let warn_msg = logger.warning(
-                    &format!("Setting '{}' is an empty array (may be optional or missing selection)", setting.id)
-                );
+                logger.warning(&tr!("settings.empty_array", setting.id));
             }
 
             if let Err(e) = settings_config.validate_setting(&setting.id, value) {
-                let msg = logger.error(&format!("Validation error for {}: {}", setting.id, e));
-                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+                let msg = logger.error(&tr!("settings.validation", setting.id, e));
+                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
             }
         } else {
             // Explicitly log missing value for parameter
-            let warn_msg = logger.warning(
-                &format!("Setting '{}' is missing in settings object", setting.id)
-            );
+            logger.warning(&tr!("settings.missing", setting.id));
         }
     }
 
@@ -86,124 +469,275 @@ pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildR
     let _schema = match load_build_settings_schema().await {
         Ok(s) => s,
         Err(e) => {
-            let msg = logger.error(&format!("Build settings schema error: {}", e));
-            return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+            let msg = logger.error(&tr!("schema.error", e));
+            return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
         }
     };
 
     // Check required paths
     if config.project_path.trim().is_empty() || config.build_dir.trim().is_empty() ||
        config.cube_ide_exe_path.trim().is_empty() || config.workspace_path.trim().is_empty() {
-        let msg = logger.error("One or more required paths are empty in BuildConfig");
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        return Ok(fail_run(logger, BuildError::config(tr!("paths.empty"))));
     }
 
     // Just copy string, without ok_or_else
     let workspace_path = config.workspace_path.clone();
-    let workspace_dir = Path::new(&workspace_path).canonicalize()
+    let workspace_dir = paths::canonicalize(Path::new(&workspace_path))
         .map_err(|e| {
-            let msg = logger.error(&format!("Invalid workspace path '{}': {}", workspace_path, e));
-            tauri::Error::from(anyhow::anyhow!(msg))
+            let msg = logger.error(&tr!("workspace.invalid", workspace_path, e));
+            BuildError::path(Path::new(&workspace_path), msg)
         })?;
-    logger.info(&format!("Using workspace: {}", workspace_path));
+    logger.info(&tr!("workspace.using", workspace_path));
 
     // Check if working directory exists
     if !workspace_dir.exists() || !workspace_dir.is_dir() {
-        let msg = logger.error(&format!("Error: Workspace '{}' does not exist", workspace_path));
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        return Ok(fail_run(logger, BuildError::path(&workspace_dir, tr!("workspace.missing", workspace_path))));
     }
 
-    // Clone and update build configuration
-    let mut build_config = config.clone();
-    build_config.cancelled = Some(build_config.cancelled.unwrap_or(false));
-    {
-        let mut config_guard = BUILD_CONFIG.lock().await;
-        *config_guard = Some(build_config.clone());
-        logger.debug("Build configuration saved in BUILD_CONFIG");
+    let build_config = config.clone();
+
+    // Queue behind the runs already building this project or in this workspace
+    let manager = window.state::<BuildManager>();
+    let project_dir = paths::canonicalize(Path::new(&config.project_path)).unwrap_or_else(|_| PathBuf::from(&config.project_path));
+    let busy_paths = vec![project_dir, workspace_dir.clone()];
+    if manager.paths_busy(&busy_paths) {
+        logger.info(&tr!("build.project_busy"));
     }
+    let _paths = tokio::select! {
+        lock = manager.acquire_paths(busy_paths) => Some(lock),
+        _ = cancel.cancelled() => None,
+    };
+
+    // Queue behind the runs already building
+    if !manager.has_free_slot() {
+        logger.info(&tr!("build.queued"));
+    }
+    let _slot = tokio::select! {
+        slot = manager.acquire_slot() => Some(slot),
+        _ = cancel.cancelled() => None,
+    };
 
     // Check cancellation
-    if build_config.cancelled.unwrap_or(false) {
-        let msg = logger.info("Build was cancelled before starting");
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+    if cancel.is_cancelled() {
+        let msg = logger.info(&tr!("build.cancelled_before_start"));
+        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, cancelled: true, error: Some(BuildError::Cancelled), ..Default::default() });
     }
 
     // Start build process
-    let start_msg = logger.info("Starting project build");
-    stages.push(start_msg.clone());
+    let start_msg = logger.info(&tr!("build.starting"));
+    logger.begin_step("start", &start_msg);
 
     // Check STM32CubeIDE path
-    stages.push("Validating STM32CubeIDE EXE path".to_string());
-    let cube_ide_exe = Path::new(&build_config.cube_ide_exe_path).canonicalize()
-        .map_err(|e| {
-            let msg = logger.error(&format!("Invalid STM32CubeIDE path '{}': {}", build_config.cube_ide_exe_path, e));
-            tauri::Error::from(anyhow::anyhow!(msg))
-        })?;
-    if !cube_ide_exe.exists() || !cube_ide_exe.is_file() {
-        let msg = logger.error(&format!("Error: STM32CubeIDE EXE '{}' not found", build_config.cube_ide_exe_path));
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+    logger.begin_step("validate_cubeide", &tr!("step.validate_cubeide"));
+    if build_config.dry_run.unwrap_or(false) {
+        // Nothing is started, so CubeIDE need not be installed
+        logger.warning(&tr!("build.dry_run"));
+    } else {
+        let cube_ide_exe = paths::canonicalize(Path::new(&build_config.cube_ide_exe_path))
+            .map_err(|e| {
+                let msg = logger.error(&tr!("cubeide.invalid_path", build_config.cube_ide_exe_path, e));
+                BuildError::ToolchainMissing { message: msg }
+            })?;
+        if !cube_ide_exe.exists() || !cube_ide_exe.is_file() {
+            let message = tr!("cubeide.not_found", build_config.cube_ide_exe_path);
+            return Ok(fail_run(logger, BuildError::ToolchainMissing { message }));
+        }
     }
 
     // Setup paths
-    let project_path = Path::new(&build_config.project_path).canonicalize()
+    let project_path = paths::canonicalize(Path::new(&build_config.project_path))
         .map_err(|e| {
-            let msg = logger.error(&format!("Invalid project path '{}': {}", build_config.project_path, e));
-            tauri::Error::from(anyhow::anyhow!(msg))
+            let msg = logger.error(&tr!("project.invalid_path", build_config.project_path, e));
+            BuildError::path(Path::new(&build_config.project_path), msg)
         })?;
-    let build_config_file = project_path.join("Inc/build_config.h");
-    let output_dir = Path::new(&build_config.build_dir).canonicalize()
+    let build_root = paths::canonicalize(Path::new(&build_config.build_dir))
         .map_err(|e| {
-            let msg = logger.error(&format!("Invalid build directory '{}': {}", build_config.build_dir, e));
-            tauri::Error::from(anyhow::anyhow!(msg))
+            let msg = logger.error(&tr!("build_dir.invalid", build_config.build_dir, e));
+            BuildError::path(Path::new(&build_config.build_dir), msg)
         })?;
+    let output_dir = run_output_dir(&build_root, run_id);
+    logger.info(&tr!("output.run_dir", output_dir.display()));
     let log_file_path = output_dir.join("build_log.txt");
+    let log_retention = build_config.log_retention.clone().unwrap_or_default();
 
     // Check directories
-    stages.push("Checking and creating directories".to_string());
+    logger.begin_step("prepare_directories", &tr!("step.prepare_directories"));
     if !project_path.exists() {
-        let msg = logger.error(&format!("Error: Project directory '{}' not found", build_config.project_path));
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        let msg = logger.error(&tr!("project.not_found", build_config.project_path));
+        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
     }
-    if let Err(e) = fs::create_dir_all(&output_dir) {
-        let msg = logger.error(&format!("Error creating directory '{}': {}", output_dir.display(), e));
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+    if let Err(e) = fs::create_dir_all(&output_dir).await {
+        return Ok(fail_run(logger, BuildError::path(&output_dir, tr!("fs.create_dir", output_dir.display(), e))));
+    }
+    // Earlier runs' folders fall under the same retention as the logs
+    if let Err(e) = prune_runs_async(&build_root, manager.active_runs(), &log_retention).await {
+        logger.warning(&tr!("log.prune_runs_failed", build_root.display(), e));
+    }
+
+    // Check disk space, memory and toolchain before spending time on the matrix
+    logger.begin_step("preflight", &tr!("step.preflight"));
+    let preflight = preflight::run_preflight(&build_config);
+    for check in &preflight.checks {
+        match check.status {
+            CheckStatus::Ok => logger.debug(&check.message),
+            CheckStatus::Warning => logger.warning(&check.message),
+            CheckStatus::Failed => logger.error(&check.message),
+        };
+    }
+    let failures = preflight.failures().map(|c| c.message.clone()).collect::<Vec<_>>();
+    *preflight_report = Some(preflight);
+    if !failures.is_empty() {
+        let msg = logger.error(&tr!("preflight.failed", failures.join("; ")));
+        return Ok(BuildResult { result: msg.clone(), logs: logger.get_logs().clone(), success: false, failure_reason: Some(msg), ..Default::default() });
     }
 
     // Check project files
-    stages.push("Checking project files".to_string());
-    validate_project_file(&project_path)?;
-    validate_cproject_file(&project_path)?;
+    logger.begin_step("check_project", &tr!("step.check_project"));
+    if let Err(e) = validate_project_file(&project_path).and_then(|_| validate_cproject_file(&project_path)) {
+        logger.error(&e.to_string());
+        return Err(e);
+    }
 
     // Check .cproject configurations
     let configs = get_cproject_configurations(&project_path)
         .map_err(|e| {
-            let msg = logger.error(&format!("Error reading .cproject: {}", e));
-            tauri::Error::from(anyhow::anyhow!(msg))
+            let msg = logger.error(&tr!("cproject.read", e));
+            BuildError::config(msg)
         })?;
     let expected_config = build_config.config_name.as_deref().unwrap_or("Debug");
     if !configs.contains(&expected_config.to_string()) {
-        let msg = logger.error(&format!("Error: Configuration '{}' not found in .cproject", expected_config));
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        let msg = logger.error(&tr!("cproject.config_missing", expected_config));
+        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
+    }
+    // Only a configuration that got this far is offered again on the next start
+    remember_config(logger, window, &build_config);
+
+    // Refuse or flag builds of uncommitted code
+    if let Some(git) = git.filter(|g| g.dirty) {
+        match build_config.dirty_tree.unwrap_or_default() {
+            DirtyTreePolicy::Allow => {}
+            DirtyTreePolicy::Warn => {
+                logger.warning(&tr!("git.dirty", git.branch));
+            }
+            DirtyTreePolicy::Refuse => {
+                let msg = logger.error(&tr!("git.dirty_refused", git.branch));
+                return Ok(BuildResult { result: msg.clone(), logs: logger.get_logs().clone(), success: false, failure_reason: Some(msg), ..Default::default() });
+            }
+        }
+    }
+    let header_git = git.filter(|_| build_config.embed_git_info.unwrap_or(false));
+    let source_date_epoch = build_config.reproducible.unwrap_or(false).then(|| reproducible::source_date_epoch(git));
+    if source_date_epoch.is_some() && reproducible::prefix_map_flags(&project_path).is_empty() {
+        logger.warning(&tr!("reproducible.no_prefix_map", project_path.display()));
+    }
+    // Appended to every combination's build_config.h
+    let mut extra_defines = String::new();
+    if let Some(git) = header_git {
+        extra_defines.push_str(&git.defines());
+    }
+    if let Some(version) = version {
+        extra_defines.push_str(&version.defines());
+    }
+    if let Some(epoch) = source_date_epoch {
+        extra_defines.push_str(&reproducible::defines(epoch));
+    }
+
+    // Build a pristine checkout of HEAD instead of the working copy; it is
+    // removed again when `clean_export` goes out of scope
+    let clean_export = if build_config.clean_export.unwrap_or(false) {
+        logger.begin_step("clean_export", &tr!("step.clean_export"));
+        match git_info::export_head(&project_path) {
+            Ok(export) => {
+                logger.info(&tr!("git.exported", export.project_dir.display()));
+                Some(export)
+            }
+            Err(e) => {
+                let msg = logger.error(&e);
+                return Ok(BuildResult { result: msg.clone(), logs: logger.get_logs().clone(), success: false, failure_reason: Some(msg), ..Default::default() });
+            }
+        }
+    } else {
+        None
+    };
+    let (project_path, workspace_path) = match &clean_export {
+        Some(export) => (export.project_dir.clone(), export.workspace.to_string_lossy().into_owned()),
+        None => (project_path, workspace_path),
+    };
+    if let Some(settings) = build_config.unit_tests.as_ref().filter(|s| s.stage == TestStage::Pre)
+        && let Some(failed) = unit_test_stage(logger, settings, &project_path, unit_test_report).await
+    {
+        return Ok(failed);
     }
 
     // Get project name
-    stages.push("Extracting project name".to_string());
-    let project_name = match build_config.project_name {
-        Some(name) => name,
+    logger.begin_step("project_name", &tr!("step.project_name"));
+    let project_name = match &build_config.project_name {
+        Some(name) => name.clone(),
         None => get_project_name(&project_path)
             .map_err(|e| {
-                let msg = logger.error(&format!("Error getting project name: {}", e));
-                tauri::Error::from(anyhow::anyhow!(msg))
+                let msg = logger.error(&tr!("project.name", e));
+                BuildError::config(msg)
             })?,
     };
 
-    // Form build parameter
-    stages.push("Forming build parameter".to_string());
-    let build_target = match &build_config.config_name {
-        Some(config_name) => format!("{}/{}", project_name, config_name),
-        None => project_name.clone(),
+    // build_config.h has to be in a folder the compiler searches
+    let header_dir = match header_placement(&project_path, &project_name, build_config.config_name.as_deref().unwrap_or("Debug")) {
+        Ok(placement) => {
+            if placement.default_unreachable {
+                logger.warning(&tr!("header.default_unreachable", DEFAULT_HEADER_DIR, placement.dir));
+            }
+            placement.dir
+        }
+        Err(e) => {
+            logger.warning(&tr!("header.placement_failed", e, DEFAULT_HEADER_DIR));
+            DEFAULT_HEADER_DIR.to_string()
+        }
+    };
+    let header_file = format!("{}/build_config.h", header_dir);
+    // The folder may be a linked resource living outside the project
+    let linked_resources = read_description(&project_path).map(|d| d.linked_resources).unwrap_or_default();
+    let build_config_file = resolve_project_path(&project_path, &linked_resources, Path::new(&header_file));
+
+    let macro_target = match MacroTarget::new(build_config.define_mode.unwrap_or_default(), &project_path, build_config_file) {
+        Ok((target, restored_stale)) => {
+            if restored_stale {
+                logger.warning(&tr!("cproject.stale_backup"));
+            }
+            target
+        }
+        Err(e) => return Ok(fail_run(logger, BuildError::config(defines_error(e)))),
     };
-    let build_flag = if build_config.clean_build { "-cleanBuild" } else { "-build" };
+
+    // Keys and certificates stay out of the repository, so they are looked
+    // up in the selected project even when building a clean export
+    let signing_root = PathBuf::from(&build_config.project_path);
+    if let Some(settings) = &build_config.signing {
+        logger.begin_step("signing_check", &tr!("step.signing_check"));
+        if let Err(e) = signing::check(settings, &signing_root).await {
+            return Ok(fail_run(logger, BuildError::config(e)));
+        }
+    }
+
+    // Combinations after the first reuse the makefiles CubeIDE generated;
+    // symbols edited into .cproject would need new ones
+    let make_tools = if build_config.warm_build.unwrap_or(false) {
+        if macro_target.header().is_none() {
+            logger.warning(&tr!("warm.cproject_mode"));
+            None
+        } else {
+            let tools = MakeTools::find(Path::new(&build_config.cube_ide_exe_path));
+            if tools.is_none() {
+                logger.warning(&tr!("warm.no_make"));
+            }
+            tools
+        }
+    } else {
+        None
+    };
+
+    // Form build parameter
+    logger.begin_step("build_parameter", &tr!("step.build_parameter"));
+    let build_target = build_target(&project_name, build_config.config_name.as_deref());
 
     // Collect settings values
     let settings_values = settings_config.build_settings.iter().map(|setting| {
@@ -247,45 +781,11 @@ pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildR
         .join(", ");
     logger.debug(&format!("settings_values for build combinations: {{ {} }}", settings_values_log));
 
-    // Check: if at least one REQUIRED parameter has no values — error
-    let missing_required: Vec<String> = settings_config.build_settings.iter()
-        .filter_map(|setting| {
-            let value = config.settings.get(&setting.id);
-            let values_count = match setting.field_type.as_str() {
-                "range" | "checkbox_group" => value
-                    .and_then(|v| v.as_array())
-                    .map(|arr| arr.iter().filter(|v| {
-                        // Ignore empty strings in array
-                        if let Some(s) = v.as_str() {
-                            !s.trim().is_empty()
-                        } else if v.is_number() {
-                            true
-                        } else {
-                            false
-                        }
-                    }).count())
-                    .unwrap_or(0),
-                "select" => value
-                    .and_then(|v| v.as_str())
-                    .map(|s| if s.trim().is_empty() { 0 } else { 1 })
-                    .unwrap_or(0),
-                _ => 1,
-            };
-            // Only if parameter is required (min_selected > 0 or for select always 1)
-            let min_required: usize = if setting.field_type == "select" {
-                1
-            } else {
-                setting.min_selected.unwrap_or(0) as usize
-            };
-            if values_count < min_required {
-                Some(setting.id.clone())
-            } else {
-                None
-            }
-        })
-        .collect();
+    // Every required setting needs a value
+    let missing_required = missing_required(&settings_config, &config.settings);
 
-    if !missing_required.is_empty() {
+    // An explicit selection does not depend on the values in the form
+    if !missing_required.is_empty() && build_config.selected_combinations.is_none() {
         let debug_settings = settings_values.iter()
             .map(|(setting, values)| format!("{}: {:?}", setting.id, values))
             .collect::<Vec<_>>()
@@ -293,421 +793,787 @@ pub async fn build_project(window: Window, config: BuildConfig) -> Result<BuildR
         logger.debug(&format!("Debug: settings_values = {{ {} }}", debug_settings));
 
         let msg = logger.error(
-            &format!("No values provided for required build parameters: {}. Please fill all required build settings.", missing_required.join(", "))
+            &tr!("settings.required_missing", missing_required.join(", "))
         );
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
     }
 
     // Create combinations for build (detailed logging)
-    let build_combinations = generate_build_combinations(&settings_config, &config.settings);
+    let build_combinations = match &build_config.selected_combinations {
+        Some(selection) => match selection_combinations(&settings_config, selection) {
+            Ok(combinations) => {
+                logger.info(&tr!("combinations.selected", combinations.len()));
+                combinations
+            }
+            Err(e) => {
+                let msg = logger.error(&tr!("combinations.selection_invalid", e));
+                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
+            }
+        },
+        None => generate_build_combinations(&settings_config, &config.settings),
+    };
 
     if build_combinations.is_empty() {
         let msg = logger.error(
-            "No build combinations generated. This usually means at least one build parameter has no values. Check settings_values and build_settings."
+            &tr!("combinations.none")
         );
-        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success: false });
+        return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, ..Default::default() });
     }
 
-    let mut any_build_executed = false;
+    // Custom build steps from plugins/
+    let (build_plugins, plugin_errors) = plugins::load(Path::new(plugins::PLUGINS_DIR));
+    for e in plugin_errors {
+        logger.warning(&e);
+    }
+    if !build_plugins.is_empty() {
+        let names: Vec<&str> = build_plugins.iter().map(|p| p.name.as_str()).collect();
+        logger.info(&tr!("plugin.loaded", names.join(", ")));
+    }
 
-    // Build for each combination
-    for combination in build_combinations {
-        any_build_executed = true;
-        // Check cancellation
-        {
-            let config_guard = BUILD_CONFIG.lock().await;
-            if let Some(conf) = &*config_guard {
-                if conf.cancelled.unwrap_or(false) {
-                    let msg = logger.info(&format!("Build cancelled for combination {:?}", combination));
-                    success = false;
-                    return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-                }
+    // User script with hooks around the run and each combination
+    if let Some(path) = &build_config.script {
+        let path = project_path.join(path);
+        *run_script = match RunScript::load(&path) {
+            Ok(script) => Some(script),
+            Err(e) => {
+                let msg = logger.error(&e);
+                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, failure_reason: Some(e), ..Default::default() });
             }
+        };
+        logger.info(&tr!("script.loaded", path.display()));
+    }
+    if let Some(script) = run_script.as_mut() {
+        let config_name = build_config.config_name.as_deref().unwrap_or("Debug");
+        let context = scripting::run_context(run_id, &project_path, &output_dir, config_name, build_combinations.len());
+        let refused = match script_hook(logger, script, scripting::BEFORE_RUN, context) {
+            Ok(HookOutcome::Continue) => None,
+            Ok(HookOutcome::Skip) => Some(tr!("script.run_refused")),
+            Err(e) => Some(e),
+        };
+        if let Some(reason) = refused {
+            let msg = logger.error(&reason);
+            return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), success: false, failure_reason: Some(reason), ..Default::default() });
+        }
+    }
+
+    // Warnings the schema or the run treat as errors, and the warning limit
+    let failure_policy = FailurePolicy::new(&settings_config, build_config.error_warnings.as_deref(), build_config.max_warnings);
+
+    // Build every combination in order, stopping at the first failure
+    let config_name = build_config.config_name.as_deref().unwrap_or("Debug");
+    let version_label = version.map(|v| v.label());
+    let dry_run_runner;
+    let (runner, cubeide_build_dir) = if build_config.dry_run.unwrap_or(false) {
+        // The dry run's empty image lives in the run's folder, never over the project's own
+        let build_dir = output_dir.join(DRY_RUN_DIR).join(config_name);
+        dry_run_runner = DryRunRunner::for_build(&build_dir, &project_name);
+        (&dry_run_runner as &dyn ProcessRunner, build_dir)
+    } else {
+        (runner, project_path.join(config_name))
+    };
+    let matrix = Matrix {
+        run_id,
+        settings_config: &settings_config,
+        project_path: &project_path,
+        project_name: &project_name,
+        config_name,
+        build_dir: &cubeide_build_dir,
+        output_dir: &output_dir,
+        macro_target: &macro_target,
+        extra_defines: &extra_defines,
+        version_label: version_label.as_deref(),
+        failure_policy: &failure_policy,
+        cube_ide_exe: &build_config.cube_ide_exe_path,
+        target: &build_target,
+        workspace: &workspace_path,
+        clean: build_config.clean_build,
+        custom_args: build_config.custom_console_args.as_deref(),
+        timeout_secs: build_config.timeout_secs,
+    };
+    let mut hooks = GuiHooks {
+        logger: &mut *logger,
+        window,
+        manager: &manager,
+        config: &build_config,
+        run_id,
+        project_path: &project_path,
+        project_name: &project_name,
+        build_dir: &cubeide_build_dir,
+        git,
+        version_label: version_label.as_deref(),
+        symbols: macro_target.header().is_none(),
+        make_tools,
+        source_date_epoch,
+        plugins: &build_plugins,
+        run_script: &mut *run_script,
+        signing_root: &signing_root,
+        log_retention: &log_retention,
+        combinations: &mut *combinations,
+        script_context: rhai::Map::new(),
+        budget_timer: None,
+        command: String::new(),
+        resource_monitor: None,
+        tracked: None,
+        exit_code: None,
+        stdout: Vec::new(),
+    };
+    let built = build_matrix(&matrix, &build_combinations, runner, &WindowEvents(window), cancel, &mut hooks).await;
+    drop(hooks);
+    if build_config.dry_run.unwrap_or(false) {
+        fs::remove_dir_all(output_dir.join(DRY_RUN_DIR)).await.ok();
+    }
+    if let Err(e) = built {
+        return matrix_failure(logger, window, combinations, e, &workspace_path, &project_name, &cubeide_build_dir);
+    }
+
+    if let Some(settings) = build_config.unit_tests.as_ref().filter(|s| s.stage == TestStage::Post) {
+        logger.set_combination(None);
+        if let Some(failed) = unit_test_stage(logger, settings, &project_path, unit_test_report).await {
+            return Ok(failed);
         }
+    }
+
+    // Write logs
+    logger.set_combination(None);
+    logger.set_stage("finalize");
+    logger.begin_step("write_logs", &tr!("step.write_logs"));
+    if let Err(e) = rotate_log_async(&log_file_path, &log_retention).await {
+        logger.warning(&tr!("log.rotate_failed", log_file_path.display(), e));
+    }
+    let log_content: String = logger.get_logs().iter().map(|log| format!("{}\n", log)).collect();
+    if let Err(e) = fs::write(&log_file_path, log_content).await {
+        let msg = logger.error(&tr!("log.write_failed", e));
+        success = false;
+        return Ok(BuildResult { 
+            result: msg, 
+            logs: logger.get_logs().clone(), 
+            success,
+            ..Default::default() 
+        });
+    }
+
+    // Finalize build result
+    let last_result = if success {
+        logger.info(&tr!("build.completed"))
+    } else {
+        logger.error(&tr!("build.completed_errors"))
+    };
+
+    Ok(BuildResult { 
+        result: last_result, 
+        logs: logger.get_logs().clone(), 
+        success,
+        ..Default::default() 
+    })
+}
+/// Marks the combination whose output could not be taken over as failed
+/// and ends the run with `msg`.
+fn fail_output(logger: &mut Logger<'_>, combinations: &mut [CombinationResult], msg: String) -> BuildResult {
+    logger.error(&msg);
+    if let Some(last) = combinations.last_mut() {
+        last.success = false;
+        last.failure_reason = Some(msg.clone());
+    }
+    BuildResult { result: msg.clone(), logs: logger.get_logs().clone(), success: false, failure_reason: Some(msg), ..Default::default() }
+}
 
-        // Create combination directory
-        let mut combo_dir_name = String::new();
-        let mut name_parts = vec![project_name.clone()];
-        for (setting_id, value) in &combination {
-            // Get the setting object to access its 'value' field
-            if let Some(setting) = settings_config.build_settings.iter().find(|s| &s.id == setting_id) {
-                combo_dir_name.push_str(&format!("{}_{}_", setting.value, value));
-                name_parts.push(format!("{}-{}", setting.value, value));
+/// The run's result when the matrix stopped early.
+fn matrix_failure(
+    logger: &mut Logger<'_>,
+    window: &Window,
+    combinations: &mut [CombinationResult],
+    error: MatrixError<BuildResult>,
+    workspace_path: &str,
+    project_name: &str,
+    build_dir: &Path,
+) -> Result<BuildResult, BuildError> {
+    let cancelled = |msg: String, logger: &mut Logger<'_>| BuildResult {
+        result: msg,
+        logs: logger.get_logs().clone(),
+        success: false,
+        cancelled: true,
+        error: Some(BuildError::Cancelled),
+        ..Default::default()
+    };
+    Ok(match error {
+        MatrixError::Cancelled { combination, running: false } => {
+            let msg = logger.info(&tr!("build.cancelled_combination", combination));
+            cancelled(msg, logger)
+        }
+        MatrixError::Cancelled { running: true, .. } => {
+            let msg = logger.info(&tr!("cancel.done"));
+            if let Err(e) = window.emit_to(window.label(), "build-cancelled", true) {
+                logger.warning(&tr!("cancel.event_failed", e));
             }
+            cancelled(msg, logger)
+        }
+        MatrixError::Timeout { combination, seconds } => fail_run(logger, BuildError::Timeout { combination, seconds }),
+        MatrixError::CreateDir(path, e) => {
+            let message = tr!("fs.create_dir", path.display(), e);
+            fail_run(logger, BuildError::path(&path, message))
         }
-        
-        let combo_dir = output_dir.join(combo_dir_name.trim_end_matches('_'));
-        
-        if let Err(e) = fs::create_dir_all(&combo_dir) {
-            let msg = logger.error(&format!("Error creating directory '{}': {}", combo_dir.display(), e));
-            success = false;
-            return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
+        MatrixError::Remove(path, e) => {
+            let message = tr!("fs.remove", path.display(), e);
+            fail_run(logger, BuildError::path(&path, message))
         }
+        MatrixError::Header(e) => return Err(BuildError::config(logger.error(&tr!("header.generate_failed", e)))),
+        MatrixError::Defines(e) => fail_run(logger, BuildError::config(defines_error(e))),
+        MatrixError::Spawn(e) => {
+            let message = logger.error(&tr!("cubeide.start_failed", e));
+            return Err(BuildError::ToolchainMissing { message });
+        }
+        MatrixError::Wait { combination, error } => {
+            let message = logger.error(&tr!("process.wait_failed", error));
+            return Err(BuildError::CompileFailed { combination, message, diagnostics: Vec::new() });
+        }
+        MatrixError::Failed { combination, failure } => {
+            let reason = failure_reason(&failure);
+            let msg = logger.error(&tr!("combination.failed", combination, reason));
+            let last = combinations.last();
+            let failure_hint = last.and_then(|c| c.failure_hint.clone());
+            let error = match failure_hint.as_ref().map(|h| h.code) {
+                Some(FailureCode::WorkspaceInUse) => BuildError::WorkspaceLocked { workspace: workspace_path.to_string() },
+                Some(FailureCode::ToolchainNotFound) => BuildError::ToolchainMissing { message: reason.clone() },
+                _ => BuildError::CompileFailed {
+                    combination,
+                    message: reason.clone(),
+                    diagnostics: last.map(|c| c.diagnostics.clone()).unwrap_or_default(),
+                },
+            };
+            BuildResult {
+                result: msg,
+                logs: logger.get_logs().clone(),
+                success: false,
+                failure_reason: Some(reason),
+                failure_hint,
+                error: Some(error),
+                ..Default::default()
+            }
+        }
+        MatrixError::NotBuilt(_) => {
+            let msg = tr!("output.not_found", project_name.to_lowercase(), build_dir.display());
+            fail_output(logger, combinations, msg)
+        }
+        MatrixError::Move { from, to, error } => {
+            let msg = tr!("fs.move", from.display(), to.display(), error);
+            fail_output(logger, combinations, msg)
+        }
+        MatrixError::Hook(result) => result,
+    })
+}
 
-        // Create file names
-        let mut name_parts = Vec::new();
-        
-        // 1. First 6 characters of project name
-        let short_project_name = if project_name.len() > 6 {
-            project_name[..6].to_string()
-        } else {
-            project_name.clone()
-        };
-        name_parts.push(short_project_name);
+/// The GUI's part of building each combination: logging, the run script
+/// and plugins, warm builds, the post-build analyses and gates, and the
+/// results the run reports.
+struct GuiHooks<'a, 'l> {
+    logger: &'a mut Logger<'l>,
+    window: &'a Window,
+    manager: &'a BuildManager,
+    config: &'a BuildConfig,
+    run_id: &'a str,
+    project_path: &'a Path,
+    project_name: &'a str,
+    /// CubeIDE's output folder of the configuration.
+    build_dir: &'a Path,
+    git: Option<&'a GitInfo>,
+    version_label: Option<&'a str>,
+    /// The macros go to the `.cproject` symbols instead of a header.
+    symbols: bool,
+    make_tools: Option<MakeTools>,
+    source_date_epoch: Option<i64>,
+    plugins: &'a [PluginManifest],
+    run_script: &'a mut Option<RunScript>,
+    signing_root: &'a Path,
+    log_retention: &'a LogRetention,
+    combinations: &'a mut Vec<CombinationResult>,
+    // What belongs to the combination being built
+    script_context: rhai::Map,
+    /// Aborted when the next combination starts or the run ends.
+    budget_timer: Option<BudgetTimer>,
+    command: String,
+    resource_monitor: Option<JoinHandle<()>>,
+    /// Lets cancel_build kill the whole process tree while it runs.
+    tracked: Option<TrackedChild<'a>>,
+    exit_code: Option<i32>,
+    stdout: Vec<String>,
+}
+
+impl GuiHooks<'_, '_> {
+    /// Marks the combination that was just built as failed.
+    fn fail(&mut self, msg: String) -> BuildResult {
+        fail_output(self.logger, self.combinations, msg)
+    }
 
-        // 2. Value from higher blocks + used lower ones
-        for (setting_id, value) in &combination {
-            if let Some(setting) = settings_config.build_settings.iter().find(|s| &s.id == setting_id) {
-                if !value.is_empty() {
-                    name_parts.push(format!("{}-{}", setting.value, value));
+    /// Post-build analyses, gates and steps of the combination just built.
+    async fn analyse(&mut self, combination: &Combination<'_>, header: &str) -> Result<(), BuildResult> {
+        let GuiHooks { logger, window, config: build_config, project_path, project_name, build_dir, combinations, .. } = self;
+        let (combo_name, settings) = (combination.name, combination.settings);
+        let elf_file = find_output(build_dir, project_name, "elf");
+        let map_file = find_output(build_dir, project_name, "map");
+        let built = CombinationBuild {
+            project_path,
+            build_dir,
+            combo_dir: combination.dir,
+            elf: elf_file.as_deref(),
+            map: map_file.as_deref(),
+            header,
+        };
+        if let Some(settings_cppcheck) = &build_config.cppcheck {
+            logger.set_stage("cppcheck");
+            logger.begin_step("cppcheck", &tr!("step.cppcheck", format!("{:?}", settings)));
+            match cppcheck::run(settings_cppcheck, &built).await {
+                Ok(findings) => {
+                    logger.info(&tr!("cppcheck.summary", findings.len()));
+                    if let Some(last) = combinations.last_mut() {
+                        last.diagnostics.extend(findings);
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&tr!("cppcheck.failed", e));
+                }
+            }
+        }
+        if let Some(tidy) = &build_config.clang_tidy {
+            logger.set_stage("clang-tidy");
+            logger.begin_step("clang_tidy", &tr!("step.clang_tidy", format!("{:?}", settings)));
+            match clang_tidy::run(tidy, &built).await {
+                Ok(findings) => {
+                    logger.info(&tr!("clang_tidy.summary", findings.len()));
+                    let failing = clang_tidy::failing_checks(tidy, &findings);
+                    if let Some(last) = combinations.last_mut() {
+                        last.diagnostics.extend(findings);
+                    }
+                    if !failing.is_empty() {
+                        let reason = tr!("clang_tidy.failing_checks", failing.join(", "));
+                        return Err(fail_combination(logger, combinations, combo_name, reason));
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&tr!("clang_tidy.failed", e));
+                }
+            }
+        }
+        for hook in build_config.analysis_hooks.iter().flatten() {
+            logger.set_stage("analysis");
+            logger.begin_step("analysis_hook", &tr!("step.analysis_hook", hook.name, format!("{:?}", settings)));
+            match analysis_hook::run(hook, &built).await {
+                Ok(findings) => {
+                    logger.info(&tr!("hook.summary", hook.name, findings.len()));
+                    if let Some(last) = combinations.last_mut() {
+                        last.diagnostics.extend(findings);
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&tr!("hook.failed", hook.name, e));
+                }
+            }
+        }
+        if let (Some(gate), Some(elf)) = (&build_config.forbidden_symbols, built.elf) {
+            logger.set_stage("symbols");
+            logger.begin_step("forbidden_symbols", &tr!("step.forbidden_symbols", format!("{:?}", settings)));
+            match symbol_gate::check(gate, elf) {
+                Ok(findings) if findings.is_empty() => {
+                    logger.info(&tr!("symbols.clean"));
+                }
+                Ok(findings) => {
+                    let names: Vec<String> = findings
+                        .iter()
+                        .filter_map(|d| d.option.as_deref()?.strip_prefix("symbols:"))
+                        .map(str::to_string)
+                        .collect();
+                    if let Some(last) = combinations.last_mut() {
+                        last.diagnostics.extend(findings);
+                    }
+                    let reason = tr!("symbols.forbidden", names.join(", "));
+                    if gate.action == GateAction::Fail {
+                        return Err(fail_combination(logger, combinations, combo_name, reason));
+                    }
+                    logger.warning(&reason);
+                }
+                Err(e) => {
+                    logger.warning(&e);
+                }
+            }
+        }
+        if let (Some(checks), Some(elf)) = (&build_config.elf_checks, built.elf) {
+            logger.set_stage("elf");
+            logger.begin_step("elf_checks", &tr!("step.elf_checks", format!("{:?}", settings)));
+            let regions = if checks.regions.is_empty() {
+                elf_check::linker_regions(project_path)
+            } else {
+                checks.regions.clone()
+            };
+            if regions.is_empty() {
+                logger.warning(&tr!("elf.no_regions"));
+            }
+            match elf_check::check(checks, &regions, elf) {
+                Ok(findings) if findings.is_empty() => {
+                    logger.info(&tr!("elf.ok"));
+                }
+                Ok(findings) => {
+                    for finding in &findings {
+                        logger.warning(&finding.message);
+                    }
+                    let reason = tr!("elf.problems", findings.len(), findings[0].message);
+                    if let Some(last) = combinations.last_mut() {
+                        last.diagnostics.extend(findings);
+                    }
+                    if checks.action == GateAction::Fail {
+                        return Err(fail_combination(logger, combinations, combo_name, reason));
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&e);
+                }
+            }
+        }
+        if let (true, Some(map)) = (build_config.unused_code_report.unwrap_or(false), built.map) {
+            logger.set_stage("map_analysis");
+            logger.begin_step("unused_code", &tr!("step.unused_code", format!("{:?}", settings)));
+            match map_file::unused_code(map) {
+                Ok(report) => {
+                    logger.info(&tr!("map.unused", report.functions.len(), report.function_bytes, report.objects.len(), report.object_bytes));
+                    if let Err(e) = map_file::write(&combination.dir.join(UNUSED_CODE_FILE), &report) {
+                        logger.warning(&e);
+                    }
+                    if let Some(last) = combinations.last_mut() {
+                        last.unused_code = Some(report.top());
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&e);
                 }
             }
         }
 
-        // 3. Build configuration first 5 symbols
-        let config_name = build_config.config_name.as_deref().unwrap_or("Debug");
-        let short_config = if config_name.len() > 5 {
-            &config_name[..5]
-        } else {
-            config_name
-        };
-        name_parts.push(short_config.to_string());
-
-        let bin_name = format!("{}.bin", name_parts.join("_"));
-        let bin_dst = combo_dir.join(&bin_name);
-        let txt_log_name = format!("{}.txt", name_parts.join("_"));
-        let txt_log_file = combo_dir.join(&txt_log_name);
-
-        // Find and delete .bin
-        stages.push(format!("Checking and removing existing .bin file for combination {:?}", combination));
-        if bin_dst.exists() {
-            if let Err(e) = fs::remove_file(&bin_dst) {
-                let msg = logger.error(&format!("Error removing existing file '{}': {}", bin_dst.display(), e));
-                success = false;
-                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-            }
-        }
-
-        // Generate file build_config.h
-        stages.push(format!("Generating build_config.h for combination {:?}", combination));
-        let build_config_content = generate_build_config_h(&settings_config, &combination)
-            .map_err(|e: String| tauri::Error::from(anyhow::anyhow!(e)))?;
-
-        // Create Inc folder
-        if let Some(parent) = build_config_file.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                let msg = logger.error(&format!("Error creating directory '{}': {}", parent.display(), e));
-                success = false;
-                return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-            }
-        }
-
-        // Write build_config.h
-        if let Err(e) = File::create(&build_config_file).and_then(|mut f| f.write_all(build_config_content.as_bytes())) {
-            let msg = logger.error(&format!("Error writing '{}': {}", build_config_file.display(), e));
-            success = false;
-            return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-        }
-
-        // Run STM32CubeIDE
-        stages.push(format!("Launching build in STM32CubeIDE for combination {:?}", combination));
-
-
-        // Create parameters for STM32CubeIDE
-        let mut headless_args = vec![
-            "-nosplash".to_string(),
-            "-application".to_string(),
-            "org.eclipse.cdt.managedbuilder.core.headlessbuild".to_string(),
-            "-include".to_string(),
-            "Inc/build_config.h".to_string(),
-            build_flag.to_string(),
-            build_target.clone(),
-            "-data".to_string(),
-            workspace_path.clone(),
-        ];
-        // Add custom arguments if they exist
-        if let Some(ref custom_args) = build_config.custom_console_args {
-            headless_args.extend(custom_args.split_whitespace().map(|s| s.to_string()));
-        }
-
-        // Add command logging (output as string, not array)
-        let msg = logger.info(
-            &format!(
-                "Executing command: {} {}",
-                &build_config.cube_ide_exe_path,
-                headless_args
-                    .iter()
-                    .map(|s| {
-                        // Add quotes only if there are spaces
-                        if s.contains(' ') { format!("\"{}\"", s) } else { s.clone() }
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            )
-        );
+        if let (true, Some(map)) = (build_config.ram_breakdown.unwrap_or(false), built.map) {
+            logger.set_stage("map_analysis");
+            logger.begin_step("ram_breakdown", &tr!("step.ram_breakdown", format!("{:?}", settings)));
+            match map_file::ram_breakdown(map) {
+                Ok(breakdown) => {
+                    if let Some(largest) = breakdown.modules.first() {
+                        logger.info(&tr!("map.ram", breakdown.data + breakdown.bss, breakdown.modules.len(), largest.module, largest.data + largest.bss));
+                    }
+                    if let Some(last) = combinations.last_mut() {
+                        last.ram_breakdown = Some(breakdown);
+                    }
+                }
+                Err(e) => {
+                    logger.warning(&e);
+                }
+            }
+        }
 
-        let mut command = Command::new(&build_config.cube_ide_exe_path);
-        command
-            .args(&headless_args)
-            .kill_on_drop(true)
-            .current_dir(&build_config.project_path)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped());
+        let flash = combinations.last().and_then(|c| c.sizes).map(|s| s.flash);
+        if let (Some(gate), Some(flash)) = (&build_config.size_gate, flash) {
+            logger.set_stage("size_gate");
+            logger.begin_step("size_gate", &tr!("step.size_gate", format!("{:?}", settings)));
+            let previous = history::history_path(window).and_then(|path| {
+                history::previous_size(&path, &build_config.project_path, build_config.config_name.as_deref(), combo_name)
+            });
+            match previous {
+                Ok(Some(previous)) => match size_gate::check(gate, flash, &previous) {
+                    Some(reason) if gate.action == GateAction::Fail => {
+                        return Err(fail_combination(logger, combinations, combo_name, reason));
+                    }
+                    Some(reason) => {
+                        logger.warning(&reason);
+                    }
+                    None => {
+                        logger.info(&tr!("size.within_limit", flash, previous.flash, previous.run_id));
+                    }
+                },
+                Ok(None) => {
+                    logger.info(&tr!("size.no_previous"));
+                }
+                Err(e) => {
+                    logger.warning(&e);
+                }
+            }
+        }
 
-        // Platform-specific settings
-        #[cfg(windows)]
-        {
-            use std::os::windows::process::CommandExt;
-            // 0x08000000 = CREATE_NO_WINDOW, 0x00000200 = CREATE_NEW_PROCESS_GROUP
-            command.creation_flags(0x08000000 | 0x00000200);
+        // Renode gets the firmware to its "boot OK" print; QEMU is only a quick fault check
+        let mut simulation: Option<(SimulationResult, GateAction)> = None;
+        if let (Some(renode), Some(elf)) = (&build_config.renode, built.elf) {
+            logger.set_stage("simulation");
+            logger.begin_step("renode", &tr!("step.renode", format!("{:?}", settings)));
+            simulation = Some((renode::run(renode, &built, elf).await, renode.action));
+        } else if let (Some(qemu), Some(elf)) = (&build_config.qemu, built.elf) {
+            logger.set_stage("simulation");
+            logger.begin_step("qemu", &tr!("step.qemu", format!("{:?}", settings)));
+            simulation = Some((qemu::run(qemu, &built, elf).await, qemu.action));
+        }
+        if let Some((simulation, action)) = simulation {
+            let error = simulation.error.clone().unwrap_or_default();
+            if let Some(last) = combinations.last_mut() {
+                last.simulation = Some(simulation.clone());
+            }
+            if simulation.passed {
+                logger.info(&tr!("simulation.passed", simulation.simulator, simulation.duration_ms));
+            } else {
+                let reason = tr!("simulation.failed", simulation.simulator, error);
+                if action == GateAction::Fail {
+                    return Err(fail_combination(logger, combinations, combo_name, reason));
+                }
+                logger.warning(&reason);
+            }
         }
 
-        #[cfg(all(unix, target_os = "macos"))]
-        unsafe {
-            command.pre_exec(|| {
-                libc::setpgid(0, 0);
-                Ok(())
-            });
+        let context = StepContext { run_id: self.run_id, combination: combo_name, bin: Some(combination.artifact) };
+        for plugin in self.plugins.iter().filter(|p| p.when == PluginStage::PostBuild) {
+            if let Err(reason) = plugin_step(self.logger, plugin, &built, &context).await {
+                return Err(fail_combination(self.logger, self.combinations, combo_name, reason));
+            }
         }
 
-        #[cfg(all(unix, target_os = "linux"))]
-        unsafe {
-            command.pre_exec(|| {
-                libc::setpgid(0, 0);
-                Ok(())
-            });
+        if let (Some(script), Some(last)) = (self.run_script.as_mut(), self.combinations.last()) {
+            let context = scripting::with_result(std::mem::take(&mut self.script_context), last);
+            if let Err(reason) = script_hook(self.logger, script, scripting::AFTER_COMBINATION, context) {
+                return Err(fail_combination(self.logger, self.combinations, combo_name, reason));
+            }
         }
+        Ok(())
+    }
+}
 
-        let child = command.spawn().map_err(|e| {
-            let msg = logger.error(&format!("Failed to start STM32CubeIDE process: {}", e));
-            tauri::Error::from(anyhow::anyhow!(msg))
-        })?;
+#[async_trait]
+impl MatrixHooks for GuiHooks<'_, '_> {
+    type Error = BuildResult;
+
+    async fn before_combination(&mut self, combination: &Combination<'_>) -> Result<Proceed, BuildResult> {
+        self.logger.set_combination(Some(combination.name.to_string()));
+        self.budget_timer = self
+            .config
+            .notifications
+            .as_ref()
+            .and_then(|n| n.combination_budget_secs)
+            .map(|secs| BudgetTimer::start(self.window, combination.name, Duration::from_secs(secs)));
+        self.script_context = scripting::combination_context(self.run_id, combination.name, combination.dir, combination.settings);
+        if let Some(script) = self.run_script.as_mut() {
+            match script_hook(self.logger, script, scripting::BEFORE_COMBINATION, self.script_context.clone()) {
+                Ok(HookOutcome::Continue) => {}
+                Ok(HookOutcome::Skip) => {
+                    self.logger.info(&tr!("script.combination_skipped", combination.name));
+                    return Ok(Proceed::Skip);
+                }
+                Err(e) => {
+                    let msg = self.logger.error(&e);
+                    return Err(BuildResult { result: msg, logs: self.logger.get_logs().clone(), success: false, failure_reason: Some(e), ..Default::default() });
+                }
+            }
+        }
+        Ok(Proceed::Build)
+    }
 
-        // --- Сохраняем handle процесса ---
-        {
-            let mut child_guard = BUILD_CHILD.lock().await;
-            *child_guard = Some(child);
-        }
-        // --- конец вставки ---
-
-        // После этого используйте child_guard.as_mut().unwrap() если нужно, или продолжайте работу как раньше:
-        // let stdout = child.stdout.take().expect("Failed to capture stdout");
-        // ...existing code...
-        let mut child_guard = BUILD_CHILD.lock().await;
-        let child_ref = child_guard.as_mut().unwrap();
-        let stdout = child_ref.stdout.take().expect("Failed to capture stdout");
-        let stderr = child_ref.stderr.take().expect("Failed to capture stderr");
-
-        use tokio::io::{AsyncBufReadExt, BufReader};
-        let window_clone = window.clone();
-        let stdout_task = {
-            // Не используем logger и не добавляем timestamp, просто собираем строки для файла
-            tokio::spawn(async move {
-                let reader = BufReader::new(stdout);
-                let mut lines = reader.lines();
-                let mut stdout_lines = Vec::new();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    stdout_lines.push(line);
-                }
-                Ok::<Vec<String>, std::io::Error>(stdout_lines)
-            })
+    async fn before_build(&mut self, combination: &Combination<'_>, header: &str, spec: &mut ProcessSpec) -> Result<(), BuildResult> {
+        if self.symbols {
+            self.logger.info(&tr!("cproject.defines", header_defines(header).join(" ")));
+        }
+        let pre_build = CombinationBuild {
+            project_path: self.project_path,
+            build_dir: self.build_dir,
+            combo_dir: combination.dir,
+            elf: None,
+            map: None,
+            header,
         };
+        let context = StepContext { run_id: self.run_id, combination: combination.name, bin: None };
+        for plugin in self.plugins.iter().filter(|p| p.when == PluginStage::PreBuild) {
+            if let Err(reason) = plugin_step(self.logger, plugin, &pre_build, &context).await {
+                // The combination has no result yet
+                let msg = self.logger.error(&tr!("combination.failed", combination.name, reason));
+                return Err(BuildResult { result: msg, logs: self.logger.get_logs().clone(), success: false, failure_reason: Some(reason), ..Default::default() });
+            }
+        }
 
-        let stderr_window_clone = window.clone();
-        let stderr_task = tokio::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            let mut stderr_lines = Vec::new();
-            while let Ok(Some(line)) = lines.next_line().await {
-                // Не добавляем timestamp, просто пишем в файл
-                let log = format!("[STDERR] {}", line.trim());
-                stderr_lines.push(log);
-            }
-            Ok::<Vec<String>, std::io::Error>(stderr_lines)
-        });
-
-        // --- асинхронное ожидание с возможностью отмены ---
-        let child_wait = child_ref.wait();
-        let cancel_notify = BUILD_CANCEL_NOTIFY.clone();
-
-        tokio::select! {
-            status = child_wait => {
-                let status = status.map_err(|e| {
-                    let msg = logger.error(&format!("Process wait failed: {}", e));
-                    tauri::Error::from(anyhow::anyhow!(msg))
-                })?;
-
-                // Wait for stdout/stderr reading tasks to complete
-                let stdout_logs = stdout_task.await.map_err(|e| {
-                    let msg = logger.error(&format!("stdout task failed: {}", e));
-                    tauri::Error::from(anyhow::anyhow!(msg))
-                })??;
-                let stderr_logs = stderr_task.await.map_err(|e| {
-                    let msg = logger.error(&format!("stderr task failed: {}", e));
-                    tauri::Error::from(anyhow::anyhow!(msg))
-                })??;
-
-                // Write stdout/stderr to txt_log_file
-                if let Ok(mut txt_log_writer) = File::create(&txt_log_file) {
-                    for log in &stdout_logs {
-                        writeln!(txt_log_writer, "{}", log).ok();
-                    }
-                    for log in &stderr_logs {
-                        writeln!(txt_log_writer, "{}", log).ok();
-                    }
-                    txt_log_writer.flush().ok();
-                } else {
-                    let msg = logger.warning(
-                        &format!("Failed to create log file '{}'", txt_log_file.display())
-                    );
+        // Combinations after the first reuse the makefiles CubeIDE generated
+        let warm_spec = match self.make_tools.as_ref().filter(|_| combination.index > 0) {
+            Some(tools) => {
+                let warm_spec = tools.spec(self.build_dir, self.config.clean_build, self.project_path);
+                if warm_spec.is_none() {
+                    self.logger.warning(&tr!("warm.no_makefile", self.build_dir.display()));
                 }
+                warm_spec
+            }
+            None => None,
+        };
+        self.command = match warm_spec {
+            Some(warm_spec) => {
+                *spec = warm_spec;
+                tr!("warm.command", spec.command_line())
+            }
+            None => tr!("cubeide.command", spec.command_line()),
+        };
+        if let Some(epoch) = self.source_date_epoch {
+            spec.envs.push(("SOURCE_DATE_EPOCH".to_string(), epoch.to_string()));
+        }
+        if let Err(e) = rotate_log_async(combination.log_file, self.log_retention).await {
+            self.logger.warning(&tr!("log.rotate_failed", combination.log_file.display(), e));
+        }
+        Ok(())
+    }
 
-                // Check process status
-                let exit_code = status.code().unwrap_or(-1);
-                let status_msg = logger.log(
-                    &format!("Build process exited with code: {}", exit_code),
-                    if exit_code == 0 { LogLevel::Info } else { LogLevel::Error }
-                );
+    async fn after_build(&mut self, combination: &Combination<'_>, header: &str) -> Result<(), BuildResult> {
+        let artifact = combination.artifact;
+        match fs::metadata(artifact).await {
+            Ok(metadata) => {
+                self.logger.info(&tr!("output.size", metadata.len()));
+            }
+            Err(_) => {
+                let msg = tr!("output.metadata", artifact.display());
+                return Err(self.fail(msg));
+            }
+        }
 
-                if exit_code != 0 {
-                    success = false;
-                    return Ok(BuildResult {
-                        result: format!("Build failed with exit code: {}", exit_code),
-                        logs: logger.get_logs().clone(),
-                        stages,
-                        success
-                    });
-                }
-
-                // Add build results check
-                time::sleep(Duration::from_secs(2)).await;
-
-                // Check build directory contents
-                stages.push(format!("Checking build directory contents for combination {:?}", combination));
-                let build_dir_name = build_config.config_name.as_deref().unwrap_or("Debug");
-                let build_dir = project_path.join(build_dir_name);
-                let expected_bin_file = build_dir.join(format!("{}.bin", project_name.to_lowercase()));
-                if !build_dir.exists() || !expected_bin_file.exists() {
-                    let msg = logger.error(&format!("Error: Output file '{}.bin' not found in '{}'", project_name.to_lowercase(), build_dir.display()));
-                    success = false;
-                    return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-                }
-
-                // Check file size
-                if let Ok(metadata) = fs::metadata(&expected_bin_file) {
-                    let msg = logger.info(&format!("Output file size: {} bytes", metadata.len()));
-                } else {
-                    let msg = logger.error(&format!("Failed to get output file metadata: {}", expected_bin_file.display()));
-                    success = false;
-                    return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-                }
-
-                // Rename bin file
-                stages.push(format!("Renaming output file for combination {:?}", combination));
-                if let Err(e) = fs::rename(&expected_bin_file, &bin_dst) {
-                    let msg = logger.error(&format!("Error moving '{}' to '{}': {}", expected_bin_file.display(), bin_dst.display(), e));
-                    success = false;
-                    return Ok(BuildResult { result: msg, logs: logger.get_logs().clone(), stages, success });
-                }
-
-                // После завершения:
-                {
-                    let mut child_guard = BUILD_CHILD.lock().await;
-                    *child_guard = None;
-                }
-            }
-            _ = cancel_notify.notified() => {
-                println!("[CANCEL] Cancel notification received in builder.rs");
-                
-                // Notify frontend before killing process
-                let msg = logger.info("Build cancellation in progress");
-                
-                // Kill the process and tasks
-                let _ = child_ref.kill().await;
-                let _ = stdout_task.abort();
-                let _ = stderr_task.abort();
-                
-                // Wait a bit to ensure process is killed
-                tokio::time::sleep(Duration::from_millis(300)).await;
-                
-                // Release handle and update config atomically
-                {
-                    let mut child_guard = BUILD_CHILD.lock().await;
-                    *child_guard = None;
-                    
-                    let mut config_guard = BUILD_CONFIG.lock().await;
-                    if let Some(config) = config_guard.as_mut() {
-                        config.cancelled = Some(true);
+        // Section sizes for the size trend
+        if let Some(elf_file) = find_output(self.build_dir, self.project_name, "elf") {
+            match elf_size::read_sizes(&elf_file) {
+                Ok(sizes) => {
+                    self.logger.info(&tr!("output.sections", sizes.text, sizes.data, sizes.bss));
+                    if let Some(last) = self.combinations.last_mut() {
+                        last.sizes = Some(sizes);
                     }
                 }
-
-                // Send events in order with confirmation
-                let msg = logger.info("Build process cancelled");
-                
-                // Send build-cancelled event and wait for confirmation
-                match window.emit("build-cancelled", true) {
-                    Ok(_) => println!("[CANCEL] build-cancelled event sent successfully"),
-                    Err(e) => println!("[CANCEL] Failed to send build-cancelled event: {}", e),
+                Err(e) => {
+                    self.logger.warning(&e);
                 }
+            }
+        }
 
-                success = false;
-                return Ok(BuildResult { 
-                    result: msg,
-                    logs: logger.get_logs().clone(), 
-                    stages,
-                    success 
-                });
+        if self.config.bin_footer.unwrap_or(false) {
+            let footer = BinFooter {
+                project: self.project_name.to_string(),
+                configuration: self.config.config_name.as_deref().unwrap_or("Debug").to_string(),
+                combination: combination.settings.iter().cloned().collect(),
+                git_hash: self.git.map(|g| g.hash.clone()),
+                git_dirty: self.git.map(|g| g.dirty),
+                version: self.version_label.map(str::to_string),
+            };
+            if let Err(e) = footer.append_to(artifact) {
+                return Err(self.fail(e));
             }
         }
-        // --- конец асинхронного ожидания ---
+        // After the footer, so the signature covers it
+        if let Some(settings) = &self.config.signing {
+            match signing::sign(settings, self.signing_root, artifact).await {
+                Ok(signature) => {
+                    self.logger.info(&tr!("signing.signed", artifact.display()));
+                    if let Some(last) = self.combinations.last_mut() {
+                        last.signature = Some(signature.display().to_string());
+                    }
+                }
+                Err(e) => return Err(self.fail(e)),
+            }
+        }
+        let artifact_size = fs::metadata(artifact).await.ok().map(|m| m.len());
+        if let Some(last) = self.combinations.last_mut() {
+            last.artifact = Some(artifact.display().to_string());
+            last.artifact_size = artifact_size;
+        }
 
-        // ...existing code...
+        // Compilation database for clang tooling, from the compiler invocations CDT echoed
+        let compile_commands = compile_db::from_output(&self.stdout, self.build_dir);
+        if !compile_commands.is_empty()
+            && let Err(e) = compile_db::write(&combination.dir.join(COMPILE_COMMANDS_FILE), &compile_commands)
         {
-            let mut child_guard = BUILD_CHILD.lock().await;
-            *child_guard = None;
+            self.logger.warning(&e);
         }
-    }
-
-    if !any_build_executed {
-        let msg = logger.error("No build combinations were executed. Check your build settings.");
-        return Ok(BuildResult { 
-            result: msg, 
-            logs: logger.get_logs().clone(), 
-            stages, 
-            success: false 
-        });
-    }
 
-    // Write logs
-    stages.push("Writing logs".to_string());
-    if let Err(e) = File::create(&log_file_path).and_then(|mut f| {
-        for log in logger.get_logs() {
-            writeln!(f, "{}", log)?;
+        self.analyse(combination, header).await?;
+        if let Some(last) = self.combinations.last() {
+            emit_to_window(self.window, COMBINATION_FINISHED, last);
+        }
+        if let Some(timer) = self.budget_timer.take()
+            && let Err(e) = timer.stop().await
+        {
+            self.logger.warning(&e);
         }
         Ok(())
-    }) {
-        let msg = logger.error(&format!("Failed to write logs: {}", e));
-        success = false;
-        return Ok(BuildResult { 
-            result: msg, 
-            logs: logger.get_logs().clone(), 
-            stages, 
-            success 
-        });
     }
 
-    // Finalize build result
-    stages.push("Build process completed".to_string());
-    let last_result = if success {
-        logger.info("Build process completed successfully")
-    } else {
-        logger.error("Build process completed with errors")
-    };
-
-    Ok(BuildResult { 
-        result: last_result, 
-        logs: logger.get_logs().clone(), 
-        stages, 
-        success 
-    })
-}
\ No newline at end of file
+    fn report(&mut self, combination: &Combination<'_>, event: MatrixEvent<'_>) {
+        let settings = combination.settings;
+        match event {
+            MatrixEvent::Step(MatrixStep::RemoveArtifact) => {
+                self.logger.set_stage("header_generation");
+                self.logger.begin_step("remove_bin", &tr!("step.remove_bin", format!("{:?}", settings)));
+            }
+            MatrixEvent::Step(MatrixStep::GenerateHeader) => {
+                self.logger.begin_step("generate_header", &tr!("step.generate_header", format!("{:?}", settings)));
+            }
+            MatrixEvent::Step(MatrixStep::Build) => {
+                self.logger.set_stage("cubeide");
+                self.logger.begin_step("cubeide", &tr!("step.cubeide", format!("{:?}", settings)));
+                self.logger.info(&self.command);
+            }
+            MatrixEvent::Step(MatrixStep::CheckOutput) => {
+                self.logger.set_stage("artifacts");
+                self.logger.begin_step("check_output", &tr!("step.check_output", format!("{:?}", settings)));
+            }
+            MatrixEvent::Step(MatrixStep::MoveArtifact) => {
+                self.logger.begin_step("rename_output", &tr!("step.rename_output", format!("{:?}", settings)));
+            }
+            MatrixEvent::Started { pid, warnings } => {
+                for warning in warnings {
+                    self.logger.warning(warning);
+                }
+                if let Some(pid) = pid {
+                    // Report CPU/RAM of CubeIDE and its children while it runs
+                    self.resource_monitor = Some(resources::spawn_monitor(self.window.clone(), self.run_id.to_string(), combination.name.to_string(), pid));
+                    self.tracked = Some(self.manager.track_child(self.run_id, combination.name, pid));
+                }
+            }
+            MatrixEvent::Stopped => {
+                if let Some(monitor) = self.resource_monitor.take() {
+                    monitor.abort();
+                }
+                self.tracked = None;
+            }
+            MatrixEvent::Exited { exit_code, stdout, stderr } => {
+                self.logger.log(
+                    &tr!("process.exit_code", exit_code),
+                    if exit_code == 0 { LogLevel::Info } else { LogLevel::Error }
+                );
+                // Index raw output for search_logs
+                if let Err(e) = log_index::index_path(self.window).and_then(|path| {
+                    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+                    log_index::index_output(&path, self.run_id, combination.name, &timestamp, stdout)?;
+                    log_index::index_output(&path, self.run_id, combination.name, &timestamp, stderr)
+                }) {
+                    self.logger.warning(&tr!("log.index_failed", e));
+                }
+                self.exit_code = Some(exit_code);
+                self.stdout = stdout.to_vec();
+            }
+            MatrixEvent::WriteFailed { path, error } => {
+                self.logger.warning(&tr!("fs.write", path.display(), error));
+            }
+            MatrixEvent::Judged(outcome) => {
+                self.logger.info(&tr!("diagnostics.summary", outcome.errors, outcome.warnings));
+                let failure_hint = outcome.failure_hint.clone().map(failure_hints::localize);
+                if let Some(hint) = &failure_hint {
+                    self.logger.error(&format!("{} ({:?}). {}", hint.evidence, hint.code, hint.hint));
+                }
+                self.combinations.push(CombinationResult {
+                    combination: combination.name.to_string(),
+                    success: outcome.failure.is_none(),
+                    exit_code: self.exit_code,
+                    errors: outcome.errors,
+                    warnings: outcome.warnings,
+                    warnings_exceeded: outcome.warnings_exceeded,
+                    failure_reason: outcome.failure.as_ref().map(failure_reason),
+                    failure_hint,
+                    diagnostics: outcome.diagnostics.clone(),
+                    artifact: None,
+                    artifact_size: None,
+                    sizes: None,
+                    log_file: Some(combination.log_file.display().to_string()),
+                    simulation: None,
+                    unused_code: None,
+                    ram_breakdown: None,
+                    signature: None,
+                });
+            }
+        }
+    }
+}
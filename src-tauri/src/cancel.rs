@@ -1,44 +1,41 @@
-use crate::process::{BUILD_CANCEL_NOTIFY, BUILD_CONFIG, kill_process_and_children, kill_build_child_process};
-use crate::utils::{LogLevel};
 use crate::logging::Logger;
-use sysinfo::{System, ProcessesToUpdate};
-use tauri::{command, Window, Emitter, Manager};
-use tokio::sync::MutexGuard;
-use std::time::Duration;
+use crate::i18n::tr;
+use crate::logging::emit_to_window;
+use tauri::{command, Window, Manager};
+use crate::process::{kill_build_children, BuildManager};
 
-#[command] 
-pub async fn cancel_build(window: Window) -> Result<(), String> {
+/// Cancels a run, or every run this window started when `run_id` is not
+/// given.
+/// The run's CubeIDE process trees are killed; the builder notices the
+/// cancelled token and ends the run.
+#[command]
+pub async fn cancel_build(window: Window, run_id: Option<String>) -> Result<(), String> {
     let mut logger = Logger::new(&window);
     logger.debug("Starting cancel_build process");
 
-    // First mark as cancelled and notify
-    {
-        let mut config_guard = BUILD_CONFIG.lock().await;
-        if let Some(config) = config_guard.as_mut() {
-            config.cancelled = Some(true);
-        }
+    let manager = window.state::<BuildManager>();
+    let cancelled = manager.cancel(run_id.as_deref(), window.label());
+    if cancelled.is_empty() {
+        return Err(match run_id {
+            Some(run_id) => tr!("cancel.not_running", run_id),
+            None => tr!("cancel.no_run"),
+        });
     }
-    
-    BUILD_CANCEL_NOTIFY.notify_waiters();
-    logger.debug("Notified cancel waiters");
+    logger.debug(&format!("Cancelled runs: {}", cancelled.join(", ")));
 
-    // Kill the process first
-    match kill_build_child_process().await {
-        Ok(_) => {
-            logger.debug("Process killed successfully");
+    for run in &cancelled {
+        for (key, pid) in manager.children(run) {
+            logger.debug(&format!("Killing build process {} of {} ({})", pid, key.run_id, key.combination));
         }
-        Err(e) => {
-            logger.error(&format!("Kill error: {}", e));
+        if let Err(e) = kill_build_children(&manager, run) {
+            logger.error(&tr!("cancel.kill_error", e));
         }
     }
 
-    // Additional wait to ensure process cleanup
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
     // Send confirmation events
-    logger.info("Build process terminated");
-    window.emit("build-cancelled", true).ok();
+    logger.info(&tr!("cancel.terminated"));
+    emit_to_window(&window, "build-cancelled", true);
     logger.debug("Sent build-cancelled event");
 
     Ok(())
-}
\ No newline at end of file
+}
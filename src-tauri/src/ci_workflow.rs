@@ -0,0 +1,156 @@
+use crate::git_info;
+use crate::i18n::tr;
+use crate::models::BuildConfig;
+use crate::utils::get_project_name;
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use stm32_builder_core::build_combinations::generate_build_combinations;
+use stm32_builder_core::build_config_gen::generate_build_config_h;
+use stm32_builder_core::builder::{combination_name, HEADLESS_APPLICATION, GENERATED_HEADER};
+use stm32_builder_core::config::BuildSettingsConfig;
+use stm32_builder_core::selection::selection_combinations;
+use tauri::command;
+
+const WORKFLOW_FILE: &str = ".github/workflows/stm32-matrix.yml";
+// GitHub-hosted runners have no STM32CubeIDE, so a self-hosted one is assumed
+const DEFAULT_RUNNER: &[&str] = &["self-hosted", "stm32cubeide"];
+
+fn map(entries: Vec<(&str, Value)>) -> Value {
+    let mut mapping = Mapping::new();
+    for (key, value) in entries {
+        mapping.insert(Value::from(key), value);
+    }
+    Value::Mapping(mapping)
+}
+
+fn step(name: &str, entries: Vec<(&str, Value)>) -> Value {
+    let mut all = vec![("name", Value::from(name))];
+    all.extend(entries);
+    map(all)
+}
+
+/// GitHub Actions workflow building the same matrix as `config`: one job
+/// per combination, each writing its `build_config.h` and running a
+/// headless CubeIDE build on a self-hosted runner.
+fn workflow(config: &BuildConfig, runs_on: &[String]) -> Result<String, String> {
+    let settings_config = BuildSettingsConfig::load()?;
+    let combinations = match &config.selected_combinations {
+        Some(selection) => selection_combinations(&settings_config, selection)?,
+        None => generate_build_combinations(&settings_config, &config.settings),
+    };
+    if combinations.is_empty() {
+        return Err(tr!("combinations.none"));
+    }
+
+    let project_path = Path::new(&config.project_path);
+    // Paths in the workflow are relative to the repository root
+    let prefix = git_info::repo_prefix(project_path).ok_or_else(|| tr!("git.not_a_repository", project_path.display()))?;
+    let project_dir = if prefix.is_empty() { ".".to_string() } else { prefix.trim_end_matches('/').to_string() };
+    let project_name = match &config.project_name {
+        Some(name) => name.clone(),
+        None => get_project_name(project_path).map_err(|e| e.to_string())?,
+    };
+    let config_name = config.config_name.as_deref().unwrap_or("Debug");
+    let build_flag = if config.clean_build { "-cleanBuild" } else { "-build" };
+
+    let mut include = Vec::new();
+    for combination in &combinations {
+        let header = generate_build_config_h(&settings_config, combination, "")?;
+        include.push(map(vec![
+            ("name", Value::from(combination_name(&settings_config, combination))),
+            ("header", Value::from(header)),
+        ]));
+    }
+
+    let build_command = format!(
+        "\"$CUBEIDE\" --launcher.suppressErrors -nosplash -application {application} \
+         -data \"$RUNNER_TEMP/workspace\" -import \"$GITHUB_WORKSPACE/{dir}\" -include \"$GITHUB_WORKSPACE/{dir}/{header}\" {flag} \"{project}/{config}\"{custom}",
+        application = HEADLESS_APPLICATION,
+        header = GENERATED_HEADER,
+        dir = project_dir,
+        flag = build_flag,
+        project = project_name,
+        config = config_name,
+        custom = config
+            .custom_console_args
+            .as_deref()
+            .map(|args| format!(" {}", args))
+            .unwrap_or_default(),
+    );
+    let steps = vec![
+        step("Check out", vec![("uses", Value::from("actions/checkout@v4")), ("with", map(vec![("submodules", Value::from("recursive"))]))]),
+        step(
+            "Generate build_config.h",
+            vec![
+                ("shell", Value::from("bash")),
+                ("env", map(vec![("HEADER", Value::from("${{ matrix.header }}"))])),
+                ("run", Value::from(format!("printf '%s' \"$HEADER\" > \"{}/{}\"", project_dir, GENERATED_HEADER))),
+            ],
+        ),
+        step("Build", vec![("shell", Value::from("bash")), ("run", Value::from(build_command))]),
+        step(
+            "Upload firmware",
+            vec![
+                ("uses", Value::from("actions/upload-artifact@v4")),
+                (
+                    "with",
+                    map(vec![
+                        ("name", Value::from("${{ matrix.name }}")),
+                        ("path", Value::from(format!("{}/{}/*.bin\n{}/{}/*.elf", project_dir, config_name, project_dir, config_name))),
+                    ]),
+                ),
+            ],
+        ),
+    ];
+
+    let runner: Vec<Value> = if runs_on.is_empty() {
+        DEFAULT_RUNNER.iter().map(|l| Value::from(*l)).collect()
+    } else {
+        runs_on.iter().map(|l| Value::from(l.as_str())).collect()
+    };
+    let job = map(vec![
+        ("name", Value::from("${{ matrix.name }}")),
+        ("runs-on", Value::Sequence(runner)),
+        (
+            "strategy",
+            map(vec![
+                ("fail-fast", Value::from(false)),
+                ("matrix", map(vec![("include", Value::Sequence(include))])),
+            ]),
+        ),
+        ("steps", Value::Sequence(steps)),
+    ]);
+    let document = map(vec![
+        ("name", Value::from(format!("{} firmware matrix", project_name))),
+        ("on", map(vec![("push", Value::Null), ("pull_request", Value::Null), ("workflow_dispatch", Value::Null)])),
+        // Adjust to the runner's installation
+        ("env", map(vec![("CUBEIDE", Value::from(config.cube_ide_exe_path.as_str()))])),
+        ("jobs", map(vec![("build", job)])),
+    ]);
+    serde_yaml::to_string(&document).map_err(|e| tr!("ci.serialize_failed", e))
+}
+
+/// Writes a GitHub Actions workflow for the current matrix, by default to
+/// `.github/workflows/stm32-matrix.yml` in the repository. Returns its path.
+#[command]
+pub async fn export_ci_workflow(
+    config: BuildConfig,
+    destination: Option<String>,
+    runs_on: Option<Vec<String>>,
+) -> Result<String, String> {
+    let content = workflow(&config, &runs_on.unwrap_or_default())?;
+    let path = match destination {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let root = git_info::repo_root(Path::new(&config.project_path))
+                .ok_or_else(|| tr!("git.not_a_repository", config.project_path))?;
+            root.join(WORKFLOW_FILE)
+        }
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| tr!("fs.create_dir", parent.display(), e))?;
+    }
+    fs::write(&path, content).map_err(|e| tr!("fs.write", path.display(), e))?;
+    Ok(path.display().to_string())
+}
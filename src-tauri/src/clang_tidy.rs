@@ -0,0 +1,107 @@
+use crate::analysis::{run_tool, CombinationBuild};
+use crate::compile_db::{self, COMPILE_COMMANDS_FILE};
+use stm32_builder_core::command_line;
+use stm32_builder_core::diagnostics::{self, Diagnostic};
+use crate::i18n::tr;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const DEFAULT_CHECKS: &str = "-*,bugprone-*,clang-analyzer-*";
+// CubeMX-generated vendor code, not worth the time or the noise
+const SKIPPED_DIRS: [&str; 2] = ["Drivers", "Middlewares"];
+
+lazy_static! {
+    // trailing " [bugprone-branch-clone]" / " [clang-analyzer-core.NullDereference,-warnings-as-errors]"
+    static ref CHECK_NAME: Regex = Regex::new(r"\s+\[(?P<check>[\w.-]+)(?:,[^\]]*)?\]$").unwrap();
+}
+
+/// clang-tidy stage, run after every successful combination on the
+/// compilation database recovered from its build.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClangTidySettings {
+    /// `clang-tidy` on the PATH by default.
+    pub path: Option<String>,
+    /// Value of `--checks`; a `.clang-tidy` file in the project also applies.
+    pub checks: Option<String>,
+    /// Checks that fail the combination when they report anything, e.g.
+    /// `bugprone-*`.
+    #[serde(rename = "failOn", default)]
+    pub fail_on: Vec<String>,
+    /// Further arguments, e.g. `--header-filter=Core/.*`.
+    pub args: Option<String>,
+}
+
+fn parse_finding(line: &str) -> Option<Diagnostic> {
+    let mut diagnostic = diagnostics::parse_line(line)?;
+    // Notes carry no check name; they only explain the finding above them
+    let caps = CHECK_NAME.captures(&diagnostic.message)?;
+    let (check, start) = (caps["check"].to_string(), caps.get(0).unwrap().start());
+    diagnostic.message.truncate(start);
+    diagnostic.option = Some(format!("clang-tidy:{}", check));
+    Some(diagnostic)
+}
+
+fn matches(pattern: &str, check: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => check.starts_with(prefix),
+        None => pattern == check,
+    }
+}
+
+/// Checks among `findings` that are configured to fail the combination.
+pub fn failing_checks(settings: &ClangTidySettings, findings: &[Diagnostic]) -> Vec<String> {
+    let mut checks: Vec<String> = findings
+        .iter()
+        .filter_map(|d| d.option.as_deref()?.strip_prefix("clang-tidy:"))
+        .filter(|check| settings.fail_on.iter().any(|pattern| matches(pattern, check)))
+        .map(str::to_string)
+        .collect();
+    checks.sort();
+    checks.dedup();
+    checks
+}
+
+/// Runs clang-tidy on the project's sources that the combination compiled,
+/// with the flags and macros they were compiled with.
+pub async fn run(settings: &ClangTidySettings, build: &CombinationBuild<'_>) -> Result<Vec<Diagnostic>, String> {
+    let database = build.combo_dir.join(COMPILE_COMMANDS_FILE);
+    if !database.exists() {
+        return Err(tr!("clang_tidy.no_compile_commands", build.combo_dir.display()));
+    }
+    let files: Vec<String> = compile_db::load(&database)?
+        .into_iter()
+        .map(|entry| Path::new(&entry.directory).join(&entry.file))
+        .filter(|file| {
+            let relative = file.strip_prefix(build.build_dir).unwrap_or(file);
+            !relative.components().any(|c| SKIPPED_DIRS.iter().any(|dir| c.as_os_str() == *dir))
+        })
+        .map(|file| file.display().to_string())
+        .collect();
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let program = settings.path.clone().unwrap_or_else(|| "clang-tidy".to_string());
+    let mut args = vec![
+        "--quiet".to_string(),
+        format!("-p={}", build.combo_dir.display()),
+        format!("--checks={}", settings.checks.as_deref().unwrap_or(DEFAULT_CHECKS)),
+        // The database holds arm-none-eabi-gcc command lines
+        "--extra-arg-before=--target=arm-none-eabi".to_string(),
+    ];
+    if let Some(extra) = &settings.args {
+        args.extend(command_line::split(extra));
+    }
+    args.extend(files);
+
+    let (_, stdout, _) = run_tool(&program, &args, build.project_path).await?;
+    let mut findings: Vec<Diagnostic> = Vec::new();
+    for finding in stdout.iter().filter_map(|line| parse_finding(line)) {
+        if !findings.contains(&finding) {
+            findings.push(finding);
+        }
+    }
+    Ok(findings)
+}
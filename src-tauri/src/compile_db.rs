@@ -0,0 +1,93 @@
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const COMPILE_COMMANDS_FILE: &str = "compile_commands.json";
+
+const SOURCE_EXTENSIONS: [&str; 4] = [".c", ".cpp", ".cc", ".cxx"];
+// gcc options clang rejects as unknown; dropped so clang tools can use the database
+const GCC_ONLY_FLAGS: [&str; 4] = ["-fstack-usage", "-fcyclomatic-complexity", "--specs=", "-fdiagnostics-format="];
+
+/// Entry of a clang compilation database.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompileCommand {
+    pub directory: String,
+    pub file: String,
+    pub arguments: Vec<String>,
+}
+
+/// Splits a command line echoed by CDT. Quotes may start mid-argument,
+/// as in `-MF"Core/Src/main.d"`.
+pub fn split_command(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+fn is_compiler(program: &str) -> bool {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    name.ends_with("gcc") || name.ends_with("g++")
+}
+
+/// Compilation database of the C/C++ sources CubeIDE compiled, recovered
+/// from the compiler invocations it echoes. Only the files rebuilt by the
+/// run are listed, so incremental builds give a partial database.
+pub fn from_output(lines: &[String], build_dir: &Path) -> Vec<CompileCommand> {
+    let directory = build_dir.display().to_string();
+    lines
+        .iter()
+        .filter_map(|line| {
+            let args = split_command(line.trim());
+            if !args.first().map(|p| is_compiler(p)).unwrap_or(false) || !args.iter().any(|a| a == "-c") {
+                return None;
+            }
+            let file = args
+                .iter()
+                .skip(1)
+                .find(|a| !a.starts_with('-') && SOURCE_EXTENSIONS.iter().any(|ext| a.ends_with(ext)))?
+                .clone();
+            let arguments = args
+                .into_iter()
+                .filter(|a| !GCC_ONLY_FLAGS.iter().any(|flag| a.starts_with(flag)))
+                .collect();
+            Some(CompileCommand { directory: directory.clone(), file, arguments })
+        })
+        .collect()
+}
+
+pub fn write(path: &Path, commands: &[CompileCommand]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(commands)
+        .map_err(|e| tr!("compile_db.serialize_failed", e))?;
+    fs::write(path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+pub fn load(path: &Path) -> Result<Vec<CompileCommand>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| tr!("fs.read", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| tr!("compile_db.invalid", path.display(), e))
+}
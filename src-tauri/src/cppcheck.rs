@@ -0,0 +1,79 @@
+use crate::analysis::{run_tool, CombinationBuild};
+use stm32_builder_core::command_line;
+use stm32_builder_core::diagnostics::{Diagnostic, Severity};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const TEMPLATE: &str = "{file}:{line}:{column}: {severity}: {message} [{id}]";
+const DEFAULT_CHECKS: &str = "warning,style,performance,portability";
+// Include folders of a CubeMX project; the HAL is left out to keep runs short
+const INCLUDE_DIRS: [&str; 2] = ["Inc", "Core/Inc"];
+
+lazy_static! {
+    // Output of `TEMPLATE`
+    static ref FINDING: Regex = Regex::new(
+        r"^(?P<file>.+?):(?P<line>\d+):(?P<column>\d+): (?P<severity>\w+): (?P<message>.*) \[(?P<id>\w+)\]$"
+    ).unwrap();
+}
+
+/// cppcheck stage, run after every successful combination.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CppcheckSettings {
+    /// `cppcheck` on the PATH by default.
+    pub path: Option<String>,
+    /// Value of `--enable`.
+    pub checks: Option<String>,
+    /// Further arguments, e.g. `--suppress=missingIncludeSystem`.
+    pub args: Option<String>,
+}
+
+fn parse_finding(line: &str) -> Option<Diagnostic> {
+    let caps = FINDING.captures(line.trim())?;
+    let severity = match &caps["severity"] {
+        "error" => Severity::Error,
+        "information" => Severity::Note,
+        _ => Severity::Warning,
+    };
+    Some(Diagnostic {
+        severity,
+        message: caps["message"].to_string(),
+        file: Some(caps["file"].to_string()),
+        line: caps["line"].parse().ok(),
+        // cppcheck reports 0 when it has no column
+        column: caps["column"].parse().ok().filter(|c| *c > 0),
+        option: Some(format!("cppcheck:{}", &caps["id"])),
+        fixits: Vec::new(),
+    })
+}
+
+/// Checks the project sources with the combination's macros, so only the
+/// code that is actually built for it is analysed.
+pub async fn run(settings: &CppcheckSettings, build: &CombinationBuild<'_>) -> Result<Vec<Diagnostic>, String> {
+    let program = settings.path.clone().unwrap_or_else(|| "cppcheck".to_string());
+    let mut args = vec![
+        "--quiet".to_string(),
+        format!("--enable={}", settings.checks.as_deref().unwrap_or(DEFAULT_CHECKS)),
+        format!("--template={}", TEMPLATE),
+        format!("-i{}", build.build_dir.display()),
+    ];
+    args.extend(build.define_flags());
+    for dir in INCLUDE_DIRS {
+        if build.project_path.join(dir).is_dir() {
+            args.push(format!("-I{}", dir));
+        }
+    }
+    if let Some(extra) = &settings.args {
+        args.extend(command_line::split(extra));
+    }
+    args.push(".".to_string());
+
+    let (_, stdout, stderr) = run_tool(&program, &args, build.project_path).await?;
+    let mut findings: Vec<Diagnostic> = Vec::new();
+    for finding in stderr.iter().chain(stdout.iter()).filter_map(|line| parse_finding(line)) {
+        if !findings.contains(&finding) {
+            findings.push(finding);
+        }
+    }
+    Ok(findings)
+}
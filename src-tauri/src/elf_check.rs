@@ -0,0 +1,309 @@
+use crate::analysis::GateAction;
+use crate::i18n::tr;
+use stm32_builder_core::diagnostics::{Diagnostic, Severity};
+use lazy_static::lazy_static;
+use object::elf::{PT_LOAD, SHF_ALLOC};
+use object::read::elf::{ElfFile32, ProgramHeader};
+use object::{Architecture, Endianness, Object, ObjectSection, ObjectSymbol, SectionFlags};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_ENTRY: &str = "Reset_Handler";
+
+lazy_static! {
+    // FLASH (rx) : ORIGIN = 0x8000000, LENGTH = 512K
+    static ref MEMORY_REGION: Regex = Regex::new(
+        r"(?m)^\s*(?P<name>\w+)\s*\((?P<attrs>[!rwxail]+)\)\s*:\s*ORIGIN\s*=\s*(?P<origin>0[xX][0-9a-fA-F]+|\d+)\s*,\s*LENGTH\s*=\s*(?P<length>0[xX][0-9a-fA-F]+|\d+)\s*(?P<unit>[KkMm]?)"
+    ).unwrap();
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+    /// RAM; initialized data must be loaded from a region that is not.
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl MemoryRegion {
+    fn contains(&self, start: u64, size: u64) -> bool {
+        start >= self.origin && start + size <= self.origin + self.length
+    }
+}
+
+/// Structural checks of each combination's ELF against the memory layout.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ElfChecks {
+    /// Memory regions; read from the project's linker script when empty.
+    #[serde(default)]
+    pub regions: Vec<MemoryRegion>,
+    /// Symbol the entry point must be; `Reset_Handler` by default.
+    pub entry: Option<String>,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Linker script of a CubeIDE project: `*_FLASH.ld` in the project root
+/// (CubeMX also generates `*_RAM.ld` for running from RAM), else any `.ld`.
+fn linker_script(project_path: &Path) -> Option<PathBuf> {
+    let mut scripts: Vec<PathBuf> = fs::read_dir(project_path)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "ld").unwrap_or(false))
+        .collect();
+    scripts.sort();
+    let flash = scripts
+        .iter()
+        .position(|path| path.to_string_lossy().to_uppercase().ends_with("_FLASH.LD"));
+    match flash {
+        Some(index) => Some(scripts.swap_remove(index)),
+        None => scripts.into_iter().next(),
+    }
+}
+
+/// `MEMORY` regions declared in the project's linker script.
+pub fn linker_regions(project_path: &Path) -> Vec<MemoryRegion> {
+    let Some(content) = linker_script(project_path).and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    MEMORY_REGION
+        .captures_iter(&content)
+        .filter_map(|caps| {
+            let multiplier = match &caps["unit"] {
+                "K" | "k" => 1024,
+                "M" | "m" => 1024 * 1024,
+                _ => 1,
+            };
+            Some(MemoryRegion {
+                name: caps["name"].to_string(),
+                origin: parse_number(&caps["origin"])?,
+                length: parse_number(&caps["length"])? * multiplier,
+                writable: caps["attrs"].contains('w') && !caps["attrs"].starts_with('!'),
+            })
+        })
+        .collect()
+}
+
+fn region_name(regions: &[MemoryRegion], address: u64, size: u64) -> Option<&str> {
+    regions
+        .iter()
+        .find(|r| r.contains(address, size))
+        .map(|r| r.name.as_str())
+}
+
+/// Problems found in the ELF, as findings of severity matching the
+/// action. `regions` are the ones to check placement against.
+pub fn check(checks: &ElfChecks, regions: &[MemoryRegion], elf: &Path) -> Result<Vec<Diagnostic>, String> {
+    let data = fs::read(elf).map_err(|e| tr!("fs.read", elf.display(), e))?;
+    let file = ElfFile32::<Endianness>::parse(&*data)
+        .map_err(|e| tr!("elf.parse_failed", elf.display(), e))?;
+    // Addresses of another target would be checked against the wrong memory map
+    if file.architecture() != Architecture::Arm {
+        return Err(tr!("elf.not_arm", elf.display(), format!("{:?}", file.architecture())));
+    }
+    let severity = match checks.action {
+        GateAction::Warn => Severity::Warning,
+        GateAction::Fail => Severity::Error,
+    };
+    let mut findings = Vec::new();
+    let mut finding = |check: &str, message: String| {
+        findings.push(Diagnostic {
+            severity,
+            message,
+            file: Some(elf.display().to_string()),
+            line: None,
+            column: None,
+            option: Some(format!("elf:{}", check)),
+            fixits: Vec::new(),
+        })
+    };
+
+    // Thumb code: the low bit of the entry point is set
+    let entry = file.entry() & !1;
+    let entry_name = checks.entry.as_deref().unwrap_or(DEFAULT_ENTRY);
+    match file.symbols().find(|s| s.name() == Ok(entry_name)) {
+        Some(symbol) if symbol.address() & !1 != entry => finding(
+            "entry",
+            tr!("elf.entry_mismatch", format!("0x{:08x}", entry), entry_name, format!("0x{:08x}", symbol.address() & !1)),
+        ),
+        Some(_) => {}
+        None => finding("entry", tr!("elf.entry_undefined", entry_name)),
+    }
+    if !regions.is_empty() && region_name(regions, entry, 2).is_none() {
+        finding("entry", tr!("elf.entry_outside", format!("0x{:08x}", entry)));
+    }
+    if regions.is_empty() {
+        return Ok(findings);
+    }
+
+    // Run-time addresses of everything that occupies memory
+    for section in file.sections() {
+        let SectionFlags::Elf { sh_flags } = section.flags() else { continue };
+        if sh_flags & u64::from(SHF_ALLOC) == 0 || section.size() == 0 {
+            continue;
+        }
+        if region_name(regions, section.address(), section.size()).is_none() {
+            finding(
+                "placement",
+                tr!(
+                    "elf.section_outside",
+                    section.name().unwrap_or("?"),
+                    format!("0x{:08x}", section.address()),
+                    section.size()
+                ),
+            );
+        }
+    }
+
+    // Load addresses: initialized contents must be stored in non-volatile memory
+    let endian = file.endian();
+    for header in file.elf_program_headers() {
+        let size = u64::from(header.p_filesz(endian));
+        if header.p_type(endian) != PT_LOAD || size == 0 {
+            continue;
+        }
+        let load = u64::from(header.p_paddr(endian));
+        match regions.iter().find(|r| r.contains(load, size)) {
+            Some(region) if region.writable => finding(
+                "load",
+                tr!("elf.load_in_ram", size, format!("0x{:08x}", load), region.name),
+            ),
+            Some(_) => {}
+            None => finding(
+                "load",
+                tr!("elf.load_outside", format!("0x{:08x}", load), format!("0x{:08x}", load + size)),
+            ),
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const EM_ARM: u16 = 40;
+    const EM_386: u16 = 3;
+
+    /// Linked ELF32 image: one `.text` word at `text` loaded from `load`,
+    /// and global Thumb functions named `symbols` there.
+    pub(crate) fn elf_with_symbols(machine: u16, entry: u32, text: u32, load: u32, symbols: &[&str]) -> Vec<u8> {
+        let pad = |data: &mut Vec<u8>| data.resize(data.len().next_multiple_of(4), 0);
+        let mut strtab = vec![0];
+        let mut names = Vec::new();
+        for symbol in symbols {
+            names.push(strtab.len() as u32);
+            strtab.extend_from_slice(symbol.as_bytes());
+            strtab.push(0);
+        }
+        let shstrtab = b"\0.text\0.symtab\0.strtab\0.shstrtab\0";
+        let strtab_offset = 88;
+        let shstrtab_offset = (strtab_offset + strtab.len()).next_multiple_of(4);
+        let symtab_offset = (shstrtab_offset + shstrtab.len()).next_multiple_of(4);
+        let symtab_size = 16 * (symbols.len() + 1);
+        let sections_offset = symtab_offset + symtab_size;
+
+        let mut data = Vec::new();
+        let u16 = |data: &mut Vec<u8>, v: u16| data.extend_from_slice(&v.to_le_bytes());
+        let u32 = |data: &mut Vec<u8>, v: u32| data.extend_from_slice(&v.to_le_bytes());
+        // ELF header
+        data.extend_from_slice(&[0x7f, b'E', b'L', b'F', 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        u16(&mut data, 2);
+        u16(&mut data, machine);
+        u32(&mut data, 1);
+        u32(&mut data, entry);
+        u32(&mut data, 52);
+        u32(&mut data, sections_offset as u32);
+        u32(&mut data, 0x0500_0200);
+        for v in [52, 32, 1, 40, 5, 4] {
+            u16(&mut data, v);
+        }
+        // PT_LOAD of .text
+        for v in [1, 84, text, load, 4, 4, 5, 4] {
+            u32(&mut data, v);
+        }
+        u32(&mut data, 0xbf00_bf00);
+        data.extend_from_slice(&strtab);
+        pad(&mut data);
+        data.extend_from_slice(shstrtab);
+        pad(&mut data);
+        // Symbols: the null one, then the functions
+        data.extend_from_slice(&[0; 16]);
+        for name in names {
+            for v in [name, text | 1, 4] {
+                u32(&mut data, v);
+            }
+            data.extend_from_slice(&[0x12, 0]);
+            u16(&mut data, 1);
+        }
+        // Section headers: null, .text, .symtab, .strtab, .shstrtab
+        let sections: [[u32; 10]; 5] = [
+            [0; 10],
+            [1, 1, 6, text, 84, 4, 0, 0, 4, 0],
+            [7, 2, 0, 0, symtab_offset as u32, symtab_size as u32, 3, 1, 4, 16],
+            [15, 3, 0, 0, strtab_offset as u32, strtab.len() as u32, 0, 0, 1, 0],
+            [23, 3, 0, 0, shstrtab_offset as u32, shstrtab.len() as u32, 0, 0, 1, 0],
+        ];
+        for v in sections.iter().flatten() {
+            u32(&mut data, *v);
+        }
+        data
+    }
+
+    fn elf(machine: u16, entry: u32, text: u32, load: u32) -> Vec<u8> {
+        elf_with_symbols(machine, entry, text, load, &[DEFAULT_ENTRY])
+    }
+
+    fn regions() -> Vec<MemoryRegion> {
+        vec![
+            MemoryRegion { name: "FLASH".to_string(), origin: 0x0800_0000, length: 512 * 1024, writable: false },
+            MemoryRegion { name: "RAM".to_string(), origin: 0x2000_0000, length: 128 * 1024, writable: true },
+        ]
+    }
+
+    fn check_bytes(data: &[u8]) -> Result<Vec<Diagnostic>, String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blinky.elf");
+        fs::write(&path, data).unwrap();
+        check(&ElfChecks::default(), &regions(), &path)
+    }
+
+    #[test]
+    fn image_in_flash_passes() {
+        let findings = check_bytes(&elf(EM_ARM, 0x0800_0001, 0x0800_0000, 0x0800_0000)).unwrap();
+        assert!(findings.is_empty(), "{:?}", findings);
+    }
+
+    #[test]
+    fn image_loaded_into_ram_and_wrong_entry_are_found() {
+        let findings = check_bytes(&elf(EM_ARM, 0x2000_0101, 0x2000_0000, 0x2000_0000)).unwrap();
+        let checks: Vec<&str> = findings.iter().filter_map(|f| f.option.as_deref()).collect();
+        assert_eq!(checks, vec!["elf:entry", "elf:load"]);
+        assert!(findings.iter().all(|f| f.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn truncated_image_is_an_error() {
+        let data = elf(EM_ARM, 0x0800_0001, 0x0800_0000, 0x0800_0000);
+        assert!(check_bytes(&data[..40]).is_err());
+        assert!(check_bytes(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn image_of_another_architecture_is_an_error() {
+        let error = check_bytes(&elf(EM_386, 0x0800_0001, 0x0800_0000, 0x0800_0000)).unwrap_err();
+        assert!(error.contains("I386"), "{}", error);
+    }
+}
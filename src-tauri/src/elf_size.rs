@@ -0,0 +1,48 @@
+use crate::i18n::tr;
+use object::{Object, ObjectSection, SectionFlags, SectionKind};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+
+/// Section totals as reported by `arm-none-eabi-size` (Berkeley format).
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct SectionSizes {
+    pub text: u64,
+    pub data: u64,
+    pub bss: u64,
+    /// `text + data`: what ends up in flash.
+    pub flash: u64,
+    /// `data + bss`: statically allocated RAM.
+    pub ram: u64,
+}
+
+/// Reads section sizes from a linked ELF image.
+pub fn read_sizes(path: &Path) -> Result<SectionSizes, String> {
+    let data = fs::read(path).map_err(|e| tr!("fs.read", path.display(), e))?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| tr!("elf.parse_failed", path.display(), e))?;
+
+    let mut sizes = SectionSizes::default();
+    for section in file.sections() {
+        let flags = match section.flags() {
+            SectionFlags::Elf { sh_flags } => sh_flags,
+            _ => continue,
+        };
+        if flags & SHF_ALLOC == 0 {
+            continue;
+        }
+        if matches!(section.kind(), SectionKind::UninitializedData | SectionKind::UninitializedTls) {
+            sizes.bss += section.size();
+        } else if flags & SHF_WRITE != 0 {
+            sizes.data += section.size();
+        } else {
+            sizes.text += section.size();
+        }
+    }
+    sizes.flash = sizes.text + sizes.data;
+    sizes.ram = sizes.data + sizes.bss;
+    Ok(sizes)
+}
@@ -0,0 +1,52 @@
+use crate::i18n::tr;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use stm32_builder_core::diagnostics::Diagnostic;
+
+/// Why a run could not go on, as `BuildResult.error` or the rejection of
+/// `build_project`; serialized with a `kind` tag, e.g.
+/// `{"kind": "path_error", "path": "...", "message": "..."}`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BuildError {
+    /// Settings schema, project files or selection that cannot be used.
+    ConfigError { message: String },
+    /// A path that does not exist or cannot be read or written.
+    PathError { path: String, message: String },
+    /// Another Eclipse instance holds the workspace.
+    WorkspaceLocked { workspace: String },
+    /// STM32CubeIDE or the toolchain it needs cannot be found or started.
+    ToolchainMissing { message: String },
+    /// CubeIDE failed to build a combination.
+    CompileFailed { combination: String, message: String, diagnostics: Vec<Diagnostic> },
+    Cancelled,
+    /// The CubeIDE build of a combination ran longer than `timeoutSecs`.
+    Timeout { combination: String, seconds: u64 },
+}
+
+impl BuildError {
+    pub fn config(message: impl Into<String>) -> Self {
+        BuildError::ConfigError { message: message.into() }
+    }
+
+    pub fn path(path: &Path, message: impl Into<String>) -> Self {
+        BuildError::PathError { path: path.display().to_string(), message: message.into() }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::ConfigError { message }
+            | BuildError::PathError { message, .. }
+            | BuildError::ToolchainMissing { message }
+            | BuildError::CompileFailed { message, .. } => write!(f, "{}", message),
+            BuildError::WorkspaceLocked { workspace } => write!(f, "{}", tr!("error.workspace_locked", workspace)),
+            BuildError::Cancelled => write!(f, "{}", tr!("error.cancelled")),
+            BuildError::Timeout { combination, seconds } => write!(f, "{}", tr!("error.timeout", combination, seconds)),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,9 @@
+use crate::i18n::translate;
+
+pub use stm32_builder_core::failure_hints::{FailureCode, FailureHint};
+
+/// `hint` with the explanation in the user's language.
+pub fn localize(mut hint: FailureHint) -> FailureHint {
+    hint.hint = translate(hint.code.message_key(), &[]);
+    hint
+}
@@ -0,0 +1,177 @@
+use crate::flashing::{flash, FlashRequest, FlashResult, FlashSettings, Transport};
+use crate::i18n::tr;
+use crate::logging::{emit_to_window, Logger};
+use crate::option_bytes::RdpLevel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::{command, Window};
+
+/// One board of a batch: which combination goes onto which probe.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FlashJob {
+    pub combination: String,
+    pub file: String,
+    pub probe: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FlashBatchRequest {
+    pub settings: FlashSettings,
+    /// Run the combinations were built in.
+    #[serde(rename = "runId")]
+    pub run_id: Option<String>,
+    #[serde(rename = "projectPath")]
+    pub project_path: Option<String>,
+    pub jobs: Vec<FlashJob>,
+    pub address: Option<String>,
+    #[serde(rename = "fullErase", default)]
+    pub full_erase: bool,
+    #[serde(default)]
+    pub verify: bool,
+    #[serde(default)]
+    pub reset: bool,
+    /// Flash all boards at once; every job then needs its own probe.
+    #[serde(default)]
+    pub parallel: bool,
+    /// In sequential mode, skip the remaining boards after a failure.
+    #[serde(rename = "stopOnFailure", default)]
+    pub stop_on_failure: bool,
+    #[serde(rename = "confirmRdp", default)]
+    pub confirm_rdp: Option<RdpLevel>,
+    #[serde(rename = "ignoreMcuMismatch", default)]
+    pub ignore_mcu_mismatch: bool,
+    #[serde(default)]
+    pub transport: Transport,
+}
+
+impl FlashBatchRequest {
+    fn job_request(&self, job: &FlashJob) -> FlashRequest {
+        FlashRequest {
+            settings: self.settings.clone(),
+            file: job.file.clone(),
+            combination: Some(job.combination.clone()),
+            run_id: self.run_id.clone(),
+            project_path: self.project_path.clone(),
+            probe: job.probe.clone(),
+            address: self.address.clone(),
+            full_erase: self.full_erase,
+            verify: self.verify,
+            reset: self.reset,
+            confirm_rdp: self.confirm_rdp,
+            ignore_mcu_mismatch: self.ignore_mcu_mismatch,
+            transport: self.transport,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardStatus {
+    Queued,
+    Flashing,
+    Done,
+    Failed,
+    Skipped,
+}
+
+/// Payload of the `flash-board-status` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct BoardStatusEvent {
+    /// Position of the job in the request.
+    pub index: usize,
+    pub combination: String,
+    pub probe: Option<String>,
+    pub status: BoardStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BoardResult {
+    pub combination: String,
+    pub probe: Option<String>,
+    pub status: BoardStatus,
+    pub result: Option<FlashResult>,
+}
+
+fn emit_status(window: &Window, index: usize, job: &FlashJob, status: BoardStatus, message: Option<String>) {
+    emit_to_window(
+        window,
+        "flash-board-status",
+        &BoardStatusEvent {
+            index,
+            combination: job.combination.clone(),
+            probe: job.probe.clone(),
+            status,
+            message,
+        },
+    );
+}
+
+async fn flash_board(window: Window, index: usize, job: FlashJob, request: FlashRequest) -> BoardResult {
+    emit_status(&window, index, &job, BoardStatus::Flashing, None);
+    let mut logger = Logger::new(&window);
+    logger.set_combination(Some(job.combination.clone()));
+    logger.set_stage("flash");
+    let result = flash(&window, &request, &mut logger).await;
+    logger.finish_stage();
+
+    let status = if result.success { BoardStatus::Done } else { BoardStatus::Failed };
+    emit_status(&window, index, &job, status, Some(result.message.clone()));
+    BoardResult { combination: job.combination, probe: job.probe, status, result: Some(result) }
+}
+
+/// Flashes several combinations onto several probes, one after another or
+/// all at once, reporting each board through `flash-board-status` events.
+#[command]
+pub async fn flash_batch(window: Window, request: FlashBatchRequest) -> Result<Vec<BoardResult>, String> {
+    if request.parallel && request.jobs.len() > 1 {
+        // A probe can only talk to one board at a time
+        let mut probes = HashSet::new();
+        for job in &request.jobs {
+            let probe = job
+                .probe
+                .as_ref()
+                .ok_or_else(|| tr!("flash.batch_probe_missing", job.combination))?;
+            if !probes.insert(probe) {
+                return Err(tr!("flash.batch_probe_reused", probe));
+            }
+        }
+    }
+
+    for (index, job) in request.jobs.iter().enumerate() {
+        emit_status(&window, index, job, BoardStatus::Queued, None);
+    }
+
+    let mut results = Vec::new();
+    if request.parallel {
+        let handles: Vec<_> = request
+            .jobs
+            .iter()
+            .enumerate()
+            .map(|(index, job)| {
+                tokio::spawn(flash_board(window.clone(), index, job.clone(), request.job_request(job)))
+            })
+            .collect();
+        for handle in handles {
+            results.push(handle.await.map_err(|e| e.to_string())?);
+        }
+    } else {
+        let mut failed = false;
+        for (index, job) in request.jobs.iter().enumerate() {
+            if failed && request.stop_on_failure {
+                emit_status(&window, index, job, BoardStatus::Skipped, None);
+                results.push(BoardResult {
+                    combination: job.combination.clone(),
+                    probe: job.probe.clone(),
+                    status: BoardStatus::Skipped,
+                    result: None,
+                });
+                continue;
+            }
+            let board = flash_board(window.clone(), index, job.clone(), request.job_request(job)).await;
+            failed |= board.status == BoardStatus::Failed;
+            results.push(board);
+        }
+    }
+    Ok(results)
+}
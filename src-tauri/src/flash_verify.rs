@@ -0,0 +1,184 @@
+use crate::flashing::{
+    cube_connect_args, dfu_util_device_args, openocd_setup_args, run_streaming, st_flash_serial_args, tcl_path,
+    FlashBackend, FlashRequest,
+};
+use crate::i18n::tr;
+use serde::Serialize;
+use std::fs;
+use tauri::Window;
+
+// What each tool prints once its own verification passed
+const VERIFIED_MARKERS: [&str; 3] = ["Download verified successfully", "Verified OK", "Flash written and verified"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyMethod {
+    /// The flash contents were read back and compared with the file.
+    Readback,
+    /// Only the programmer's own verification message was checked (hex/elf files).
+    Programmer,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VerifyMismatch {
+    pub address: u64,
+    pub expected: u8,
+    pub actual: u8,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VerifyReport {
+    pub method: VerifyMethod,
+    pub passed: bool,
+    pub checked_bytes: u64,
+    pub mismatched_bytes: u64,
+    /// The first few differing bytes.
+    pub mismatches: Vec<VerifyMismatch>,
+    pub error: Option<String>,
+}
+
+const MAX_REPORTED_MISMATCHES: usize = 16;
+
+fn parse_address(address: &str) -> Result<u64, String> {
+    let trimmed = address.trim();
+    let parsed = match trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => trimmed.parse(),
+    };
+    parsed.map_err(|_| tr!("flash.invalid_address", address))
+}
+
+fn readback_args(request: &FlashRequest, target: &str, size: usize) -> Result<Vec<String>, String> {
+    let address = request.address();
+    let size_hex = format!("0x{:X}", size);
+    if request.uses_dfu_util() {
+        let mut args = dfu_util_device_args(request);
+        args.extend([
+            "-s".to_string(),
+            format!("{}:{}", address, size),
+            "-U".to_string(),
+            target.to_string(),
+        ]);
+        return Ok(args);
+    }
+    Ok(match request.settings.backend {
+        FlashBackend::CubeProgrammer => {
+            let mut args = cube_connect_args(request);
+            args.extend(["-u".to_string(), address, size_hex, target.to_string()]);
+            args
+        }
+        FlashBackend::OpenOcd => {
+            let mut args = openocd_setup_args(request)?;
+            for cmd in [
+                "init".to_string(),
+                "reset halt".to_string(),
+                format!("dump_image {} {} {}", tcl_path(target), address, size_hex),
+            ] {
+                args.extend(["-c".to_string(), cmd]);
+            }
+            if request.reset {
+                args.extend(["-c".to_string(), "reset run".to_string()]);
+            }
+            args.extend(["-c".to_string(), "exit".to_string()]);
+            args
+        }
+        FlashBackend::StFlash => {
+            let mut args = st_flash_serial_args(request);
+            args.extend(["read".to_string(), target.to_string(), address, size.to_string()]);
+            args
+        }
+    })
+}
+
+/// Byte-wise comparison of the file with what was read back.
+pub fn compare(expected: &[u8], actual: &[u8], base_address: u64) -> VerifyReport {
+    let mut report = VerifyReport {
+        method: VerifyMethod::Readback,
+        passed: true,
+        checked_bytes: expected.len() as u64,
+        mismatched_bytes: 0,
+        mismatches: Vec::new(),
+        error: None,
+    };
+    for (offset, expected_byte) in expected.iter().enumerate() {
+        let actual_byte = actual.get(offset).copied();
+        if actual_byte == Some(*expected_byte) {
+            continue;
+        }
+        report.mismatched_bytes += 1;
+        if report.mismatches.len() < MAX_REPORTED_MISMATCHES {
+            report.mismatches.push(VerifyMismatch {
+                address: base_address + offset as u64,
+                expected: *expected_byte,
+                // Missing bytes read as erased flash
+                actual: actual_byte.unwrap_or(0xFF),
+            });
+        }
+    }
+    report.passed = report.mismatched_bytes == 0;
+    if actual.len() < expected.len() {
+        report.error = Some(tr!("flash.readback_short", actual.len(), expected.len()));
+        report.passed = false;
+    }
+    report
+}
+
+async fn readback(window: &Window, request: &FlashRequest, program: &str) -> VerifyReport {
+    let failed = |error: String| VerifyReport {
+        method: VerifyMethod::Readback,
+        passed: false,
+        checked_bytes: 0,
+        mismatched_bytes: 0,
+        mismatches: Vec::new(),
+        error: Some(error),
+    };
+
+    let expected = match fs::read(&request.file) {
+        Ok(bytes) => bytes,
+        Err(e) => return failed(tr!("fs.read", request.file, e)),
+    };
+    let base_address = match parse_address(&request.address()) {
+        Ok(address) => address,
+        Err(e) => return failed(e),
+    };
+    let target = std::env::temp_dir().join(format!(
+        "stm32guibuilder_readback_{}.bin",
+        uuid::Uuid::new_v4().simple()
+    ));
+    let target_str = target.to_string_lossy().into_owned();
+    let args = match readback_args(request, &target_str, expected.len()) {
+        Ok(args) => args,
+        Err(e) => return failed(e),
+    };
+
+    let report = match run_streaming(window, program, &args, &request.file, request.probe.as_deref()).await {
+        Ok((0, _)) => match fs::read(&target) {
+            Ok(actual) => compare(&expected, &actual, base_address),
+            Err(e) => failed(tr!("fs.read", target.display(), e)),
+        },
+        Ok((code, _)) => failed(tr!("flash.readback_failed", code)),
+        Err(e) => failed(e),
+    };
+    fs::remove_file(&target).ok();
+    report
+}
+
+/// Checks that the target holds the flashed image. Raw binaries are read
+/// back and compared byte by byte; for hex/elf files, which may span several
+/// regions, the programmer's verification output is required instead.
+pub async fn verify(window: &Window, request: &FlashRequest, program: &str, output: &[String]) -> VerifyReport {
+    if request.extension() == "bin" {
+        return readback(window, request, program).await;
+    }
+    let confirmed = output
+        .iter()
+        .any(|line| VERIFIED_MARKERS.iter().any(|marker| line.contains(marker)));
+    VerifyReport {
+        method: VerifyMethod::Programmer,
+        passed: confirmed,
+        checked_bytes: 0,
+        mismatched_bytes: 0,
+        mismatches: Vec::new(),
+        error: (!confirmed).then(|| tr!("flash.verify_unconfirmed")),
+    }
+}
@@ -0,0 +1,631 @@
+use crate::flash_verify::{self, VerifyReport};
+use crate::i18n::tr;
+use crate::logging::{emit_to_window, Logger};
+use crate::option_bytes::{self, OptionBytes, RdpLevel};
+use crate::personalize::{self, PersonalizationConfig, ProvisioningRecord};
+use crate::smoke_test::{self, SmokeTestConfig, SmokeTestResult};
+use crate::target_check::{self, TargetCheck, TargetStatus};
+use crate::models::LogEntry;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use stm32_builder_core::command_line;
+use tauri::{command, Window};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+const DEFAULT_FLASH_ADDRESS: &str = "0x08000000";
+
+lazy_static! {
+    // CubeProgrammer draws its progress bar as "[=====     ]  45%"
+    static ref PROGRESS: Regex = Regex::new(r"(\d{1,3})\s*%").unwrap();
+}
+
+/// Flashing tool used for a project.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashBackend {
+    #[default]
+    CubeProgrammer,
+    OpenOcd,
+    StFlash,
+}
+
+impl FlashBackend {
+    fn default_program(self) -> &'static str {
+        match self {
+            FlashBackend::CubeProgrammer => "STM32_Programmer_CLI",
+            FlashBackend::OpenOcd => "openocd",
+            FlashBackend::StFlash => "st-flash",
+        }
+    }
+}
+
+/// How the board is reached: through a debug probe or its USB DFU bootloader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Swd,
+    Dfu,
+}
+
+/// Flashing tool and its options; stored per project in `BuildConfig`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FlashSettings {
+    #[serde(default)]
+    pub backend: FlashBackend,
+    /// Path of the tool; empty to look it up in `PATH`.
+    #[serde(rename = "programmerPath", default)]
+    pub programmer_path: String,
+    /// OpenOCD interface script, e.g. `interface/stlink.cfg`.
+    pub interface: Option<String>,
+    /// OpenOCD target script, e.g. `target/stm32f4x.cfg`.
+    pub target: Option<String>,
+    /// Extra OpenOCD script search directory (`-s`).
+    #[serde(rename = "scriptsDir")]
+    pub scripts_dir: Option<String>,
+    /// Option bytes programmed after every flash of this project.
+    #[serde(rename = "optionBytes")]
+    pub option_bytes: Option<OptionBytes>,
+    /// Per-combination overrides of `option_bytes`, keyed by combination name.
+    #[serde(rename = "combinationOptionBytes")]
+    pub combination_option_bytes: Option<HashMap<String, OptionBytes>>,
+    /// GDB script run against every freshly flashed board.
+    #[serde(rename = "smokeTest")]
+    pub smoke_test: Option<SmokeTestConfig>,
+    /// Per-device field patched into the image before programming.
+    pub personalization: Option<PersonalizationConfig>,
+    /// CubeProgrammer external loader (`.stldr`) for QSPI/OSPI flash, so
+    /// images with external-flash sections are programmed in one step.
+    #[serde(rename = "externalLoader")]
+    pub external_loader: Option<String>,
+    /// Path of `dfu-util`, used for DFU unless the backend is CubeProgrammer.
+    #[serde(rename = "dfuUtilPath")]
+    pub dfu_util_path: Option<String>,
+}
+
+impl FlashSettings {
+    pub fn program(&self) -> String {
+        if self.programmer_path.trim().is_empty() {
+            self.backend.default_program().to_string()
+        } else {
+            self.programmer_path.clone()
+        }
+    }
+}
+
+/// What to flash and how; sent by the flash dialog.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FlashRequest {
+    pub settings: FlashSettings,
+    /// Binary to program, usually a combination's artifact.
+    pub file: String,
+    /// Combination the file was built for; selects its option bytes.
+    pub combination: Option<String>,
+    /// Run that built the file; smoke test results are added to its manifest.
+    #[serde(rename = "runId")]
+    pub run_id: Option<String>,
+    /// Project the file was built from; the connected MCU is checked against it.
+    #[serde(rename = "projectPath")]
+    pub project_path: Option<String>,
+    /// Serial number of the probe to use, as returned by `list_probes`;
+    /// `None` lets the tool pick the first one.
+    pub probe: Option<String>,
+    /// Start address for `.bin` files; defaults to the start of internal flash.
+    pub address: Option<String>,
+    /// Mass-erase the chip before programming.
+    #[serde(rename = "fullErase", default)]
+    pub full_erase: bool,
+    #[serde(default)]
+    pub verify: bool,
+    /// Reset and start the target afterwards.
+    #[serde(default)]
+    pub reset: bool,
+    /// Read-out protection level the user confirmed changing to; it has to
+    /// match the level of the option bytes.
+    #[serde(rename = "confirmRdp", default)]
+    pub confirm_rdp: Option<RdpLevel>,
+    /// Flash even if the connected MCU does not match the project's.
+    #[serde(rename = "ignoreMcuMismatch", default)]
+    pub ignore_mcu_mismatch: bool,
+    #[serde(default)]
+    pub transport: Transport,
+}
+
+impl FlashRequest {
+    pub fn extension(&self) -> String {
+        Path::new(&self.file)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+
+    pub fn address(&self) -> String {
+        self.address.clone().unwrap_or_else(|| DEFAULT_FLASH_ADDRESS.to_string())
+    }
+
+    /// CubeProgrammer talks DFU itself; the other backends hand it to dfu-util.
+    pub fn uses_dfu_util(&self) -> bool {
+        self.transport == Transport::Dfu && self.settings.backend != FlashBackend::CubeProgrammer
+    }
+
+    /// Tool that programs this request.
+    pub fn program(&self) -> String {
+        if self.uses_dfu_util() {
+            self.settings.dfu_util_path.clone().unwrap_or_else(|| "dfu-util".to_string())
+        } else {
+            self.settings.program()
+        }
+    }
+}
+
+/// Payload of the `flash-output` event: one raw line of programmer output.
+#[derive(Clone, Debug, Serialize)]
+pub struct FlashOutputLine {
+    pub file: String,
+    pub probe: Option<String>,
+    pub line: String,
+}
+
+/// Payload of the `flash-progress` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct FlashProgress {
+    pub file: String,
+    pub probe: Option<String>,
+    pub percent: u8,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FlashResult {
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub message: String,
+    pub duration_ms: u64,
+    /// Connected MCU compared with the project's, if a project was given.
+    pub target: Option<TargetCheck>,
+    /// Outcome of the check requested with `verify`.
+    pub verify: Option<VerifyReport>,
+    pub smoke_test: Option<SmokeTestResult>,
+    /// Device UID and patched value, if the image was personalized.
+    pub provisioning: Option<ProvisioningRecord>,
+    /// Option bytes that were programmed.
+    pub option_bytes: Option<OptionBytes>,
+    pub logs: Vec<LogEntry>,
+}
+
+/// CubeProgrammer connection to the requested probe.
+pub fn cube_connect_args(request: &FlashRequest) -> Vec<String> {
+    let port = match request.transport {
+        Transport::Swd => "port=SWD",
+        Transport::Dfu => "port=USB1",
+    };
+    let mut args = vec!["-c".to_string(), port.to_string()];
+    if let Some(serial) = &request.probe {
+        args.push(format!("sn={}", serial));
+    }
+    // Every command touching external memory needs the loader, so it goes
+    // along with the connection
+    if let Some(loader) = &request.settings.external_loader {
+        args.extend(["-el".to_string(), loader.clone()]);
+    }
+    args
+}
+
+fn cube_programmer_args(request: &FlashRequest) -> Vec<String> {
+    let mut args = cube_connect_args(request);
+    if request.full_erase {
+        args.extend(["-e".to_string(), "all".to_string()]);
+    }
+    args.extend(["-w".to_string(), request.file.clone()]);
+    // Only raw binaries need an address; hex/elf carry their own
+    if request.extension() == "bin" {
+        args.push(request.address());
+    }
+    if request.verify {
+        args.push("-v".to_string());
+    }
+    if request.reset {
+        args.push("-rst".to_string());
+    }
+    args
+}
+
+/// OpenOCD scripts and probe selection, up to but not including `init`.
+pub fn openocd_setup_args(request: &FlashRequest) -> Result<Vec<String>, String> {
+    let settings = &request.settings;
+    let (Some(interface), Some(target)) = (&settings.interface, &settings.target) else {
+        return Err(tr!("flash.openocd_scripts_missing"));
+    };
+    let mut args = Vec::new();
+    if let Some(dir) = &settings.scripts_dir {
+        args.extend(["-s".to_string(), dir.clone()]);
+    }
+    args.extend(["-f".to_string(), interface.clone()]);
+    // Has to follow the interface script and precede the target's init
+    if let Some(serial) = &request.probe {
+        args.extend(["-c".to_string(), format!("adapter serial {}", serial)]);
+    }
+    args.extend(["-f".to_string(), target.clone()]);
+    Ok(args)
+}
+
+/// OpenOCD takes file names in Tcl syntax, where backslashes are escapes.
+pub fn tcl_path(path: &str) -> String {
+    format!("{{{}}}", path.replace('\\', "/"))
+}
+
+fn openocd_args(request: &FlashRequest) -> Result<Vec<String>, String> {
+    let mut args = openocd_setup_args(request)?;
+    if request.full_erase {
+        for cmd in ["init", "reset halt", "flash erase_sector 0 0 last"] {
+            args.extend(["-c".to_string(), cmd.to_string()]);
+        }
+    }
+    let mut program = format!("program {}", tcl_path(&request.file));
+    if request.extension() == "bin" {
+        program.push_str(&format!(" {}", request.address()));
+    }
+    if request.verify {
+        program.push_str(" verify");
+    }
+    if request.reset {
+        program.push_str(" reset");
+    }
+    program.push_str(" exit");
+    args.extend(["-c".to_string(), program]);
+    Ok(args)
+}
+
+pub fn st_flash_serial_args(request: &FlashRequest) -> Vec<String> {
+    request
+        .probe
+        .as_ref()
+        .map(|s| vec!["--serial".to_string(), s.clone()])
+        .unwrap_or_default()
+}
+
+/// st-flash has no erase-and-write mode, so a full erase is a separate run.
+fn st_flash_invocations(request: &FlashRequest) -> Result<Vec<Vec<String>>, String> {
+    let serial = st_flash_serial_args(request);
+    let mut write = serial.clone();
+    if request.reset {
+        write.push("--reset".to_string());
+    }
+    match request.extension().as_str() {
+        "bin" => write.extend(["write".to_string(), request.file.clone(), request.address()]),
+        "hex" => write.extend([
+            "--format".to_string(),
+            "ihex".to_string(),
+            "write".to_string(),
+            request.file.clone(),
+        ]),
+        _ => return Err(tr!("flash.st_flash_format", request.file)),
+    }
+    // st-flash always reads back what it wrote, `verify` needs no flag
+    let mut invocations = Vec::new();
+    if request.full_erase {
+        invocations.push([serial, vec!["erase".to_string()]].concat());
+    }
+    invocations.push(write);
+    Ok(invocations)
+}
+
+/// dfu-util selection of the device in DFU mode, by USB serial number.
+pub fn dfu_util_device_args(request: &FlashRequest) -> Vec<String> {
+    let mut args = vec!["-a".to_string(), "0".to_string()];
+    if let Some(serial) = &request.probe {
+        args.extend(["-S".to_string(), serial.clone()]);
+    }
+    args
+}
+
+/// The `.dfu` next to a binary, if the build produced one.
+fn dfu_image(request: &FlashRequest) -> Option<String> {
+    if request.extension() == "dfu" {
+        return Some(request.file.clone());
+    }
+    let dfu = Path::new(&request.file).with_extension("dfu");
+    dfu.is_file().then(|| dfu.to_string_lossy().into_owned())
+}
+
+fn dfu_util_args(request: &FlashRequest) -> Result<Vec<String>, String> {
+    let mut args = dfu_util_device_args(request);
+    // A DfuSe file carries its own target addresses
+    if let Some(dfu) = dfu_image(request) {
+        if request.reset {
+            args.push("-R".to_string());
+        }
+        args.extend(["-D".to_string(), dfu]);
+        return Ok(args);
+    }
+    if request.extension() != "bin" {
+        return Err(tr!("flash.dfu_format", request.file));
+    }
+    let mut target = request.address();
+    if request.full_erase {
+        target.push_str(":mass-erase:force");
+    }
+    if request.reset {
+        target.push_str(":leave");
+    }
+    args.extend(["-s".to_string(), target, "-D".to_string(), request.file.clone()]);
+    Ok(args)
+}
+
+/// Command lines to run, in order, for the selected backend.
+fn flash_invocations(request: &FlashRequest) -> Result<Vec<Vec<String>>, String> {
+    if let Some(loader) = &request.settings.external_loader {
+        if request.settings.backend != FlashBackend::CubeProgrammer || request.uses_dfu_util() {
+            return Err(tr!("flash.external_loader_backend"));
+        }
+        if !Path::new(loader).is_file() {
+            return Err(tr!("flash.external_loader_missing", loader));
+        }
+    }
+    if request.uses_dfu_util() {
+        return Ok(vec![dfu_util_args(request)?]);
+    }
+    match request.settings.backend {
+        FlashBackend::CubeProgrammer => Ok(vec![cube_programmer_args(request)]),
+        FlashBackend::OpenOcd => Ok(vec![openocd_args(request)?]),
+        FlashBackend::StFlash => st_flash_invocations(request),
+    }
+}
+
+/// Runs a programmer and streams its output as `flash-output` and
+/// `flash-progress` events. Returns the exit code and all output lines.
+pub async fn run_streaming(
+    window: &Window,
+    program: &str,
+    args: &[String],
+    file: &str,
+    probe: Option<&str>,
+) -> Result<(i32, Vec<String>), String> {
+    let output_line = |line: &str| FlashOutputLine {
+        file: file.to_string(),
+        probe: probe.map(str::to_string),
+        line: line.to_string(),
+    };
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .kill_on_drop(true)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = command.spawn().map_err(|e| tr!("flash.start_failed", program, e))?;
+
+    let stdout = child.stdout.take().expect("Failed to capture stdout");
+    let stderr = child.stderr.take().expect("Failed to capture stderr");
+    let stderr_task = tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut collected = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            collected.push(line);
+        }
+        collected
+    });
+
+    let mut output = Vec::new();
+    let mut last_percent = None;
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(percent) = PROGRESS
+            .captures_iter(&line)
+            .last()
+            .and_then(|c| c[1].parse::<u8>().ok())
+            .filter(|p| *p <= 100)
+            && last_percent != Some(percent)
+        {
+            last_percent = Some(percent);
+            emit_to_window(window, "flash-progress", &FlashProgress { file: file.to_string(), probe: probe.map(str::to_string), percent });
+        }
+        emit_to_window(window, "flash-output", &output_line(&line));
+        output.push(line);
+    }
+
+    let status = child.wait().await.map_err(|e| tr!("process.wait_failed", e))?;
+    if let Ok(stderr_lines) = stderr_task.await {
+        for line in stderr_lines {
+            emit_to_window(window, "flash-output", &output_line(&line));
+            output.push(line);
+        }
+    }
+    Ok((status.code().unwrap_or(-1), output))
+}
+
+/// Flashes one board and returns the outcome with the entries logged
+/// meanwhile. Shared by `flash_firmware` and the batch queue.
+pub async fn flash(window: &Window, request: &FlashRequest, logger: &mut Logger<'_>) -> FlashResult {
+    let started = Instant::now();
+    let failed = |logger: &mut Logger<'_>, message: String| FlashResult {
+        message: logger.error(&message),
+        logs: logger.get_logs().clone(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        ..Default::default()
+    };
+
+    if !Path::new(&request.file).is_file() {
+        return failed(logger, tr!("flash.file_missing", request.file));
+    }
+
+    let mut invocations = match flash_invocations(request) {
+        Ok(invocations) => invocations,
+        Err(e) => return failed(logger, e),
+    };
+    let program = request.program();
+    let source_file = request.file.clone();
+
+    // Refused before anything is written, so an unconfirmed RDP change
+    // cannot leave a half-configured board behind
+    let option_bytes = option_bytes::resolve(&request.settings, request.combination.as_deref());
+    let option_bytes_args = match &option_bytes {
+        Some(ob) => match option_bytes::option_bytes_args(request, ob) {
+            Ok(args) => Some(args),
+            Err(e) => return failed(logger, e),
+        },
+        None => None,
+    };
+
+    let mut target = None;
+    if request.uses_dfu_util() && request.project_path.is_some() {
+        // dfu-util cannot read the device id
+        logger.warning(&tr!("flash.dfu_no_target_check"));
+    } else if let Some(project_path) = &request.project_path {
+        let check = match target_check::check(window, request, Path::new(project_path)).await {
+            Ok(check) => check,
+            Err(e) => return failed(logger, tr!("target.detect_failed", e)),
+        };
+        let device = check.device_name.clone().or(check.device_id.clone()).unwrap_or_default();
+        let declared = check.declared_mcu.clone().unwrap_or_default();
+        match check.status {
+            TargetStatus::Mismatch if !request.ignore_mcu_mismatch => {
+                return FlashResult {
+                    target: Some(check),
+                    ..failed(logger, tr!("target.mismatch", device, declared))
+                };
+            }
+            TargetStatus::Mismatch => {
+                logger.warning(&tr!("target.mismatch_ignored", device, declared));
+            }
+            TargetStatus::Unknown => {
+                logger.warning(&tr!("target.unknown", device, declared));
+            }
+            TargetStatus::Match => {}
+        }
+        target = Some(check);
+    }
+
+    // From here on a per-device copy of the image is programmed
+    let personalized;
+    let mut provisioning = None;
+    let request = match &request.settings.personalization {
+        Some(config) => {
+            let (record, image) = match personalize::personalize(window, request, config).await {
+                Ok(result) => result,
+                Err(e) => return failed(logger, e),
+            };
+            logger.info(&tr!("personalize.patched", record.uid, record.value));
+            provisioning = Some(record);
+            personalized = FlashRequest { file: image.to_string_lossy().into_owned(), ..request.clone() };
+            invocations = match flash_invocations(&personalized) {
+                Ok(invocations) => invocations,
+                Err(e) => return failed(logger, e),
+            };
+            &personalized
+        }
+        None => request,
+    };
+
+    let mut exit_code = 0;
+    let mut output = Vec::new();
+    for args in &invocations {
+        logger.info(&tr!("cubeide.command", command_line::display(std::iter::once(&program).chain(args.iter()))));
+        exit_code = match run_streaming(window, &program, args, &request.file, request.probe.as_deref()).await {
+            Ok((code, lines)) => {
+                output.extend(lines);
+                code
+            }
+            Err(e) => return failed(logger, e),
+        };
+        if exit_code != 0 {
+            break;
+        }
+    }
+
+    // A zero exit code alone does not prove the image landed intact
+    let mut verify = None;
+    if exit_code == 0 && request.verify {
+        logger.info(&tr!("flash.verifying", request.file));
+        verify = Some(flash_verify::verify(window, request, &program, &output).await);
+    }
+
+    let verified = verify.as_ref().map(|r| r.passed).unwrap_or(true);
+
+    let mut smoke_test = None;
+    if let (true, true, Some(config)) = (exit_code == 0, verified, &request.settings.smoke_test) {
+        logger.info(&tr!("smoke.running", config.script));
+        let result = smoke_test::run(request, config).await;
+        if let Some(run_id) = &request.run_id
+            && let Err(e) = smoke_test::record(window, run_id, &result)
+        {
+            logger.warning(&e);
+        }
+        smoke_test = Some(result);
+    }
+    let smoke_passed = smoke_test.as_ref().map(|r| r.passed).unwrap_or(true);
+
+    // Last, since read-out protection blocks both the readback and the debugger
+    let mut applied_option_bytes = None;
+    if let (true, true, true, Some(args)) = (exit_code == 0, verified, smoke_passed, option_bytes_args) {
+        logger.info(&tr!("cubeide.command", command_line::display(std::iter::once(&program).chain(args.iter()))));
+        exit_code = match run_streaming(window, &program, &args, &request.file, request.probe.as_deref()).await {
+            Ok((code, _)) => code,
+            Err(e) => return failed(logger, e),
+        };
+        if exit_code != 0 {
+            return FlashResult {
+                exit_code: Some(exit_code),
+                target,
+                verify,
+                smoke_test,
+                ..failed(logger, tr!("flash.option_bytes_failed", exit_code))
+            };
+        }
+        applied_option_bytes = option_bytes;
+    }
+
+    let message = match (&verify, &smoke_test) {
+        _ if exit_code != 0 => logger.error(&tr!("flash.failed", exit_code)),
+        (Some(report), _) if !report.passed => logger.error(&match &report.error {
+            Some(error) => tr!("flash.verify_failed", error),
+            None => tr!("flash.verify_mismatch", report.mismatched_bytes, report.checked_bytes),
+        }),
+        (_, Some(result)) if !result.passed => logger.error(&match &result.error {
+            Some(error) => tr!("smoke.failed", error),
+            None => tr!("smoke.failed", tr!("smoke.exit_code", result.exit_code.unwrap_or(-1))),
+        }),
+        _ => logger.info(&tr!("flash.done", source_file)),
+    };
+
+    let success = exit_code == 0 && verified && smoke_passed;
+    if let (Some(config), Some(record)) = (&request.settings.personalization, &provisioning) {
+        if success
+            && let Err(e) = personalize::record(window, config, record)
+        {
+            logger.warning(&e);
+        }
+        fs::remove_file(&request.file).ok();
+    }
+    FlashResult {
+        success,
+        exit_code: Some(exit_code),
+        message,
+        duration_ms: started.elapsed().as_millis() as u64,
+        target,
+        verify,
+        smoke_test,
+        provisioning,
+        option_bytes: applied_option_bytes,
+        logs: logger.get_logs().clone(),
+    }
+}
+
+/// Programs a binary with the project's flashing backend (CubeProgrammer,
+/// OpenOCD or st-flash).
+#[command]
+pub async fn flash_firmware(window: Window, request: FlashRequest) -> Result<FlashResult, String> {
+    let mut logger = Logger::new(&window);
+    logger.set_stage("flash");
+    let result = flash(&window, &request, &mut logger).await;
+    logger.finish_stage();
+    Ok(result)
+}
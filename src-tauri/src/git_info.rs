@@ -0,0 +1,162 @@
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use stm32_builder_core::cproject_defines::BACKUP_SUFFIX;
+use stm32_builder_core::project::PROJECT_CONFIG_FILE;
+use tempfile::TempDir;
+
+// Rewritten for every combination, so it must not make the tree dirty;
+// its folder depends on the project's include paths
+const GENERATED_HEADER: &str = "build_config.h";
+
+/// Commit the project was built from, recorded in the manifest and
+/// optionally compiled into `build_config.h`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GitInfo {
+    pub hash: String,
+    /// `HEAD` when detached.
+    pub branch: String,
+    /// Uncommitted changes besides the generated header and the builder's
+    /// own files.
+    pub dirty: bool,
+    /// Committer date of `HEAD`, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub commit_time: i64,
+}
+
+/// Tagging of the built commit after a successful run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TagSettings {
+    /// Prepended to the version label (or run id), e.g. `fw-`.
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub push: bool,
+    /// Remote to push to; `origin` by default.
+    pub remote: Option<String>,
+}
+
+impl GitInfo {
+    /// `GIT_HASH`, `GIT_BRANCH` and `GIT_DIRTY` for `build_config.h`.
+    pub fn defines(&self) -> String {
+        format!(
+            "#define GIT_HASH \"{}\"\n#define GIT_BRANCH \"{}\"\n#define GIT_DIRTY {}\n",
+            self.hash,
+            self.branch.replace('\\', "\\\\").replace('"', "\\\""),
+            if self.dirty { 1 } else { 0 }
+        )
+    }
+}
+
+fn git(project_path: &Path, args: &[&str]) -> Option<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(project_path).args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `<short hash> <subject>` of the commits after `from` up to `to`,
+/// newest first; without `from`, the whole history up to `to`.
+pub fn commits_between(project_path: &Path, from: Option<&str>, to: &str) -> Option<Vec<String>> {
+    let range = match from {
+        Some(from) => format!("{}..{}", from, to),
+        None => to.to_string(),
+    };
+    let log = git(project_path, &["log", "--no-merges", "--format=%h %s", &range])?;
+    Some(log.lines().map(str::to_string).collect())
+}
+
+/// Path of the project relative to the repository root, with a trailing
+/// slash (empty at the root).
+pub fn repo_prefix(project_path: &Path) -> Option<String> {
+    git(project_path, &["rev-parse", "--show-prefix"])
+}
+
+pub fn repo_root(project_path: &Path) -> Option<PathBuf> {
+    git(project_path, &["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// Creates an annotated tag on `commit`.
+pub fn create_tag(project_path: &Path, name: &str, commit: &str, message: &str) -> Result<(), String> {
+    git(project_path, &["tag", "-a", name, commit, "-m", message])
+        .map(|_| ())
+        .ok_or_else(|| tr!("git.tag_failed", name))
+}
+
+pub fn push_tag(project_path: &Path, remote: &str, name: &str) -> Result<(), String> {
+    git(project_path, &["push", remote, &format!("refs/tags/{}", name)])
+        .map(|_| ())
+        .ok_or_else(|| tr!("git.push_failed", name, remote))
+}
+
+/// Git state of the project; `None` if it is not in a repository (or git
+/// is not installed).
+pub fn detect(project_path: &Path) -> Option<GitInfo> {
+    let hash = git(project_path, &["rev-parse", "HEAD"])?;
+    let branch = git(project_path, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_else(|| "HEAD".to_string());
+    let commit_time = git(project_path, &["log", "-1", "--format=%ct"])
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(0);
+    // The saved configuration and the .cproject backup are the builder's, not the project's
+    let excludes = [GENERATED_HEADER.to_string(), PROJECT_CONFIG_FILE.to_string(), format!(".cproject{}", BACKUP_SUFFIX)]
+        .map(|file| format!(":(exclude,glob)**/{}", file));
+    let mut args = vec!["status", "--porcelain", "--", "."];
+    args.extend(excludes.iter().map(String::as_str));
+    let status = git(project_path, &args)?;
+    Some(GitInfo { hash, branch, dirty: !status.is_empty(), commit_time })
+}
+
+/// Pristine checkout of `HEAD` in a temporary directory, with an empty
+/// CubeIDE workspace next to it. Both are removed when it is dropped.
+pub struct CleanExport {
+    repo: PathBuf,
+    worktree: PathBuf,
+    /// The project inside the checkout.
+    pub project_dir: PathBuf,
+    pub workspace: PathBuf,
+    // Dropped last, deleting whatever `git worktree remove` left behind
+    _dir: TempDir,
+}
+
+/// Checks `HEAD` of the project's repository out into a detached worktree,
+/// so uncommitted edits and untracked (e.g. stale generated) files are not
+/// part of the build.
+pub fn export_head(project_path: &Path) -> Result<CleanExport, String> {
+    let prefix = repo_prefix(project_path)
+        .ok_or_else(|| tr!("git.not_a_repository", project_path.display()))?;
+    let dir = tempfile::Builder::new()
+        .prefix("stm32guibuilder_export_")
+        .tempdir()
+        .map_err(|e| tr!("git.export_failed", e))?;
+    let worktree = dir.path().join("src");
+    let workspace = dir.path().join("workspace");
+    fs::create_dir_all(&workspace).map_err(|e| tr!("git.export_failed", e))?;
+    let worktree_str = worktree.to_string_lossy().into_owned();
+    git(project_path, &["worktree", "add", "--detach", &worktree_str, "HEAD"])
+        .ok_or_else(|| tr!("git.export_failed", worktree.display()))?;
+    // Best effort: projects without submodules have nothing to update
+    git(&worktree, &["submodule", "update", "--init", "--recursive"]);
+    Ok(CleanExport {
+        repo: project_path.to_path_buf(),
+        project_dir: worktree.join(prefix),
+        worktree,
+        workspace,
+        _dir: dir,
+    })
+}
+
+impl Drop for CleanExport {
+    fn drop(&mut self) {
+        let worktree = self.worktree.to_string_lossy().into_owned();
+        git(&self.repo, &["worktree", "remove", "--force", &worktree]);
+    }
+}
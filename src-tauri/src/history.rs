@@ -0,0 +1,658 @@
+use crate::i18n::tr;
+use crate::{log_index, manifest::{self, RunManifest}, size_gate::PreviousSize};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use stm32_builder_core::paths;
+use tauri::{command, Manager, Window};
+
+const HISTORY_FILE: &str = "history.sqlite";
+
+/// One row of the history page.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub project_path: String,
+    pub config_name: Option<String>,
+    pub output_dir: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: i64,
+    pub success: bool,
+    pub cancelled: bool,
+    pub total_errors: i64,
+    pub total_warnings: i64,
+    pub combination_count: i64,
+    /// Labels such as `v2.3-rc1`, in the order they were added.
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+/// Outcome of one combination as stored in the history.
+#[derive(Debug, Serialize)]
+pub struct CombinationRecord {
+    pub combination: String,
+    pub success: bool,
+    pub errors: i64,
+    pub warnings: i64,
+    pub failure_reason: Option<String>,
+    pub artifact: Option<String>,
+    pub artifact_size: Option<i64>,
+}
+
+/// Sizes of one combination in one run, for plotting flash/RAM usage over time.
+#[derive(Debug, Serialize)]
+pub struct SizePoint {
+    pub run_id: String,
+    pub started_at: String,
+    pub config_name: Option<String>,
+    pub bin_size: Option<i64>,
+    pub text: i64,
+    pub data: i64,
+    pub bss: i64,
+    pub flash: i64,
+    pub ram: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunDetails {
+    #[serde(flatten)]
+    pub summary: RunSummary,
+    pub combinations: Vec<CombinationRecord>,
+}
+
+/// Filter for `list_runs`; all fields are optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct RunFilter {
+    #[serde(rename = "projectPath")]
+    pub project_path: Option<String>,
+    pub success: Option<bool>,
+    /// RFC 3339 timestamps bounding `started_at`, in any offset.
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub tag: Option<String>,
+    /// Substring matched against tags and notes.
+    pub text: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Location of the history database inside the app data directory.
+pub fn history_path(window: &Window) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+/// Form a project path is stored and looked up in, so a project opened
+/// through a symlink or with a trailing separator is the same project.
+fn project_key(project_path: &str) -> String {
+    let project_path = project_path.trim();
+    match paths::canonicalize(Path::new(project_path)) {
+        Ok(path) => path.display().to_string(),
+        Err(_) => project_path.trim_end_matches(['/', '\\']).to_string(),
+    }
+}
+
+/// An RFC 3339 timestamp in UTC with fixed precision, so stored times
+/// compare and sort as text whatever offset they were recorded in.
+fn utc(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn open(path: &PathBuf) -> Result<Connection, String> {
+    let mut conn = Connection::open(path)
+        .map_err(|e| tr!("history.open_failed", path.display(), e))?;
+    init(&mut conn)?;
+    Ok(conn)
+}
+
+fn init(conn: &mut Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         CREATE TABLE IF NOT EXISTS runs (
+             run_id TEXT PRIMARY KEY,
+             project_path TEXT NOT NULL,
+             config_name TEXT,
+             output_dir TEXT NOT NULL,
+             started_at TEXT NOT NULL,
+             finished_at TEXT NOT NULL,
+             duration_ms INTEGER NOT NULL,
+             success INTEGER NOT NULL,
+             cancelled INTEGER NOT NULL,
+             total_errors INTEGER NOT NULL,
+             total_warnings INTEGER NOT NULL,
+             combination_count INTEGER NOT NULL,
+             note TEXT
+         );
+         CREATE INDEX IF NOT EXISTS runs_started ON runs(started_at);
+         CREATE TABLE IF NOT EXISTS run_combinations (
+             run_id TEXT NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+             combination TEXT NOT NULL,
+             success INTEGER NOT NULL,
+             errors INTEGER NOT NULL,
+             warnings INTEGER NOT NULL,
+             failure_reason TEXT,
+             artifact TEXT,
+             artifact_size INTEGER,
+             text_size INTEGER,
+             data_size INTEGER,
+             bss_size INTEGER
+         );
+         CREATE INDEX IF NOT EXISTS run_combinations_run ON run_combinations(run_id);
+         CREATE TABLE IF NOT EXISTS run_tags (
+             run_id TEXT NOT NULL REFERENCES runs(run_id) ON DELETE CASCADE,
+             tag TEXT NOT NULL,
+             PRIMARY KEY (run_id, tag)
+         );",
+    )
+    .map_err(|e| tr!("history.init_failed", e))?;
+    migrate(conn).map_err(|e| tr!("history.init_failed", e))
+}
+
+/// Rewrites runs recorded with local times and project paths as typed.
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= 1 {
+        return Ok(());
+    }
+    let tx = conn.transaction()?;
+    let runs: Vec<(String, String, String, String)> = tx
+        .prepare("SELECT run_id, project_path, started_at, finished_at FROM runs")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<_, _>>()?;
+    for (run_id, project_path, started_at, finished_at) in runs {
+        tx.execute(
+            "UPDATE runs SET project_path = ?2, started_at = ?3, finished_at = ?4 WHERE run_id = ?1",
+            params![run_id, project_key(&project_path), utc(&started_at), utc(&finished_at)],
+        )?;
+    }
+    tx.execute_batch("PRAGMA user_version = 1")?;
+    tx.commit()
+}
+
+/// Stores a finished run.
+pub fn record_run(path: &PathBuf, manifest: &RunManifest) -> Result<(), String> {
+    insert_run(&mut open(path)?, manifest)
+}
+
+fn insert_run(conn: &mut Connection, manifest: &RunManifest) -> Result<(), String> {
+    let duration_ms = match (
+        DateTime::parse_from_rfc3339(&manifest.started_at),
+        DateTime::parse_from_rfc3339(&manifest.finished_at),
+    ) {
+        (Ok(start), Ok(end)) => (end - start).num_milliseconds(),
+        _ => 0,
+    };
+
+    let tx = conn.transaction().map_err(|e| tr!("history.write_failed", e))?;
+    tx.execute(
+        "INSERT INTO runs (run_id, project_path, config_name, output_dir, started_at,
+             finished_at, duration_ms, success, cancelled, total_errors, total_warnings, combination_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            manifest.run_id,
+            project_key(&manifest.project_path),
+            manifest.config_name,
+            manifest.output_dir,
+            utc(&manifest.started_at),
+            utc(&manifest.finished_at),
+            duration_ms,
+            manifest.success,
+            manifest.cancelled,
+            manifest.total_errors as i64,
+            manifest.total_warnings as i64,
+            manifest.combinations.len() as i64,
+        ],
+    )
+    .map_err(|e| tr!("history.write_failed", e))?;
+    for combination in &manifest.combinations {
+        tx.execute(
+            "INSERT INTO run_combinations (run_id, combination, success, errors, warnings,
+                 failure_reason, artifact, artifact_size, text_size, data_size, bss_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                manifest.run_id,
+                combination.combination,
+                combination.success,
+                combination.errors as i64,
+                combination.warnings as i64,
+                combination.failure_reason,
+                combination.artifact,
+                combination.artifact_size.map(|s| s as i64),
+                combination.sizes.map(|s| s.text as i64),
+                combination.sizes.map(|s| s.data as i64),
+                combination.sizes.map(|s| s.bss as i64),
+            ],
+        )
+        .map_err(|e| tr!("history.write_failed", e))?;
+    }
+    tx.commit().map_err(|e| tr!("history.write_failed", e))
+}
+
+// Tags are joined with the unit separator, which cannot appear in a label typed by a user
+const SUMMARY_COLUMNS: &str = "run_id, project_path, config_name, output_dir, started_at, finished_at,
+     duration_ms, success, cancelled, total_errors, total_warnings, combination_count,
+     (SELECT group_concat(tag, char(31)) FROM run_tags t WHERE t.run_id = runs.run_id), note";
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<RunSummary> {
+    Ok(RunSummary {
+        run_id: row.get(0)?,
+        project_path: row.get(1)?,
+        config_name: row.get(2)?,
+        output_dir: row.get(3)?,
+        started_at: row.get(4)?,
+        finished_at: row.get(5)?,
+        duration_ms: row.get(6)?,
+        success: row.get(7)?,
+        cancelled: row.get(8)?,
+        total_errors: row.get(9)?,
+        total_warnings: row.get(10)?,
+        combination_count: row.get(11)?,
+        tags: row
+            .get::<_, Option<String>>(12)?
+            .map(|tags| tags.split('\u{1f}').map(String::from).collect())
+            .unwrap_or_default(),
+        note: row.get(13)?,
+    })
+}
+
+/// Past runs, newest first.
+#[command]
+pub async fn list_runs(window: Window, filter: Option<RunFilter>) -> Result<Vec<RunSummary>, String> {
+    query_runs(&open(&history_path(&window)?)?, &filter.unwrap_or_default())
+}
+
+fn query_runs(conn: &Connection, filter: &RunFilter) -> Result<Vec<RunSummary>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM runs
+             WHERE (?1 IS NULL OR project_path = ?1)
+               AND (?2 IS NULL OR success = ?2)
+               AND (?3 IS NULL OR started_at >= ?3)
+               AND (?4 IS NULL OR started_at <= ?4)
+               AND (?5 IS NULL OR EXISTS (SELECT 1 FROM run_tags t WHERE t.run_id = runs.run_id AND t.tag = ?5))
+               AND (?6 IS NULL
+                    OR instr(lower(coalesce(note, '')), lower(?6)) > 0
+                    OR EXISTS (SELECT 1 FROM run_tags t WHERE t.run_id = runs.run_id
+                               AND instr(lower(t.tag), lower(?6)) > 0))
+             ORDER BY started_at DESC
+             LIMIT ?7 OFFSET ?8",
+            SUMMARY_COLUMNS
+        ))
+        .map_err(|e| tr!("history.query_failed", e))?;
+
+    let runs = stmt
+        .query_map(
+            params![
+                filter.project_path.as_deref().map(project_key),
+                filter.success,
+                filter.since.as_deref().map(utc),
+                filter.until.as_deref().map(utc),
+                filter.tag,
+                filter.text,
+                filter.limit.unwrap_or(100),
+                filter.offset.unwrap_or(0),
+            ],
+            summary_from_row,
+        )
+        .map_err(|e| tr!("history.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("history.read_failed", e))?;
+
+    Ok(runs)
+}
+
+/// Ids of a project's successful runs, newest first.
+pub fn previous_runs(path: &PathBuf, project_path: &str, limit: u32) -> Result<Vec<String>, String> {
+    let conn = open(path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT run_id FROM runs WHERE project_path = ?1 AND success = 1
+             ORDER BY started_at DESC LIMIT ?2",
+        )
+        .map_err(|e| tr!("history.query_failed", e))?;
+    let runs = stmt
+        .query_map(params![project_key(project_path), limit], |row| row.get(0))
+        .map_err(|e| tr!("history.query_failed", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| tr!("history.read_failed", e))?;
+    Ok(runs)
+}
+
+/// Flash usage of a combination in the project's latest successful run
+/// with the same build configuration.
+pub fn previous_size(
+    path: &PathBuf,
+    project_path: &str,
+    config_name: Option<&str>,
+    combination: &str,
+) -> Result<Option<PreviousSize>, String> {
+    query_previous_size(&open(path)?, project_path, config_name, combination)
+}
+
+fn query_previous_size(
+    conn: &Connection,
+    project_path: &str,
+    config_name: Option<&str>,
+    combination: &str,
+) -> Result<Option<PreviousSize>, String> {
+    conn.query_row(
+        "SELECT r.run_id, c.text_size + c.data_size
+         FROM run_combinations c JOIN runs r ON r.run_id = c.run_id
+         WHERE r.project_path = ?1 AND r.config_name IS ?2 AND c.combination = ?3
+           AND r.success = 1 AND c.text_size IS NOT NULL
+         ORDER BY r.started_at DESC LIMIT 1",
+        params![project_key(project_path), config_name, combination],
+        |row| {
+            let flash: i64 = row.get(1)?;
+            Ok(PreviousSize { run_id: row.get(0)?, flash: flash as u64 })
+        },
+    )
+    .optional()
+    .map_err(|e| tr!("history.query_failed", e))
+}
+
+/// Latest run of a project that started before `before` and has a tag.
+pub fn previous_tagged_run(path: &PathBuf, project_path: &str, before: &str) -> Result<Option<String>, String> {
+    let conn = open(path)?;
+    conn.query_row(
+        "SELECT run_id FROM runs
+         WHERE project_path = ?1 AND started_at < ?2
+           AND EXISTS (SELECT 1 FROM run_tags t WHERE t.run_id = runs.run_id)
+         ORDER BY started_at DESC LIMIT 1",
+        params![project_key(project_path), utc(before)],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| tr!("history.query_failed", e))
+}
+
+/// A run with its per-combination outcomes and artifacts.
+#[command]
+pub async fn get_run(window: Window, run_id: String) -> Result<RunDetails, String> {
+    let conn = open(&history_path(&window)?)?;
+    let summary = conn
+        .query_row(
+            &format!("SELECT {} FROM runs WHERE run_id = ?1", SUMMARY_COLUMNS),
+            params![run_id],
+            summary_from_row,
+        )
+        .optional()
+        .map_err(|e| tr!("history.query_failed", e))?
+        .ok_or_else(|| tr!("history.run_not_found", run_id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT combination, success, errors, warnings, failure_reason, artifact, artifact_size
+             FROM run_combinations WHERE run_id = ?1 ORDER BY rowid",
+        )
+        .map_err(|e| tr!("history.query_failed", e))?;
+    let combinations = stmt
+        .query_map(params![run_id], |row| {
+            Ok(CombinationRecord {
+                combination: row.get(0)?,
+                success: row.get(1)?,
+                errors: row.get(2)?,
+                warnings: row.get(3)?,
+                failure_reason: row.get(4)?,
+                artifact: row.get(5)?,
+                artifact_size: row.get(6)?,
+            })
+        })
+        .map_err(|e| tr!("history.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("history.read_failed", e))?;
+
+    Ok(RunDetails { summary, combinations })
+}
+
+/// Forgets a run: its history record, indexed log and archived manifest.
+/// Artifacts in the output directory are left alone.
+#[command]
+pub async fn delete_run(window: Window, run_id: String) -> Result<(), String> {
+    let conn = open(&history_path(&window)?)?;
+    conn.execute("DELETE FROM runs WHERE run_id = ?1", params![run_id])
+        .map_err(|e| tr!("history.delete_failed", run_id, e))?;
+
+    log_index::delete_run(&log_index::index_path(&window)?, &run_id)?;
+
+    let manifest_path = manifest::archived_path(&window, &run_id)?;
+    if manifest_path.exists() {
+        fs::remove_file(&manifest_path)
+            .map_err(|e| tr!("fs.remove", manifest_path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Size history of one combination of a project, oldest first.
+/// Runs where the combination did not link are skipped.
+#[command]
+pub async fn get_size_trend(
+    window: Window,
+    project_path: String,
+    combination: String,
+    limit: Option<u32>,
+) -> Result<Vec<SizePoint>, String> {
+    let conn = open(&history_path(&window)?)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT * FROM (
+                 SELECT r.run_id, r.started_at, r.config_name, c.artifact_size,
+                        c.text_size, c.data_size, c.bss_size
+                 FROM run_combinations c JOIN runs r ON r.run_id = c.run_id
+                 WHERE r.project_path = ?1 AND c.combination = ?2 AND c.text_size IS NOT NULL
+                 ORDER BY r.started_at DESC
+                 LIMIT ?3
+             ) ORDER BY started_at",
+        )
+        .map_err(|e| tr!("history.query_failed", e))?;
+
+    let points = stmt
+        .query_map(params![project_key(&project_path), combination, limit.unwrap_or(200)], |row| {
+            let text: i64 = row.get(4)?;
+            let data: i64 = row.get(5)?;
+            let bss: i64 = row.get(6)?;
+            Ok(SizePoint {
+                run_id: row.get(0)?,
+                started_at: row.get(1)?,
+                config_name: row.get(2)?,
+                bin_size: row.get(3)?,
+                text,
+                data,
+                bss,
+                flash: text + data,
+                ram: data + bss,
+            })
+        })
+        .map_err(|e| tr!("history.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("history.read_failed", e))?;
+
+    Ok(points)
+}
+
+fn ensure_run(conn: &Connection, run_id: &str) -> Result<(), String> {
+    let exists: bool = conn
+        .query_row("SELECT EXISTS (SELECT 1 FROM runs WHERE run_id = ?1)", params![run_id], |row| row.get(0))
+        .map_err(|e| tr!("history.query_failed", e))?;
+    if exists {
+        Ok(())
+    } else {
+        Err(tr!("history.run_not_found", run_id))
+    }
+}
+
+/// Labels a run; adding a tag twice is a no-op.
+#[command]
+pub async fn add_run_tag(window: Window, run_id: String, tag: String) -> Result<(), String> {
+    tag_run(&history_path(&window)?, &run_id, &tag)
+}
+
+pub fn tag_run(path: &PathBuf, run_id: &str, tag: &str) -> Result<(), String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(tr!("history.tag_empty"));
+    }
+    let conn = open(path)?;
+    ensure_run(&conn, run_id)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO run_tags (run_id, tag) VALUES (?1, ?2)",
+        params![run_id, tag],
+    )
+    .map_err(|e| tr!("history.write_failed", e))?;
+    Ok(())
+}
+
+#[command]
+pub async fn remove_run_tag(window: Window, run_id: String, tag: String) -> Result<(), String> {
+    let conn = open(&history_path(&window)?)?;
+    conn.execute(
+        "DELETE FROM run_tags WHERE run_id = ?1 AND tag = ?2",
+        params![run_id, tag.trim()],
+    )
+    .map_err(|e| tr!("history.write_failed", e))?;
+    Ok(())
+}
+
+/// Sets or (with `None` or an empty string) clears the free-text note of a run.
+#[command]
+pub async fn set_run_note(window: Window, run_id: String, note: Option<String>) -> Result<(), String> {
+    let note = note.filter(|n| !n.trim().is_empty());
+    let conn = open(&history_path(&window)?)?;
+    ensure_run(&conn, &run_id)?;
+    conn.execute("UPDATE runs SET note = ?2 WHERE run_id = ?1", params![run_id, note])
+        .map_err(|e| tr!("history.write_failed", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn manifest(run_id: &str, project_path: &str, started_at: &str, success: bool, text: u64) -> RunManifest {
+        serde_json::from_value(json!({
+            "run_id": run_id,
+            "started_at": started_at,
+            "finished_at": started_at,
+            "project_path": project_path,
+            "output_dir": "/tmp/out",
+            "config_name": "Debug",
+            "success": success,
+            "cancelled": false,
+            "total_errors": 0,
+            "total_warnings": 0,
+            "stage_timings": [],
+            "provenance": null,
+            "combinations": [{
+                "combination": "brd4",
+                "success": success,
+                "exit_code": 0,
+                "errors": 0,
+                "warnings": 0,
+                "warnings_exceeded": false,
+                "failure_reason": null,
+                "failure_hint": null,
+                "diagnostics": [],
+                "artifact": "blinky_brd4.bin",
+                "artifact_size": text,
+                "sizes": { "text": text, "data": 16, "bss": 64, "flash": text + 16, "ram": 80 },
+                "log_file": null,
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn memory() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn runs_are_listed_newest_first_across_offsets() {
+        let mut conn = memory();
+        // 09:30 UTC, 10:00 UTC and 10:15 UTC, recorded before and after a DST change
+        insert_run(&mut conn, &manifest("b", "/fw", "2026-03-29T11:00:00.000+01:00", true, 1000)).unwrap();
+        insert_run(&mut conn, &manifest("a", "/fw", "2026-03-29T10:30:00+01:00", true, 1000)).unwrap();
+        insert_run(&mut conn, &manifest("c", "/fw", "2026-03-29T12:15:00.5+02:00", false, 1000)).unwrap();
+
+        let runs = query_runs(&conn, &RunFilter::default()).unwrap();
+        let ids: Vec<&str> = runs.iter().map(|r| r.run_id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "b", "a"]);
+        assert_eq!(runs[0].started_at, "2026-03-29T10:15:00.500Z");
+        assert_eq!(runs[0].combination_count, 1);
+
+        let filter = RunFilter { since: Some("2026-03-29T11:45:00+02:00".to_string()), ..Default::default() };
+        let ids: Vec<String> = query_runs(&conn, &filter).unwrap().into_iter().map(|r| r.run_id).collect();
+        assert_eq!(ids, vec!["c", "b"]);
+    }
+
+    #[test]
+    fn previous_size_is_the_latest_successful_run() {
+        let mut conn = memory();
+        assert!(query_previous_size(&conn, "/fw", Some("Debug"), "brd4").unwrap().is_none());
+        insert_run(&mut conn, &manifest("old", "/fw", "2026-10-01T08:00:00Z", true, 1000)).unwrap();
+        insert_run(&mut conn, &manifest("new", "/fw", "2026-10-02T08:00:00Z", true, 1200)).unwrap();
+        insert_run(&mut conn, &manifest("failed", "/fw", "2026-10-03T08:00:00Z", false, 900)).unwrap();
+        insert_run(&mut conn, &manifest("other", "/other", "2026-10-04T08:00:00Z", true, 5000)).unwrap();
+
+        let previous = query_previous_size(&conn, "/fw", Some("Debug"), "brd4").unwrap().unwrap();
+        assert_eq!((previous.run_id.as_str(), previous.flash), ("new", 1216));
+        assert!(query_previous_size(&conn, "/fw", Some("Release"), "brd4").unwrap().is_none());
+        assert!(query_previous_size(&conn, "/fw", Some("Debug"), "brd7").unwrap().is_none());
+    }
+
+    #[test]
+    fn previous_size_matches_other_spellings_of_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("blinky");
+        fs::create_dir(&project).unwrap();
+        let mut conn = memory();
+        insert_run(&mut conn, &manifest("r1", &project.display().to_string(), "2026-10-01T08:00:00Z", true, 1000)).unwrap();
+
+        let trailing = format!("{}{}", project.display(), std::path::MAIN_SEPARATOR);
+        let dotted = dir.path().join(".").join("blinky");
+        let mut spellings = vec![trailing, dotted.display().to_string()];
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link");
+            std::os::unix::fs::symlink(&project, &link).unwrap();
+            spellings.push(link.display().to_string());
+        }
+        #[cfg(windows)]
+        spellings.push(project.display().to_string().to_uppercase());
+        for spelling in spellings {
+            let previous = query_previous_size(&conn, &spelling, Some("Debug"), "brd4").unwrap();
+            assert_eq!(previous.map(|p| p.run_id), Some("r1".to_string()), "{}", spelling);
+        }
+    }
+
+    #[test]
+    fn older_records_are_migrated() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init(&mut conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 0").unwrap();
+        conn.execute(
+            "INSERT INTO runs (run_id, project_path, config_name, output_dir, started_at, finished_at, duration_ms,
+                 success, cancelled, total_errors, total_warnings, combination_count)
+             VALUES ('r', '/fw/', 'Debug', '/tmp/out', '2026-10-17T03:00:00+03:00', '2026-10-17T03:01:00+03:00', 60000, 1, 0, 0, 0, 0)",
+            [],
+        )
+        .unwrap();
+        init(&mut conn).unwrap();
+        let runs = query_runs(&conn, &RunFilter { project_path: Some("/fw".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].started_at.as_str(), runs[0].finished_at.as_str()), ("2026-10-17T00:00:00.000Z", "2026-10-17T00:01:00.000Z"));
+    }
+}
@@ -0,0 +1,394 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::command;
+
+/// Language of messages produced by the backend (logs, results, errors).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Ru,
+}
+
+lazy_static! {
+    static ref LANGUAGE: RwLock<Language> = RwLock::new(Language::En);
+}
+
+// (key, English, Russian); `{}` placeholders are filled in order
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("config.error", "Configuration error: {}", "Ошибка конфигурации: {}"),
+    ("settings.empty_array", "Setting '{}' is an empty array (may be optional or missing selection)", "Параметр '{}' — пустой массив (необязательный параметр или ничего не выбрано)"),
+    ("settings.validation", "Validation error for {}: {}", "Ошибка проверки параметра {}: {}"),
+    ("settings.missing", "Setting '{}' is missing in settings object", "Параметр '{}' отсутствует в настройках"),
+    ("settings.required_missing", "No values provided for required build parameters: {}. Please fill all required build settings.", "Не заданы значения обязательных параметров сборки: {}. Заполните все обязательные параметры."),
+    ("schema.error", "Build settings schema error: {}", "Ошибка схемы параметров сборки: {}"),
+    ("git.dirty", "The project's git tree ('{}') has uncommitted changes; the artifacts will not match a commit", "В git-репозитории проекта ('{}') есть незафиксированные изменения; артефакты не будут соответствовать коммиту"),
+    ("git.dirty_refused", "The project's git tree ('{}') has uncommitted changes; commit or stash them before building", "В git-репозитории проекта ('{}') есть незафиксированные изменения; зафиксируйте или спрячьте их перед сборкой"),
+    ("reproducible.no_prefix_map", "No -ffile-prefix-map flag in the project's compiler settings; absolute paths will differ between machines (add -ffile-prefix-map={}=.)", "В настройках компилятора проекта нет флага -ffile-prefix-map; абсолютные пути будут различаться между машинами (добавьте -ffile-prefix-map={}=.)"),
+    ("reproducible.identical", "{} artifact(s) are byte-identical to run {}", "{} артефакт(ов) побайтно совпадают с запуском {}"),
+    ("reproducible.differs", "Artifacts differ from run {} built from the same commit: {}", "Артефакты отличаются от запуска {}, собранного из того же коммита: {}"),
+    ("release.run_failed", "Run '{}' did not succeed; only successful runs can be released", "Запуск '{}' завершился неудачно; выпускать можно только успешные запуски"),
+    ("release.artifact_missing", "Cannot copy artifact '{}': {}", "Не удалось скопировать артефакт '{}': {}"),
+    ("release.git_log_failed", "Failed to read the git history of '{}'", "Не удалось прочитать историю git проекта '{}'"),
+    ("git.not_a_repository", "'{}' is not in a git repository", "'{}' не находится в git-репозитории"),
+    ("git.export_failed", "Failed to export the repository: {}", "Не удалось экспортировать репозиторий: {}"),
+    ("git.exported", "Building the committed state from {}", "Сборка зафиксированного состояния из {}"),
+    ("git.tag_failed", "Failed to create git tag '{}' (does it already exist?)", "Не удалось создать git-тег '{}' (возможно, он уже существует)"),
+    ("git.push_failed", "Failed to push git tag '{}' to '{}'", "Не удалось отправить git-тег '{}' в '{}'"),
+    ("git.tag_dirty", "Not tagging: the build contains uncommitted changes", "Тег не создан: сборка содержит незафиксированные изменения"),
+    ("git.tagged", "Tagged '{}' at commit {}", "Создан тег '{}' на коммите {}"),
+    ("git.pushed", "Pushed tag '{}' to '{}'", "Тег '{}' отправлен в '{}'"),
+    ("cppcheck.summary", "cppcheck: {} finding(s)", "cppcheck: найдено замечаний: {}"),
+    ("cppcheck.failed", "cppcheck could not be run: {}", "Не удалось запустить cppcheck: {}"),
+    ("clang_tidy.summary", "clang-tidy: {} finding(s)", "clang-tidy: найдено замечаний: {}"),
+    ("clang_tidy.failed", "clang-tidy could not be run: {}", "Не удалось запустить clang-tidy: {}"),
+    ("clang_tidy.no_compile_commands", "no compile_commands.json in '{}'; CubeIDE did not echo any compiler invocations", "В '{}' нет compile_commands.json: CubeIDE не вывел ни одного вызова компилятора"),
+    ("clang_tidy.failing_checks", "clang-tidy checks configured to fail the build reported findings: {}", "Проверки clang-tidy, настроенные как блокирующие, нашли замечания: {}"),
+    ("hook.summary", "{}: {} finding(s)", "{}: найдено замечаний: {}"),
+    ("hook.failed", "Analysis hook '{}' failed: {}", "Ошибка анализатора '{}': {}"),
+    ("hook.command_empty", "Analysis hook '{}' has no command", "У анализатора '{}' не задана команда"),
+    ("hook.pattern_missing", "Analysis hook '{}' uses the regex format but has no pattern", "Анализатор '{}' использует формат regex, но шаблон не задан"),
+    ("hook.pattern_invalid", "Invalid pattern of analysis hook '{}': {}", "Неверный шаблон анализатора '{}': {}"),
+    ("hook.sarif_invalid", "Analysis hook '{}' produced invalid SARIF: {}", "Анализатор '{}' выдал некорректный SARIF: {}"),
+    ("symbols.clean", "No forbidden symbols are linked in", "Запрещённые символы не обнаружены"),
+    ("symbols.linked_in", "forbidden symbol '{}' is linked in", "запрещённый символ '{}' попал в прошивку"),
+    ("symbols.forbidden", "Forbidden symbols are linked in: {}", "В прошивку попали запрещённые символы: {}"),
+    ("elf.no_regions", "No memory regions configured or found in a linker script; only the entry point is checked", "Области памяти не заданы и не найдены в скрипте компоновщика; проверяется только точка входа"),
+    ("elf.entry_mismatch", "entry point {} is not {} ({}); check ENTRY() in the linker script", "точка входа {} не совпадает с {} ({}); проверьте ENTRY() в скрипте компоновщика"),
+    ("elf.entry_undefined", "entry symbol {} is not defined", "символ точки входа {} не определён"),
+    ("elf.entry_outside", "entry point {} is outside all memory regions", "точка входа {} вне всех областей памяти"),
+    ("elf.section_outside", "section {} ({}, {} bytes) is outside all memory regions", "секция {} ({}, {} байт) вне всех областей памяти"),
+    ("elf.load_in_ram", "{} bytes at {} are loaded into {}, which is RAM; they will not survive a reset", "{} байт по адресу {} загружаются в {}, а это RAM; после сброса они не сохранятся"),
+    ("elf.load_outside", "load image {}..{} does not fit into any memory region; is flash overflowing with initialized data?", "образ загрузки {}..{} не помещается ни в одну область памяти; не переполнена ли flash инициализированными данными?"),
+    ("elf.ok", "ELF layout checks passed", "Проверки структуры ELF пройдены"),
+    ("elf.problems", "{} ELF layout problem(s), first: {}", "Проблем структуры ELF: {}, первая: {}"),
+    ("simulation.passed", "{}: firmware booted in {} ms", "{}: прошивка загрузилась за {} мс"),
+    ("simulation.failed", "{} boot test failed: {}", "Тест загрузки в {} не пройден: {}"),
+    ("simulation.pattern_invalid", "Invalid boot pattern '{}': {}", "Неверный шаблон загрузки '{}': {}"),
+    ("renode.script_missing", "Renode platform script '{}' not found", "Скрипт платформы Renode '{}' не найден"),
+    ("qemu.unsupported_mcu", "QEMU cannot emulate the project's MCU; set a machine explicitly", "QEMU не эмулирует МК проекта; укажите машину явно"),
+    ("tests.summary", "Unit tests: {} run, {} failed, {} errors, {} skipped", "Модульные тесты: выполнено {}, провалено {}, ошибок {}, пропущено {}"),
+    ("tests.failed", "Unit tests failed: {}", "Модульные тесты не пройдены: {}"),
+    ("tests.command_empty", "The unit test command is empty", "Команда модульных тестов не задана"),
+    ("tests.no_results", "No test results at '{}' (exit code {}): {}", "Нет результатов тестов в '{}' (код выхода {}): {}"),
+    ("tests.results_invalid", "Invalid JUnit XML: {}", "Некорректный JUnit XML: {}"),
+    ("size.grew", "Flash usage grew by {} bytes ({}%): {} -> {} bytes since run {}", "Занимаемая flash выросла на {} байт ({}%): {} -> {} байт с запуска {}"),
+    ("size.within_limit", "Flash usage {} bytes (was {} in run {})", "Занято flash: {} байт (было {} в запуске {})"),
+    ("size.no_previous", "No earlier successful run of this combination to compare sizes with", "Нет предыдущего успешного запуска этой комбинации для сравнения размеров"),
+    ("verify.build_failed", "Verification build {} failed: {}", "Проверочная сборка {} завершилась с ошибкой: {}"),
+    ("verify.no_artifact", "Verification build of {} produced no binary", "Проверочная сборка {} не создала бинарный файл"),
+    ("verify.workspace_failed", "Error creating temporary workspace: {}", "Ошибка создания временного рабочего пространства: {}"),
+    ("map.unused", "Linker discarded {} function(s) ({} bytes) and {} object(s) ({} bytes)", "Компоновщик отбросил функций: {} ({} байт), объектов: {} ({} байт)"),
+    ("map.ram", "Static RAM: {} bytes in {} module(s), largest {} ({} bytes)", "Статическая RAM: {} байт в {} модулях, больше всего {} ({} байт)"),
+    ("warnings.promoted", "{} warning(s) configured as errors, first: {}", "Предупреждений, считающихся ошибками: {}, первое: {}"),
+    ("api.unauthorized", "Missing or wrong API token", "Отсутствует или неверный токен API"),
+    ("api.busy", "Run {} started through the API is still in progress", "Запуск {}, начатый через API, ещё выполняется"),
+    ("api.idle", "No build started through the API is running", "Нет выполняющейся сборки, начатой через API"),
+    ("api.already_running", "The API server is already listening on {}", "Сервер API уже слушает {}"),
+    ("api.bind_failed", "Cannot listen on {}: {}", "Не удалось открыть {}: {}"),
+    ("api.not_running", "The API server is not running", "Сервер API не запущен"),
+    ("api.starting", "The API server is being started", "Сервер API запускается"),
+    ("api.server_failed", "The API server stopped: {}", "Сервер API остановился: {}"),
+    ("api.unknown_config", "No saved configuration for '{}'; open the project in the app and save its configuration first", "Нет сохранённой конфигурации для '{}'; откройте проект в приложении и сохраните его конфигурацию"),
+    ("webhook.failed", "Webhook {} failed: {}", "Веб-хук {} не выполнен: {}"),
+    ("notify.show_failed", "Cannot show the desktop notification: {}", "Не удалось показать уведомление: {}"),
+    ("notify.over_budget_title", "Combination is taking long", "Комбинация собирается долго"),
+    ("notify.over_budget", "{} has been building for more than {} s", "{} собирается дольше {} с"),
+    ("notify.cancelled_title", "Build cancelled", "Сборка отменена"),
+    ("notify.cancelled", "Run {} was cancelled", "Запуск {} отменён"),
+    ("notify.failed_title", "Build failed", "Сборка не удалась"),
+    ("notify.failed", "Run {}: {}", "Запуск {}: {}"),
+    ("notify.succeeded_title", "Build succeeded", "Сборка завершена"),
+    ("notify.succeeded", "{} combination(s) built in run {}", "Собрано комбинаций: {}, запуск {}"),
+    ("plugin.invalid", "Invalid plugin manifest '{}': {}", "Неверный манифест плагина '{}': {}"),
+    ("plugin.command_empty", "Plugin {} has no command", "У плагина {} не задана команда"),
+    ("plugin.exit_code", "Plugin {} exited with code {}: {}", "Плагин {} завершился с кодом {}: {}"),
+    ("plugin.output_missing", "Plugin {} did not produce '{}'", "Плагин {} не создал '{}'"),
+    ("plugin.loaded", "Plugins: {}", "Плагины: {}"),
+    ("plugin.done", "Plugin {} finished", "Плагин {} выполнен"),
+    ("script.invalid", "Cannot load run script '{}': {}", "Не удалось загрузить скрипт запуска '{}': {}"),
+    ("script.failed", "Run script hook {} failed: {}", "Ошибка в обработчике {} скрипта запуска: {}"),
+    ("script.loaded", "Run script loaded: {}", "Загружен скрипт запуска: {}"),
+    ("script.run_refused", "The run script's before_run hook refused the run", "Обработчик before_run скрипта запуска отменил запуск"),
+    ("script.combination_skipped", "Combination {} skipped by the run script", "Комбинация {} пропущена скриптом запуска"),
+    ("build.dry_run", "Dry run: CubeIDE is not started and the artifacts are empty placeholders", "Пробный запуск: CubeIDE не запускается, артефакты — пустые заглушки"),
+    ("error.workspace_locked", "Workspace {} is in use by another STM32CubeIDE instance", "Рабочее пространство {} занято другим экземпляром STM32CubeIDE"),
+    ("error.cancelled", "Build cancelled", "Сборка отменена"),
+    ("error.timeout", "Combination {} did not build within {} s", "Комбинация {} не собралась за {} с"),
+    ("cancel.no_run", "No build is running", "Сборка не выполняется"),
+    ("cancel.not_running", "Run {} is not running", "Запуск {} не выполняется"),
+    ("build.project_busy", "Waiting for the running build of this project or workspace to finish", "Ожидание завершения текущей сборки этого проекта или рабочего пространства"),
+    ("build.queued", "Waiting for a running build to finish (max concurrent builds reached)", "Ожидание завершения текущей сборки (достигнут предел одновременных сборок)"),
+    ("build.concurrency_invalid", "At least one build must be allowed at a time", "Должна быть разрешена хотя бы одна сборка"),
+    ("watch.started", "Watching {} for source changes", "Отслеживание изменений исходников в {}"),
+    ("watch.changed", "Changed: {}; rebuilding", "Изменены: {}; пересборка"),
+    ("watch.build_failed", "Watch build failed: {}", "Сборка в режиме отслеживания не удалась: {}"),
+    ("watch.no_project", "Project folder not found: {}", "Папка проекта не найдена: {}"),
+    ("watch.failed", "Cannot watch {}: {}", "Не удалось отслеживать {}: {}"),
+    ("watch.not_running", "Watch mode is not active", "Режим отслеживания не запущен"),
+    ("watch.invalid_glob", "Invalid watch pattern '{}': {}", "Некорректный шаблон отслеживания '{}': {}"),
+    ("mcu.no_ioc", "No CubeMX .ioc file in {}", "В {} нет файла CubeMX .ioc"),
+    ("header.default_unreachable", "{}/ is not on the include path of the configuration, build_config.h goes to {}/", "{}/ нет в путях включения конфигурации, build_config.h будет записан в {}/"),
+    ("header.generate_failed", "Error generating build_config.h: {}", "Ошибка создания build_config.h: {}"),
+    ("header.placement_failed", "Cannot read the include paths ({}), build_config.h goes to {}/", "Не удалось прочитать пути включения ({}), build_config.h будет записан в {}/"),
+    ("cproject.parse", "Error parsing .cproject: {}", "Ошибка разбора .cproject: {}"),
+    ("cproject.no_symbols", "Configuration {} has no preprocessor symbol list in .cproject; add a symbol in the project settings or use the header mode", "В .cproject у конфигурации {} нет списка символов препроцессора; добавьте символ в настройках проекта или используйте режим заголовочного файла"),
+    ("cproject.stale_backup", "Restored .cproject from the backup of an interrupted run", "Файл .cproject восстановлен из резервной копии прерванного запуска"),
+    ("cproject.defines", "Defines written to the .cproject symbol list: {}", "Макросы записаны в список символов .cproject: {}"),
+    ("signing.openssl_failed", "openssl {} failed: {}", "Ошибка openssl {}: {}"),
+    ("signing.no_certificates", "Signing needs at least the key's certificate", "Для подписи нужен как минимум сертификат ключа"),
+    ("signing.missing", "Signing file not found: {}", "Файл для подписи не найден: {}"),
+    ("signing.key_mismatch", "Certificate {} does not belong to the signing key", "Сертификат {} не соответствует ключу подписи"),
+    ("signing.signed", "Signed {}", "Подписан {}"),
+    ("warm.command", "Rebuilding with make: {}", "Пересборка через make: {}"),
+    ("warm.no_make", "make or the GNU toolchain of STM32CubeIDE not found; every combination is built by STM32CubeIDE", "make или GNU toolchain из STM32CubeIDE не найдены; каждая комбинация собирается в STM32CubeIDE"),
+    ("warm.cproject_mode", "Warm builds need the header define mode; every combination is built by STM32CubeIDE", "Быстрая пересборка требует режима заголовочного файла; каждая комбинация собирается в STM32CubeIDE"),
+    ("warm.no_makefile", "No makefile in {}; starting STM32CubeIDE", "В {} нет makefile; запуск STM32CubeIDE"),
+    ("build.affinity_invalid", "CPU {} does not exist; this machine has {}", "Процессора {} нет; на этой машине их {}"),
+    ("priority.nice_failed", "Could not set the build priority (nice {}), building at the default priority: {}", "Не удалось задать приоритет сборки (nice {}), сборка с приоритетом по умолчанию: {}"),
+    ("priority.affinity_failed", "Could not restrict the build to the selected CPUs, building on all of them: {}", "Не удалось ограничить сборку выбранными процессорами, сборка на всех: {}"),
+    ("priority.affinity_unsupported", "CPU affinity is not supported on this system; the build may use every CPU", "Привязка к процессорам не поддерживается в этой системе; сборка может использовать все процессоры"),
+    ("paths.empty", "One or more required paths are empty in BuildConfig", "Один или несколько обязательных путей не заданы"),
+    ("workspace.invalid", "Invalid workspace path '{}': {}", "Неверный путь к рабочему пространству '{}': {}"),
+    ("workspace.using", "Using workspace: {}", "Рабочее пространство: {}"),
+    ("workspace.missing", "Error: Workspace '{}' does not exist", "Ошибка: рабочее пространство '{}' не существует"),
+    ("cubeide.invalid_path", "Invalid STM32CubeIDE path '{}': {}", "Неверный путь к STM32CubeIDE '{}': {}"),
+    ("cubeide.not_found", "Error: STM32CubeIDE EXE '{}' not found", "Ошибка: исполняемый файл STM32CubeIDE '{}' не найден"),
+    ("cubeide.command", "Executing command: {}", "Выполняется команда: {}"),
+    ("cubeide.start_failed", "Failed to start STM32CubeIDE process: {}", "Не удалось запустить процесс STM32CubeIDE: {}"),
+    ("project.invalid_path", "Invalid project path '{}': {}", "Неверный путь к проекту '{}': {}"),
+    ("project.not_found", "Error: Project directory '{}' not found", "Ошибка: каталог проекта '{}' не найден"),
+    ("project.name", "Error getting project name: {}", "Ошибка получения имени проекта: {}"),
+    ("build_dir.invalid", "Invalid build directory '{}': {}", "Неверный каталог сборки '{}': {}"),
+    ("cproject.read", "Error reading .cproject: {}", "Ошибка чтения .cproject: {}"),
+    ("cproject.config_missing", "Error: Configuration '{}' not found in .cproject", "Ошибка: конфигурация '{}' не найдена в .cproject"),
+    ("step.unit_tests", "Running unit tests", "Запуск модульных тестов"),
+    ("step.validate_cubeide", "Validating STM32CubeIDE EXE path", "Проверка пути к исполняемому файлу STM32CubeIDE"),
+    ("step.prepare_directories", "Checking and creating directories", "Проверка и создание каталогов"),
+    ("step.preflight", "Running preflight checks", "Предварительные проверки"),
+    ("step.check_project", "Checking project files", "Проверка файлов проекта"),
+    ("step.clean_export", "Exporting HEAD into a temporary worktree", "Экспорт HEAD во временную рабочую копию"),
+    ("step.project_name", "Extracting project name", "Определение имени проекта"),
+    ("step.signing_check", "Checking the signing key and certificates", "Проверка ключа подписи и сертификатов"),
+    ("step.build_parameter", "Forming build parameter", "Формирование параметров сборки"),
+    ("step.write_logs", "Writing logs", "Запись журналов"),
+    ("step.plugin", "Running plugin {} for combination {}", "Запуск плагина {} для комбинации {}"),
+    ("step.cppcheck", "Running cppcheck for combination {}", "Запуск cppcheck для комбинации {}"),
+    ("step.clang_tidy", "Running clang-tidy for combination {}", "Запуск clang-tidy для комбинации {}"),
+    ("step.analysis_hook", "Running {} for combination {}", "Запуск {} для комбинации {}"),
+    ("step.forbidden_symbols", "Checking linked symbols for combination {}", "Проверка скомпонованных символов комбинации {}"),
+    ("step.elf_checks", "Checking ELF layout for combination {}", "Проверка структуры ELF комбинации {}"),
+    ("step.unused_code", "Analysing map file of combination {}", "Анализ map-файла комбинации {}"),
+    ("step.ram_breakdown", "Breaking down RAM usage of combination {}", "Разбор использования RAM комбинации {}"),
+    ("step.size_gate", "Comparing flash usage of combination {}", "Сравнение использования flash комбинации {}"),
+    ("step.renode", "Booting combination {} in Renode", "Загрузка комбинации {} в Renode"),
+    ("step.qemu", "Booting combination {} in QEMU", "Загрузка комбинации {} в QEMU"),
+    ("step.remove_bin", "Checking and removing existing .bin file for combination {}", "Проверка и удаление существующего .bin комбинации {}"),
+    ("step.generate_header", "Generating build_config.h for combination {}", "Генерация build_config.h для комбинации {}"),
+    ("step.cubeide", "Launching build in STM32CubeIDE for combination {}", "Запуск сборки в STM32CubeIDE для комбинации {}"),
+    ("step.check_output", "Checking build directory contents for combination {}", "Проверка содержимого каталога сборки комбинации {}"),
+    ("step.rename_output", "Renaming output file for combination {}", "Переименование выходного файла комбинации {}"),
+    ("fs.create_dir", "Error creating directory '{}': {}", "Ошибка создания каталога '{}': {}"),
+    ("fs.remove", "Error removing existing file '{}': {}", "Ошибка удаления файла '{}': {}"),
+    ("fs.read", "Error reading '{}': {}", "Ошибка чтения '{}': {}"),
+    ("fs.write", "Error writing '{}': {}", "Ошибка записи '{}': {}"),
+    ("fs.move", "Error moving '{}' to '{}': {}", "Ошибка перемещения '{}' в '{}': {}"),
+    ("fs.create", "Error creating '{}': {}", "Ошибка создания '{}': {}"),
+    ("fs.open", "Error opening '{}': {}", "Ошибка открытия '{}': {}"),
+    ("fs.read_dir", "Error reading directory '{}': {}", "Ошибка чтения каталога '{}': {}"),
+    ("elf.parse_failed", "Error parsing '{}': {}", "Ошибка разбора '{}': {}"),
+    ("elf.not_arm", "'{}' is not an ARM image ({})", "'{}' не является образом для ARM ({})"),
+    ("tool.start_failed", "Failed to start '{}': {}", "Не удалось запустить '{}': {}"),
+    ("unused_code.serialize_failed", "Failed to serialize unused code report: {}", "Не удалось сериализовать отчёт о неиспользуемом коде: {}"),
+    ("compile_db.serialize_failed", "Failed to serialize compilation database: {}", "Не удалось сериализовать базу компиляции: {}"),
+    ("compile_db.invalid", "Invalid compilation database '{}': {}", "Некорректная база компиляции '{}': {}"),
+    ("bundle.add_failed", "Error adding '{}' to bundle: {}", "Ошибка добавления '{}' в пакет: {}"),
+    ("bundle.environment_failed", "Failed to describe the environment for the bundle: {}", "Не удалось описать окружение для пакета: {}"),
+    ("bundle.finish_failed", "Error writing bundle '{}': {}", "Ошибка записи пакета '{}': {}"),
+    ("manifest.missing", "No manifest for run '{}': {}", "Нет манифеста запуска '{}': {}"),
+    ("manifest.invalid", "Invalid manifest for run '{}': {}", "Некорректный манифест запуска '{}': {}"),
+    ("manifest.serialize_failed", "Failed to serialize manifest: {}", "Не удалось сериализовать манифест: {}"),
+    ("ci.serialize_failed", "Failed to serialize workflow: {}", "Не удалось сериализовать workflow: {}"),
+    ("recent.serialize_failed", "Failed to serialize recent entries: {}", "Не удалось сериализовать недавние записи: {}"),
+    ("project_config.serialize_failed", "Failed to serialize build configuration: {}", "Не удалось сериализовать конфигурацию сборки: {}"),
+    ("project_config.invalid", "Invalid project configuration '{}': {}", "Некорректная конфигурация проекта '{}': {}"),
+    ("selection.invalid_csv", "Invalid CSV: {}", "Некорректный CSV: {}"),
+    ("selection.invalid_json", "Invalid JSON: {}", "Некорректный JSON: {}"),
+    ("path.not_a_directory", "Path '{}' does not exist or is not a directory", "Путь '{}' не существует или не является каталогом"),
+    ("language.set_failed", "Failed to set language: {}", "Не удалось сменить язык: {}"),
+    ("app_data.unavailable", "Failed to resolve app data directory: {}", "Не удалось определить каталог данных приложения: {}"),
+    ("project.invalid_file", "File '{}' is not a valid .project file", "Файл '{}' не является корректным файлом .project"),
+    ("cproject.missing", ".cproject file not found", "Файл .cproject не найден"),
+    ("cproject.invalid_file", "File '{}' is not a valid .cproject file", "Файл '{}' не является корректным файлом .cproject"),
+    ("project.configurations_failed", "Failed to get project configurations: {}", "Не удалось получить конфигурации проекта: {}"),
+    ("project.linked_resources_failed", "Failed to read linked resources: {}", "Не удалось прочитать связанные ресурсы: {}"),
+    ("project.toolchain_failed", "Failed to read the project toolchain: {}", "Не удалось прочитать набор инструментов проекта: {}"),
+    ("project.configuration_details_failed", "Failed to get configuration details: {}", "Не удалось получить параметры конфигурации: {}"),
+    ("project.include_folders_failed", "Failed to find the include folders: {}", "Не удалось найти каталоги включения: {}"),
+    ("history.open_failed", "Error opening history '{}': {}", "Ошибка открытия истории '{}': {}"),
+    ("history.init_failed", "Error initializing history: {}", "Ошибка инициализации истории: {}"),
+    ("history.write_failed", "Error writing history: {}", "Ошибка записи истории: {}"),
+    ("history.query_failed", "Error querying history: {}", "Ошибка запроса к истории: {}"),
+    ("history.read_failed", "Error reading history: {}", "Ошибка чтения истории: {}"),
+    ("history.run_not_found", "Run '{}' not found", "Запуск '{}' не найден"),
+    ("history.delete_failed", "Error deleting run '{}': {}", "Ошибка удаления запуска '{}': {}"),
+    ("history.tag_empty", "Tag must not be empty", "Метка не должна быть пустой"),
+    ("log_index.open_failed", "Error opening log index '{}': {}", "Ошибка открытия индекса журналов '{}': {}"),
+    ("log_index.init_failed", "Error initializing log index: {}", "Ошибка инициализации индекса журналов: {}"),
+    ("log_index.write_failed", "Error writing log index: {}", "Ошибка записи индекса журналов: {}"),
+    ("log_index.query_failed", "Error querying log index: {}", "Ошибка запроса к индексу журналов: {}"),
+    ("log_index.read_failed", "Error reading log index: {}", "Ошибка чтения индекса журналов: {}"),
+    ("log_index.delete_failed", "Error deleting from log index: {}", "Ошибка удаления из индекса журналов: {}"),
+    ("report.csv_failed", "Failed to write CSV: {}", "Не удалось записать CSV: {}"),
+    ("report.serialize_failed", "Failed to serialize report: {}", "Не удалось сериализовать отчёт: {}"),
+    ("versions.invalid", "Invalid version file '{}': {}", "Неверный файл версий '{}': {}"),
+    ("versions.serialize_failed", "Failed to serialize versions: {}", "Не удалось сериализовать версии: {}"),
+    ("build.starting", "Starting project build", "Запуск сборки проекта"),
+    ("build.cancelled_before_start", "Build was cancelled before starting", "Сборка отменена до начала"),
+    ("build.cancelled_combination", "Build cancelled for combination {}", "Сборка отменена для комбинации {}"),
+    ("build.completed", "Build process completed successfully", "Сборка успешно завершена"),
+    ("build.completed_errors", "Build process completed with errors", "Сборка завершена с ошибками"),
+    ("combinations.none", "No build combinations generated. This usually means at least one build parameter has no values. Check settings_values and build_settings.", "Не сформировано ни одной комбинации сборки. Обычно это означает, что у какого-то параметра нет значений. Проверьте settings_values и build_settings."),
+    ("combinations.selected", "Building {} selected combination(s)", "Сборка выбранных комбинаций: {}"),
+    ("combinations.selection_invalid", "Invalid combination selection: {}", "Неверный список комбинаций: {}"),
+    ("combination.failed", "Combination {} failed: {}", "Комбинация {} завершилась с ошибкой: {}"),
+    ("combination.exit_code", "Build failed with exit code: {}", "Сборка завершилась с кодом ошибки: {}"),
+    ("process.exit_code", "Build process exited with code: {}", "Процесс сборки завершился с кодом: {}"),
+    ("process.wait_failed", "Process wait failed: {}", "Ошибка ожидания процесса: {}"),
+    ("diagnostics.summary", "Diagnostics: {} error(s), {} warning(s)", "Диагностика: ошибок — {}, предупреждений — {}"),
+    ("warnings.exceeded", "Warning threshold exceeded: {} warning(s), maximum allowed is {}", "Превышен порог предупреждений: {}, допустимо не более {}"),
+    ("output.run_dir", "Output directory of this run: {}", "Каталог результатов этого запуска: {}"),
+    ("output.not_found", "Error: Output file '{}.bin' not found in '{}'", "Ошибка: выходной файл '{}.bin' не найден в '{}'"),
+    ("output.size", "Output file size: {} bytes", "Размер выходного файла: {} байт"),
+    ("output.sections", "Sections: text {} bytes, data {} bytes, bss {} bytes", "Секции: text {} байт, data {} байт, bss {} байт"),
+    ("output.metadata", "Failed to get output file metadata: {}", "Не удалось получить сведения о выходном файле: {}"),
+    ("log.rotate_failed", "Failed to rotate log file '{}': {}", "Не удалось выполнить ротацию журнала '{}': {}"),
+    ("log.prune_runs_failed", "Failed to remove old runs from '{}': {}", "Не удалось удалить старые запуски из '{}': {}"),
+    ("log.write_failed", "Failed to write logs: {}", "Не удалось записать журнал: {}"),
+    ("log.index_failed", "Failed to index build output: {}", "Не удалось проиндексировать вывод сборки: {}"),
+    ("cancel.done", "Build process cancelled", "Сборка отменена"),
+    ("cancel.event_failed", "Failed to report the cancellation: {}", "Не удалось сообщить об отмене: {}"),
+    ("cancel.kill_error", "Kill error: {}", "Ошибка завершения процесса: {}"),
+    ("cancel.terminated", "Build process terminated", "Процесс сборки остановлен"),
+    ("preflight.disk_ok", "{}: {} free", "{}: свободно {}"),
+    ("preflight.disk_low", "Low disk space for '{}': only {} free", "Мало места на диске для '{}': свободно всего {}"),
+    ("preflight.disk_unknown", "Could not determine free disk space for '{}'", "Не удалось определить свободное место на диске для '{}'"),
+    ("preflight.memory_ok", "{} RAM available, JVM heap limit is {}", "Доступно ОЗУ: {}, лимит кучи JVM: {}"),
+    ("preflight.memory_low", "Only {} RAM available but the STM32CubeIDE JVM may use up to {} (-Xmx); the build may become very slow or fail", "Доступно только {} ОЗУ, а JVM STM32CubeIDE может занять до {} (-Xmx); сборка может сильно замедлиться или завершиться ошибкой"),
+    ("preflight.memory_available", "{} RAM available", "Доступно ОЗУ: {}"),
+    ("preflight.tool_found", "{} found: {}", "{} найден: {}"),
+    ("preflight.tool_missing", "{} not found in the STM32CubeIDE plugins or on PATH", "{} не найден ни в плагинах STM32CubeIDE, ни в PATH"),
+    ("preflight.failed", "Preflight checks failed: {}", "Предварительные проверки не пройдены: {}"),
+    ("flash.start_failed", "Failed to start '{}': {}", "Не удалось запустить '{}': {}"),
+    ("flash.file_missing", "Firmware file '{}' not found", "Файл прошивки '{}' не найден"),
+    ("flash.openocd_scripts_missing", "OpenOCD needs an interface and a target script", "Для OpenOCD нужны скрипты interface и target"),
+    ("flash.st_flash_format", "st-flash can only program .bin and .hex files: '{}'", "st-flash может записывать только файлы .bin и .hex: '{}'"),
+    ("flash.batch_probe_missing", "Parallel flashing needs a probe for every board ('{}' has none)", "Для параллельной прошивки каждой плате нужен программатор (у '{}' его нет)"),
+    ("flash.batch_probe_reused", "Probe {} is assigned to more than one board", "Программатор {} назначен нескольким платам"),
+    ("flash.verifying", "Verifying '{}'", "Проверка '{}'"),
+    ("flash.verify_failed", "Verification failed: {}", "Проверка не пройдена: {}"),
+    ("flash.verify_mismatch", "Verification failed: {} of {} bytes differ", "Проверка не пройдена: отличаются {} из {} байт"),
+    ("flash.verify_unconfirmed", "The programmer did not confirm the verification", "Программатор не подтвердил проверку"),
+    ("flash.readback_failed", "Reading back the flash failed with exit code: {}", "Чтение флеш-памяти завершилось с кодом ошибки: {}"),
+    ("flash.readback_short", "Read back only {} of {} bytes", "Прочитано только {} из {} байт"),
+    ("flash.invalid_address", "Invalid address '{}'", "Некорректный адрес '{}'"),
+    ("flash.option_bytes_backend", "Option bytes can only be programmed with STM32CubeProgrammer", "Option bytes можно записать только через STM32CubeProgrammer"),
+    ("flash.rdp_level2_unconfirmed", "Read-out protection level 2 locks the chip permanently; it must be confirmed for level 2 explicitly (confirmRdp: \"level2\")", "Защита от чтения уровня 2 необратимо блокирует микроконтроллер; её нужно подтвердить именно для уровня 2 (confirmRdp: \"level2\")"),
+    ("flash.rdp_unconfirmed", "Changing read-out protection (RDP={}) must be confirmed", "Изменение защиты от чтения (RDP={}) требует подтверждения"),
+    ("flash.option_bytes_failed", "Programming option bytes failed with exit code: {}", "Запись option bytes завершилась с кодом ошибки: {}"),
+    ("flash.dfu_format", "dfu-util can only program .bin and .dfu files: '{}'", "dfu-util может записывать только файлы .bin и .dfu: '{}'"),
+    ("flash.dfu_no_target_check", "The connected MCU cannot be checked over dfu-util", "Через dfu-util нельзя проверить подключённый микроконтроллер"),
+    ("flash.external_loader_backend", "External loaders can only be used with STM32CubeProgrammer", "Внешние загрузчики можно использовать только с STM32CubeProgrammer"),
+    ("flash.external_loader_missing", "External loader '{}' not found", "Внешний загрузчик '{}' не найден"),
+    ("production.log_failed", "Failed to write the production log: {}", "Не удалось записать журнал производства: {}"),
+    ("production.not_running", "No production run '{}'", "Производственный запуск '{}' не найден"),
+    ("flash.done", "Flashed '{}' successfully", "Прошивка '{}' записана успешно"),
+    ("flash.failed", "Flashing failed with exit code: {}", "Прошивка завершилась с кодом ошибки: {}"),
+    ("personalize.patched", "Device UID {}: patched value {}", "UID устройства {}: записано значение {}"),
+    ("personalize.dfu_unsupported", "The device UID cannot be read over dfu-util", "Через dfu-util нельзя прочитать UID устройства"),
+    ("personalize.bin_only", "Only .bin images can be personalized", "Персонализировать можно только образы .bin"),
+    ("personalize.invalid_placeholder", "Invalid placeholder '{}', expected hex bytes", "Некорректный маркер '{}', ожидаются байты в hex"),
+    ("personalize.placeholder_missing", "Placeholder {} not found in the image", "Маркер {} не найден в образе"),
+    ("personalize.out_of_range", "Cannot patch {} bytes at offset {}: the image has {} bytes", "Невозможно записать {} байт по смещению {}: размер образа {} байт"),
+    ("personalize.uid_address_unknown", "Unknown UID address for this MCU, set it in the personalization settings", "Адрес UID для этого микроконтроллера неизвестен, укажите его в настройках персонализации"),
+    ("personalize.uid_read_failed", "Could not read the device UID", "Не удалось прочитать UID устройства"),
+    ("target.detect_failed", "Could not identify the connected MCU: {}", "Не удалось определить подключённый микроконтроллер: {}"),
+    ("target.mismatch", "Connected MCU {} does not match the project's {}", "Подключённый микроконтроллер {} не соответствует проекту ({})"),
+    ("target.mismatch_ignored", "Connected MCU {} does not match the project's {}, flashing anyway", "Подключённый микроконтроллер {} не соответствует проекту ({}), прошивка продолжается"),
+    ("target.unknown", "Could not cross-check the connected MCU '{}' with the project's '{}'", "Не удалось сверить подключённый микроконтроллер '{}' с проектом ('{}')"),
+    ("smoke.running", "Running smoke test '{}'", "Запуск smoke-теста '{}'"),
+    ("smoke.dfu_unsupported", "Smoke tests need a debug probe, not DFU", "Для smoke-тестов нужен отладчик, а не DFU"),
+    ("smoke.script_missing", "Smoke test script '{}' not found", "Скрипт smoke-теста '{}' не найден"),
+    ("smoke.timeout", "Smoke test did not finish within {} s", "Smoke-тест не завершился за {} с"),
+    ("smoke.exit_code", "GDB exited with code {}", "GDB завершился с кодом {}"),
+    ("smoke.failed", "Smoke test failed: {}", "Smoke-тест не пройден: {}"),
+    ("trace.frequency_missing", "SWO capture needs the core clock frequency", "Для захвата SWO нужна частота ядра"),
+    ("trace.connect_failed", "Could not connect to the RTT server on port {}: {}", "Не удалось подключиться к RTT-серверу на порту {}: {}"),
+    ("trace.tool_failed", "Trace capture ended with exit code: {}", "Захват трассировки завершился с кодом: {}"),
+    ("trace.not_running", "Trace {} is not running", "Трассировка {} не запущена"),
+    ("serial.list_failed", "Failed to list serial ports: {}", "Не удалось получить список последовательных портов: {}"),
+    ("serial.open_failed", "Failed to open serial port {}: {}", "Не удалось открыть последовательный порт {}: {}"),
+    ("serial.already_open", "Serial port {} is already open", "Последовательный порт {} уже открыт"),
+    ("serial.not_open", "Serial port {} is not open", "Последовательный порт {} не открыт"),
+    ("hint.workspace_in_use", "The workspace is locked by another Eclipse instance. Close STM32CubeIDE (and any other running build) that uses this workspace, or select a separate workspace for headless builds.", "Рабочее пространство заблокировано другим экземпляром Eclipse. Закройте STM32CubeIDE (и другие запущенные сборки), использующие это пространство, или выберите отдельное рабочее пространство для сборки."),
+    ("hint.project_not_found", "The project is not imported into the selected workspace. Open the workspace in STM32CubeIDE once and import the project, or check the project name and workspace path.", "Проект не импортирован в выбранное рабочее пространство. Откройте его в STM32CubeIDE и импортируйте проект либо проверьте имя проекта и путь к рабочему пространству."),
+    ("hint.toolchain_not_found", "The GNU Arm toolchain could not be started. Check the toolchain settings of the project (Properties > C/C++ Build > Settings > MCU Toolchain) and that STM32CubeIDE's bundled tools are installed.", "Не удалось запустить тулчейн GNU Arm. Проверьте настройки тулчейна проекта (Properties > C/C++ Build > Settings > MCU Toolchain) и наличие инструментов, поставляемых с STM32CubeIDE."),
+    ("hint.jvm_out_of_memory", "STM32CubeIDE ran out of Java heap. Increase -Xmx in stm32cubeide.ini next to the executable, or build fewer combinations at once.", "STM32CubeIDE не хватило памяти Java. Увеличьте -Xmx в stm32cubeide.ini рядом с исполняемым файлом или собирайте меньше комбинаций за раз."),
+    ("hint.jvm_start_failed", "The Java VM of STM32CubeIDE failed to start. Check the -vm and -Xmx entries in stm32cubeide.ini and that enough memory is free.", "Не удалось запустить Java VM STM32CubeIDE. Проверьте параметры -vm и -Xmx в stm32cubeide.ini и наличие свободной памяти."),
+];
+
+pub fn current_language() -> Language {
+    LANGUAGE.read().map(|l| *l).unwrap_or(Language::En)
+}
+
+/// Looks up `key` in the current language and fills `{}` placeholders in order.
+/// Unknown keys are returned as-is so a missing entry is visible but harmless.
+pub fn translate(key: &str, args: &[String]) -> String {
+    let template = MESSAGES
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, en, ru)| match current_language() {
+            Language::En => *en,
+            Language::Ru => *ru,
+        })
+        .unwrap_or(key);
+
+    let mut result = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{}") {
+        result.push_str(&rest[..pos]);
+        if let Some(arg) = args.next() {
+            result.push_str(arg);
+        }
+        rest = &rest[pos + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// `tr!("key", arg1, arg2)` — localized message with positional arguments.
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::translate($key, &[$($arg.to_string()),+])
+    };
+}
+pub(crate) use tr;
+
+#[command]
+pub fn set_language(language: Language) -> Result<(), String> {
+    let mut current = LANGUAGE
+        .write()
+        .map_err(|e| tr!("language.set_failed", e))?;
+    *current = language;
+    Ok(())
+}
+
+#[command]
+pub fn get_language() -> Language {
+    current_language()
+}
@@ -0,0 +1,125 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::io;
+use std::os::windows::io::RawHandle;
+use std::sync::{Arc, Mutex, Weak};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject, TerminateJobObject};
+use winapi::um::processthreadsapi::{OpenThread, ResumeThread};
+use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32};
+use winapi::um::winnt::{
+    JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    THREAD_SUSPEND_RESUME,
+};
+
+/// Creation flag starting a process with its main thread suspended, so it
+/// can join a job before it runs.
+pub const CREATE_SUSPENDED: u32 = 0x00000004;
+
+lazy_static! {
+    // Jobs of the running build processes by pid, for cancellation
+    static ref JOBS: Mutex<HashMap<u32, Weak<JobObject>>> = Mutex::new(HashMap::new());
+}
+
+/// Job holding a build process and everything it starts (the Java VM,
+/// make, the compilers), so the tree can be ended as a whole; a parent pid
+/// walk misses processes whose parent already exited. Closing the job
+/// kills whatever is still running in it.
+pub struct JobObject {
+    handle: HANDLE,
+    pid: u32,
+}
+
+// The handle is only passed to thread-safe Win32 calls
+unsafe impl Send for JobObject {}
+unsafe impl Sync for JobObject {}
+
+impl JobObject {
+    /// Puts the process into a new kill-on-close job, registered under
+    /// `pid` until the job is dropped. Processes it started before
+    /// joining stay outside, so it should still be suspended, see
+    /// [`CREATE_SUSPENDED`] and [`resume`].
+    pub fn assign(pid: u32, process: RawHandle) -> io::Result<Arc<JobObject>> {
+        unsafe {
+            let handle = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = JobObject { handle, pid };
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let set = SetInformationJobObject(
+                handle,
+                JobObjectExtendedLimitInformation,
+                &mut limits as *mut _ as *mut _,
+                std::mem::size_of_val(&limits) as u32,
+            );
+            if set == 0 || AssignProcessToJobObject(handle, process as HANDLE) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Arc::new(job);
+            JOBS.lock().unwrap().insert(pid, Arc::downgrade(&job));
+            Ok(job)
+        }
+    }
+
+    /// Ends every process in the job.
+    pub fn terminate(&self) -> io::Result<()> {
+        if unsafe { TerminateJobObject(self.handle, 1) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.handle);
+        }
+        // Unless a newer process with the same pid registered a job since
+        JOBS.lock().unwrap().retain(|pid, job| *pid != self.pid || job.strong_count() > 0);
+    }
+}
+
+/// Ends the job of the build process `pid`; `None` when it has none, e.g.
+/// because the app itself runs in a job that does not allow nesting.
+pub fn terminate(pid: u32) -> Option<io::Result<()>> {
+    let job = JOBS.lock().unwrap().get(&pid).and_then(Weak::upgrade)?;
+    Some(job.terminate())
+}
+
+/// Resumes the threads of a process started with [`CREATE_SUSPENDED`]; until
+/// it runs, that is only its main thread.
+pub fn resume(pid: u32) -> io::Result<()> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let mut entry: THREADENTRY32 = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+        let mut resumed = 0;
+        let mut result = Ok(());
+        let mut more = Thread32First(snapshot, &mut entry) != 0;
+        while more {
+            if entry.th32OwnerProcessID == pid {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if thread.is_null() || ResumeThread(thread) == u32::MAX {
+                    result = Err(io::Error::last_os_error());
+                } else {
+                    resumed += 1;
+                }
+                if !thread.is_null() {
+                    CloseHandle(thread);
+                }
+            }
+            more = Thread32Next(snapshot, &mut entry) != 0;
+        }
+        CloseHandle(snapshot);
+        if resumed == 0 && result.is_ok() {
+            result = Err(io::Error::other(format!("no thread of process {} found", pid)));
+        }
+        result
+    }
+}
@@ -1,9 +0,0 @@
-pub mod models;
-pub mod process;
-pub mod utils;
-pub mod config;
-pub mod builder;
-pub mod defaults;
-pub mod build_combinations;
-pub mod build_config_gen;
-pub mod logging;
\ No newline at end of file
@@ -0,0 +1,277 @@
+use crate::i18n::tr;
+use crate::models::LogEntry;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, Manager, Window};
+
+const INDEX_FILE: &str = "log_index.sqlite";
+
+/// A single match returned by `search_logs`.
+#[derive(Debug, Serialize)]
+pub struct LogSearchHit {
+    pub run: String,
+    pub timestamp: String,
+    pub level: String,
+    pub combination: Option<String>,
+    pub stage: Option<String>,
+    pub message: String,
+    /// Message excerpt with the matched terms wrapped in `[` `]`.
+    pub snippet: String,
+}
+
+/// Location of the log index inside the app data directory.
+pub fn index_path(window: &Window) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(INDEX_FILE))
+}
+
+fn open(path: &PathBuf) -> Result<Connection, String> {
+    let conn = Connection::open(path)
+        .map_err(|e| tr!("log_index.open_failed", path.display(), e))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS log_entries (
+             id INTEGER PRIMARY KEY,
+             run TEXT NOT NULL,
+             timestamp TEXT NOT NULL,
+             level TEXT NOT NULL,
+             combination TEXT,
+             stage TEXT,
+             message TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS log_entries_run ON log_entries(run);
+         CREATE VIRTUAL TABLE IF NOT EXISTS log_fts USING fts5(
+             message, content='log_entries', content_rowid='id'
+         );",
+    )
+    .map_err(|e| tr!("log_index.init_failed", e))?;
+    Ok(conn)
+}
+
+fn insert(
+    conn: &Connection,
+    run: &str,
+    timestamp: &str,
+    level: &str,
+    combination: Option<&str>,
+    stage: Option<&str>,
+    message: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO log_entries (run, timestamp, level, combination, stage, message)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![run, timestamp, level, combination, stage, message],
+    )?;
+    conn.execute(
+        "INSERT INTO log_fts (rowid, message) VALUES (last_insert_rowid(), ?1)",
+        params![message],
+    )?;
+    Ok(())
+}
+
+/// Stores the backend log entries of a finished run.
+pub fn index_entries(path: &PathBuf, run: &str, entries: &[LogEntry]) -> Result<(), String> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction().map_err(|e| tr!("log_index.write_failed", e))?;
+    for entry in entries {
+        let level = format!("{:?}", entry.level).to_lowercase();
+        insert(
+            &tx,
+            run,
+            &entry.timestamp,
+            &level,
+            entry.combination.as_deref(),
+            entry.stage.as_deref(),
+            &entry.message,
+        )
+        .map_err(|e| tr!("log_index.write_failed", e))?;
+    }
+    tx.commit().map_err(|e| tr!("log_index.write_failed", e))
+}
+
+/// Stores raw CubeIDE output of one combination with level `output`,
+/// so compiler and linker messages are searchable too.
+pub fn index_output(
+    path: &PathBuf,
+    run: &str,
+    combination: &str,
+    timestamp: &str,
+    lines: &[String],
+) -> Result<(), String> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction().map_err(|e| tr!("log_index.write_failed", e))?;
+    for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+        insert(&tx, run, timestamp, "output", Some(combination), Some("cubeide"), line)
+            .map_err(|e| tr!("log_index.write_failed", e))?;
+    }
+    tx.commit().map_err(|e| tr!("log_index.write_failed", e))
+}
+
+/// Full-text search over all indexed runs, newest first.
+///
+/// `query` is matched as a phrase, so strings such as
+/// `region FLASH overflowed` work without FTS syntax.
+#[command]
+pub async fn search_logs(
+    window: Window,
+    query: String,
+    run: Option<String>,
+    level: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<LogSearchHit>, String> {
+    let path = index_path(&window)?;
+    let conn = open(&path)?;
+    let phrase = format!("\"{}\"", query.trim().replace('"', "\"\""));
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.run, e.timestamp, e.level, e.combination, e.stage, e.message,
+                    snippet(log_fts, 0, '[', ']', '…', 16)
+             FROM log_fts JOIN log_entries e ON e.id = log_fts.rowid
+             WHERE log_fts MATCH ?1
+               AND (?2 IS NULL OR e.run = ?2)
+               AND (?3 IS NULL OR e.level = ?3)
+             ORDER BY e.id DESC
+             LIMIT ?4",
+        )
+        .map_err(|e| tr!("log_index.query_failed", e))?;
+
+    let hits = stmt
+        .query_map(
+            params![phrase, run, level.map(|l| l.to_lowercase()), limit.unwrap_or(200)],
+            |row| {
+                Ok(LogSearchHit {
+                    run: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    level: row.get(2)?,
+                    combination: row.get(3)?,
+                    stage: row.get(4)?,
+                    message: row.get(5)?,
+                    snippet: row.get(6)?,
+                })
+            },
+        )
+        .map_err(|e| tr!("log_index.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("log_index.read_failed", e))?;
+
+    Ok(hits)
+}
+
+/// A page of indexed log lines for one run (optionally one combination).
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    /// Total number of lines matching run/combination.
+    pub total: u64,
+    pub offset: u64,
+    pub entries: Vec<LogSearchHit>,
+}
+
+/// Pages through the log of a run in chronological order.
+///
+/// Without `offset` the last `limit` lines are returned, which is what a
+/// log view wants when it first opens.
+#[command]
+pub async fn get_log_tail(
+    window: Window,
+    run: String,
+    combo: Option<String>,
+    offset: Option<u64>,
+    limit: Option<u64>,
+) -> Result<LogPage, String> {
+    let path = index_path(&window)?;
+    let conn = open(&path)?;
+    let limit = limit.unwrap_or(500);
+
+    let total: u64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM log_entries WHERE run = ?1 AND (?2 IS NULL OR combination = ?2)",
+            params![run, combo],
+            |row| row.get(0),
+        )
+        .map_err(|e| tr!("log_index.query_failed", e))?;
+    let offset = offset.unwrap_or_else(|| total.saturating_sub(limit));
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT run, timestamp, level, combination, stage, message
+             FROM log_entries
+             WHERE run = ?1 AND (?2 IS NULL OR combination = ?2)
+             ORDER BY timestamp, id
+             LIMIT ?3 OFFSET ?4",
+        )
+        .map_err(|e| tr!("log_index.query_failed", e))?;
+
+    let entries = stmt
+        .query_map(params![run, combo, limit, offset], |row| {
+            let message: String = row.get(5)?;
+            Ok(LogSearchHit {
+                run: row.get(0)?,
+                timestamp: row.get(1)?,
+                level: row.get(2)?,
+                combination: row.get(3)?,
+                stage: row.get(4)?,
+                snippet: message.clone(),
+                message,
+            })
+        })
+        .map_err(|e| tr!("log_index.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("log_index.read_failed", e))?;
+
+    Ok(LogPage { total, offset, entries })
+}
+
+/// All indexed lines of a run in chronological order.
+pub fn run_entries(path: &PathBuf, run: &str) -> Result<Vec<LogSearchHit>, String> {
+    let conn = open(path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT run, timestamp, level, combination, stage, message
+             FROM log_entries
+             WHERE run = ?1
+             ORDER BY timestamp, id",
+        )
+        .map_err(|e| tr!("log_index.query_failed", e))?;
+
+    let entries = stmt
+        .query_map(params![run], |row| {
+            let message: String = row.get(5)?;
+            Ok(LogSearchHit {
+                run: row.get(0)?,
+                timestamp: row.get(1)?,
+                level: row.get(2)?,
+                combination: row.get(3)?,
+                stage: row.get(4)?,
+                snippet: message.clone(),
+                message,
+            })
+        })
+        .map_err(|e| tr!("log_index.query_failed", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("log_index.read_failed", e))?;
+
+    Ok(entries)
+}
+
+/// Removes all indexed lines of a run.
+pub fn delete_run(path: &PathBuf, run: &str) -> Result<(), String> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction().map_err(|e| tr!("log_index.write_failed", e))?;
+    // External-content FTS tables have to be told which rows go away
+    tx.execute(
+        "INSERT INTO log_fts (log_fts, rowid, message)
+         SELECT 'delete', id, message FROM log_entries WHERE run = ?1",
+        params![run],
+    )
+    .and_then(|_| tx.execute("DELETE FROM log_entries WHERE run = ?1", params![run]))
+    .map_err(|e| tr!("log_index.delete_failed", e))?;
+    tx.commit().map_err(|e| tr!("log_index.write_failed", e))
+}
@@ -0,0 +1,234 @@
+use chrono::Local;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Retention policy for build logs kept under the output directory, and
+/// for the `<run_id>` folders of earlier runs next to the current one.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LogRetention {
+    /// How many archived copies of each log, and how many earlier runs, to keep.
+    #[serde(rename = "maxFiles", default = "default_max_files")]
+    pub max_files: usize,
+    /// Archives and runs older than this are deleted.
+    #[serde(rename = "maxAgeDays", default)]
+    pub max_age_days: Option<u64>,
+    /// Upper bound for the combined size of all archives of one log, and
+    /// of all earlier runs.
+    #[serde(rename = "maxTotalBytes", default)]
+    pub max_total_bytes: Option<u64>,
+    /// Gzip archived logs.
+    #[serde(default)]
+    pub compress: bool,
+}
+
+fn default_max_files() -> usize {
+    10
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        LogRetention {
+            max_files: default_max_files(),
+            max_age_days: Some(30),
+            max_total_bytes: None,
+            compress: true,
+        }
+    }
+}
+
+/// Moves an existing log out of the way before a new run overwrites it,
+/// then prunes old archives of the same log according to `policy`.
+///
+/// `build_log.txt` becomes `build_log.<timestamp>.txt` (or `.txt.gz`).
+pub fn rotate_log(path: &Path, policy: &LogRetention) -> io::Result<()> {
+    if path.is_file() {
+        let (stem, ext) = split_name(path);
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+        let archived = path.with_file_name(format!("{}.{}{}", stem, stamp, ext));
+
+        if policy.compress {
+            let gz_path = PathBuf::from(format!("{}.gz", archived.display()));
+            let mut input = File::open(path)?;
+            let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+            fs::remove_file(path)?;
+        } else {
+            fs::rename(path, &archived)?;
+        }
+    }
+
+    prune_archives(path, policy)
+}
+
+/// `rotate_log` on the blocking pool, for the build pipeline.
+pub async fn rotate_log_async(path: &Path, policy: &LogRetention) -> io::Result<()> {
+    let (path, policy) = (path.to_path_buf(), policy.clone());
+    tokio::task::spawn_blocking(move || rotate_log(&path, &policy))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Deletes the folders of earlier runs under `build_dir` according to
+/// `policy`, newest kept first. Only folders named like a run id count;
+/// runs in `keep` (the current one, others still building) are left alone.
+pub fn prune_runs(build_dir: &Path, keep: &[String], policy: &LogRetention) -> io::Result<()> {
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(build_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !is_run_id(&name) || keep.contains(&name) || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let modified = entry.metadata()?.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        runs.push((name, entry.path(), modified));
+    }
+    // Run ids sort chronologically
+    runs.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let max_age = policy.max_age_days.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+    let now = SystemTime::now();
+    let mut total_bytes = 0u64;
+    for (index, (_, run, modified)) in runs.into_iter().enumerate() {
+        let size = dir_size(&run)?;
+        let too_old = max_age.is_some_and(|age| now.duration_since(modified).unwrap_or_default() > age);
+        let too_many = index >= policy.max_files;
+        let too_big = policy.max_total_bytes.is_some_and(|limit| total_bytes + size > limit);
+        if too_old || too_many || too_big {
+            fs::remove_dir_all(&run)?;
+        } else {
+            total_bytes += size;
+        }
+    }
+    Ok(())
+}
+
+/// `prune_runs` on the blocking pool, for the build pipeline.
+pub async fn prune_runs_async(build_dir: &Path, keep: Vec<String>, policy: &LogRetention) -> io::Result<()> {
+    let (build_dir, policy) = (build_dir.to_path_buf(), policy.clone());
+    tokio::task::spawn_blocking(move || prune_runs(&build_dir, &keep, &policy))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// `YYYYmmdd-HHMMSS-<8 hex>`, see `new_run_id`.
+fn is_run_id(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('-').collect();
+    matches!(parts.as_slice(), [date, time, suffix]
+        if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit())
+            && time.len() == 6 && time.bytes().all(|b| b.is_ascii_digit())
+            && suffix.len() == 8 && suffix.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() { dir_size(&entry.path())? } else { metadata.len() };
+    }
+    Ok(size)
+}
+
+/// Lists archives of `path` as (path, modified, size), newest first.
+fn list_archives(path: &Path) -> io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+    let dir = match path.parent() {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return Ok(Vec::new()),
+    };
+    let (stem, ext) = split_name(path);
+    let prefix = format!("{}.", stem);
+
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let rest = match name.strip_prefix(&prefix) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let stamp = rest.strip_suffix(".gz").unwrap_or(rest);
+        let stamp = match stamp.strip_suffix(&ext) {
+            Some(stamp) => stamp,
+            None => continue,
+        };
+        // Only our own `<stem>.<YYYYmmdd-HHMMSS.mmm><ext>` names count as archives
+        if stamp.len() < 15 || !stamp.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        archives.push((entry.path(), modified, metadata.len()));
+    }
+    archives.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(archives)
+}
+
+fn prune_archives(path: &Path, policy: &LogRetention) -> io::Result<()> {
+    let max_age = policy
+        .max_age_days
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+    let now = SystemTime::now();
+
+    let mut total_bytes = 0u64;
+    for (index, (archive, modified, size)) in list_archives(path)?.into_iter().enumerate() {
+        let too_old = max_age
+            .map(|age| now.duration_since(modified).unwrap_or_default() > age)
+            .unwrap_or(false);
+        let too_many = index >= policy.max_files;
+        let too_big = policy
+            .max_total_bytes
+            .map(|limit| total_bytes + size > limit)
+            .unwrap_or(false);
+
+        if too_old || too_many || too_big {
+            fs::remove_file(&archive)?;
+        } else {
+            total_bytes += size;
+        }
+    }
+    Ok(())
+}
+
+/// Splits `build_log.txt` into ("build_log", ".txt").
+fn split_name(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (stem, ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_runs_keeps_the_newest_runs_and_other_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let runs = ["20260101-080000-0a1b2c3d", "20260102-080000-0a1b2c3d", "20260103-080000-0a1b2c3d", "20260104-080000-0a1b2c3d"];
+        for run in runs.iter().chain(&["firmware", "20260101-backup"]) {
+            fs::create_dir(dir.path().join(run)).unwrap();
+            fs::write(dir.path().join(run).join("build_log.txt"), "log").unwrap();
+        }
+        let policy = LogRetention { max_files: 1, max_age_days: None, ..Default::default() };
+        // The newest is the current run
+        prune_runs(dir.path(), &[runs[3].to_string()], &policy).unwrap();
+
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["20260101-backup", runs[2], runs[3], "firmware"]);
+    }
+}
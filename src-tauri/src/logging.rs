@@ -1,28 +1,141 @@
 use tauri::{Window, Emitter};
+use crate::models::{LogEntry, StageRecord, StageStatus, StageTiming};
 use crate::utils::LogLevel;
 use chrono::Local;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Instant;
+use stm32_builder_core::events::{scoped_channel, EventSink};
+
+/// Emits `event` to `window` only, so each window follows just the builds
+/// it started.
+pub fn emit_to_window<S: Serialize + Clone>(window: &Window, event: &str, payload: S) {
+    window.emit_to(window.label(), event, payload).ok();
+}
+
+/// Forwards build events to the frontend of a window.
+pub struct WindowEvents<'a>(pub &'a Window);
+
+impl EventSink for WindowEvents<'_> {
+    fn emit_value(&self, event: &str, payload: Value) {
+        emit_to_window(self.0, event, payload);
+    }
+}
 
 pub struct Logger<'a> {
-    window: &'a Window,
-    logs: Vec<String>,
+    events: Box<dyn EventSink + 'a>,
+    logs: Vec<LogEntry>,
+    run_id: Option<String>,
+    combination: Option<String>,
+    stage: Option<String>,
+    // combination and start time of the stage currently being timed
+    stage_combination: Option<String>,
+    stage_started: Option<Instant>,
+    stage_timings: Vec<StageTiming>,
+    steps: Vec<StageRecord>,
 }
 
 impl<'a> Logger<'a> {
     pub fn new(window: &'a Window) -> Self {
+        Self::with_events(Box::new(WindowEvents(window)))
+    }
+
+    /// Logger reporting to something other than a window.
+    pub fn with_events(events: Box<dyn EventSink + 'a>) -> Self {
         Logger {
-            window,
+            events,
             logs: Vec::new(),
+            run_id: None,
+            combination: None,
+            stage: None,
+            stage_combination: None,
+            stage_started: None,
+            stage_timings: Vec::new(),
+            steps: Vec::new(),
         }
     }
 
+    /// Stamps all subsequent entries with the id of the current run.
+    pub fn set_run_id(&mut self, run_id: &str) {
+        self.run_id = Some(run_id.to_string());
+    }
+
+    /// Sets the combination that subsequent entries are attributed to.
+    pub fn set_combination(&mut self, combination: Option<String>) {
+        self.combination = combination;
+    }
+
+    /// Sets the pipeline stage that subsequent entries are attributed to.
+    /// The previous stage is closed and its duration recorded.
+    pub fn set_stage(&mut self, stage: &str) {
+        self.finish_stage();
+        self.stage = Some(stage.to_string());
+        self.stage_combination = self.combination.clone();
+        self.stage_started = Some(Instant::now());
+    }
+
+    /// Records the duration of the current stage, if it is still running.
+    pub fn finish_stage(&mut self) {
+        if let (Some(stage), Some(started)) = (&self.stage, self.stage_started.take()) {
+            self.stage_timings.push(StageTiming {
+                stage: stage.clone(),
+                combination: self.stage_combination.clone(),
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+    }
+
+    pub fn get_stage_timings(&self) -> &Vec<StageTiming> {
+        &self.stage_timings
+    }
+
+    /// Starts a new step of the run; the running one, if any, is completed.
+    pub fn begin_step(&mut self, id: &str, label: &str) {
+        self.end_step(StageStatus::Completed);
+        let record = StageRecord {
+            run_id: self.run_id.clone(),
+            id: id.to_string(),
+            label: label.to_string(),
+            combination: self.combination.clone(),
+            status: StageStatus::Running,
+            started_at: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            finished_at: None,
+        };
+        self.events.emit("build-stage", &record);
+        self.steps.push(record);
+    }
+
+    /// Finishes the running step with `status`.
+    pub fn end_step(&mut self, status: StageStatus) {
+        if let Some(record) = self.steps.last_mut().filter(|r| r.status == StageStatus::Running) {
+            record.status = status;
+            record.finished_at = Some(Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+            self.events.emit("build-stage", &*record);
+        }
+    }
+
+    pub fn get_steps(&self) -> &Vec<StageRecord> {
+        &self.steps
+    }
+
     pub fn log(&mut self, message: &str, level: LogLevel) -> String {
-        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-        // Формируем строку лога только здесь, не допускаем вложенных [DEBUG] и т.п. в message
-        let log_message = format!("[{}] [{:?}] {}", timestamp, level, message);
+        // Не допускаем вложенных [DEBUG] и т.п. в message: уровень и время хранятся отдельными полями
+        let entry = LogEntry {
+            run_id: self.run_id.clone(),
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            level,
+            message: message.to_string(),
+            combination: self.combination.clone(),
+            stage: self.stage.clone(),
+        };
 
-        self.logs.push(log_message.clone());
-        self.window.emit("build-log", &log_message).ok();
-        log_message
+        self.events.emit("build-log", &entry);
+        if let Some(combination) = &self.combination {
+            let channel = scoped_channel("build-log", self.run_id.as_deref(), combination);
+            self.events.emit(&channel, &entry);
+        }
+        self.logs.push(entry);
+        message.to_string()
     }
 
     pub fn info(&mut self, message: &str) -> String {
@@ -41,7 +154,7 @@ impl<'a> Logger<'a> {
         self.log(message, LogLevel::Warning)
     }
 
-    pub fn get_logs(&self) -> &Vec<String> {
+    pub fn get_logs(&self) -> &Vec<LogEntry> {
         &self.logs
     }
 }
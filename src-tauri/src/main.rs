@@ -6,25 +6,141 @@
 mod builder;
 mod cancel;
 mod config;
-mod defaults;
 mod models;
 mod process;
 mod utils;
-mod build_combinations;
-mod build_config_gen;
 mod logging;
+mod i18n;
+mod failure_hints;
+mod log_storage;
+mod log_index;
+mod manifest;
+mod support_bundle;
+mod resources;
+mod preflight;
+mod history;
+mod elf_size;
+mod recent;
+mod project_config;
+mod report;
+mod selection;
+mod provenance;
+mod flashing;
+mod flash_verify;
+mod option_bytes;
+mod probes;
+mod flash_queue;
+mod serial_monitor;
+mod trace;
+mod smoke_test;
+mod target_check;
+mod personalize;
+mod production;
+mod git_info;
+mod versioning;
+mod reproducible;
+mod release_bundle;
+mod ci_workflow;
+mod analysis;
+mod cppcheck;
+mod compile_db;
+mod clang_tidy;
+mod analysis_hook;
+mod symbol_gate;
+mod elf_check;
+mod simulation;
+mod renode;
+mod qemu;
+mod unit_tests;
+mod size_gate;
+mod map_file;
+mod api_server;
+mod webhook;
+mod notifications;
+mod plugins;
+mod scripting;
+mod runner;
+mod error;
+mod watch;
+mod mcu_info;
+mod workspace;
+mod middleware;
+mod source_stats;
+mod bin_footer;
+mod signing;
+mod warm_build;
+#[cfg(windows)]
+mod job_object;
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(crate::process::BuildManager::default())
+        .manage(crate::watch::WatchManager::default())
+        .manage(crate::api_server::ApiServer::default())
         .invoke_handler(tauri::generate_handler![
             crate::builder::build_project,
             crate::config::load_build_settings_schema, // Fixed: changed from builder to config
             crate::cancel::cancel_build,
+            crate::process::set_max_concurrent_builds,
+            crate::process::set_build_priority,
+            crate::watch::start_watch,
+            crate::watch::stop_watch,
             crate::utils::validate_path,
             crate::utils::get_project_configurations,
             crate::utils::get_project_name_from_path,
+            crate::utils::find_projects,
+            crate::utils::get_linked_resources,
+            crate::utils::get_project_toolchain,
+            crate::utils::get_configuration_details,
+            crate::utils::suggest_header_dir,
+            crate::workspace::validate_workspace,
+            crate::workspace::list_workspace_projects,
+            crate::bin_footer::read_bin_footer,
+            crate::middleware::detect_middlewares,
+            crate::source_stats::get_source_stats,
+            crate::mcu_info::get_project_mcu_info,
             crate::config::check_project_settings,
+            crate::log_index::search_logs,
+            crate::log_index::get_log_tail,
+            crate::support_bundle::export_diagnostics,
+            crate::preflight::preflight_check,
+            crate::history::list_runs,
+            crate::history::get_run,
+            crate::history::delete_run,
+            crate::history::get_size_trend,
+            crate::history::add_run_tag,
+            crate::history::remove_run_tag,
+            crate::history::set_run_note,
+            crate::recent::get_recent_entries,
+            crate::recent::add_recent_entry,
+            crate::project_config::save_project_config,
+            crate::project_config::load_project_config,
+            crate::project_config::get_last_build_config,
+            crate::report::export_run_report,
+            crate::selection::import_combination_selection,
+            crate::flashing::flash_firmware,
+            crate::probes::list_probes,
+            crate::flash_queue::flash_batch,
+            crate::serial_monitor::list_serial_ports,
+            crate::serial_monitor::open_serial_port,
+            crate::serial_monitor::close_serial_port,
+            crate::trace::start_trace,
+            crate::trace::stop_trace,
+            crate::target_check::detect_target,
+            crate::production::start_production,
+            crate::production::stop_production,
+            crate::versioning::get_firmware_version,
+            crate::versioning::set_firmware_version,
+            crate::release_bundle::create_release_bundle,
+            crate::ci_workflow::export_ci_workflow,
+            crate::reproducible::verify_reproducibility,
+            crate::api_server::start_api_server,
+            crate::api_server::stop_api_server,
+            crate::plugins::list_plugins,
+            crate::i18n::set_language,
+            crate::i18n::get_language,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,113 @@
+use crate::models::{BuildConfig, BuildResult, CombinationResult, StageTiming};
+use crate::git_info::GitInfo;
+use crate::i18n::tr;
+use crate::provenance::Provenance;
+use crate::reproducible::ReproducibilityReport;
+use crate::smoke_test::SmokeTestResult;
+use crate::unit_tests::UnitTestReport;
+use crate::versioning::FirmwareVersion;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Manager, Window};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const RUNS_DIR: &str = "runs";
+
+/// Summary of one `build_project` run, written to the output directory so
+/// artifacts can be traced back to the run (and its logs) that produced them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub run_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub project_path: String,
+    pub output_dir: String,
+    pub config_name: Option<String>,
+    pub success: bool,
+    pub cancelled: bool,
+    pub total_errors: usize,
+    pub total_warnings: usize,
+    pub combinations: Vec<CombinationResult>,
+    pub stage_timings: Vec<StageTiming>,
+    /// Build environment the artifacts were produced in.
+    pub provenance: Option<Provenance>,
+    /// Commit the project was at, if it is a git repository.
+    #[serde(default)]
+    pub git: Option<GitInfo>,
+    /// Version the artifacts were built as, if versioning is enabled.
+    #[serde(default)]
+    pub version: Option<FirmwareVersion>,
+    /// Set for runs built in reproducible mode.
+    #[serde(default)]
+    pub reproducibility: Option<ReproducibilityReport>,
+    /// On-target smoke tests run after flashing this run's artifacts.
+    #[serde(default)]
+    pub smoke_tests: Vec<SmokeTestResult>,
+    #[serde(default)]
+    pub unit_tests: Option<UnitTestReport>,
+}
+
+impl RunManifest {
+    pub fn new(config: &BuildConfig, result: &BuildResult, output_dir: &Path, started_at: String) -> Self {
+        RunManifest {
+            run_id: result.run_id.clone(),
+            started_at,
+            finished_at: Local::now().to_rfc3339(),
+            project_path: config.project_path.clone(),
+            output_dir: output_dir.display().to_string(),
+            config_name: config.config_name.clone(),
+            success: result.success,
+            cancelled: result.cancelled,
+            total_errors: result.total_errors,
+            total_warnings: result.total_warnings,
+            combinations: result.combinations.clone(),
+            stage_timings: result.stage_timings.clone(),
+            provenance: None,
+            git: None,
+            version: None,
+            reproducibility: None,
+            smoke_tests: Vec::new(),
+            unit_tests: result.unit_tests.clone(),
+        }
+    }
+
+    /// Writes `manifest.json` into the output directory.
+    pub fn write(&self, output_dir: &Path) -> Result<(), String> {
+        self.write_to(&output_dir.join(MANIFEST_FILE))
+    }
+
+    /// Keeps a copy under the app data directory, since the output
+    /// directory's manifest is replaced by the next run.
+    pub fn archive(&self, window: &Window) -> Result<(), String> {
+        self.write_to(&archived_path(window, &self.run_id)?)
+    }
+
+    /// Loads the archived manifest of `run_id`.
+    pub fn load_archived(window: &Window, run_id: &str) -> Result<Self, String> {
+        let path = archived_path(window, run_id)?;
+        let json = fs::read_to_string(&path)
+            .map_err(|e| tr!("manifest.missing", run_id, e))?;
+        serde_json::from_str(&json).map_err(|e| tr!("manifest.invalid", run_id, e))
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| tr!("manifest.serialize_failed", e))?;
+        fs::write(path, json).map_err(|e| tr!("fs.write", path.display(), e))
+    }
+}
+
+/// Location of the archived manifest of `run_id`.
+pub fn archived_path(window: &Window, run_id: &str) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?
+        .join(RUNS_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(format!("{}.json", run_id)))
+}
@@ -0,0 +1,300 @@
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+// How many of the largest discarded sections are kept with the combination
+const TOP_DISCARDED: usize = 50;
+pub const UNUSED_CODE_FILE: &str = "unused_code.json";
+
+/// An input section the linker dropped because nothing referenced it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscardedSection {
+    /// Function or object name, from `-ffunction-sections`/`-fdata-sections`.
+    pub name: String,
+    pub size: u64,
+    /// Object file it came from.
+    pub object: String,
+}
+
+/// Code and data `--gc-sections` removed from a combination, largest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UnusedCodeReport {
+    pub functions: Vec<DiscardedSection>,
+    pub objects: Vec<DiscardedSection>,
+    pub function_bytes: u64,
+    pub object_bytes: u64,
+}
+
+impl UnusedCodeReport {
+    /// The same report cut down to the largest entries.
+    pub fn top(&self) -> UnusedCodeReport {
+        UnusedCodeReport {
+            functions: self.functions.iter().take(TOP_DISCARDED).cloned().collect(),
+            objects: self.objects.iter().take(TOP_DISCARDED).cloned().collect(),
+            ..*self
+        }
+    }
+}
+
+/// `.data` and `.bss` bytes contributed by one object file or library.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RamUsage {
+    pub module: String,
+    pub data: u64,
+    pub bss: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RamBreakdown {
+    pub modules: Vec<RamUsage>,
+    pub data: u64,
+    pub bss: u64,
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+/// `<address> <size> <object>` following a section name.
+fn parse_placement(fields: &[&str]) -> Option<(u64, String)> {
+    match fields {
+        [address, size, object @ ..] if parse_hex(address).is_some() && !object.is_empty() => {
+            Some((parse_hex(size)?, object.join(" ")))
+        }
+        _ => None,
+    }
+}
+
+/// An input section of a GNU ld map with the output section it is in.
+struct InputSection {
+    output: Option<String>,
+    name: String,
+    size: u64,
+    object: String,
+}
+
+/// Input sections of a part of a map. Long section names put the
+/// address, size and object on the next line.
+fn input_sections<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<InputSection> {
+    let mut sections = Vec::new();
+    let mut output: Option<String> = None;
+    let mut pending: Option<String> = None;
+    for line in lines {
+        // Output sections start in the first column, their input sections are indented
+        if !line.starts_with(char::is_whitespace) {
+            pending = None;
+            if line.starts_with('.') {
+                output = line.split_whitespace().next().map(str::to_string);
+            }
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let Some(name) = pending.take()
+            && let Some((size, object)) = parse_placement(&fields)
+        {
+            sections.push(InputSection { output: output.clone(), name, size, object });
+            continue;
+        }
+        match fields.split_first() {
+            Some((name, rest)) if name.starts_with('.') || *name == "COMMON" => match parse_placement(rest) {
+                Some((size, object)) => {
+                    sections.push(InputSection { output: output.clone(), name: name.to_string(), size, object })
+                }
+                None if rest.is_empty() => pending = Some(name.to_string()),
+                None => {}
+            },
+            _ => {}
+        }
+    }
+    sections
+}
+
+/// Input sections listed under "Discarded input sections".
+fn discarded_sections(map: &str) -> Vec<InputSection> {
+    input_sections(
+        map.lines()
+            .skip_while(|line| !line.starts_with("Discarded input sections"))
+            .skip(1)
+            .take_while(|line| !line.starts_with("Memory Configuration")),
+    )
+}
+
+/// Input sections placed by the linker script.
+fn placed_sections(map: &str) -> Vec<InputSection> {
+    input_sections(
+        map.lines()
+            .skip_while(|line| !line.starts_with("Linker script and memory map"))
+            .skip(1),
+    )
+}
+
+/// Library an archive member belongs to (`libc_nano.a(lib_a-memcpy.o)` ->
+/// `libc_nano.a`, without its directory), or the object file itself.
+fn module(object: &str) -> String {
+    match object.split_once('(') {
+        Some((archive, _)) => archive.rsplit(['/', '\\']).next().unwrap_or(archive).to_string(),
+        None => object.to_string(),
+    }
+}
+
+/// Splits discarded sections into functions and data objects, dropping
+/// empty ones (every object file has a few).
+pub fn unused_code(map_path: &Path) -> Result<UnusedCodeReport, String> {
+    let map = fs::read_to_string(map_path)
+        .map_err(|e| tr!("fs.read", map_path.display(), e))?;
+    let mut report = UnusedCodeReport::default();
+    for section in discarded_sections(&map) {
+        let size = section.size;
+        if size == 0 {
+            continue;
+        }
+        let entry = |name: &str| DiscardedSection { name: name.to_string(), size, object: section.object.clone() };
+        if let Some(name) = section.name.strip_prefix(".text.") {
+            report.function_bytes += size;
+            report.functions.push(entry(name));
+        } else if let Some(name) = [".rodata.", ".data.", ".bss."].iter().find_map(|p| section.name.strip_prefix(p)) {
+            report.object_bytes += size;
+            report.objects.push(entry(name));
+        }
+    }
+    report.functions.sort_by_key(|f| Reverse(f.size));
+    report.objects.sort_by_key(|o| Reverse(o.size));
+    Ok(report)
+}
+
+/// Statically allocated RAM of the combination per object file or library,
+/// largest first.
+pub fn ram_breakdown(map_path: &Path) -> Result<RamBreakdown, String> {
+    let map = fs::read_to_string(map_path)
+        .map_err(|e| tr!("fs.read", map_path.display(), e))?;
+    let mut breakdown = RamBreakdown::default();
+    for section in placed_sections(&map) {
+        let (data, bss) = match section.output.as_deref() {
+            Some(".data") => (section.size, 0),
+            Some(".bss") => (0, section.size),
+            _ => continue,
+        };
+        if data + bss == 0 {
+            continue;
+        }
+        breakdown.data += data;
+        breakdown.bss += bss;
+        let module = module(&section.object);
+        match breakdown.modules.iter_mut().find(|m| m.module == module) {
+            Some(usage) => {
+                usage.data += data;
+                usage.bss += bss;
+            }
+            None => breakdown.modules.push(RamUsage { module, data, bss }),
+        }
+    }
+    breakdown.modules.sort_by_key(|m| Reverse(m.data + m.bss));
+    Ok(breakdown)
+}
+
+pub fn write(path: &Path, report: &UnusedCodeReport) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| tr!("unused_code.serialize_failed", e))?;
+    fs::write(path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cut down from an arm-none-eabi-ld map, with a wrapped long name, a
+    // fill line and a stray line that is none of these
+    const MAP: &str = "\
+Archive member included to satisfy reference by file (symbol)
+
+/opt/gcc/lib/libc_nano.a(lib_a-memcpy.o)
+                              ./Core/Src/main.o (memcpy)
+
+Discarded input sections
+
+ .text          0x00000000        0x0 ./Core/Src/main.o
+ .text.unused_helper
+                0x00000000       0x24 ./Core/Src/main.o
+ .text.HAL_Delay
+                0x00000000       0x30 ./Drivers/HAL/stm32f4xx_hal.o
+ .rodata.lookup_table
+                0x00000000      0x100 ./Core/Src/tables.o
+ .bss.debug_buffer
+                0x00000000       0x40 ./Core/Src/main.o
+ .ARM.attributes
+                0x00000000       0x2c ./Core/Src/main.o
+ garbage line without fields
+ .text.truncated
+
+Memory Configuration
+
+Name             Origin             Length             Attributes
+FLASH            0x08000000         0x00080000         xr
+RAM              0x20000000         0x00020000         xrw
+
+Linker script and memory map
+
+.text           0x08000000      0x1a4
+ .text.main     0x08000000       0x40 ./Core/Src/main.o
+                0x08000000                main
+ .text.memcpy   0x08000040       0x14 /opt/gcc/lib/libc_nano.a(lib_a-memcpy.o)
+ *fill*         0x08000054        0x4 
+
+.data           0x20000000       0x10 load address 0x080001a4
+ .data.counter  0x20000000        0x4 ./Core/Src/main.o
+ .data.impure_data
+                0x20000004        0xc /opt/gcc/lib/libc_nano.a(lib_a-impure.o)
+
+.bss            0x20000010      0x220
+ .bss.rx_buffer
+                0x20000010      0x200 ./Core/Src/uart.o
+ .bss.state     0x20000210        0x8 ./Core/Src/main.o
+ COMMON         0x20000218       0x18 /opt/gcc/lib/libc_nano.a(lib_a-reent.o)
+ malformed      0x20000230
+";
+
+    fn write_map(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("blinky.map");
+        fs::write(&path, MAP).unwrap();
+        path
+    }
+
+    #[test]
+    fn unused_code_splits_discarded_sections_into_functions_and_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = unused_code(&write_map(dir.path())).unwrap();
+
+        let functions: Vec<(&str, u64)> = report.functions.iter().map(|f| (f.name.as_str(), f.size)).collect();
+        assert_eq!(functions, vec![("HAL_Delay", 0x30), ("unused_helper", 0x24)]);
+        assert_eq!(report.functions[1].object, "./Core/Src/main.o");
+        let objects: Vec<(&str, u64)> = report.objects.iter().map(|o| (o.name.as_str(), o.size)).collect();
+        assert_eq!(objects, vec![("lookup_table", 0x100), ("debug_buffer", 0x40)]);
+        assert_eq!(report.function_bytes, 0x54);
+        assert_eq!(report.object_bytes, 0x140);
+    }
+
+    #[test]
+    fn ram_breakdown_adds_up_data_and_bss_per_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let breakdown = ram_breakdown(&write_map(dir.path())).unwrap();
+
+        let modules: Vec<(&str, u64, u64)> = breakdown.modules.iter().map(|m| (m.module.as_str(), m.data, m.bss)).collect();
+        assert_eq!(
+            modules,
+            vec![("./Core/Src/uart.o", 0, 0x200), ("libc_nano.a", 0xc, 0x18), ("./Core/Src/main.o", 0x4, 0x8)]
+        );
+        assert_eq!((breakdown.data, breakdown.bss), (0x10, 0x220));
+    }
+
+    #[test]
+    fn unused_code_of_a_map_without_discarded_sections_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.map");
+        fs::write(&path, "Memory Configuration\n\nLinker script and memory map\n").unwrap();
+        let report = unused_code(&path).unwrap();
+        assert!(report.functions.is_empty() && report.objects.is_empty());
+        assert!(unused_code(&dir.path().join("missing.map")).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::i18n::tr;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::command;
+
+/// Flash size letters of STM32 part numbers, in KiB.
+const FLASH_SIZES: &[(char, u32)] = &[
+    ('4', 16),
+    ('6', 32),
+    ('8', 64),
+    ('B', 128),
+    ('Z', 192),
+    ('C', 256),
+    ('D', 384),
+    ('E', 512),
+    ('F', 768),
+    ('G', 1024),
+    ('H', 1536),
+    ('I', 2048),
+];
+
+/// Device and firmware package of a CubeMX project.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct McuInfo {
+    pub ioc_file: String,
+    /// Part number, e.g. `STM32F407VGT6`, or the device with `x` for the
+    /// temperature range when CubeMX does not know it (`STM32F407VGTx`).
+    pub device: Option<String>,
+    /// Device line, e.g. `STM32F4`.
+    pub family: Option<String>,
+    pub package: Option<String>,
+    /// Development board the project was generated for; `None` for custom boards.
+    pub board: Option<String>,
+    /// e.g. `STM32Cube FW_F4 V1.27.1`.
+    pub firmware_package: Option<String>,
+    /// Version of the firmware package, e.g. `1.27.1`.
+    pub hal_version: Option<String>,
+    pub flash_kb: Option<u32>,
+}
+
+/// CubeMX file of the project: the one named after the folder, or else the
+/// first one found.
+pub fn find_ioc(project_path: &Path) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(project_path)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e.eq_ignore_ascii_case("ioc")).unwrap_or(false))
+        .collect();
+    files.sort();
+    let folder = project_path.file_name();
+    files
+        .iter()
+        .find(|p| p.file_stem() == folder)
+        .or(files.first())
+        .cloned()
+}
+
+/// Flash size encoded in a part number: the letter after the pin count,
+/// i.e. the third-to-last character of `STM32F407VGTx`-style names.
+pub fn flash_size_kb(device: &str) -> Option<u32> {
+    let chars: Vec<char> = device.to_uppercase().chars().collect();
+    let code = chars.len().checked_sub(3).map(|i| chars[i])?;
+    FLASH_SIZES.iter().find(|(c, _)| *c == code).map(|(_, kb)| *kb)
+}
+
+/// Reads the `key=value` lines of an `.ioc` file.
+pub fn parse_ioc(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+pub fn mcu_info(ioc_file: &Path) -> Result<McuInfo, String> {
+    let content = fs::read_to_string(ioc_file).map_err(|e| tr!("fs.read", ioc_file.display(), e))?;
+    let entries = parse_ioc(&content);
+    let get = |key: &str| entries.get(key).filter(|v| !v.is_empty()).cloned();
+
+    // `Mcu.UserName` ends in `x`; flash size sits at the same place in both
+    let device_id = get("Mcu.UserName").or_else(|| get("ProjectManager.DeviceId"));
+    let firmware_package = get("ProjectManager.FirmwarePackage");
+    Ok(McuInfo {
+        ioc_file: ioc_file.display().to_string(),
+        flash_kb: device_id.as_deref().and_then(flash_size_kb),
+        device: get("Mcu.CPN").or(device_id),
+        family: get("Mcu.Family"),
+        package: get("Mcu.Package"),
+        board: get("board").filter(|b| !b.eq_ignore_ascii_case("custom")),
+        hal_version: firmware_package
+            .as_deref()
+            .and_then(|p| p.rsplit(' ').next())
+            .and_then(|v| v.strip_prefix('V'))
+            .map(str::to_string),
+        firmware_package,
+    })
+}
+
+/// MCU, package, board and firmware package of the project, from its
+/// CubeMX `.ioc` file.
+#[command]
+pub async fn get_project_mcu_info(project_path: String) -> Result<McuInfo, String> {
+    let project_path = Path::new(&project_path);
+    let ioc_file = find_ioc(project_path).ok_or_else(|| tr!("mcu.no_ioc", project_path.display()))?;
+    mcu_info(&ioc_file)
+}
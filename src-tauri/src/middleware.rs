@@ -0,0 +1,67 @@
+use crate::i18n::tr;
+use crate::mcu_info::{find_ioc, parse_ioc};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use stm32_builder_core::project::{read_description, resolve_project_path};
+use tauri::command;
+
+/// Middleware ids with their CubeMX IP names and the folders their sources
+/// are generated into.
+const MIDDLEWARES: &[(&str, &[&str], &[&str])] = &[
+    ("freertos", &["FREERTOS"], &["Middlewares/Third_Party/FreeRTOS"]),
+    ("lwip", &["LWIP"], &["Middlewares/Third_Party/LwIP"]),
+    ("usb_device", &["USB_DEVICE"], &["Middlewares/ST/STM32_USB_Device_Library"]),
+    ("usb_host", &["USB_HOST"], &["Middlewares/ST/STM32_USB_Host_Library"]),
+    ("fatfs", &["FATFS"], &["Middlewares/Third_Party/FatFs"]),
+    ("touchgfx", &["TOUCHGFX"], &["Middlewares/ST/touchgfx", "TouchGFX"]),
+];
+
+/// Middleware the project uses.
+#[derive(Clone, Debug, Serialize)]
+pub struct Middleware {
+    /// `freertos`, `lwip`, `usb_device`, `usb_host`, `fatfs` or `touchgfx`.
+    pub id: String,
+    /// Enabled in the CubeMX `.ioc`.
+    pub in_ioc: bool,
+    /// Folder holding its sources, relative to the project.
+    pub folder: Option<String>,
+}
+
+/// CubeMX IP names of the project (`Mcu.IP0=FREERTOS`, ...), upper case.
+/// X-CUBE packs such as TouchGFX are listed under their pack name.
+fn ioc_components(project_path: &Path) -> Vec<String> {
+    let Some(content) = find_ioc(project_path).and_then(|ioc| fs::read_to_string(ioc).ok()) else {
+        return Vec::new();
+    };
+    parse_ioc(&content)
+        .into_iter()
+        .filter(|(key, _)| key.starts_with("Mcu.IP") && key[6..].chars().all(|c| c.is_ascii_digit()))
+        .map(|(_, value)| value.to_uppercase())
+        .collect()
+}
+
+/// Middlewares found in the project's `.ioc` or its source folders.
+pub fn detect(project_path: &Path) -> Vec<Middleware> {
+    let components = ioc_components(project_path);
+    // CubeIDE projects often link the middlewares in from a shared folder
+    let linked_resources = read_description(project_path).map(|d| d.linked_resources).unwrap_or_default();
+    MIDDLEWARES
+        .iter()
+        .filter_map(|(id, ips, folders)| {
+            let in_ioc = components.iter().any(|c| ips.iter().any(|ip| c == ip || c.contains(&format!("X-CUBE-{}", ip))));
+            let folder = folders.iter().find(|f| resolve_project_path(project_path, &linked_resources, Path::new(f)).is_dir()).map(|f| f.to_string());
+            (in_ioc || folder.is_some()).then(|| Middleware { id: id.to_string(), in_ioc, folder })
+        })
+        .collect()
+}
+
+/// Middlewares (FreeRTOS, LwIP, USB, FatFS, TouchGFX) the project uses.
+#[command]
+pub async fn detect_middlewares(project_path: String) -> Result<Vec<Middleware>, String> {
+    let project_path = Path::new(&project_path);
+    if !project_path.is_dir() {
+        return Err(tr!("path.not_a_directory", project_path.display()));
+    }
+    Ok(detect(project_path))
+}
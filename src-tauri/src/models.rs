@@ -1,5 +1,30 @@
 
-use serde::{Serialize};
+use serde::{Deserialize, Serialize};
+use crate::cppcheck::CppcheckSettings;
+use crate::clang_tidy::ClangTidySettings;
+use crate::analysis_hook::AnalysisHook;
+use crate::symbol_gate::SymbolGate;
+use crate::elf_check::ElfChecks;
+use crate::renode::RenodeSettings;
+use crate::qemu::QemuSettings;
+use crate::size_gate::SizeGate;
+use crate::signing::SigningSettings;
+use crate::map_file::{RamBreakdown, UnusedCodeReport};
+use crate::unit_tests::{UnitTestReport, UnitTestSettings};
+use crate::simulation::SimulationResult;
+use stm32_builder_core::diagnostics::Diagnostic;
+pub use stm32_builder_core::pipeline::DefineMode;
+use crate::elf_size::SectionSizes;
+use crate::failure_hints::FailureHint;
+use crate::flashing::FlashSettings;
+use crate::git_info::TagSettings;
+use crate::log_storage::LogRetention;
+use crate::preflight::PreflightReport;
+use stm32_builder_core::selection::Selection;
+use crate::utils::LogLevel;
+use crate::webhook::Webhook;
+use crate::notifications::NotificationSettings;
+use crate::error::BuildError;
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct BuildConfig {
@@ -21,12 +46,215 @@ pub struct BuildConfig {
     pub custom_console_args: Option<String>,
     pub settings: serde_json::Map<String, serde_json::Value>,
     pub cancelled: Option<bool>,
+    #[serde(rename = "logRetention")]
+    pub log_retention: Option<LogRetention>,
+    /// A combination with more warnings than this is marked failed.
+    #[serde(rename = "maxWarnings")]
+    pub max_warnings: Option<usize>,
+    /// Build exactly these combinations (setting id -> value) instead of the
+    /// full matrix from `settings`.
+    #[serde(rename = "selectedCombinations")]
+    pub selected_combinations: Option<Vec<Selection>>,
+    /// Flashing backend of the project, saved with the project configuration.
+    pub flash: Option<FlashSettings>,
+    /// Emit `GIT_HASH`, `GIT_BRANCH` and `GIT_DIRTY` into `build_config.h`.
+    #[serde(rename = "embedGitInfo")]
+    pub embed_git_info: Option<bool>,
+    /// What to do when the project's git tree has uncommitted changes.
+    #[serde(rename = "dirtyTree")]
+    pub dirty_tree: Option<DirtyTreePolicy>,
+    /// Emit `FW_VERSION_*` defines, add the version to artifact names and
+    /// bump the project's build number on success.
+    pub versioning: Option<bool>,
+    /// Fix `__DATE__`/`__TIME__` and `SOURCE_DATE_EPOCH` to the commit time
+    /// and compare the artifacts with the previous run of the same commit.
+    pub reproducible: Option<bool>,
+    /// Build a temporary worktree of `HEAD` instead of the working copy.
+    #[serde(rename = "cleanExport")]
+    pub clean_export: Option<bool>,
+    /// Tag the built commit once every combination succeeded.
+    #[serde(rename = "gitTag")]
+    pub git_tag: Option<TagSettings>,
+    /// Run cppcheck over the sources of every built combination.
+    pub cppcheck: Option<CppcheckSettings>,
+    /// Run clang-tidy on the sources compiled for every combination.
+    #[serde(rename = "clangTidy")]
+    pub clang_tidy: Option<ClangTidySettings>,
+    /// External checkers run on every built combination, in order.
+    #[serde(rename = "analysisHooks")]
+    pub analysis_hooks: Option<Vec<AnalysisHook>>,
+    /// Library symbols (float printf, heap...) no combination may link in.
+    #[serde(rename = "forbiddenSymbols")]
+    pub forbidden_symbols: Option<SymbolGate>,
+    /// Check entry point and section placement of every combination's ELF.
+    #[serde(rename = "elfChecks")]
+    pub elf_checks: Option<ElfChecks>,
+    /// Boot every combination in Renode and wait for its UART to report in.
+    pub renode: Option<RenodeSettings>,
+    /// Run every combination briefly in QEMU and check it does not fault.
+    pub qemu: Option<QemuSettings>,
+    /// Unit tests run before or after the matrix.
+    #[serde(rename = "unitTests")]
+    pub unit_tests: Option<UnitTestSettings>,
+    /// Limit flash growth of each combination over the last successful run.
+    #[serde(rename = "sizeGate")]
+    pub size_gate: Option<SizeGate>,
+    /// List code and data `--gc-sections` discarded from each combination.
+    #[serde(rename = "unusedCodeReport")]
+    pub unused_code_report: Option<bool>,
+    /// Break each combination's `.data`/`.bss` down by object file and library.
+    #[serde(rename = "ramBreakdown")]
+    pub ram_breakdown: Option<bool>,
+    /// Warnings that fail a combination, on top of the schema's `error_warnings`.
+    #[serde(rename = "errorWarnings")]
+    pub error_warnings: Option<Vec<String>>,
+    /// URLs the outcome of every run is posted to.
+    pub webhooks: Option<Vec<Webhook>>,
+    /// Desktop notifications at the end of the run and for slow combinations.
+    pub notifications: Option<NotificationSettings>,
+    /// Rhai script with `before_run`, `before_combination`,
+    /// `after_combination` and `after_run` hooks, relative to the project.
+    pub script: Option<String>,
+    /// Go through the whole run without starting CubeIDE; its build leaves
+    /// an empty `.bin` where the real one would be.
+    #[serde(rename = "dryRun")]
+    pub dry_run: Option<bool>,
+    /// Kill a combination's CubeIDE build after this many seconds.
+    #[serde(rename = "timeoutSecs")]
+    pub timeout_secs: Option<u64>,
+    /// How the combination's macros reach the compiler.
+    #[serde(rename = "defineMode")]
+    pub define_mode: Option<DefineMode>,
+    /// Append the combination's values and the git hash to every `.bin`.
+    #[serde(rename = "binFooter")]
+    pub bin_footer: Option<bool>,
+    /// Sign every `.bin` and put the certificate chain next to it.
+    pub signing: Option<SigningSettings>,
+    /// Build the combinations after the first with CubeIDE's `make` on the
+    /// makefiles it generated, instead of starting CubeIDE for each.
+    #[serde(rename = "warmBuild")]
+    pub warm_build: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyTreePolicy {
+    #[default]
+    Allow,
+    Warn,
+    /// Do not start the run, e.g. for release matrices.
+    Refuse,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct BuildResult {
+    pub run_id: String,
     pub result: String,
-    pub logs: Vec<String>,
-    pub stages: Vec<String>,
+    pub logs: Vec<LogEntry>,
+    pub stages: Vec<StageRecord>,
+    pub success: bool,
+    /// Set when the run ended because the user cancelled it.
+    pub cancelled: bool,
+    pub combinations: Vec<CombinationResult>,
+    pub total_errors: usize,
+    pub total_warnings: usize,
+    /// First compiler/linker error of the failed combination, if any.
+    pub failure_reason: Option<String>,
+    /// Recognized Eclipse failure with a remediation hint.
+    pub failure_hint: Option<FailureHint>,
+    /// Why the run stopped, when it did not succeed.
+    pub error: Option<BuildError>,
+    /// Outcome of the system checks run before the first combination.
+    pub preflight: Option<PreflightReport>,
+    /// Outcome of the unit test stage, if it ran.
+    pub unit_tests: Option<UnitTestReport>,
+    /// Wall-clock time spent in each stage, in execution order.
+    pub stage_timings: Vec<StageTiming>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StageStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// One step of a run, emitted as the `build-stage` event payload whenever it
+/// starts or finishes.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageRecord {
+    pub run_id: Option<String>,
+    /// Stable identifier such as `preflight` or `generate_header`.
+    pub id: String,
+    pub label: String,
+    pub combination: Option<String>,
+    pub status: StageStatus,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// How long one stage took; `combination` is `None` for run-wide stages
+/// such as validation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub combination: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Outcome of a single build combination.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CombinationResult {
+    pub combination: String,
     pub success: bool,
-}
\ No newline at end of file
+    pub exit_code: Option<i32>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub warnings_exceeded: bool,
+    pub failure_reason: Option<String>,
+    pub failure_hint: Option<FailureHint>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Renamed `.bin` in the output directory, once it has been copied there.
+    pub artifact: Option<String>,
+    pub artifact_size: Option<u64>,
+    /// Section sizes of the linked ELF.
+    pub sizes: Option<SectionSizes>,
+    /// CubeIDE output of this combination.
+    pub log_file: Option<String>,
+    /// Boot test in a simulator, if one is configured.
+    #[serde(default)]
+    pub simulation: Option<SimulationResult>,
+    /// Largest discarded functions and objects; the full list is written
+    /// to `unused_code.json` in the combination's folder.
+    #[serde(default)]
+    pub unused_code: Option<UnusedCodeReport>,
+    /// Static RAM per object file and library, from the linker map.
+    #[serde(default)]
+    pub ram_breakdown: Option<RamBreakdown>,
+    /// Signature of the `.bin`, when signing is configured.
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+/// A single backend log record, emitted as the `build-log` event payload.
+#[derive(Clone, Debug, Serialize)]
+pub struct LogEntry {
+    pub run_id: Option<String>,
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub message: String,
+    pub combination: Option<String>,
+    pub stage: Option<String>,
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] [{:?}]", self.timestamp, self.level)?;
+        if let Some(combination) = &self.combination {
+            write!(f, " [{}]", combination)?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::i18n::tr;
+use crate::models::BuildResult;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Window;
+use tauri_plugin_notification::NotificationExt;
+use tokio::task::JoinHandle;
+
+/// Desktop notifications about a run, for matrices left building in the
+/// background.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Also notify when a run succeeds (default); failed and cancelled runs
+    /// always notify.
+    #[serde(rename = "onSuccess")]
+    pub on_success: Option<bool>,
+    /// Notify when one combination has been building for longer than this.
+    #[serde(rename = "combinationBudgetSecs")]
+    pub combination_budget_secs: Option<u64>,
+}
+
+pub fn show(window: &Window, title: &str, body: &str) -> Result<(), String> {
+    window
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| tr!("notify.show_failed", e))
+}
+
+/// Notifies that a combination ran over its time budget, unless it is
+/// dropped before the budget is spent.
+pub struct BudgetTimer(JoinHandle<Result<(), String>>);
+
+impl BudgetTimer {
+    pub fn start(window: &Window, combination: &str, budget: Duration) -> Self {
+        let window = window.clone();
+        let combination = combination.to_string();
+        BudgetTimer(tokio::spawn(async move {
+            tokio::time::sleep(budget).await;
+            show(&window, &tr!("notify.over_budget_title"), &tr!("notify.over_budget", combination, budget.as_secs()))
+        }))
+    }
+
+    /// Stops the timer; fails if the notification it already showed did.
+    pub async fn stop(mut self) -> Result<(), String> {
+        if !self.0.is_finished() {
+            return Ok(());
+        }
+        (&mut self.0).await.unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for BudgetTimer {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Notifies the end of a run.
+pub fn run_finished(window: &Window, settings: &NotificationSettings, result: &BuildResult) -> Result<(), String> {
+    if result.cancelled {
+        show(window, &tr!("notify.cancelled_title"), &tr!("notify.cancelled", result.run_id))
+    } else if !result.success {
+        let reason = result.failure_reason.as_deref().unwrap_or(&result.result);
+        show(window, &tr!("notify.failed_title"), &tr!("notify.failed", result.run_id, reason))
+    } else if settings.on_success.unwrap_or(true) {
+        show(window, &tr!("notify.succeeded_title"), &tr!("notify.succeeded", result.combinations.len(), result.run_id))
+    } else {
+        Ok(())
+    }
+}
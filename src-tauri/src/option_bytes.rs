@@ -0,0 +1,119 @@
+use crate::flashing::{cube_connect_args, FlashBackend, FlashRequest, FlashSettings};
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Read-out protection level. Level 2 is permanent, and going back from
+/// level 1 to 0 mass-erases the chip.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RdpLevel {
+    Level0,
+    Level1,
+    Level2,
+}
+
+impl RdpLevel {
+    fn value(self) -> &'static str {
+        match self {
+            RdpLevel::Level0 => "0xAA",
+            RdpLevel::Level1 => "0xBB",
+            RdpLevel::Level2 => "0xCC",
+        }
+    }
+}
+
+/// Option bytes to program after flashing. Names in `other` are passed to
+/// CubeProgrammer as-is and differ between families (see `-ob displ`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OptionBytes {
+    pub rdp: Option<RdpLevel>,
+    /// Brown-out reset threshold (`BOR_LEV`).
+    #[serde(rename = "borLevel")]
+    pub bor_level: Option<u8>,
+    /// Independent watchdog started by hardware (`false`) or software (`true`).
+    #[serde(rename = "iwdgSw")]
+    pub iwdg_sw: Option<bool>,
+    #[serde(rename = "wwdgSw")]
+    pub wwdg_sw: Option<bool>,
+    pub other: Option<BTreeMap<String, String>>,
+}
+
+impl OptionBytes {
+    /// Fields set in `overrides` replace those of `self`.
+    fn merged(&self, overrides: &OptionBytes) -> OptionBytes {
+        let mut other = self.other.clone().unwrap_or_default();
+        other.extend(overrides.other.clone().unwrap_or_default());
+        OptionBytes {
+            rdp: overrides.rdp.or(self.rdp),
+            bor_level: overrides.bor_level.or(self.bor_level),
+            iwdg_sw: overrides.iwdg_sw.or(self.iwdg_sw),
+            wwdg_sw: overrides.wwdg_sw.or(self.wwdg_sw),
+            other: Some(other).filter(|o| !o.is_empty()),
+        }
+    }
+
+    fn assignments(&self) -> Vec<String> {
+        let flag = |b: bool| if b { "1" } else { "0" };
+        let mut assignments = Vec::new();
+        if let Some(level) = self.bor_level {
+            assignments.push(format!("BOR_LEV={}", level));
+        }
+        if let Some(sw) = self.iwdg_sw {
+            assignments.push(format!("IWDG_SW={}", flag(sw)));
+        }
+        if let Some(sw) = self.wwdg_sw {
+            assignments.push(format!("WWDG_SW={}", flag(sw)));
+        }
+        for (name, value) in self.other.iter().flatten() {
+            assignments.push(format!("{}={}", name, value));
+        }
+        // Last, so the other bytes are still writable when protection kicks in
+        if let Some(rdp) = self.rdp {
+            assignments.push(format!("RDP={}", rdp.value()));
+        }
+        assignments
+    }
+}
+
+/// Option bytes for a combination: the project's, overridden by the
+/// combination's own entry.
+pub fn resolve(settings: &FlashSettings, combination: Option<&str>) -> Option<OptionBytes> {
+    let combination_bytes = combination.and_then(|c| {
+        settings
+            .combination_option_bytes
+            .as_ref()
+            .and_then(|map| map.get(c))
+    });
+    match (&settings.option_bytes, combination_bytes) {
+        (Some(project), Some(combination)) => Some(project.merged(combination)),
+        (Some(project), None) => Some(project.clone()),
+        (None, Some(combination)) => Some(combination.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Fails unless `confirmed` is the level `option_bytes` set, so confirming
+/// level 1 does not let the permanent level 2 through.
+pub fn check_rdp_confirmed(option_bytes: &OptionBytes, confirmed: Option<RdpLevel>) -> Result<(), String> {
+    match option_bytes.rdp {
+        Some(rdp) if confirmed != Some(rdp) => Err(match rdp {
+            RdpLevel::Level2 => tr!("flash.rdp_level2_unconfirmed"),
+            _ => tr!("flash.rdp_unconfirmed", rdp.value()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// CubeProgrammer arguments that program `option_bytes`. Fails unless the
+/// backend supports it and an RDP change has been confirmed.
+pub fn option_bytes_args(request: &FlashRequest, option_bytes: &OptionBytes) -> Result<Vec<String>, String> {
+    if request.settings.backend != FlashBackend::CubeProgrammer || request.uses_dfu_util() {
+        return Err(tr!("flash.option_bytes_backend"));
+    }
+    check_rdp_confirmed(option_bytes, request.confirm_rdp)?;
+    let mut args = cube_connect_args(request);
+    args.push("-ob".to_string());
+    args.extend(option_bytes.assignments());
+    Ok(args)
+}
@@ -0,0 +1,333 @@
+use crate::flashing::{cube_connect_args, openocd_setup_args, run_streaming, st_flash_serial_args, FlashBackend, FlashRequest};
+use crate::i18n::tr;
+use crate::target_check::declared_mcu;
+use chrono::Local;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Manager, Window};
+
+const PROVISIONING_FILE: &str = "provisioning.csv";
+const UID_LEN: usize = 12;
+
+lazy_static! {
+    // "0x1FFF7A10 : 00320024 31385106 36383331" (CubeProgrammer) or
+    // "0x1fff7a10: 00320024 31385106 36383331" (OpenOCD)
+    static ref MEMORY_WORDS: Regex =
+        Regex::new(r"(?i)^\s*0x[0-9a-f]+\s*:\s*((?:[0-9a-f]{8}\s*)+)$").unwrap();
+    // Serials handed out but not logged yet, so boards flashed in parallel
+    // do not get the same one
+    static ref RESERVED_SERIALS: Mutex<HashMap<PathBuf, u32>> = Mutex::new(HashMap::new());
+}
+
+/// Base address of the 96-bit unique ID per family.
+const UID_ADDRESSES: &[(&str, &str)] = &[
+    ("STM32F0", "0x1FFFF7AC"),
+    ("STM32F1", "0x1FFFF7E8"),
+    ("STM32F2", "0x1FFF7A10"),
+    ("STM32F3", "0x1FFFF7AC"),
+    ("STM32F4", "0x1FFF7A10"),
+    ("STM32F7", "0x1FF0F420"),
+    ("STM32G0", "0x1FFF7590"),
+    ("STM32G4", "0x1FFF7590"),
+    ("STM32H7", "0x1FF1E800"),
+    ("STM32L0", "0x1FF80050"),
+    ("STM32L1", "0x1FF80050"),
+    ("STM32L4", "0x1FFF7590"),
+    ("STM32WB", "0x1FFF7590"),
+    ("STM32WL", "0x1FFF7590"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchValue {
+    /// The 12 UID bytes as read from the chip.
+    Uid,
+    /// A little-endian counter continuing from the provisioning log.
+    Serial,
+    /// Random bytes, e.g. to fill a key slot.
+    Random,
+}
+
+/// Per-device patch of the image; stored with the project's flash settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PersonalizationConfig {
+    /// Hex bytes marking the field in the image, e.g. `DEADBEEFDEADBEEF`.
+    /// The value replaces them and is cut or zero-padded to their length.
+    pub placeholder: String,
+    pub value: PatchValue,
+    #[serde(rename = "serialStart")]
+    pub serial_start: Option<u32>,
+    /// Overrides the family's UID address.
+    #[serde(rename = "uidAddress")]
+    pub uid_address: Option<String>,
+    /// Provisioning log; defaults to `provisioning.csv` in the app data directory.
+    #[serde(rename = "logFile")]
+    pub log_file: Option<String>,
+}
+
+/// One programmed device, as appended to the provisioning log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProvisioningRecord {
+    pub timestamp: String,
+    pub uid: String,
+    pub combination: Option<String>,
+    pub probe: Option<String>,
+    pub source_file: String,
+    pub serial: Option<u32>,
+    pub value: String,
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .trim()
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(tr!("personalize.invalid_placeholder", text));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| tr!("personalize.invalid_placeholder", text)))
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Writes `value` over the image at `offset`.
+fn patch(image: &mut [u8], offset: usize, value: &[u8]) -> Result<(), String> {
+    let end = offset
+        .checked_add(value.len())
+        .filter(|end| *end <= image.len())
+        .ok_or_else(|| tr!("personalize.out_of_range", value.len(), offset, image.len()))?;
+    image[offset..end].copy_from_slice(value);
+    Ok(())
+}
+
+fn uid_address(config: &PersonalizationConfig, request: &FlashRequest) -> Result<String, String> {
+    if let Some(address) = &config.uid_address {
+        return Ok(address.clone());
+    }
+    let mcu = request
+        .project_path
+        .as_deref()
+        .and_then(|p| declared_mcu(Path::new(p)))
+        .unwrap_or_default()
+        .to_uppercase();
+    UID_ADDRESSES
+        .iter()
+        .find(|(family, _)| mcu.starts_with(family))
+        .map(|(_, address)| address.to_string())
+        .ok_or_else(|| tr!("personalize.uid_address_unknown"))
+}
+
+fn parse_words(output: &[String]) -> Vec<u8> {
+    output
+        .iter()
+        .filter_map(|line| MEMORY_WORDS.captures(line))
+        .flat_map(|caps| {
+            caps[1]
+                .split_whitespace()
+                .filter_map(|word| u32::from_str_radix(word, 16).ok())
+                .flat_map(|word| word.to_le_bytes())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+async fn read_uid(window: &Window, request: &FlashRequest, address: &str) -> Result<Vec<u8>, String> {
+    if request.uses_dfu_util() {
+        return Err(tr!("personalize.dfu_unsupported"));
+    }
+    let program = request.settings.program();
+    let size = UID_LEN.to_string();
+    let uid = match request.settings.backend {
+        FlashBackend::CubeProgrammer => {
+            let mut args = cube_connect_args(request);
+            args.extend(["-r32".to_string(), address.to_string(), size]);
+            let (_, output) = run_streaming(window, &program, &args, &request.file, request.probe.as_deref()).await?;
+            parse_words(&output)
+        }
+        FlashBackend::OpenOcd => {
+            let mut args = openocd_setup_args(request)?;
+            for cmd in ["init".to_string(), format!("mdw {} 3", address), "exit".to_string()] {
+                args.extend(["-c".to_string(), cmd]);
+            }
+            let (_, output) = run_streaming(window, &program, &args, &request.file, request.probe.as_deref()).await?;
+            parse_words(&output)
+        }
+        FlashBackend::StFlash => {
+            let target = std::env::temp_dir().join(format!("stm32guibuilder_uid_{}.bin", uuid::Uuid::new_v4().simple()));
+            let mut args = st_flash_serial_args(request);
+            args.extend(["read".to_string(), target.to_string_lossy().into_owned(), address.to_string(), size]);
+            run_streaming(window, &program, &args, &request.file, request.probe.as_deref()).await?;
+            let bytes = fs::read(&target).unwrap_or_default();
+            fs::remove_file(&target).ok();
+            bytes
+        }
+    };
+    if uid.len() < UID_LEN {
+        return Err(tr!("personalize.uid_read_failed"));
+    }
+    Ok(uid[..UID_LEN].to_vec())
+}
+
+fn log_path(window: &Window, config: &PersonalizationConfig) -> Result<PathBuf, String> {
+    match &config.log_file {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_data_file(window, PROVISIONING_FILE),
+    }
+}
+
+/// `name` inside the app data directory, which is created if needed.
+pub fn app_data_file(window: &Window, name: &str) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(name))
+}
+
+fn read_log(path: &Path) -> Vec<ProvisioningRecord> {
+    csv::Reader::from_path(path)
+        .map(|mut reader| reader.deserialize().filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+fn next_serial(config: &PersonalizationConfig, log_path: &Path) -> u32 {
+    let logged = read_log(log_path).iter().filter_map(|r| r.serial).max();
+    let mut reserved = RESERVED_SERIALS.lock().unwrap();
+    let next = match logged.max(reserved.get(log_path).copied()) {
+        Some(last) => (last + 1).max(config.serial_start.unwrap_or(1)),
+        None => config.serial_start.unwrap_or(1),
+    };
+    reserved.insert(log_path.to_path_buf(), next);
+    next
+}
+
+/// Reads the chip's UID and writes a temporary copy of the image with the
+/// placeholder replaced. The record is appended to the provisioning log
+/// only once the device has been programmed (see `record`).
+pub async fn personalize(
+    window: &Window,
+    request: &FlashRequest,
+    config: &PersonalizationConfig,
+) -> Result<(ProvisioningRecord, PathBuf), String> {
+    if request.extension() != "bin" {
+        return Err(tr!("personalize.bin_only"));
+    }
+    let placeholder = parse_hex(&config.placeholder)?;
+    let mut image = fs::read(&request.file).map_err(|e| tr!("fs.read", request.file, e))?;
+    let offset = image
+        .windows(placeholder.len())
+        .position(|w| w == placeholder.as_slice())
+        .ok_or_else(|| tr!("personalize.placeholder_missing", config.placeholder))?;
+
+    let uid = read_uid(window, request, &uid_address(config, request)?).await?;
+    let log_path = log_path(window, config)?;
+    let mut serial = None;
+    let mut value = match config.value {
+        PatchValue::Uid => uid.clone(),
+        PatchValue::Serial => {
+            let next = next_serial(config, &log_path);
+            serial = Some(next);
+            next.to_le_bytes().to_vec()
+        }
+        PatchValue::Random => {
+            let mut bytes = Vec::new();
+            while bytes.len() < placeholder.len() {
+                bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+            }
+            bytes
+        }
+    };
+    value.resize(placeholder.len(), 0);
+    patch(&mut image, offset, &value)?;
+
+    let image_file = std::env::temp_dir().join(format!("stm32guibuilder_{}_{}.bin", to_hex(&uid), uuid::Uuid::new_v4().simple()));
+    fs::write(&image_file, &image).map_err(|e| tr!("fs.write", image_file.display(), e))?;
+
+    let record = ProvisioningRecord {
+        timestamp: Local::now().to_rfc3339(),
+        uid: to_hex(&uid),
+        combination: request.combination.clone(),
+        probe: request.probe.clone(),
+        source_file: request.file.clone(),
+        serial,
+        value: to_hex(&value),
+    };
+    Ok((record, image_file))
+}
+
+/// Appends a programmed device to the provisioning log.
+pub fn record(window: &Window, config: &PersonalizationConfig, record: &ProvisioningRecord) -> Result<(), String> {
+    append_csv(&log_path(window, config)?, record)
+}
+
+/// Appends a row to a CSV file, writing the header if the file is new.
+pub fn append_csv<T: Serialize>(path: &Path, record: &T) -> Result<(), String> {
+    let is_new = !path.exists();
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| tr!("fs.open", path.display(), e))?;
+    let mut writer = csv::WriterBuilder::new().has_headers(is_new).from_writer(file);
+    writer
+        .serialize(record)
+        .and_then(|_| writer.flush().map_err(csv::Error::from))
+        .map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UID: [u8; UID_LEN] = [0x24, 0x00, 0x32, 0x00, 0x06, 0x51, 0x38, 0x31, 0x31, 0x33, 0x38, 0x36];
+
+    #[test]
+    fn patches_the_placeholder_bytes() {
+        let placeholder = parse_hex("DEADBEEF DEADBEEF DEADBEEF").unwrap();
+        let mut image = vec![0x11, 0x22, 0x33, 0x44];
+        image.extend_from_slice(&placeholder);
+        image.extend_from_slice(&[0x55, 0x66]);
+
+        let offset = image.windows(placeholder.len()).position(|w| w == placeholder.as_slice()).unwrap();
+        assert_eq!(offset, 4);
+        patch(&mut image, offset, &UID).unwrap();
+        assert_eq!(&image[..4], &[0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(&image[4..16], &UID);
+        assert_eq!(&image[16..], &[0x55, 0x66]);
+        assert_eq!(to_hex(&image[4..16]), "240032000651383131333836");
+    }
+
+    #[test]
+    fn uid_words_are_read_little_endian() {
+        let output = vec![
+            "Reading 32-bit memory content".to_string(),
+            "0x1FFF7A10 : 00320024 31385106 36383331".to_string(),
+        ];
+        assert_eq!(parse_words(&output), UID.to_vec());
+    }
+
+    #[test]
+    fn patch_past_the_image_is_an_error() {
+        let mut image = vec![0; 8];
+        assert!(patch(&mut image, 4, &UID).is_err());
+        assert!(patch(&mut image, usize::MAX, &[1]).is_err());
+        assert_eq!(image, vec![0; 8]);
+        patch(&mut image, 4, &UID[..4]).unwrap();
+        assert_eq!(image, vec![0, 0, 0, 0, 0x24, 0x00, 0x32, 0x00]);
+    }
+}
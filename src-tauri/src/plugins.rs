@@ -0,0 +1,153 @@
+use crate::analysis::{run_tool, CombinationBuild, GateAction};
+use crate::compile_db::{split_command, COMPILE_COMMANDS_FILE};
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::command;
+
+/// Folder the plugin manifests are read from, next to `build_settings.yaml`.
+pub const PLUGINS_DIR: &str = "plugins";
+
+/// When a plugin runs, relative to a combination's CubeIDE build.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginStage {
+    /// After `build_config.h` is written, before CubeIDE starts.
+    PreBuild,
+    /// After the artifacts are in the combination's folder and the
+    /// post-build checks passed.
+    PostBuild,
+}
+
+/// Custom build step, one YAML file in `plugins/`:
+///
+/// ```yaml
+/// name: sign
+/// command: python tools/sign.py {bin} {combo_dir}/signed.bin
+/// when: post_build
+/// outputs: ["{combo_dir}/signed.bin"]
+/// action: fail
+/// ```
+///
+/// `command` is split like a shell would and may use `{project}`,
+/// `{build_dir}`, `{combo_dir}`, `{header}`, `{compile_commands}`,
+/// `{combination}`, `{run_id}` and, after the build, `{elf}`, `{map}` and
+/// `{bin}`; an argument that is exactly `{defines}` expands to the
+/// combination's `-D`/`-U` flags. It runs in the project directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub description: Option<String>,
+    pub command: String,
+    pub when: PluginStage,
+    /// Files the step must produce (same placeholders as `command`,
+    /// relative to the project directory).
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Whether a failing step fails the combination.
+    #[serde(default)]
+    pub action: GateAction,
+    /// Manifest the plugin was loaded from.
+    #[serde(skip_deserializing, default)]
+    pub file: String,
+}
+
+/// Placeholder values that are not part of the combination's build.
+pub struct StepContext<'a> {
+    pub run_id: &'a str,
+    pub combination: &'a str,
+    /// Renamed `.bin` in the combination's folder; unset before the build.
+    pub bin: Option<&'a Path>,
+}
+
+/// Reads every `*.yaml`/`*.yml` manifest in `dir`, sorted by file name.
+/// Manifests that cannot be read are reported and skipped.
+pub fn load(dir: &Path) -> (Vec<PluginManifest>, Vec<String>) {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "yaml" || e == "yml").unwrap_or(false))
+            .collect(),
+        Err(_) => return (Vec::new(), Vec::new()),
+    };
+    files.sort();
+
+    let mut plugins = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let parsed = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_yaml::from_str::<PluginManifest>(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(mut plugin) => {
+                plugin.file = file.display().to_string();
+                plugins.push(plugin);
+            }
+            Err(e) => errors.push(tr!("plugin.invalid", file.display(), e)),
+        }
+    }
+    (plugins, errors)
+}
+
+fn expand(template: &str, build: &CombinationBuild<'_>, context: &StepContext<'_>) -> String {
+    let path = |p: Option<&Path>| p.map(|p| p.display().to_string()).unwrap_or_default();
+    template
+        .replace("{project}", &build.project_path.display().to_string())
+        .replace("{build_dir}", &build.build_dir.display().to_string())
+        .replace("{combo_dir}", &build.combo_dir.display().to_string())
+        .replace("{header}", &build.combo_dir.join("build_config.h").display().to_string())
+        .replace("{compile_commands}", &build.combo_dir.join(COMPILE_COMMANDS_FILE).display().to_string())
+        .replace("{combination}", context.combination)
+        .replace("{run_id}", context.run_id)
+        .replace("{elf}", &path(build.elf))
+        .replace("{map}", &path(build.map))
+        .replace("{bin}", &path(context.bin))
+}
+
+/// Runs a plugin for a combination and returns its output. Fails if the
+/// command exits non-zero or an expected output is missing.
+pub async fn run(
+    plugin: &PluginManifest,
+    build: &CombinationBuild<'_>,
+    context: &StepContext<'_>,
+) -> Result<Vec<String>, String> {
+    let mut words = split_command(&plugin.command).into_iter();
+    let program = words
+        .next()
+        .map(|p| expand(&p, build, context))
+        .ok_or_else(|| tr!("plugin.command_empty", plugin.name))?;
+    let mut args = Vec::new();
+    for word in words {
+        if word == "{defines}" {
+            args.extend(build.define_flags());
+        } else {
+            args.push(expand(&word, build, context));
+        }
+    }
+
+    let (exit_code, stdout, stderr) = run_tool(&program, &args, build.project_path).await?;
+    let output: Vec<String> = stdout.into_iter().chain(stderr).collect();
+    if exit_code != 0 {
+        let last = output.last().cloned().unwrap_or_default();
+        return Err(tr!("plugin.exit_code", plugin.name, exit_code, last));
+    }
+    for expected in &plugin.outputs {
+        let path = build.project_path.join(expand(expected, build, context));
+        if !path.exists() {
+            return Err(tr!("plugin.output_missing", plugin.name, path.display()));
+        }
+    }
+    Ok(output)
+}
+
+/// Plugins found in `plugins/`, for the settings page.
+#[command]
+pub async fn list_plugins() -> Result<Vec<PluginManifest>, String> {
+    let (plugins, errors) = load(Path::new(PLUGINS_DIR));
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(plugins),
+    }
+}
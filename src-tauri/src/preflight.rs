@@ -0,0 +1,180 @@
+use crate::i18n::tr;
+use crate::models::BuildConfig;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use stm32_builder_core::paths;
+use sysinfo::{Disks, System};
+use tauri::command;
+
+/// Below this a run is refused; a matrix easily writes several GB of objects.
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Below this a warning is reported.
+const LOW_FREE_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PreflightCheck {
+    /// Stable identifier, e.g. `disk_output` or `toolchain_gcc`.
+    pub id: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+/// Result of all checks; `passed` is false if any check failed.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PreflightReport {
+    pub passed: bool,
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    fn push(&mut self, id: &str, status: CheckStatus, message: String) {
+        self.checks.push(PreflightCheck { id: id.to_string(), status, message });
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Failed)
+    }
+}
+
+/// Checks the machine before a run: free disk space for the output and
+/// workspace, RAM against the JVM heap of CubeIDE, and the bundled toolchain.
+pub fn run_preflight(config: &BuildConfig) -> PreflightReport {
+    let mut report = PreflightReport::default();
+    let disks = Disks::new_with_refreshed_list();
+
+    for (id, dir) in [("disk_output", &config.build_dir), ("disk_workspace", &config.workspace_path)] {
+        match free_space(&disks, Path::new(dir)) {
+            Some(free) if free < MIN_FREE_BYTES => {
+                report.push(id, CheckStatus::Failed, tr!("preflight.disk_low", dir, format_bytes(free)))
+            }
+            Some(free) if free < LOW_FREE_BYTES => {
+                report.push(id, CheckStatus::Warning, tr!("preflight.disk_low", dir, format_bytes(free)))
+            }
+            Some(free) => report.push(id, CheckStatus::Ok, tr!("preflight.disk_ok", dir, format_bytes(free))),
+            None => report.push(id, CheckStatus::Warning, tr!("preflight.disk_unknown", dir)),
+        }
+    }
+
+    let ide_dir = Path::new(&config.cube_ide_exe_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut system = System::new();
+    system.refresh_memory();
+    let available = system.available_memory();
+    match jvm_max_heap(&ide_dir) {
+        Some(heap) if available < heap => report.push(
+            "memory",
+            CheckStatus::Warning,
+            tr!("preflight.memory_low", format_bytes(available), format_bytes(heap)),
+        ),
+        Some(heap) => report.push(
+            "memory",
+            CheckStatus::Ok,
+            tr!("preflight.memory_ok", format_bytes(available), format_bytes(heap)),
+        ),
+        None => report.push("memory", CheckStatus::Ok, tr!("preflight.memory_available", format_bytes(available))),
+    }
+
+    for (id, tool, plugin) in [
+        ("toolchain_gcc", "arm-none-eabi-gcc", "externaltools.gnu-tools-for-stm32"),
+        ("toolchain_make", "make", "externaltools.make"),
+    ] {
+        match find_tool(&ide_dir, plugin, tool) {
+            Some(path) => report.push(id, CheckStatus::Ok, tr!("preflight.tool_found", tool, path.display())),
+            None => report.push(id, CheckStatus::Failed, tr!("preflight.tool_missing", tool)),
+        }
+    }
+
+    report.passed = !report.checks.iter().any(|c| c.status == CheckStatus::Failed);
+    report
+}
+
+/// Runs the preflight checks without starting a build.
+#[command]
+pub async fn preflight_check(config: BuildConfig) -> Result<PreflightReport, String> {
+    Ok(run_preflight(&config))
+}
+
+/// Free space of the disk `path` lives on (longest matching mount point).
+fn free_space(disks: &Disks, path: &Path) -> Option<u64> {
+    // Mount points are `C:\` or `\\server\share\`, never verbatim paths
+    let path = normalize(&paths::canonicalize(path).ok()?.to_string_lossy());
+    disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(&normalize(&d.mount_point().to_string_lossy())))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+fn normalize(path: &str) -> String {
+    if cfg!(windows) {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Reads `-Xmx` from `stm32cubeide.ini` next to the executable.
+fn jvm_max_heap(ide_dir: &Path) -> Option<u64> {
+    let ini = fs::read_to_string(ide_dir.join("stm32cubeide.ini")).ok()?;
+    ini.lines()
+        .filter_map(|l| l.trim().strip_prefix("-Xmx"))
+        .next_back()
+        .and_then(parse_java_size)
+}
+
+/// Parses Java memory sizes such as `4096m`, `2G` or `1048576`.
+fn parse_java_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last()?.to_ascii_lowercase() {
+        'k' => (&value[..value.len() - 1], 1024),
+        'm' => (&value[..value.len() - 1], 1024 * 1024),
+        'g' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>().ok().map(|n| n * multiplier)
+}
+
+/// Looks for `tool` in the CubeIDE plugin that bundles it, then on `PATH`.
+pub fn find_tool(ide_dir: &Path, plugin: &str, tool: &str) -> Option<PathBuf> {
+    let exe = if cfg!(windows) { format!("{}.exe", tool) } else { tool.to_string() };
+
+    if let Ok(entries) = fs::read_dir(ide_dir.join("plugins")) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.file_name().to_string_lossy().contains(plugin) {
+                let candidate = entry.path().join("tools").join("bin").join(&exe);
+                if candidate.is_file() {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(&exe))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= GIB {
+        format!("{:.1} GiB", bytes as f64 / GIB)
+    } else {
+        format!("{:.0} MiB", bytes as f64 / MIB)
+    }
+}
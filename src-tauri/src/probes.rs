@@ -0,0 +1,166 @@
+use crate::flashing::{FlashBackend, FlashSettings};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::command;
+use tokio::process::Command;
+
+lazy_static! {
+    // "   ST-LINK SN  : 066DFF495351885087121234" / "  serial:     066DFF49..."
+    static ref FIELD: Regex = Regex::new(r"^\s*([^:]+?)\s*:\s*(.*?)\s*$").unwrap();
+    // "J-Link[0]: Connection: USB, Serial number: 801012345, ProductName: J-Link EDU"
+    static ref JLINK: Regex =
+        Regex::new(r"Serial number:\s*(\d+)(?:,\s*ProductName:\s*(.+?)\s*$)?").unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    StLink,
+    JLink,
+}
+
+/// An attached debug probe; `serial` is what the flash request passes as `probe`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProbeInfo {
+    pub kind: ProbeKind,
+    pub serial: String,
+    pub board_name: Option<String>,
+    pub firmware: Option<String>,
+}
+
+impl ProbeInfo {
+    fn new(kind: ProbeKind, serial: &str) -> Self {
+        ProbeInfo { kind, serial: serial.to_string(), board_name: None, firmware: None }
+    }
+}
+
+/// Runs a tool and returns its stdout; `None` if it is not installed.
+pub async fn capture(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command.args(args).kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command.output().await.ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses `STM32_Programmer_CLI -l st-link`.
+fn parse_cube_programmer(output: &str) -> Vec<ProbeInfo> {
+    let mut probes: Vec<ProbeInfo> = Vec::new();
+    for line in output.lines() {
+        let Some(caps) = FIELD.captures(line) else { continue };
+        let (key, value) = (&caps[1], caps[2].to_string());
+        match key {
+            "ST-LINK SN" => probes.push(ProbeInfo::new(ProbeKind::StLink, &value)),
+            "ST-LINK FW" => {
+                if let Some(probe) = probes.last_mut() {
+                    probe.firmware = Some(value);
+                }
+            }
+            "Board Name" => {
+                if let Some(probe) = probes.last_mut() {
+                    probe.board_name = Some(value).filter(|v| !v.is_empty());
+                }
+            }
+            _ => {}
+        }
+    }
+    probes
+}
+
+/// Parses `st-info --probe`, which reports the connected chip but no board.
+fn parse_st_info(output: &str) -> Vec<ProbeInfo> {
+    let mut probes: Vec<ProbeInfo> = Vec::new();
+    for line in output.lines() {
+        let Some(caps) = FIELD.captures(line) else { continue };
+        let (key, value) = (&caps[1], caps[2].to_string());
+        match key {
+            "serial" => probes.push(ProbeInfo::new(ProbeKind::StLink, &value)),
+            "version" => {
+                if let Some(probe) = probes.last_mut() {
+                    probe.firmware = Some(value);
+                }
+            }
+            _ => {}
+        }
+    }
+    probes
+}
+
+fn parse_jlink(output: &str) -> Vec<ProbeInfo> {
+    output
+        .lines()
+        .filter_map(|line| JLINK.captures(line))
+        .map(|caps| {
+            let mut probe = ProbeInfo::new(ProbeKind::JLink, &caps[1]);
+            probe.board_name = caps.get(2).map(|m| m.as_str().to_string());
+            probe
+        })
+        .collect()
+}
+
+/// `st-info` ships next to `st-flash`.
+pub fn st_info_path(settings: &FlashSettings) -> String {
+    if settings.backend != FlashBackend::StFlash || settings.programmer_path.trim().is_empty() {
+        return "st-info".to_string();
+    }
+    let st_flash = Path::new(&settings.programmer_path);
+    let name = match st_flash.extension() {
+        Some(ext) => format!("st-info.{}", ext.to_string_lossy()),
+        None => "st-info".to_string(),
+    };
+    st_flash.with_file_name(name).to_string_lossy().into_owned()
+}
+
+async fn list_jlink() -> Vec<ProbeInfo> {
+    let program = if cfg!(windows) { "JLink.exe" } else { "JLinkExe" };
+    // J-Link Commander only takes commands from a script file
+    let script = std::env::temp_dir().join("stm32guibuilder_probes.jlink");
+    if fs::write(&script, "ShowEmuList\nexit\n").is_err() {
+        return Vec::new();
+    }
+    let script = script.to_string_lossy().into_owned();
+    capture(program, &["-CommandFile", &script])
+        .await
+        .map(|output| parse_jlink(&output))
+        .unwrap_or_default()
+}
+
+/// Lists attached ST-LINK and J-Link probes using whichever tools are
+/// installed. With `settings`, the project's tool paths are used.
+#[command]
+pub async fn list_probes(settings: Option<FlashSettings>) -> Result<Vec<ProbeInfo>, String> {
+    let settings = settings.unwrap_or_default();
+    let cube_programmer = match settings.backend {
+        FlashBackend::CubeProgrammer => settings.program(),
+        _ => FlashSettings::default().program(),
+    };
+
+    let mut probes = Vec::new();
+    if let Some(output) = capture(&cube_programmer, &["-l", "st-link"]).await {
+        probes.extend(parse_cube_programmer(&output));
+    }
+    if let Some(output) = capture(&st_info_path(&settings), &["--probe"]).await {
+        probes.extend(parse_st_info(&output));
+    }
+    probes.extend(list_jlink().await);
+
+    // The same ST-LINK is usually reported by more than one tool
+    let mut unique: Vec<ProbeInfo> = Vec::new();
+    for probe in probes {
+        match unique.iter_mut().find(|p| p.kind == probe.kind && p.serial == probe.serial) {
+            Some(existing) => {
+                if existing.board_name.is_none() {
+                    existing.board_name = probe.board_name;
+                }
+            }
+            None => unique.push(probe),
+        }
+    }
+    Ok(unique)
+}
@@ -1,275 +1,285 @@
-use crate::models::BuildConfig;
-use crate::utils::{LogLevel};
-use crate::logging::Logger;
-use sysinfo::{Pid, System, ProcessesToUpdate};
-use tauri::{command, Window, Emitter};
-use tokio::sync::{Mutex, Notify};
-use tokio::time::{self, Duration};
-use std::process::Command;
-use tokio::process::Child;
-use lazy_static::lazy_static;
-use winapi::um::wincon::GenerateConsoleCtrlEvent;
-use std::sync::Arc;
+use crate::i18n::tr;
+use crate::runner::ProcessPriority;
+use tauri::{command, Window, Manager};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
-
-#[cfg(unix)]
-use std::os::unix::process::CommandExt;
+#[cfg(windows)]
+use std::process::Command;
 
 #[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-// Define BUILD_CONFIG as a static Mutex
-lazy_static! {
-    pub static ref BUILD_CONFIG: Mutex<Option<BuildConfig>> = Mutex::new(None);
-    pub static ref BUILD_CHILD: Mutex<Option<Child>> = Mutex::new(None); // Новый глобальный процесс
-    pub static ref BUILD_CANCEL_NOTIFY: Arc<Notify> = Arc::new(Notify::new()); // Add this line
+/// CubeIDE process of a run's combination.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChildKey {
+    pub run_id: String,
+    pub combination: String,
 }
 
-#[command]
-pub async fn kill_process_and_children(
-    pid: u32,
-    window: Window,
-) -> Result<(), String> {
-    let mut logger = Logger::new(&window);
-    let mut system = System::new_all();
-    system.refresh_all();
+struct RunSession {
+    /// Label of the window that started the run.
+    window: String,
+    token: CancellationToken,
+}
 
-    if system.process(Pid::from(pid as usize)).is_none() {
-        let msg = logger.error(&format!("Process with PID {} not found", pid));
-        return Err(msg);
-    }
+// Runs allowed at once and runs holding a slot
+struct Slots {
+    limit: usize,
+    active: usize,
+}
 
-    logger.info(&format!("Attempting soft termination for PID {}", pid));
+/// Builds in progress, registered with `app.manage()`: the window and
+/// cancellation token of every run, the pid of every CubeIDE process a
+/// run waits on, the projects and workspaces being built, how many runs
+/// may build at once and at what priority.
+pub struct BuildManager {
+    runs: Mutex<HashMap<String, RunSession>>,
+    children: Mutex<HashMap<ChildKey, u32>>,
+    busy_paths: Mutex<HashSet<PathBuf>>,
+    paths_changed: Notify,
+    slots: Mutex<Slots>,
+    slot_changed: Notify,
+    priority: Mutex<ProcessPriority>,
+}
 
-    #[cfg(windows)]
-    {
-        let taskkill_soft = Command::new("taskkill")
-            .args(&["/PID", &pid.to_string()])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+impl Default for BuildManager {
+    fn default() -> Self {
+        BuildManager {
+            runs: Mutex::default(),
+            children: Mutex::default(),
+            busy_paths: Mutex::default(),
+            paths_changed: Notify::new(),
+            // Parallel CubeIDE builds only pay off with one workspace per run
+            slots: Mutex::new(Slots { limit: 1, active: 0 }),
+            slot_changed: Notify::new(),
+            priority: Mutex::default(),
+        }
+    }
+}
 
-        match taskkill_soft {
-            Ok(output) if output.status.success() => {
-                logger.info(&format!("Soft termination successful for PID {}", pid));
-            }
-            Ok(output) => {
-                logger.error(&format!(
-                    "Soft termination failed for PID {}: {}",
-                    pid,
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-            Err(e) => {
-                logger.error(&format!("Error during soft termination for PID {}: {}", pid, e));
+impl BuildManager {
+    /// Waits until no other run builds in any of `paths` (a project and
+    /// its workspace): two runs would overwrite each other's
+    /// `build_config.h` and output folder. The paths are held until the
+    /// returned guard is dropped; take them before the slot, so a run
+    /// waiting for its project does not keep others from building.
+    pub async fn acquire_paths(&self, paths: Vec<PathBuf>) -> PathsLock<'_> {
+        loop {
+            let changed = self.paths_changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            {
+                let mut busy = self.busy_paths.lock().unwrap();
+                if !paths.iter().any(|p| busy.contains(p)) {
+                    busy.extend(paths.iter().cloned());
+                    return PathsLock { manager: self, paths };
+                }
             }
+            changed.await;
         }
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        let kill_soft = Command::new("kill")
-            .arg("-15") // SIGTERM
-            .arg(pid.to_string())
-            .output();
+    pub fn paths_busy(&self, paths: &[PathBuf]) -> bool {
+        let busy = self.busy_paths.lock().unwrap();
+        paths.iter().any(|p| busy.contains(p))
+    }
 
-        match kill_soft {
-            Ok(output) if output.status.success() => {
-                logger.info(&format!("Soft termination successful for PID {}", pid));
-            }
-            Ok(output) => {
-                logger.error(&format!(
-                    "Soft termination failed for PID {}: {}",
-                    pid,
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-            Err(e) => {
-                logger.error(&format!("Error during soft termination for PID {}: {}", pid, e));
+    /// Waits until fewer runs than the limit are building; the slot is
+    /// held until the returned guard is dropped.
+    pub async fn acquire_slot(&self) -> RunSlot<'_> {
+        loop {
+            let changed = self.slot_changed.notified();
+            tokio::pin!(changed);
+            // Registered before the check so a slot freed in between is not missed
+            changed.as_mut().enable();
+            {
+                let mut slots = self.slots.lock().unwrap();
+                if slots.active < slots.limit {
+                    slots.active += 1;
+                    return RunSlot { manager: self };
+                }
             }
+            changed.await;
         }
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        let kill_soft = Command::new("kill")
-            .arg("-15") // SIGTERM
-            .arg(pid.to_string())
-            .output();
+    pub fn has_free_slot(&self) -> bool {
+        let slots = self.slots.lock().unwrap();
+        slots.active < slots.limit
+    }
 
-        match kill_soft {
-            Ok(output) if output.status.success() => {
-                logger.info(&format!("Soft termination successful for PID {}", pid));
-            }
-            Ok(output) => {
-                logger.error(&format!(
-                    "Soft termination failed for PID {}: {}",
-                    pid,
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-            Err(e) => {
-                logger.error(&format!("Error during soft termination for PID {}: {}", pid, e));
-            }
-        }
+    /// Changes how many runs may build at once. Runs already building are
+    /// left alone; queued runs start as soon as the new limit allows.
+    pub fn set_limit(&self, limit: usize) {
+        self.slots.lock().unwrap().limit = limit;
+        self.slot_changed.notify_waiters();
     }
 
-    // Wait for process termination (10 seconds timeout)
-    time::sleep(Duration::from_secs(10)).await;
+    /// Priority and CPUs of the processes runs start from now on.
+    pub fn priority(&self) -> ProcessPriority {
+        self.priority.lock().unwrap().clone()
+    }
 
-    // Check if process has terminated
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    if system.process(Pid::from(pid as usize)).is_some() {
-        logger.info(&format!("Process PID {} still running, attempting force kill", pid));
+    /// Token of a run that `window` is starting.
+    pub fn start_run(&self, run_id: &str, window: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        let session = RunSession { window: window.to_string(), token: token.clone() };
+        self.runs.lock().unwrap().insert(run_id.to_string(), session);
+        token
+    }
 
-        #[cfg(windows)]
-        {
-            let taskkill_force = Command::new("taskkill")
-                .args(&["/F", "/PID", &pid.to_string()])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output();
+    /// Forgets a run that ended, with any process it left tracked.
+    pub fn end_run(&self, run_id: &str) {
+        self.runs.lock().unwrap().remove(run_id);
+        self.children.lock().unwrap().retain(|key, _| key.run_id != run_id);
+    }
 
-            match taskkill_force {
-                Ok(output) if output.status.success() => {
-                    logger.info(&format!("Force termination successful for PID {}", pid));
-                }
-                Ok(output) => {
-                    let msg = logger.error(&format!(
-                        "Force termination failed for PID {}: {}",
-                        pid,
-                        String::from_utf8_lossy(&output.stderr)
-                    ));
-                    return Err(msg);
-                }
-                Err(e) => {
-                    let msg = logger.error(&format!("Error during force termination for PID {}: {}", pid, e));
-                    return Err(msg);
-                }
-            }
-        }
+    /// Ids of the runs started and not yet ended.
+    pub fn active_runs(&self) -> Vec<String> {
+        self.runs.lock().unwrap().keys().cloned().collect()
+    }
 
-        #[cfg(unix)]
-        {
-            let kill_force = Command::new("kill")
-                .args(&["-KILL", &pid.to_string()])
-                .output();
+    /// Cancels `run_id`, or every run `window` started when unset; returns
+    /// the runs cancelled.
+    pub fn cancel(&self, run_id: Option<&str>, window: &str) -> Vec<String> {
+        self.runs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, session)| match run_id {
+                Some(run_id) => run_id == id.as_str(),
+                None => session.window == window,
+            })
+            .map(|(id, session)| {
+                session.token.cancel();
+                id.clone()
+            })
+            .collect()
+    }
 
-            match kill_force {
-                Ok(output) if output.status.success() => {
-                    logger.info(&format!("Force termination successful for PID {}", pid));
-                }
-                Ok(output) => {
-                    let msg = logger.error(&format!(
-                        "Force termination failed for PID {}: {}",
-                        pid,
-                        String::from_utf8_lossy(&output.stderr)
-                    ));
-                    return Err(msg);
-                }
-                Err(e) => {
-                    let msg = logger.error(&format!("Error during force termination for PID {}: {}", pid, e));
-                    return Err(msg);
-                }
-            }
-        }
+    /// Tracks a started CubeIDE process until the returned guard is dropped.
+    pub fn track_child(&self, run_id: &str, combination: &str, pid: u32) -> TrackedChild<'_> {
+        let key = ChildKey { run_id: run_id.to_string(), combination: combination.to_string() };
+        self.children.lock().unwrap().insert(key.clone(), pid);
+        TrackedChild { manager: self, key }
     }
 
-    // Check child processes
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    let children: Vec<Pid> = system
-        .processes()
-        .iter()
-        .filter(|(_, p)| p.parent() == Some(Pid::from(pid as usize)))
-        .map(|(pid, _)| *pid)
-        .collect();
+    /// Processes of `run_id`.
+    pub fn children(&self, run_id: &str) -> Vec<(ChildKey, u32)> {
+        self.children
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.run_id == run_id)
+            .map(|(key, pid)| (key.clone(), *pid))
+            .collect()
+    }
+}
 
-    if !children.is_empty() {
-        logger.info(&format!("Found {} child processes for PID {}", children.len(), pid));
+pub struct PathsLock<'a> {
+    manager: &'a BuildManager,
+    paths: Vec<PathBuf>,
+}
 
-        for child_pid in children {
-            // Рекурсивно убиваем всех потомков, независимо от имени
-            let child_result = Box::pin(kill_process_and_children(
-                Into::<usize>::into(child_pid) as u32,
-                window.clone(),
-            )).await;
-            if let Err(e) = child_result {
-                let msg = logger.error(&format!("Failed to kill child PID {}: {}", child_pid, e));
-                return Err(msg);
-            }
+impl Drop for PathsLock<'_> {
+    fn drop(&mut self) {
+        let mut busy = self.manager.busy_paths.lock().unwrap();
+        for path in &self.paths {
+            busy.remove(path);
         }
+        drop(busy);
+        self.manager.paths_changed.notify_waiters();
+    }
+}
+
+pub struct RunSlot<'a> {
+    manager: &'a BuildManager,
+}
+
+impl Drop for RunSlot<'_> {
+    fn drop(&mut self) {
+        self.manager.slots.lock().unwrap().active -= 1;
+        self.manager.slot_changed.notify_waiters();
     }
+}
 
-    logger.info(&format!("Successfully terminated PID {} and its children", pid));
+/// Sets how many runs may build at the same time (1 by default); takes
+/// effect without restarting the runs in progress.
+#[command]
+pub async fn set_max_concurrent_builds(window: Window, n: usize) -> Result<(), String> {
+    if n == 0 {
+        return Err(tr!("build.concurrency_invalid"));
+    }
+    window.state::<BuildManager>().set_limit(n);
     Ok(())
 }
 
-// Новый метод для завершения процесса по handle:
+/// Sets the priority (nice level or Windows priority class) and CPU
+/// affinity of the CubeIDE and make processes of later builds.
 #[command]
-pub async fn kill_build_child_process() -> Result<(), String> {
-    // Use a timeout for the lock acquisition
-    let mut child_guard = match tokio::time::timeout(
-        Duration::from_secs(1),
-        BUILD_CHILD.lock()
-    ).await {
-        Ok(guard) => guard,
-        Err(_) => return Ok(()) // Return OK if we can't get lock
-    };
+pub async fn set_build_priority(window: Window, priority: ProcessPriority) -> Result<(), String> {
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    if let Some(cpu) = priority.cpus.iter().find(|c| **c >= cpus) {
+        return Err(tr!("build.affinity_invalid", cpu, cpus));
+    }
+    *window.state::<BuildManager>().priority.lock().unwrap() = priority;
+    Ok(())
+}
 
-    if let Some(child) = child_guard.as_mut() {
-        println!("[KILL] Found active build process");
+pub struct TrackedChild<'a> {
+    manager: &'a BuildManager,
+    key: ChildKey,
+}
 
-        #[cfg(windows)]
-        {
-            // Run taskkill in a separate task to avoid blocking
-            let kill_task = tokio::spawn(async {
-                Command::new("taskkill")
-                    .args(&["/F", "/T", "/IM", "stm32cubeidec.exe"])
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .output()
-            });
+impl Drop for TrackedChild<'_> {
+    fn drop(&mut self) {
+        self.manager.children.lock().unwrap().remove(&self.key);
+    }
+}
 
-            // Wait for taskkill with timeout
-            match tokio::time::timeout(Duration::from_secs(2), kill_task).await {
-                Ok(result) => match result {
-                    Ok(output) => if let Ok(output) = output {
-                        println!("[KILL] taskkill result: {}", output.status.success());
-                    },
-                    Err(e) => println!("[KILL] taskkill task failed: {}", e),
-                },
-                Err(_) => println!("[KILL] taskkill timeout"),
+/// Kills the process groups of the CubeIDE builds of `run_id`, so the
+/// compilers they started go too.
+pub fn kill_build_children(manager: &BuildManager, run_id: &str) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for (_, pid) in manager.children(run_id) {
+        // The job of the build holds every process it started
+        #[cfg(windows)]
+        if let Some(result) = crate::job_object::terminate(pid) {
+            if let Err(e) = result {
+                errors.push(format!("job of {}: {}", pid, e));
             }
+            continue;
+        }
 
-            // Kill child process without waiting
-            let _ = child.kill().await;
-            println!("[KILL] Child process kill signal sent");
-
-            // Force drop the handle
-            drop(child);
-            *child_guard = None;
-            println!("[KILL] Process handle released");
+        #[cfg(windows)]
+        {
+            let output = Command::new("taskkill")
+                .args(&["/F", "/T", "/PID", &pid.to_string()])
+                .creation_flags(CREATE_NO_WINDOW)
+                .output();
+            match output {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => errors.push(format!("taskkill {}: {}", pid, String::from_utf8_lossy(&output.stderr).trim())),
+                Err(e) => errors.push(format!("taskkill {}: {}", pid, e)),
+            }
         }
 
         #[cfg(unix)]
         {
             use nix::sys::signal::{self, Signal};
             use nix::unistd::Pid;
-            
-            let pid = Pid::from_raw(-(child.id().unwrap_or(0) as i32));
-            if let Err(e) = signal::kill(pid, Signal::SIGTERM) {
-                // Fallback to regular process kill
-                if let Err(e2) = child.kill().await {
-                    return Err(format!(
-                        "Failed to kill process group ({}), and process kill failed: {}", 
-                        e, e2
-                    ));
-                }
+
+            // The builder starts CubeIDE as the leader of its own process group
+            if let Err(e) = signal::kill(Pid::from_raw(-(pid as i32)), Signal::SIGTERM) {
+                errors.push(format!("kill -{}: {}", pid, e));
             }
-            *child_guard = None;
-            return Ok(());
         }
-    } else {
-        println!("[KILL] No active build process found");
     }
-    Ok(())
-}
\ No newline at end of file
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+}
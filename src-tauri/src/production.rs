@@ -0,0 +1,265 @@
+use crate::flashing::{flash, FlashRequest, FlashSettings, Transport};
+use crate::i18n::tr;
+use crate::logging::{emit_to_window, Logger};
+use crate::option_bytes::{self, RdpLevel};
+use crate::personalize::{append_csv, app_data_file};
+use crate::target_check::detect_device;
+use chrono::Local;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{command, Window};
+use tokio::time::{sleep, Duration};
+
+const PRODUCTION_FILE: &str = "production.csv";
+const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+
+lazy_static! {
+    // Stop flags of the running production loops, by session id
+    static ref SESSIONS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+/// One production station: a probe and the variant programmed onto every
+/// board connected to it. Serialization and option bytes come from the
+/// flash settings, as for a single flash.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProductionRequest {
+    pub settings: FlashSettings,
+    pub file: String,
+    pub combination: Option<String>,
+    #[serde(rename = "runId")]
+    pub run_id: Option<String>,
+    #[serde(rename = "projectPath")]
+    pub project_path: Option<String>,
+    pub probe: Option<String>,
+    pub address: Option<String>,
+    #[serde(rename = "fullErase", default)]
+    pub full_erase: bool,
+    #[serde(default)]
+    pub verify: bool,
+    #[serde(default)]
+    pub reset: bool,
+    /// Confirms the read-out protection level for this session only; level 2
+    /// locks every board of the session for good, so it is never taken
+    /// from saved settings.
+    #[serde(rename = "confirmRdp", default)]
+    pub confirm_rdp: Option<RdpLevel>,
+    /// Result log; defaults to `production.csv` in the app data directory.
+    #[serde(rename = "logFile")]
+    pub log_file: Option<String>,
+    /// How often the probe is polled for a board being connected or removed.
+    #[serde(rename = "pollIntervalMs")]
+    pub poll_interval_ms: Option<u64>,
+}
+
+impl ProductionRequest {
+    fn flash_request(&self) -> FlashRequest {
+        FlashRequest {
+            settings: self.settings.clone(),
+            file: self.file.clone(),
+            combination: self.combination.clone(),
+            run_id: self.run_id.clone(),
+            project_path: self.project_path.clone(),
+            probe: self.probe.clone(),
+            address: self.address.clone(),
+            full_erase: self.full_erase,
+            verify: self.verify,
+            reset: self.reset,
+            confirm_rdp: self.confirm_rdp,
+            // A wrong chip on the line is exactly what has to be caught
+            ignore_mcu_mismatch: false,
+            transport: Transport::Swd,
+        }
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductionState {
+    /// Waiting for the next board to be connected.
+    Waiting,
+    Flashing,
+    Passed,
+    Failed,
+    /// Waiting for the programmed board to be disconnected.
+    RemoveBoard,
+    Stopped,
+}
+
+/// Payload of the `production-status` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProductionStatus {
+    pub session_id: String,
+    pub state: ProductionState,
+    /// Number of the current board, counting from 1.
+    pub board: u32,
+    pub passed: u32,
+    pub failed: u32,
+    pub message: Option<String>,
+}
+
+/// One board, as appended to the production log whether it passed or not.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProductionRecord {
+    pub timestamp: String,
+    pub board: u32,
+    pub combination: Option<String>,
+    pub probe: Option<String>,
+    pub device_id: String,
+    pub uid: Option<String>,
+    pub serial: Option<u32>,
+    pub passed: bool,
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProductionSession {
+    pub session_id: String,
+    pub log_file: String,
+}
+
+struct Station {
+    window: Window,
+    session_id: String,
+    stop: Arc<AtomicBool>,
+    board: u32,
+    passed: u32,
+    failed: u32,
+}
+
+impl Station {
+    fn emit(&self, state: ProductionState, message: Option<String>) {
+        emit_to_window(
+            &self.window,
+            "production-status",
+            &ProductionStatus {
+                session_id: self.session_id.clone(),
+                state,
+                board: self.board,
+                passed: self.passed,
+                failed: self.failed,
+                message,
+            },
+        );
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Polls the probe until a board is attached (`attached`) or gone.
+    /// Returns the device id, or `None` if the loop was stopped meanwhile.
+    async fn wait_for_board(&self, request: &FlashRequest, attached: bool, interval: Duration) -> Option<Option<u16>> {
+        loop {
+            if self.stopped() {
+                return None;
+            }
+            let device = detect_device(request).await;
+            if device.is_some() == attached {
+                return Some(device);
+            }
+            sleep(interval).await;
+        }
+    }
+}
+
+async fn run(mut station: Station, request: ProductionRequest, log_file: PathBuf) {
+    let flash_request = request.flash_request();
+    let interval = request.poll_interval();
+    loop {
+        station.emit(ProductionState::Waiting, None);
+        let Some(Some(device_id)) = station.wait_for_board(&flash_request, true, interval).await else {
+            break;
+        };
+
+        station.board += 1;
+        station.emit(ProductionState::Flashing, None);
+        let mut logger = Logger::new(&station.window);
+        logger.set_combination(request.combination.clone());
+        logger.set_stage("flash");
+        let result = flash(&station.window, &flash_request, &mut logger).await;
+        logger.finish_stage();
+
+        let record = ProductionRecord {
+            timestamp: Local::now().to_rfc3339(),
+            board: station.board,
+            combination: request.combination.clone(),
+            probe: request.probe.clone(),
+            device_id: format!("0x{:03X}", device_id),
+            uid: result.provisioning.as_ref().map(|p| p.uid.clone()),
+            serial: result.provisioning.as_ref().and_then(|p| p.serial),
+            passed: result.success,
+            message: result.message.clone(),
+            duration_ms: result.duration_ms,
+        };
+        let mut message = result.message.clone();
+        if let Err(e) = append_csv(&log_file, &record) {
+            message = format!("{} ({})", message, tr!("production.log_failed", e));
+        }
+        if result.success {
+            station.passed += 1;
+            station.emit(ProductionState::Passed, Some(message));
+        } else {
+            station.failed += 1;
+            station.emit(ProductionState::Failed, Some(message));
+        }
+
+        station.emit(ProductionState::RemoveBoard, None);
+        if station.wait_for_board(&flash_request, false, interval).await.is_none() {
+            break;
+        }
+    }
+    SESSIONS.lock().unwrap().remove(&station.session_id);
+    station.emit(ProductionState::Stopped, None);
+}
+
+/// Starts a production loop on one probe: every board connected to it is
+/// flashed (with serialization, option bytes and verification as set up
+/// in the flash settings) and logged, then the operator swaps boards.
+/// Progress is reported through `production-status` events until
+/// `stop_production` is called.
+#[command]
+pub async fn start_production(window: Window, request: ProductionRequest) -> Result<ProductionSession, String> {
+    if !std::path::Path::new(&request.file).is_file() {
+        return Err(tr!("flash.file_missing", request.file));
+    }
+    // Refused before the first board rather than on every one of them
+    if let Some(option_bytes) = option_bytes::resolve(&request.settings, request.combination.as_deref()) {
+        option_bytes::check_rdp_confirmed(&option_bytes, request.confirm_rdp)?;
+    }
+    let log_file = match &request.log_file {
+        Some(path) => PathBuf::from(path),
+        None => app_data_file(&window, PRODUCTION_FILE)?,
+    };
+    let session_id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let stop = Arc::new(AtomicBool::new(false));
+    SESSIONS.lock().unwrap().insert(session_id.clone(), stop.clone());
+
+    let session = ProductionSession {
+        session_id: session_id.clone(),
+        log_file: log_file.to_string_lossy().into_owned(),
+    };
+    let station = Station { window, session_id, stop, board: 0, passed: 0, failed: 0 };
+    tokio::spawn(run(station, request, log_file));
+    Ok(session)
+}
+
+/// Stops a production loop. A board being flashed is finished first.
+#[command]
+pub async fn stop_production(session_id: String) -> Result<(), String> {
+    match SESSIONS.lock().unwrap().get(&session_id) {
+        Some(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(tr!("production.not_running", session_id)),
+    }
+}
@@ -0,0 +1,83 @@
+use crate::i18n::tr;
+use crate::models::BuildConfig;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, Manager, Window};
+
+pub use stm32_builder_core::project::PROJECT_CONFIG_FILE;
+const LAST_CONFIG_FILE: &str = "last_config.json";
+
+fn config_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(PROJECT_CONFIG_FILE)
+}
+
+/// Saves the build configuration into the project directory so it is
+/// restored the next time the project is opened.
+#[command]
+pub async fn save_project_config(config: BuildConfig) -> Result<(), String> {
+    if !Path::new(&config.project_path).is_dir() {
+        return Err(tr!("project.not_found", config.project_path));
+    }
+    let mut config = config;
+    // Runtime state, not part of the project's configuration
+    config.cancelled = None;
+
+    let path = config_path(&config.project_path);
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| tr!("project_config.serialize_failed", e))?;
+    fs::write(&path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+/// Loads the saved configuration of a project; `None` if it has none yet.
+#[command]
+pub async fn load_project_config(project_path: String) -> Result<Option<BuildConfig>, String> {
+    let path = config_path(&project_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| tr!("fs.read", path.display(), e))?;
+    let mut config: BuildConfig = serde_json::from_str(&content)
+        .map_err(|e| tr!("project_config.invalid", path.display(), e))?;
+    // The project may have been moved since the file was written
+    config.project_path = project_path;
+    Ok(Some(config))
+}
+
+fn last_config_path(window: &Window) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(LAST_CONFIG_FILE))
+}
+
+/// Remembers the configuration of the latest build for the next start of
+/// the app, without what only applied to that run: its cancellation, a dry
+/// run and an imported list of combinations.
+pub fn save_last_config(window: &Window, config: &BuildConfig) -> Result<(), String> {
+    let mut config = config.clone();
+    config.cancelled = None;
+    config.dry_run = None;
+    config.selected_combinations = None;
+    let path = last_config_path(window)?;
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| tr!("project_config.serialize_failed", e))?;
+    fs::write(&path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+/// Configuration of the last build, including settings values; `None` on first start.
+#[command]
+pub async fn get_last_build_config(window: Window) -> Result<Option<BuildConfig>, String> {
+    let path = last_config_path(&window)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| tr!("fs.read", path.display(), e))?;
+    // An outdated file should not keep the app from starting with an empty form
+    Ok(serde_json::from_str(&content).ok())
+}
@@ -0,0 +1,77 @@
+use crate::models::BuildConfig;
+use crate::preflight::find_tool;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use sysinfo::System;
+use tauri::{Manager, Window};
+
+/// Where and with what a run was built, recorded in its manifest.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    pub app_version: String,
+    pub cubeide_version: Option<String>,
+    /// First line of `arm-none-eabi-gcc --version`.
+    pub toolchain_version: Option<String>,
+    pub os: String,
+    pub os_version: Option<String>,
+    pub arch: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+}
+
+pub fn collect(window: &Window, config: &BuildConfig) -> Provenance {
+    let ide_dir = Path::new(&config.cube_ide_exe_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    Provenance {
+        app_version: window.app_handle().package_info().version.to_string(),
+        cubeide_version: cubeide_version(&ide_dir),
+        toolchain_version: find_tool(&ide_dir, "externaltools.gnu-tools-for-stm32", "arm-none-eabi-gcc")
+            .and_then(|gcc| tool_version(&gcc)),
+        os: std::env::consts::OS.to_string(),
+        os_version: System::long_os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        hostname: System::host_name(),
+        user: std::env::var("USERNAME").or_else(|_| std::env::var("USER")).ok(),
+    }
+}
+
+/// Reads `version=` from `.eclipseproduct`, falling back to the version in
+/// the name of the STM32CubeIDE branding plugin.
+fn cubeide_version(ide_dir: &Path) -> Option<String> {
+    if let Ok(product) = fs::read_to_string(ide_dir.join(".eclipseproduct"))
+        && let Some(version) = product.lines().find_map(|l| l.trim().strip_prefix("version="))
+    {
+        return Some(version.trim().to_string());
+    }
+    fs::read_dir(ide_dir.join("plugins"))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .strip_prefix("com.st.stm32cube.ide_")
+                .map(|version| version.trim_end_matches(".jar").to_string())
+        })
+        .max()
+}
+
+fn tool_version(tool: &Path) -> Option<String> {
+    let mut command = Command::new(tool);
+    command.arg("--version");
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command.output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+}
@@ -0,0 +1,69 @@
+use crate::analysis::{CombinationBuild, GateAction};
+use crate::i18n::tr;
+use crate::simulation::{self, SimulationResult};
+use crate::target_check::declared_mcu;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::process::Command;
+use tokio::time::Duration;
+
+const DEFAULT_RUN_MS: u64 = 2000;
+// Also matches QEMU's "Lockup: can't escalate 3 to HardFault"
+const DEFAULT_FAULT_PATTERN: &str = "(?i)hard ?fault";
+
+// MCU prefixes and the QEMU machine emulating the closest part
+const MACHINES: &[(&str, &str)] = &[
+    ("STM32F100", "stm32vldiscovery"),
+    ("STM32F205", "netduino2"),
+    ("STM32F405", "netduinoplus2"),
+    ("STM32F407", "netduinoplus2"),
+    ("STM32L475", "b-l475e-iot01a"),
+];
+
+/// Quick QEMU boot check run on every built combination.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QemuSettings {
+    /// `qemu-system-arm` on the PATH by default.
+    pub path: Option<String>,
+    /// QEMU machine; chosen from the project's MCU when not set.
+    pub machine: Option<String>,
+    /// How long the firmware runs.
+    #[serde(rename = "runMs")]
+    pub run_ms: Option<u64>,
+    /// Regex that fails the check when it shows up in the semihosting output.
+    #[serde(rename = "faultPattern")]
+    pub fault_pattern: Option<String>,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+fn machine_for(project_path: &Path) -> Option<&'static str> {
+    let mcu = declared_mcu(project_path)?.to_uppercase();
+    MACHINES
+        .iter()
+        .find(|(prefix, _)| mcu.starts_with(prefix))
+        .map(|(_, machine)| *machine)
+}
+
+pub async fn run(settings: &QemuSettings, build: &CombinationBuild<'_>, elf: &Path) -> SimulationResult {
+    let pattern = settings.fault_pattern.as_deref().unwrap_or(DEFAULT_FAULT_PATTERN);
+    let fail = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => return SimulationResult::failed("qemu", tr!("simulation.pattern_invalid", pattern, e)),
+    };
+    let machine = match settings.machine.as_deref().or_else(|| machine_for(build.project_path)) {
+        Some(machine) => machine.to_string(),
+        None => return SimulationResult::failed("qemu", tr!("qemu.unsupported_mcu")),
+    };
+
+    let mut command = Command::new(settings.path.as_deref().unwrap_or("qemu-system-arm"));
+    command
+        .args(["-M", &machine, "-nographic", "-monitor", "none", "-serial", "null"])
+        .args(["-semihosting-config", "enable=on,target=native"])
+        .arg("-kernel")
+        .arg(elf)
+        .current_dir(build.project_path);
+    let duration = Duration::from_millis(settings.run_ms.unwrap_or(DEFAULT_RUN_MS));
+    simulation::run_for("qemu", command, &fail, duration).await
+}
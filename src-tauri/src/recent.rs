@@ -0,0 +1,80 @@
+use crate::i18n::tr;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, Manager, Window};
+
+const RECENT_FILE: &str = "recent.json";
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentKind {
+    Project,
+    Workspace,
+    CubeIde,
+    OutputDir,
+}
+
+lazy_static! {
+    // Serializes read-modify-write of the file between concurrent commands
+    static ref RECENT_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn recent_path(window: &Window) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(RECENT_FILE))
+}
+
+// A missing or unreadable file just means there is no history yet
+fn load(path: &PathBuf) -> HashMap<RecentKind, Vec<String>> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Moves `value` to the front of the `kind` list, keeping the last `MAX_ENTRIES`.
+pub fn add_entry(window: &Window, kind: RecentKind, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(());
+    }
+    let _guard = RECENT_LOCK.lock().map_err(|e| e.to_string())?;
+    let path = recent_path(window)?;
+    let mut recent = load(&path);
+    let entries = recent.entry(kind).or_default();
+    entries.retain(|e| e != value);
+    entries.insert(0, value.to_string());
+    entries.truncate(MAX_ENTRIES);
+
+    let json = serde_json::to_string_pretty(&recent)
+        .map_err(|e| tr!("recent.serialize_failed", e))?;
+    fs::write(&path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+/// Recently used paths of `kind`, most recent first.
+pub fn entries(window: &Window, kind: RecentKind) -> Result<Vec<String>, String> {
+    let _guard = RECENT_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut recent = load(&recent_path(window)?);
+    Ok(recent.remove(&kind).unwrap_or_default())
+}
+
+#[command]
+pub async fn get_recent_entries(window: Window, kind: RecentKind) -> Result<Vec<String>, String> {
+    entries(&window, kind)
+}
+
+#[command]
+pub async fn add_recent_entry(window: Window, kind: RecentKind, value: String) -> Result<(), String> {
+    add_entry(&window, kind, &value)
+}
@@ -0,0 +1,166 @@
+use crate::git_info;
+use crate::history;
+use crate::i18n::tr;
+use crate::manifest::{RunManifest, MANIFEST_FILE};
+use crate::signing::CHAIN_EXTENSION;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{command, Window};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+const FIRMWARE_DIR: &str = "firmware";
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseBundle {
+    pub directory: String,
+    pub zip: Option<String>,
+    /// Tagged run the changelog starts after, if there is one.
+    pub previous_run: Option<String>,
+    pub commits: usize,
+}
+
+/// Name of the release: the firmware version if the run was versioned.
+fn release_name(manifest: &RunManifest) -> String {
+    match &manifest.version {
+        Some(version) => version.label(),
+        None => manifest.run_id.clone(),
+    }
+}
+
+/// Changelog of the commits since the previous tagged run of the project.
+fn changelog(window: &Window, manifest: &RunManifest) -> Result<(String, Option<String>, usize), String> {
+    let mut text = format!("# {}\n\n", release_name(manifest));
+    text.push_str(&format!("Built {} from run {}", manifest.finished_at, manifest.run_id));
+    let Some(git) = &manifest.git else {
+        text.push_str(".\n\nThe project was not in a git repository; no changes are listed.\n");
+        return Ok((text, None, 0));
+    };
+    text.push_str(&format!(", commit {} ({})", git.hash, git.branch));
+    if git.dirty {
+        text.push_str(", with uncommitted changes");
+    }
+    text.push_str(".\n\n");
+
+    let previous_run = history::previous_tagged_run(&history::history_path(window)?, &manifest.project_path, &manifest.started_at)?;
+    let previous_hash = previous_run
+        .as_ref()
+        .and_then(|run_id| RunManifest::load_archived(window, run_id).ok())
+        .and_then(|previous| previous.git)
+        .map(|g| g.hash);
+    let commits = git_info::commits_between(Path::new(&manifest.project_path), previous_hash.as_deref(), &git.hash)
+        .ok_or_else(|| tr!("release.git_log_failed", manifest.project_path))?;
+
+    match (&previous_run, &previous_hash) {
+        (Some(run_id), Some(hash)) => text.push_str(&format!("## Changes since run {} ({})\n\n", run_id, hash)),
+        _ => text.push_str("## Changes\n\n"),
+    }
+    for commit in &commits {
+        text.push_str(&format!("- {}\n", commit));
+    }
+    if commits.is_empty() {
+        text.push_str("No new commits.\n");
+    }
+    Ok((text, previous_run.filter(|_| previous_hash.is_some()), commits.len()))
+}
+
+fn zip_directory(dir: &Path, zip_path: &Path) -> Result<(), String> {
+    let file = File::create(zip_path)
+        .map_err(|e| tr!("fs.create", zip_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|e| tr!("fs.read_dir", current.display(), e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let data = fs::read(&path).map_err(|e| tr!("fs.read", path.display(), e))?;
+            zip.start_file(name.as_str(), options)
+                .and_then(|_| zip.write_all(&data).map_err(Into::into))
+                .map_err(|e| tr!("bundle.add_failed", name, e))?;
+        }
+    }
+    zip.finish()
+        .map_err(|e| tr!("fs.write", zip_path.display(), e))?;
+    Ok(())
+}
+
+/// Packages a run for distribution: its artifacts, manifest and a
+/// changelog of the commits since the previous tagged run.
+///
+/// The bundle is written to `destination` or to `release_<name>` in the
+/// run's output directory; with `zip`, a zip of it is written next to it.
+#[command]
+pub async fn create_release_bundle(
+    window: Window,
+    run_id: String,
+    destination: Option<String>,
+    zip: Option<bool>,
+) -> Result<ReleaseBundle, String> {
+    let manifest = RunManifest::load_archived(&window, &run_id)?;
+    if !manifest.success {
+        return Err(tr!("release.run_failed", run_id));
+    }
+    let dir = match destination {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&manifest.output_dir).join(format!("release_{}", release_name(&manifest))),
+    };
+    let firmware_dir = dir.join(FIRMWARE_DIR);
+    fs::create_dir_all(&firmware_dir)
+        .map_err(|e| tr!("fs.create_dir", firmware_dir.display(), e))?;
+
+    for combination in &manifest.combinations {
+        let Some(artifact) = &combination.artifact else { continue };
+        let mut files = vec![PathBuf::from(artifact)];
+        // Signed images ship with their signature and certificate chain
+        if let Some(signature) = &combination.signature {
+            files.push(PathBuf::from(signature));
+            files.push(Path::new(artifact).with_extension(CHAIN_EXTENSION));
+        }
+        for source in files {
+            let Some(name) = source.file_name() else { continue };
+            fs::copy(&source, firmware_dir.join(name))
+                .map_err(|e| tr!("release.artifact_missing", source.display(), e))?;
+        }
+    }
+
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| tr!("manifest.serialize_failed", e))?;
+    fs::write(dir.join(MANIFEST_FILE), manifest_json)
+        .map_err(|e| tr!("fs.write", dir.join(MANIFEST_FILE).display(), e))?;
+
+    let (changelog, previous_run, commits) = changelog(&window, &manifest)?;
+    fs::write(dir.join(CHANGELOG_FILE), changelog)
+        .map_err(|e| tr!("fs.write", dir.join(CHANGELOG_FILE).display(), e))?;
+
+    let zip_path = if zip.unwrap_or(false) {
+        // Not `with_extension`, which would eat the version's dots
+        let mut path = dir.clone().into_os_string();
+        path.push(".zip");
+        let path = PathBuf::from(path);
+        zip_directory(&dir, &path)?;
+        Some(path.display().to_string())
+    } else {
+        None
+    };
+
+    Ok(ReleaseBundle {
+        directory: dir.display().to_string(),
+        zip: zip_path,
+        previous_run,
+        commits,
+    })
+}
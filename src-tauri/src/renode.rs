@@ -0,0 +1,74 @@
+use crate::analysis::{CombinationBuild, GateAction};
+use crate::i18n::tr;
+use crate::simulation::{self, SimulationResult};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+use tokio::time::Duration;
+
+const DEFAULT_UART: &str = "sysbus.usart2";
+const DEFAULT_PATTERN: &str = "BOOT OK";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const WRAPPER_FILE: &str = "renode_smoke.resc";
+const UART_LOG_FILE: &str = "renode_uart.log";
+
+/// Renode boot test run on every built combination.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RenodeSettings {
+    /// `renode` on the PATH by default.
+    pub path: Option<String>,
+    /// Platform script (`.resc`) creating the machine. The ELF to load is
+    /// passed in `$bin`, e.g. `sysbus LoadELF $bin`.
+    pub script: String,
+    /// UART peripheral the firmware prints to.
+    pub uart: Option<String>,
+    /// Regex the UART output must match for the boot to pass.
+    pub pattern: Option<String>,
+    #[serde(rename = "timeoutSecs")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+/// Renode script that sets `$bin`, runs the platform script and logs the
+/// UART to a file next to it.
+fn wrapper(settings: &RenodeSettings, script: &Path, elf: &Path, uart_log: &Path) -> String {
+    format!(
+        "$bin=@{elf}\ninclude @{script}\n{uart} CreateFileBackend @{log} true\nstart\n",
+        elf = elf.display(),
+        script = script.display(),
+        uart = settings.uart.as_deref().unwrap_or(DEFAULT_UART),
+        log = uart_log.display(),
+    )
+}
+
+pub async fn run(settings: &RenodeSettings, build: &CombinationBuild<'_>, elf: &Path) -> SimulationResult {
+    let pattern = settings.pattern.as_deref().unwrap_or(DEFAULT_PATTERN);
+    let pass = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => return SimulationResult::failed("renode", tr!("simulation.pattern_invalid", pattern, e)),
+    };
+    // Relative scripts are relative to the project
+    let script = build.project_path.join(&settings.script);
+    if !script.exists() {
+        return SimulationResult::failed("renode", tr!("renode.script_missing", script.display()));
+    }
+    let wrapper_path = build.combo_dir.join(WRAPPER_FILE);
+    let uart_log = build.combo_dir.join(UART_LOG_FILE);
+    if let Err(e) = fs::write(&wrapper_path, wrapper(settings, &script, elf, &uart_log)) {
+        return SimulationResult::failed("renode", tr!("fs.write", wrapper_path.display(), e));
+    }
+
+    let mut command = Command::new(settings.path.as_deref().unwrap_or("renode"));
+    command
+        .args(["--disable-xwt", "--console", "--plain"])
+        .arg(&wrapper_path)
+        .current_dir(build.project_path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    let timeout = Duration::from_secs(settings.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    simulation::run_until_output("renode", command, &uart_log, &pass, timeout).await
+}
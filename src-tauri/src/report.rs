@@ -0,0 +1,340 @@
+use stm32_builder_core::diagnostics::{Diagnostic, Severity};
+use crate::i18n::tr;
+use crate::elf_size::SectionSizes;
+use crate::manifest::RunManifest;
+use crate::unit_tests::TestStatus;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{command, Window};
+
+pub const REPORT_FILE: &str = "report.html";
+// Largest RAM users listed per combination
+const RAM_MODULES_SHOWN: usize = 15;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:4px 10px;text-align:left}\
+th{background:#f3f3f3}td.num{text-align:right}\
+.ok{color:#1a7f37;font-weight:bold}.failed{color:#cf222e;font-weight:bold}\
+.reason{font-family:monospace;font-size:90%}";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}.{}s", secs, (ms % 1000) / 100)
+    }
+}
+
+fn diagnostic_item(diagnostic: &Diagnostic) -> String {
+    let location = match (&diagnostic.file, diagnostic.line) {
+        (Some(file), Some(line)) => format!("{}:{}: ", file, line),
+        (Some(file), None) => format!("{}: ", file),
+        _ => String::new(),
+    };
+    format!(
+        "<li class=\"reason\">{}{}{}</li>\n",
+        escape(&location),
+        escape(&diagnostic.message),
+        diagnostic
+            .option
+            .as_ref()
+            .map(|o| format!(" [{}]", escape(o)))
+            .unwrap_or_default(),
+    )
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "—".to_string())
+}
+
+/// Wall-clock time of one combination, summed over its stages.
+pub fn combination_duration(manifest: &RunManifest, combination: &str) -> u64 {
+    manifest
+        .stage_timings
+        .iter()
+        .filter(|t| t.combination.as_deref() == Some(combination))
+        .map(|t| t.duration_ms)
+        .sum()
+}
+
+/// Renders a self-contained HTML page for a run. Links are relative to the
+/// output directory, so the report can be zipped together with it.
+pub fn render_html(manifest: &RunManifest) -> String {
+    let mut html = String::new();
+    let status = if manifest.cancelled {
+        "Cancelled"
+    } else if manifest.success {
+        "Succeeded"
+    } else {
+        "Failed"
+    };
+
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+    html.push_str(&format!("<title>Build report {}</title>", escape(&manifest.run_id)));
+    html.push_str(&format!("<style>{}</style></head><body>\n", STYLE));
+    html.push_str(&format!("<h1>Build report {}</h1>\n", escape(&manifest.run_id)));
+
+    html.push_str("<table>\n");
+    for (label, value) in [
+        ("Status", status.to_string()),
+        ("Project", manifest.project_path.clone()),
+        ("Configuration", optional(manifest.config_name.as_ref())),
+        ("Started", manifest.started_at.clone()),
+        ("Finished", manifest.finished_at.clone()),
+        ("Combinations", manifest.combinations.len().to_string()),
+        ("Errors", manifest.total_errors.to_string()),
+        ("Warnings", manifest.total_warnings.to_string()),
+    ] {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", label, escape(&value)));
+    }
+    html.push_str("<tr><th>Log</th><td><a href=\"build_log.txt\">build_log.txt</a></td></tr>\n");
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Combinations</h2>\n<table>\n<tr><th>Combination</th><th>Status</th>\
+        <th>Errors</th><th>Warnings</th><th>Text</th><th>Data</th><th>BSS</th>\
+        <th>Binary</th><th>Duration</th><th>Log</th><th>Failure</th></tr>\n");
+    for combination in &manifest.combinations {
+        let (class, label) = if combination.success { ("ok", "OK") } else { ("failed", "Failed") };
+        let log = combination
+            .log_file
+            .as_deref()
+            .and_then(|path| Path::new(path).file_name())
+            .map(|name| {
+                let href = format!("{}/{}", combination.combination, name.to_string_lossy());
+                format!("<a href=\"{}\">log</a>", escape(&href))
+            })
+            .unwrap_or_default();
+        let size = |f: fn(&SectionSizes) -> u64| optional(combination.sizes.as_ref().map(f));
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td><td>{}</td><td class=\"reason\">{}</td></tr>\n",
+            escape(&combination.combination),
+            class,
+            label,
+            combination.errors,
+            combination.warnings,
+            size(|s| s.text),
+            size(|s| s.data),
+            size(|s| s.bss),
+            optional(combination.artifact_size),
+            format_duration(combination_duration(manifest, &combination.combination)),
+            log,
+            escape(combination.failure_reason.as_deref().unwrap_or("")),
+        ));
+    }
+    html.push_str("</table>\n");
+
+    let with_breakdown = manifest.combinations.iter().filter_map(|c| Some((c, c.ram_breakdown.as_ref()?)));
+    let mut ram_html = String::new();
+    for (combination, breakdown) in with_breakdown {
+        ram_html.push_str(&format!(
+            "<h3>{}</h3>\n<table>\n<tr><th>Module</th><th>.data</th><th>.bss</th><th>Total</th></tr>\n",
+            escape(&combination.combination)
+        ));
+        for usage in breakdown.modules.iter().take(RAM_MODULES_SHOWN) {
+            ram_html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+                escape(&usage.module),
+                usage.data,
+                usage.bss,
+                usage.data + usage.bss,
+            ));
+        }
+        ram_html.push_str("</table>\n");
+    }
+    if !ram_html.is_empty() {
+        html.push_str("<h2>Static RAM by module</h2>\n");
+        html.push_str(&ram_html);
+    }
+
+    if let Some(tests) = &manifest.unit_tests {
+        html.push_str(&format!(
+            "<h2>Unit tests</h2>\n<p class=\"{}\">{} run, {} failed, {} errors, {} skipped</p>\n",
+            if tests.passed() { "ok" } else { "failed" },
+            tests.tests,
+            tests.failures,
+            tests.errors,
+            tests.skipped,
+        ));
+        let failed: Vec<_> = tests.cases.iter().filter(|c| matches!(c.status, TestStatus::Failed | TestStatus::Error)).collect();
+        if !failed.is_empty() {
+            html.push_str("<ul>\n");
+            for case in failed {
+                html.push_str(&format!(
+                    "<li class=\"reason\">{}::{}: {}</li>\n",
+                    escape(&case.suite),
+                    escape(&case.name),
+                    escape(case.message.as_deref().unwrap_or("")),
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+    }
+
+    // Warnings are listed per combination; errors already show up as failure reason
+    let with_warnings = manifest.combinations.iter().filter(|c| c.warnings > 0);
+    let mut warnings_html = String::new();
+    for combination in with_warnings {
+        warnings_html.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape(&combination.combination)));
+        for diagnostic in combination
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning && d.tool().is_none())
+        {
+            warnings_html.push_str(&diagnostic_item(diagnostic));
+        }
+        warnings_html.push_str("</ul>\n");
+    }
+    if !warnings_html.is_empty() {
+        html.push_str("<h2>Warnings</h2>\n");
+        html.push_str(&warnings_html);
+    }
+
+    // Findings of cppcheck, clang-tidy and analysis hooks, whatever their severity
+    let mut findings_html = String::new();
+    for combination in &manifest.combinations {
+        let findings: Vec<&Diagnostic> = combination.diagnostics.iter().filter(|d| d.tool().is_some()).collect();
+        if findings.is_empty() {
+            continue;
+        }
+        findings_html.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape(&combination.combination)));
+        for diagnostic in findings {
+            findings_html.push_str(&diagnostic_item(diagnostic));
+        }
+        findings_html.push_str("</ul>\n");
+    }
+    if !findings_html.is_empty() {
+        html.push_str("<h2>Analysis findings</h2>\n");
+        html.push_str(&findings_html);
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Writes `report.html` into the output directory.
+pub fn write_html(manifest: &RunManifest, output_dir: &Path) -> Result<(), String> {
+    let path = output_dir.join(REPORT_FILE);
+    fs::write(&path, render_html(manifest))
+        .map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+/// One combination as exported by `export_run_report`; flat so it maps
+/// directly onto a CSV row.
+#[derive(Debug, Serialize)]
+pub struct ReportRow {
+    pub run_id: String,
+    pub combination: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub text: Option<u64>,
+    pub data: Option<u64>,
+    pub bss: Option<u64>,
+    pub flash: Option<u64>,
+    pub ram: Option<u64>,
+    pub bin_size: Option<u64>,
+    pub duration_ms: u64,
+    pub artifact: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonReport<'a> {
+    run_id: &'a str,
+    project_path: &'a str,
+    config_name: Option<&'a str>,
+    started_at: &'a str,
+    finished_at: &'a str,
+    success: bool,
+    cancelled: bool,
+    combinations: Vec<ReportRow>,
+}
+
+pub fn report_rows(manifest: &RunManifest) -> Vec<ReportRow> {
+    manifest
+        .combinations
+        .iter()
+        .map(|c| ReportRow {
+            run_id: manifest.run_id.clone(),
+            combination: c.combination.clone(),
+            success: c.success,
+            exit_code: c.exit_code,
+            errors: c.errors,
+            warnings: c.warnings,
+            text: c.sizes.map(|s| s.text),
+            data: c.sizes.map(|s| s.data),
+            bss: c.sizes.map(|s| s.bss),
+            flash: c.sizes.map(|s| s.flash),
+            ram: c.sizes.map(|s| s.ram),
+            bin_size: c.artifact_size,
+            duration_ms: combination_duration(manifest, &c.combination),
+            artifact: c.artifact.clone(),
+            failure_reason: c.failure_reason.clone(),
+        })
+        .collect()
+}
+
+pub fn render_csv(manifest: &RunManifest) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in report_rows(manifest) {
+        writer.serialize(row).map_err(|e| tr!("report.csv_failed", e))?;
+    }
+    let bytes = writer.into_inner().map_err(|e| tr!("report.csv_failed", e))?;
+    String::from_utf8(bytes).map_err(|e| tr!("report.csv_failed", e))
+}
+
+pub fn render_json(manifest: &RunManifest) -> Result<String, String> {
+    let report = JsonReport {
+        run_id: &manifest.run_id,
+        project_path: &manifest.project_path,
+        config_name: manifest.config_name.as_deref(),
+        started_at: &manifest.started_at,
+        finished_at: &manifest.finished_at,
+        success: manifest.success,
+        cancelled: manifest.cancelled,
+        combinations: report_rows(manifest),
+    };
+    serde_json::to_string_pretty(&report).map_err(|e| tr!("report.serialize_failed", e))
+}
+
+/// Combination outcomes, sizes and durations of a run as CSV or JSON.
+///
+/// The report is returned; with `destination` it is also written to that file.
+#[command]
+pub async fn export_run_report(
+    window: Window,
+    run_id: String,
+    format: ReportFormat,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let manifest = RunManifest::load_archived(&window, &run_id)?;
+    let report = match format {
+        ReportFormat::Csv => render_csv(&manifest)?,
+        ReportFormat::Json => render_json(&manifest)?,
+    };
+    if let Some(destination) = destination {
+        fs::write(&destination, &report)
+            .map_err(|e| tr!("fs.write", destination, e))?;
+    }
+    Ok(report)
+}
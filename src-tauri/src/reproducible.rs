@@ -0,0 +1,182 @@
+use crate::builder::build_project;
+use crate::git_info::GitInfo;
+use crate::i18n::tr;
+use crate::manifest::RunManifest;
+use crate::models::BuildConfig;
+use stm32_builder_core::selection::Selection;
+use chrono::DateTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{command, Window};
+
+/// How many earlier runs are searched for one built from the same commit.
+pub const PREVIOUS_RUNS_SEARCHED: u32 = 20;
+
+lazy_static! {
+    static ref PREFIX_MAP_FLAG: Regex = Regex::new(r#"-f(?:file|debug|macro)-prefix-map=[^\s"<]+"#).unwrap();
+}
+
+/// What a reproducible run was built with and whether its artifacts
+/// matched the previous reproducible run of the same commit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReproducibilityReport {
+    pub source_date_epoch: i64,
+    /// `-ffile-prefix-map` style flags found in the project's `.cproject`.
+    pub prefix_map_flags: Vec<String>,
+    /// Run the artifacts were compared with.
+    pub compared_run: Option<String>,
+    pub identical: Vec<String>,
+    /// Combinations whose `.bin` differs from the compared run's.
+    pub differing: Vec<String>,
+}
+
+/// Outcome of building one combination twice from scratch.
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub combination: Option<String>,
+    /// The two runs, in build order.
+    pub run_ids: Vec<String>,
+    pub deterministic: bool,
+    /// Sizes of the two `.bin` files.
+    pub sizes: [u64; 2],
+    /// Offset of the first differing byte of the two `.bin` files.
+    pub first_difference: Option<u64>,
+}
+
+/// Commit time of the project, or 0 outside a repository.
+pub fn source_date_epoch(git: Option<&GitInfo>) -> i64 {
+    git.map(|g| g.commit_time).unwrap_or(0)
+}
+
+/// Replacements for `__DATE__`/`__TIME__` derived from the epoch, in the
+/// compiler's format (`"Oct 16 2026"`, `"18:51:54"`).
+pub fn defines(source_date_epoch: i64) -> String {
+    let time = DateTime::from_timestamp(source_date_epoch, 0).unwrap_or_default();
+    format!(
+        "#define BUILD_DATE \"{}\"\n#define BUILD_TIME \"{}\"\n",
+        time.format("%b %e %Y"),
+        time.format("%H:%M:%S")
+    )
+}
+
+/// Path-prefix normalization flags set in the project's `.cproject`;
+/// without them absolute paths end up in `__FILE__` and debug info.
+pub fn prefix_map_flags(project_path: &Path) -> Vec<String> {
+    let content = fs::read_to_string(project_path.join(".cproject")).unwrap_or_default();
+    let mut flags: Vec<String> = PREFIX_MAP_FLAG
+        .find_iter(&content)
+        .map(|m| m.as_str().to_string())
+        .collect();
+    flags.sort();
+    flags.dedup();
+    flags
+}
+
+fn comparable(current: &RunManifest, previous: &RunManifest) -> bool {
+    let same_commit = match (&current.git, &previous.git) {
+        (Some(a), Some(b)) => a.hash == b.hash && !a.dirty && !b.dirty,
+        _ => false,
+    };
+    same_commit
+        && previous.reproducibility.is_some()
+        && previous.config_name == current.config_name
+        && previous.version == current.version
+}
+
+/// Compares the run's artifacts with those of the latest earlier
+/// reproducible run of the same clean commit. `previous_runs` are run ids,
+/// newest first.
+pub fn compare_with_previous(window: &Window, manifest: &RunManifest, previous_runs: &[String]) -> ReproducibilityReport {
+    let mut report = manifest.reproducibility.clone().unwrap_or_default();
+    let previous = previous_runs
+        .iter()
+        .filter(|run_id| **run_id != manifest.run_id)
+        .filter_map(|run_id| RunManifest::load_archived(window, run_id).ok())
+        .find(|previous| comparable(manifest, previous));
+    let Some(previous) = previous else {
+        return report;
+    };
+
+    report.compared_run = Some(previous.run_id.clone());
+    for combination in &manifest.combinations {
+        let Some(artifact) = &combination.artifact else { continue };
+        let Some(earlier) = previous
+            .combinations
+            .iter()
+            .find(|c| c.combination == combination.combination)
+            .and_then(|c| c.artifact.as_ref())
+        else {
+            continue;
+        };
+        match (fs::read(artifact), fs::read(earlier)) {
+            (Ok(a), Ok(b)) if a == b => report.identical.push(combination.combination.clone()),
+            (Ok(_), Ok(_)) => report.differing.push(combination.combination.clone()),
+            // The earlier output directory may have been cleaned up
+            _ => {}
+        }
+    }
+    report
+}
+
+/// Builds one combination twice, each time with a clean build in an empty
+/// workspace, and compares the two binaries byte for byte.
+///
+/// Versioning and tagging are off for both builds, since a bumped build
+/// number alone would make them differ.
+#[command]
+pub async fn verify_reproducibility(
+    window: Window,
+    config: BuildConfig,
+    selection: Selection,
+) -> Result<VerificationReport, String> {
+    let mut artifacts = Vec::new();
+    let mut run_ids = Vec::new();
+    let mut combination = None;
+    for _ in 0..2 {
+        let workspace = tempfile::Builder::new()
+            .prefix("stm32guibuilder_verify_")
+            .tempdir()
+            .map_err(|e| tr!("verify.workspace_failed", e))?;
+        let build = BuildConfig {
+            workspace_path: workspace.path().display().to_string(),
+            clean_build: true,
+            selected_combinations: Some(vec![selection.clone()]),
+            versioning: Some(false),
+            git_tag: None,
+            webhooks: None,
+            notifications: None,
+            script: None,
+            dry_run: None,
+            timeout_secs: None,
+            ..config.clone()
+        };
+        let result = build_project(window.clone(), build).await.map_err(|e| e.to_string())?;
+        run_ids.push(result.run_id.clone());
+        if !result.success {
+            return Err(tr!("verify.build_failed", result.run_id, result.failure_reason.unwrap_or(result.result)));
+        }
+        let built = result.combinations.into_iter().next().ok_or_else(|| tr!("combinations.none"))?;
+        let artifact = built.artifact.ok_or_else(|| tr!("verify.no_artifact", built.combination))?;
+        let data = fs::read(&artifact).map_err(|e| tr!("fs.read", artifact, e))?;
+        combination = Some(built.combination);
+        artifacts.push(data);
+    }
+
+    let (first, second) = (&artifacts[0], &artifacts[1]);
+    let first_difference = first
+        .iter()
+        .zip(second.iter())
+        .position(|(a, b)| a != b)
+        .or_else(|| (first.len() != second.len()).then(|| first.len().min(second.len())))
+        .map(|offset| offset as u64);
+    Ok(VerificationReport {
+        combination,
+        run_ids,
+        deterministic: first_difference.is_none(),
+        sizes: [first.len() as u64, second.len() as u64],
+        first_difference,
+    })
+}
@@ -0,0 +1,74 @@
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashSet;
+use sysinfo::{Pid, ProcessesToUpdate, System};
+use crate::logging::emit_to_window;
+use tauri::Window;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Payload of the `build-resources` event: combined usage of the CubeIDE
+/// process and everything it spawned (make, gcc, ...).
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceSample {
+    pub run_id: String,
+    pub combination: String,
+    pub timestamp: String,
+    pub pid: u32,
+    pub process_count: usize,
+    /// Sum over the process tree; can exceed 100 on multi-core machines.
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+}
+
+/// Samples the process tree rooted at `pid` until the returned task is aborted.
+pub fn spawn_monitor(window: Window, run_id: String, combination: String, pid: u32) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut system = System::new();
+        let mut interval = time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            system.refresh_processes(ProcessesToUpdate::All, true);
+            let tree = process_tree(&system, Pid::from_u32(pid));
+            if tree.is_empty() {
+                break;
+            }
+            let (cpu_percent, memory_bytes) = tree
+                .iter()
+                .filter_map(|p| system.process(*p))
+                .fold((0.0, 0), |(cpu, mem), p| (cpu + p.cpu_usage(), mem + p.memory()));
+            let sample = ResourceSample {
+                run_id: run_id.clone(),
+                combination: combination.clone(),
+                timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+                pid,
+                process_count: tree.len(),
+                cpu_percent,
+                memory_bytes,
+            };
+            emit_to_window(&window, "build-resources", &sample);
+        }
+    })
+}
+
+/// `root` and all of its descendants that are still alive.
+fn process_tree(system: &System, root: Pid) -> Vec<Pid> {
+    if system.process(root).is_none() {
+        return Vec::new();
+    }
+    let mut tree = vec![root];
+    let mut seen: HashSet<Pid> = tree.iter().copied().collect();
+    let mut index = 0;
+    while index < tree.len() {
+        let parent = tree[index];
+        for (pid, process) in system.processes() {
+            if process.parent() == Some(parent) && seen.insert(*pid) {
+                tree.push(*pid);
+            }
+        }
+        index += 1;
+    }
+    tree
+}
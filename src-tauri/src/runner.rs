@@ -0,0 +1,291 @@
+use crate::i18n::tr;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::Stdio;
+use stm32_builder_core::runner::{ProcessHandle, ProcessRunner, ProcessSpec, RunningProcess};
+use tokio::process::{Child, Command};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriorityLevel {
+    Idle,
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl PriorityLevel {
+    /// Nice value; raising the priority needs privileges and is skipped
+    /// without them.
+    #[cfg(unix)]
+    fn nice(self) -> i32 {
+        match self {
+            PriorityLevel::Idle => 19,
+            PriorityLevel::Low => 10,
+            PriorityLevel::Normal => 0,
+            PriorityLevel::High => -5,
+        }
+    }
+
+    /// Windows priority class.
+    #[cfg(windows)]
+    fn creation_flag(self) -> u32 {
+        match self {
+            PriorityLevel::Idle => 0x00000040,   // IDLE_PRIORITY_CLASS
+            PriorityLevel::Low => 0x00004000,    // BELOW_NORMAL_PRIORITY_CLASS
+            PriorityLevel::Normal => 0x00000020, // NORMAL_PRIORITY_CLASS
+            PriorityLevel::High => 0x00008000,   // ABOVE_NORMAL_PRIORITY_CLASS
+        }
+    }
+}
+
+/// Scheduling of the build processes, so a matrix building in the
+/// background leaves the machine usable. CubeIDE passes it on to make and
+/// the compilers it starts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessPriority {
+    #[serde(default)]
+    pub level: PriorityLevel,
+    /// CPUs the processes may run on, numbered from 0; all when empty.
+    /// Applied on Linux and Windows only; other systems, macOS among them,
+    /// have no affinity call and ignore it with a warning.
+    #[serde(default)]
+    pub cpus: Vec<usize>,
+}
+
+/// Runs real processes, each in its own process group so cancelling also
+/// reaches the processes CubeIDE starts.
+#[derive(Clone, Debug, Default)]
+pub struct SystemRunner {
+    pub priority: ProcessPriority,
+}
+
+impl ProcessRunner for SystemRunner {
+    fn spawn(&self, spec: &ProcessSpec) -> io::Result<RunningProcess> {
+        let mut command = Command::new(&spec.program);
+        command
+            .args(&spec.args)
+            .kill_on_drop(true)
+            .current_dir(&spec.cwd)
+            .envs(spec.envs.iter().cloned())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        // Platform-specific settings
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            // 0x08000000 = CREATE_NO_WINDOW, 0x00000200 = CREATE_NEW_PROCESS_GROUP;
+            // suspended until it is in its job
+            command.creation_flags(
+                0x08000000 | 0x00000200 | crate::job_object::CREATE_SUSPENDED | self.priority.level.creation_flag(),
+            );
+        }
+
+        // Best effort: a build at the default priority beats none
+        let mut warnings = Vec::new();
+        // Applied between fork and exec, so the build and everything it
+        // starts run with it from the first instruction
+        #[cfg(unix)]
+        let (failures, report) = {
+            let nice = self.priority.level.nice();
+            #[cfg(target_os = "linux")]
+            let affinity = (!self.priority.cpus.is_empty()).then(|| unsafe {
+                // Built here: the child must not allocate
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                for cpu in &self.priority.cpus {
+                    libc::CPU_SET(*cpu, &mut set);
+                }
+                set
+            });
+            #[cfg(not(target_os = "linux"))]
+            if !self.priority.cpus.is_empty() {
+                warnings.push(tr!("priority.affinity_unsupported"));
+            }
+            let (failures, report) = FailurePipe::new()?;
+            unsafe {
+                command.pre_exec(move || {
+                    libc::setpgid(0, 0);
+                    if nice != 0 && libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                        report.send(SchedulingFailure::Nice);
+                    }
+                    #[cfg(target_os = "linux")]
+                    if let Some(set) = &affinity
+                        && libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), set) == -1
+                    {
+                        report.send(SchedulingFailure::Affinity);
+                    }
+                    Ok(())
+                });
+            }
+            (failures, report)
+        };
+
+        let spawned = command.spawn();
+        // The child wrote its failures before exec, which spawn waits for
+        #[cfg(unix)]
+        for (failure, error) in failures.receive(report) {
+            warnings.push(match failure {
+                SchedulingFailure::Nice => tr!("priority.nice_failed", self.priority.level.nice(), error),
+                SchedulingFailure::Affinity => tr!("priority.affinity_failed", error),
+            });
+        }
+        let mut child = spawned?;
+
+        // Children started from here on inherit the mask
+        #[cfg(windows)]
+        if !self.priority.cpus.is_empty()
+            && let Some(handle) = child.raw_handle()
+        {
+            // A mask covers one processor group of up to 64 CPUs
+            let mask = self.priority.cpus.iter().fold(0usize, |mask, cpu| mask | 1usize.checked_shl(*cpu as u32).unwrap_or(0));
+            if unsafe { winapi::um::winbase::SetProcessAffinityMask(handle as _, mask) } == 0 {
+                warnings.push(tr!("priority.affinity_failed", io::Error::last_os_error()));
+            }
+        }
+        // Without a job (the app's own job may forbid nesting) cancelling
+        // falls back to taskkill
+        #[cfg(windows)]
+        let job = child
+            .id()
+            .zip(child.raw_handle())
+            .and_then(|(pid, handle)| crate::job_object::JobObject::assign(pid, handle).ok());
+        // Only now may it start anything, which then joins the job too
+        #[cfg(windows)]
+        if let Err(e) = child.id().ok_or_else(|| io::Error::other("process exited")).and_then(crate::job_object::resume) {
+            child.start_kill().ok();
+            return Err(e);
+        }
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("stdout not captured"))?;
+        let stderr = child.stderr.take().ok_or_else(|| io::Error::other("stderr not captured"))?;
+        let process = SystemProcess {
+            child,
+            #[cfg(windows)]
+            job,
+        };
+        Ok(RunningProcess { stdout: Box::new(stdout), stderr: Box::new(stderr), handle: Box::new(process), warnings })
+    }
+}
+
+/// Scheduling call that failed in the child before exec.
+#[cfg(unix)]
+#[derive(Clone, Copy)]
+enum SchedulingFailure {
+    Nice = 1,
+    Affinity = 2,
+}
+
+/// Pipe the child reports its [`SchedulingFailure`]s through, as one kind
+/// byte and the errno each. Both ends close on exec.
+#[cfg(unix)]
+struct FailurePipe(std::fs::File);
+
+#[cfg(unix)]
+#[derive(Clone, Copy)]
+struct FailureReport(libc::c_int);
+
+#[cfg(unix)]
+impl FailurePipe {
+    fn new() -> io::Result<(FailurePipe, FailureReport)> {
+        use std::os::fd::FromRawFd;
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        for fd in fds {
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+        // Only what was written before exec is read
+        unsafe { libc::fcntl(fds[0], libc::F_SETFL, libc::O_NONBLOCK) };
+        Ok((FailurePipe(unsafe { std::fs::File::from_raw_fd(fds[0]) }), FailureReport(fds[1])))
+    }
+
+    /// The failures sent; closes the parent's write end first.
+    fn receive(mut self, report: FailureReport) -> Vec<(SchedulingFailure, io::Error)> {
+        use std::io::Read;
+        unsafe { libc::close(report.0) };
+        let mut data = Vec::new();
+        self.0.read_to_end(&mut data).ok();
+        data.chunks_exact(5)
+            .filter_map(|record| {
+                let failure = match record[0] {
+                    1 => SchedulingFailure::Nice,
+                    2 => SchedulingFailure::Affinity,
+                    _ => return None,
+                };
+                let errno = i32::from_ne_bytes([record[1], record[2], record[3], record[4]]);
+                Some((failure, io::Error::from_raw_os_error(errno)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(unix)]
+impl FailureReport {
+    /// Called in the child: only async-signal-safe calls.
+    fn send(self, failure: SchedulingFailure) {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0).to_ne_bytes();
+        let record = [failure as u8, errno[0], errno[1], errno[2], errno[3]];
+        unsafe { libc::write(self.0, record.as_ptr().cast(), record.len()) };
+    }
+}
+
+/// Process started by [`SystemRunner`]. On Windows it holds the job of the
+/// process tree, which takes the tree down when dropped.
+struct SystemProcess {
+    child: Child,
+    #[cfg(windows)]
+    job: Option<std::sync::Arc<crate::job_object::JobObject>>,
+}
+
+#[async_trait]
+impl ProcessHandle for SystemProcess {
+    fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    async fn wait(&mut self) -> io::Result<i32> {
+        Ok(self.child.wait().await?.code().unwrap_or(-1))
+    }
+
+    async fn kill(&mut self) -> io::Result<()> {
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            // The process itself is killed below either way
+            job.terminate().ok();
+        }
+        self.child.kill().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn lower_priority_and_affinity_apply_without_warnings() {
+        let runner = SystemRunner { priority: ProcessPriority { level: PriorityLevel::Low, cpus: vec![0] } };
+        let spec = ProcessSpec { program: "sleep".into(), args: vec!["0.2".into()], cwd: std::env::temp_dir(), envs: Vec::new() };
+        let mut process = runner.spawn(&spec).unwrap();
+        assert!(process.warnings.is_empty(), "{:?}", process.warnings);
+        // Field 19 of stat, counted after the parenthesised command name
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", process.handle.id().unwrap())).unwrap();
+        let nice = stat.rsplit(')').next().unwrap().split_whitespace().nth(16).unwrap();
+        assert_eq!(nice, "10");
+        assert_eq!(process.handle.wait().await.unwrap(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn affinity_failure_in_the_child_is_reported() {
+        let cpus = vec![libc::CPU_SETSIZE as usize - 1];
+        let runner = SystemRunner { priority: ProcessPriority { level: PriorityLevel::Normal, cpus } };
+        let spec = ProcessSpec { program: "true".into(), args: Vec::new(), cwd: std::env::temp_dir(), envs: Vec::new() };
+        let mut process = runner.spawn(&spec).unwrap();
+        assert_eq!(process.warnings.len(), 1, "{:?}", process.warnings);
+        assert_eq!(process.handle.wait().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::i18n::tr;
+use crate::models::{BuildResult, CombinationResult};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Stops runaway loops in user scripts
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// Functions a run script may define; each gets a map describing the run
+/// or the combination.
+pub const BEFORE_RUN: &str = "before_run";
+pub const BEFORE_COMBINATION: &str = "before_combination";
+pub const AFTER_COMBINATION: &str = "after_combination";
+pub const AFTER_RUN: &str = "after_run";
+
+#[derive(Debug, PartialEq)]
+pub enum HookOutcome {
+    Continue,
+    /// The hook returned `false`: refuse the run or skip the combination.
+    Skip,
+}
+
+/// Rhai script of a run (`BuildConfig.script`). Its top-level statements
+/// run once when it is loaded, so variables set there are shared by the
+/// hooks; `print` goes to the build log and throwing fails the run (or,
+/// from `after_combination`, the combination).
+pub struct RunScript {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    output: Arc<Mutex<Vec<String>>>,
+}
+
+pub fn map(entries: Vec<(&str, Dynamic)>) -> Map {
+    entries.into_iter().map(|(key, value)| (key.into(), value)).collect()
+}
+
+fn text(value: &str) -> Dynamic {
+    Dynamic::from(value.to_string())
+}
+
+impl RunScript {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let printed = output.clone();
+        engine.on_print(move |line| printed.lock().unwrap().push(line.to_string()));
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| tr!("script.invalid", path.display(), e))?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| tr!("script.invalid", path.display(), e))?;
+        Ok(RunScript { engine, ast, scope, output })
+    }
+
+    /// Calls `hook` if the script defines it.
+    pub fn call(&mut self, hook: &str, context: Map) -> Result<HookOutcome, String> {
+        if !self.ast.iter_functions().any(|f| f.name == hook) {
+            return Ok(HookOutcome::Continue);
+        }
+        // The top-level statements already ran in `load`
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, hook, (context,))
+            .map_err(|e| tr!("script.failed", hook, e))?;
+        Ok(if result.as_bool() == Ok(false) { HookOutcome::Skip } else { HookOutcome::Continue })
+    }
+
+    /// Lines printed since the last call.
+    pub fn take_output(&self) -> Vec<String> {
+        std::mem::take(&mut *self.output.lock().unwrap())
+    }
+}
+
+/// `run_id`, `project_path`, `output_dir`, `config_name` and `combinations`
+/// (their count).
+pub fn run_context(run_id: &str, project_path: &Path, output_dir: &Path, config_name: &str, combinations: usize) -> Map {
+    map(vec![
+        ("run_id", text(run_id)),
+        ("project_path", text(&project_path.display().to_string())),
+        ("output_dir", text(&output_dir.display().to_string())),
+        ("config_name", text(config_name)),
+        ("combinations", Dynamic::from(combinations as i64)),
+    ])
+}
+
+/// `run_id`, `name`, `combo_dir` and `values` (setting id -> value).
+pub fn combination_context(run_id: &str, name: &str, combo_dir: &Path, combination: &[(String, String)]) -> Map {
+    let values = combination.iter().map(|(id, value)| (id.as_str(), text(value))).collect();
+    map(vec![
+        ("run_id", text(run_id)),
+        ("name", text(name)),
+        ("combo_dir", text(&combo_dir.display().to_string())),
+        ("values", Dynamic::from(map(values))),
+    ])
+}
+
+/// Adds `success`, `errors`, `warnings`, `artifact` and `failure_reason`
+/// (empty when unset) to a combination context.
+pub fn with_result(mut context: Map, result: &CombinationResult) -> Map {
+    context.extend(map(vec![
+        ("success", Dynamic::from(result.success)),
+        ("errors", Dynamic::from(result.errors as i64)),
+        ("warnings", Dynamic::from(result.warnings as i64)),
+        ("artifact", text(result.artifact.as_deref().unwrap_or_default())),
+        ("failure_reason", text(result.failure_reason.as_deref().unwrap_or_default())),
+    ]));
+    context
+}
+
+/// Adds `success`, `cancelled`, `total_errors` and `total_warnings` to a run context.
+pub fn with_run_result(mut context: Map, result: &BuildResult) -> Map {
+    context.extend(map(vec![
+        ("success", Dynamic::from(result.success)),
+        ("cancelled", Dynamic::from(result.cancelled)),
+        ("total_errors", Dynamic::from(result.total_errors as i64)),
+        ("total_warnings", Dynamic::from(result.total_warnings as i64)),
+    ]));
+    context
+}
@@ -0,0 +1,146 @@
+use crate::i18n::tr;
+use stm32_builder_core::config::BuildSettingsConfig;
+use stm32_builder_core::selection::{normalize_selection, Selection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::command;
+
+fn read_csv(content: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    // QA spreadsheets exported from Excel often use `;`
+    let delimiter = match content.lines().next() {
+        Some(header) if header.matches(';').count() > header.matches(',').count() => b';',
+        _ => b',',
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    reader
+        .deserialize::<HashMap<String, String>>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tr!("selection.invalid_csv", e))
+}
+
+fn read_json(content: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> =
+        serde_json::from_str(content).map_err(|e| tr!("selection.invalid_json", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .filter_map(|(key, value)| match value {
+                    serde_json::Value::String(s) => Some((key, s)),
+                    serde_json::Value::Number(n) => Some((key, n.to_string())),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Reads a list of combinations from a CSV file (header row with setting
+/// ids, short names or labels) or a JSON array of objects.
+///
+/// The result can be passed as `selectedCombinations` in `BuildConfig`.
+#[command]
+pub async fn import_combination_selection(path: String) -> Result<Vec<Selection>, String> {
+    let content = fs::read_to_string(&path).map_err(|e| tr!("fs.read", path, e))?;
+    let is_json = Path::new(&path)
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    let rows = if is_json { read_json(&content)? } else { read_csv(&content)? };
+
+    let settings_config = BuildSettingsConfig::load()?;
+    normalize_selection(&settings_config, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> BuildSettingsConfig {
+        serde_yaml::from_str(
+            r#"
+version: "1"
+build_settings:
+  - id: board
+    label: Board
+    value: brd
+    description: ""
+    field_type: select
+    format: ""
+    define: null
+    options:
+      - { label: F4, value: "4", define: BOARD_F4, description: null }
+      - { label: H7, value: "7", define: BOARD_H7, description: null }
+    validation: null
+    exclusive: null
+    min_selected: null
+  - id: clock
+    label: Clock MHz
+    value: clk
+    description: ""
+    field_type: range
+    format: ""
+    define: CLOCK_MHZ
+    options: null
+    validation: { min: 8, max: 180 }
+    exclusive: null
+    min_selected: null
+"#,
+        )
+        .unwrap()
+    }
+
+    fn selection(pairs: &[(&str, &str)]) -> Selection {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn csv_headers_may_be_ids_short_names_or_labels() {
+        let rows = read_csv("board;clk;Clock MHz\n4;168;\n7;;180\n").unwrap();
+        let selections = normalize_selection(&settings(), rows).unwrap();
+        assert_eq!(
+            selections,
+            vec![
+                selection(&[("board", "4"), ("clock", "168")]),
+                selection(&[("board", "7"), ("clock", "180")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_numbers_are_read_as_values() {
+        let rows = read_json(r#"[{"brd": "7", "clock": 72}, {"Board": "4"}]"#).unwrap();
+        let selections = normalize_selection(&settings(), rows).unwrap();
+        assert_eq!(
+            selections,
+            vec![selection(&[("board", "7"), ("clock", "72")]), selection(&[("board", "4")])]
+        );
+    }
+
+    #[test]
+    fn unknown_names_and_values_are_errors() {
+        let rows = read_csv("board,voltage\n4,3.3\n").unwrap();
+        let error = normalize_selection(&settings(), rows).unwrap_err();
+        assert!(error.contains("Row 1") && error.contains("voltage"), "{}", error);
+
+        let rows = read_csv("board\n4\nG0\n").unwrap();
+        let error = normalize_selection(&settings(), rows).unwrap_err();
+        assert!(error.contains("Row 2") && error.contains("G0"), "{}", error);
+
+        assert!(read_json(r#"{"board": "4"}"#).is_err());
+    }
+
+    #[test]
+    fn empty_rows_are_skipped() {
+        let rows = read_csv("board;clock\n;\n4;8\n\n ; \n").unwrap();
+        let selections = normalize_selection(&settings(), rows).unwrap();
+        assert_eq!(selections, vec![selection(&[("board", "4"), ("clock", "8")])]);
+
+        let rows = read_json(r#"[{}, {"board": ""}]"#).unwrap();
+        assert!(normalize_selection(&settings(), rows).unwrap().is_empty());
+    }
+}
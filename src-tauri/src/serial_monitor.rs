@@ -0,0 +1,159 @@
+use crate::i18n::tr;
+use crate::logging::emit_to_window;
+use chrono::Local;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serialport::SerialPortType;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{command, Window};
+
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    // Stop flags of the reader threads, by port name
+    static ref OPEN_PORTS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SerialPortInfo {
+    pub name: String,
+    /// `usb`, `pci`, `bluetooth` or `unknown`.
+    pub kind: String,
+    pub product: Option<String>,
+    pub manufacturer: Option<String>,
+    pub serial_number: Option<String>,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+}
+
+/// Payload of the `serial-line` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct SerialLine {
+    pub port: String,
+    pub timestamp: String,
+    pub line: String,
+}
+
+/// Payload of the `serial-closed` event; `error` is set when the port was
+/// lost (e.g. the board was unplugged) rather than closed by the user.
+#[derive(Clone, Debug, Serialize)]
+pub struct SerialClosed {
+    pub port: String,
+    pub error: Option<String>,
+}
+
+/// Serial ports present on the system; ST-LINK virtual COM ports show up
+/// as USB ports with vendor id 0x0483.
+#[command]
+pub async fn list_serial_ports() -> Result<Vec<SerialPortInfo>, String> {
+    let ports = serialport::available_ports().map_err(|e| tr!("serial.list_failed", e))?;
+    Ok(ports
+        .into_iter()
+        .map(|port| {
+            let mut info = SerialPortInfo {
+                name: port.port_name,
+                kind: "unknown".to_string(),
+                product: None,
+                manufacturer: None,
+                serial_number: None,
+                vid: None,
+                pid: None,
+            };
+            match port.port_type {
+                SerialPortType::UsbPort(usb) => {
+                    info.kind = "usb".to_string();
+                    info.product = usb.product;
+                    info.manufacturer = usb.manufacturer;
+                    info.serial_number = usb.serial_number;
+                    info.vid = Some(usb.vid);
+                    info.pid = Some(usb.pid);
+                }
+                SerialPortType::PciPort => info.kind = "pci".to_string(),
+                SerialPortType::BluetoothPort => info.kind = "bluetooth".to_string(),
+                SerialPortType::Unknown => {}
+            }
+            info
+        })
+        .collect())
+}
+
+fn emit_line(window: &Window, port: &str, bytes: &[u8]) {
+    let line = String::from_utf8_lossy(bytes).trim_end_matches('\r').to_string();
+    emit_to_window(
+        window,
+        "serial-line",
+        &SerialLine {
+            port: port.to_string(),
+            timestamp: Local::now().format("%H:%M:%S%.3f").to_string(),
+            line,
+        },
+    );
+}
+
+fn read_loop(window: Window, name: String, mut port: Box<dyn serialport::SerialPort>, stop: Arc<AtomicBool>) {
+    let mut buffer = [0u8; 1024];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut error = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        match port.read(&mut buffer) {
+            Ok(0) => {}
+            Ok(n) => {
+                pending.extend_from_slice(&buffer[..n]);
+                while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    emit_line(&window, &name, &line[..line.len() - 1]);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => {
+                error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+    if !pending.is_empty() {
+        emit_line(&window, &name, &pending);
+    }
+
+    // Only drop our own entry; the port may have been reopened meanwhile
+    let mut open_ports = OPEN_PORTS.lock().unwrap();
+    if open_ports.get(&name).map(|flag| Arc::ptr_eq(flag, &stop)).unwrap_or(false) {
+        open_ports.remove(&name);
+    }
+    drop(open_ports);
+    emit_to_window(&window, "serial-closed", &SerialClosed { port: name, error });
+}
+
+/// Opens a serial port and emits every received line as a `serial-line`
+/// event until `close_serial_port` is called or the port disappears.
+#[command]
+pub async fn open_serial_port(window: Window, port: String, baud_rate: u32) -> Result<(), String> {
+    if OPEN_PORTS.lock().unwrap().contains_key(&port) {
+        return Err(tr!("serial.already_open", port));
+    }
+    let serial = serialport::new(&port, baud_rate)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|e| tr!("serial.open_failed", port, e))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    OPEN_PORTS.lock().unwrap().insert(port.clone(), stop.clone());
+    std::thread::spawn(move || read_loop(window, port, serial, stop));
+    Ok(())
+}
+
+#[command]
+pub async fn close_serial_port(port: String) -> Result<(), String> {
+    match OPEN_PORTS.lock().unwrap().remove(&port) {
+        Some(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(tr!("serial.not_open", port)),
+    }
+}
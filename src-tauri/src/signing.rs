@@ -0,0 +1,117 @@
+use crate::analysis::run_tool;
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extension of the DER signature written next to each `.bin`.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+/// Extension of the PEM certificate chain written next to each `.bin`.
+pub const CHAIN_EXTENSION: &str = "chain.pem";
+
+const DEFAULT_DIGEST: &str = "sha256";
+
+/// Signing of every `.bin` with a key whose certificate chain is shipped
+/// alongside, for secure bootloaders that validate certificates rather
+/// than a raw public key. Signatures are made with OpenSSL.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SigningSettings {
+    /// PEM private key, relative to the project, or a `pkcs11:` URI of a
+    /// key on a token or HSM, used through OpenSSL's `pkcs11` engine.
+    pub key: String,
+    /// PEM certificates, relative to the project: the key's own first,
+    /// then its issuers.
+    pub certificates: Vec<String>,
+    /// `sha256` by default.
+    pub digest: Option<String>,
+    /// Key password or token PIN as OpenSSL's `-passin` takes it, e.g.
+    /// `env:FW_SIGNING_PIN`, so the secret itself is never stored.
+    #[serde(rename = "passSource")]
+    pub pass_source: Option<String>,
+    /// `openssl` on the PATH by default.
+    pub openssl: Option<String>,
+}
+
+impl SigningSettings {
+    fn on_token(&self) -> bool {
+        self.key.starts_with("pkcs11:")
+    }
+
+    /// Arguments selecting the key; the option names differ between
+    /// OpenSSL commands (`-sign`/`-keyform` for `dgst`, `-in`/`-inform`
+    /// for `pkey`).
+    fn key_args(&self, project_path: &Path, key_option: &str, form_option: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.on_token() {
+            args.extend(["-engine".to_string(), "pkcs11".to_string(), form_option.to_string(), "engine".to_string()]);
+        }
+        args.push(key_option.to_string());
+        args.push(if self.on_token() {
+            self.key.clone()
+        } else {
+            project_path.join(&self.key).display().to_string()
+        });
+        if let Some(source) = &self.pass_source {
+            args.extend(["-passin".to_string(), source.clone()]);
+        }
+        args
+    }
+
+    fn certificate_paths(&self, project_path: &Path) -> Vec<PathBuf> {
+        self.certificates.iter().map(|c| project_path.join(c)).collect()
+    }
+
+    async fn openssl(&self, args: &[String], project_path: &Path) -> Result<String, String> {
+        let program = self.openssl.as_deref().unwrap_or("openssl");
+        let (exit_code, stdout, stderr) = run_tool(program, args, project_path).await?;
+        if exit_code != 0 {
+            return Err(tr!("signing.openssl_failed", args[0], stderr.join(" ")));
+        }
+        Ok(stdout.join("\n"))
+    }
+}
+
+/// Checks the settings before the first combination: the files exist and
+/// the first certificate is the one of the key.
+pub async fn check(settings: &SigningSettings, project_path: &Path) -> Result<(), String> {
+    let certificates = settings.certificate_paths(project_path);
+    let Some(leaf) = certificates.first() else {
+        return Err(tr!("signing.no_certificates"));
+    };
+    let key = (!settings.on_token()).then(|| project_path.join(&settings.key));
+    if let Some(missing) = key.iter().chain(&certificates).find(|p| !p.is_file()) {
+        return Err(tr!("signing.missing", missing.display()));
+    }
+
+    let certificate_key = settings
+        .openssl(&["x509".to_string(), "-in".to_string(), leaf.display().to_string(), "-noout".to_string(), "-pubkey".to_string()], project_path)
+        .await?;
+    let mut args = vec!["pkey".to_string()];
+    args.extend(settings.key_args(project_path, "-in", "-inform"));
+    args.push("-pubout".to_string());
+    let signing_key = settings.openssl(&args, project_path).await?;
+    if certificate_key.trim() != signing_key.trim() {
+        return Err(tr!("signing.key_mismatch", leaf.display()));
+    }
+    Ok(())
+}
+
+/// Signs `bin`, writing the DER signature and the PEM certificate chain
+/// next to it. Returns the signature file.
+pub async fn sign(settings: &SigningSettings, project_path: &Path, bin: &Path) -> Result<PathBuf, String> {
+    let signature = bin.with_extension(SIGNATURE_EXTENSION);
+    let mut args = vec!["dgst".to_string(), format!("-{}", settings.digest.as_deref().unwrap_or(DEFAULT_DIGEST))];
+    args.extend(settings.key_args(project_path, "-sign", "-keyform"));
+    args.extend(["-out".to_string(), signature.display().to_string(), bin.display().to_string()]);
+    settings.openssl(&args, project_path).await?;
+
+    let mut chain = String::new();
+    for certificate in settings.certificate_paths(project_path) {
+        let pem = fs::read_to_string(&certificate).map_err(|e| tr!("fs.read", certificate.display(), e))?;
+        chain.push_str(pem.trim_end());
+        chain.push('\n');
+    }
+    let chain_file = bin.with_extension(CHAIN_EXTENSION);
+    fs::write(&chain_file, chain).map_err(|e| tr!("fs.write", chain_file.display(), e))?;
+    Ok(signature)
+}
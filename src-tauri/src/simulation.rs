@@ -0,0 +1,154 @@
+use crate::i18n::tr;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Duration};
+
+// How much of the simulator output is kept in the manifest
+const OUTPUT_TAIL_LINES: usize = 40;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Outcome of booting a combination's ELF in a simulator, recorded with
+/// the combination in the manifest.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SimulationResult {
+    pub simulator: String,
+    pub passed: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// Last lines of the firmware's output.
+    pub output: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl SimulationResult {
+    pub fn failed(simulator: &str, error: String) -> Self {
+        SimulationResult {
+            simulator: simulator.to_string(),
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+fn tail(content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+fn collect_lines<R: AsyncRead + Unpin + Send + 'static>(reader: Option<R>) -> JoinHandle<Vec<String>> {
+    tokio::spawn(async move {
+        let mut collected = Vec::new();
+        if let Some(reader) = reader {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                collected.push(line);
+            }
+        }
+        collected
+    })
+}
+
+/// Runs the simulator for `duration` and passes unless its output matches
+/// `fail` or it exits with a non-zero code (e.g. through semihosting
+/// `SYS_EXIT`) before the time is up.
+pub async fn run_for(simulator: &str, mut command: Command, fail: &Regex, duration: Duration) -> SimulationResult {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped()).stdin(Stdio::null()).kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let started = Instant::now();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return SimulationResult::failed(simulator, tr!("tool.start_failed", simulator, e)),
+    };
+    let stdout = collect_lines(child.stdout.take());
+    let stderr = collect_lines(child.stderr.take());
+
+    let exit_code = match timeout(duration, child.wait()).await {
+        Ok(status) => status.ok().and_then(|s| s.code()),
+        Err(_) => {
+            let _ = child.kill().await;
+            None
+        }
+    };
+    let mut output = stdout.await.unwrap_or_default();
+    output.extend(stderr.await.unwrap_or_default());
+    let content = output.join("\n");
+
+    let error = if let Some(found) = fail.find(&content) {
+        Some(format!("{} reported '{}'", simulator, found.as_str()))
+    } else {
+        exit_code
+            .filter(|code| *code != 0)
+            .map(|code| format!("{} exited with code {}", simulator, code))
+    };
+    SimulationResult {
+        simulator: simulator.to_string(),
+        passed: error.is_none(),
+        timed_out: false,
+        duration_ms: started.elapsed().as_millis() as u64,
+        output: tail(&content),
+        error,
+    }
+}
+
+/// Runs the simulator until `pass` shows up in the file the firmware's
+/// output is written to, or `timeout` elapses. The simulator is killed
+/// either way.
+pub async fn run_until_output(
+    simulator: &str,
+    mut command: Command,
+    output_file: &Path,
+    pass: &Regex,
+    timeout: Duration,
+) -> SimulationResult {
+    fs::remove_file(output_file).ok();
+    command.kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let started = Instant::now();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return SimulationResult::failed(simulator, tr!("tool.start_failed", simulator, e)),
+    };
+
+    let mut content = String::new();
+    let mut exited = false;
+    while started.elapsed() < timeout {
+        content = fs::read_to_string(output_file).unwrap_or_default();
+        if pass.is_match(&content) || exited {
+            break;
+        }
+        // One more read after the simulator exits, for output flushed on exit
+        exited = matches!(child.try_wait(), Ok(Some(_)));
+        sleep(POLL_INTERVAL).await;
+    }
+    let _ = child.kill().await;
+
+    let passed = pass.is_match(&content);
+    SimulationResult {
+        simulator: simulator.to_string(),
+        passed,
+        timed_out: !passed && !exited,
+        duration_ms: started.elapsed().as_millis() as u64,
+        output: tail(&content),
+        error: match (passed, exited) {
+            (true, _) => None,
+            (false, true) => Some(format!("{} exited before the firmware printed '{}'", simulator, pass.as_str())),
+            (false, false) => Some(format!("'{}' not seen within {} s", pass.as_str(), timeout.as_secs())),
+        },
+    }
+}
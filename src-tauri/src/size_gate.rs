@@ -0,0 +1,39 @@
+use crate::analysis::GateAction;
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+
+/// Limit on how much a combination's flash usage may grow between runs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SizeGate {
+    #[serde(rename = "maxGrowthPercent")]
+    pub max_growth_percent: Option<f64>,
+    #[serde(rename = "maxGrowthBytes")]
+    pub max_growth_bytes: Option<u64>,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+/// Flash size of the same combination in an earlier successful run.
+#[derive(Clone, Debug)]
+pub struct PreviousSize {
+    pub run_id: String,
+    pub flash: u64,
+}
+
+/// Why the combination's growth over `previous` exceeds the gate, if it does.
+pub fn check(gate: &SizeGate, flash: u64, previous: &PreviousSize) -> Option<String> {
+    let growth = flash.saturating_sub(previous.flash);
+    if growth == 0 {
+        return None;
+    }
+    let percent = if previous.flash == 0 {
+        100.0
+    } else {
+        growth as f64 * 100.0 / previous.flash as f64
+    };
+    let over_bytes = gate.max_growth_bytes.map(|max| growth > max).unwrap_or(false);
+    let over_percent = gate.max_growth_percent.map(|max| percent > max).unwrap_or(false);
+    (over_bytes || over_percent).then(|| {
+        tr!("size.grew", growth, format!("{:.1}", percent), previous.flash, flash, previous.run_id)
+    })
+}
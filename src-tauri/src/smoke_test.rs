@@ -0,0 +1,215 @@
+use crate::flashing::{openocd_setup_args, FlashBackend, FlashRequest, Transport};
+use crate::i18n::tr;
+use crate::manifest::RunManifest;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::Window;
+use tokio::process::{Child, Command};
+use tokio::time::{sleep, timeout, Duration};
+
+const DEFAULT_GDB_PORT: u16 = 3333;
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+// How much of the GDB output is kept in the result and the manifest
+const OUTPUT_TAIL_LINES: usize = 40;
+
+/// Post-flash check run through GDB; stored with the project's flash settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmokeTestConfig {
+    /// GDB command file or Python script (`.py`). It passes by exiting
+    /// with code 0, e.g. `quit 1` on a failed check.
+    pub script: String,
+    /// Path of `arm-none-eabi-gdb`; `None` to look it up in `PATH`.
+    #[serde(rename = "gdbPath")]
+    pub gdb_path: Option<String>,
+    /// Path of the GDB server; by default the one matching the flash backend.
+    #[serde(rename = "serverPath")]
+    pub server_path: Option<String>,
+    /// ELF with symbols; defaults to the `.elf` next to the flashed file.
+    pub elf: Option<String>,
+    pub port: Option<u16>,
+    #[serde(rename = "timeoutSecs")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Outcome of a smoke test, also recorded in the run's manifest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmokeTestResult {
+    pub combination: Option<String>,
+    pub probe: Option<String>,
+    pub passed: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub finished_at: String,
+    /// Last lines of the GDB output.
+    pub output: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl SmokeTestResult {
+    fn failed(request: &FlashRequest, error: String) -> Self {
+        SmokeTestResult {
+            combination: request.combination.clone(),
+            probe: request.probe.clone(),
+            passed: false,
+            exit_code: None,
+            timed_out: false,
+            finished_at: Local::now().to_rfc3339(),
+            output: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+fn sibling(tool: &str, name: &str) -> String {
+    let path = Path::new(tool);
+    if tool.trim().is_empty() || path.parent().map(|p| p.as_os_str().is_empty()).unwrap_or(true) {
+        return name.to_string();
+    }
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}", name, ext.to_string_lossy()),
+        None => name.to_string(),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// GDB server command for the project's backend.
+fn server_command(request: &FlashRequest, config: &SmokeTestConfig, port: u16) -> Result<(String, Vec<String>), String> {
+    let settings = &request.settings;
+    let port_str = port.to_string();
+    match settings.backend {
+        FlashBackend::CubeProgrammer => {
+            let program = config.server_path.clone().unwrap_or_else(|| "ST-LINK_gdbserver".to_string());
+            // The server loads CubeProgrammer's libraries from its bin directory
+            let programmer = settings.program();
+            let programmer_dir = Path::new(&programmer)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            let mut args = vec!["-p".to_string(), port_str, "-d".to_string(), "-cp".to_string(), programmer_dir];
+            if let Some(serial) = &request.probe {
+                args.extend(["-i".to_string(), serial.clone()]);
+            }
+            Ok((program, args))
+        }
+        FlashBackend::OpenOcd => {
+            let program = config.server_path.clone().unwrap_or_else(|| settings.program());
+            let mut args = openocd_setup_args(request)?;
+            args.extend(["-c".to_string(), format!("gdb_port {}", port)]);
+            Ok((program, args))
+        }
+        FlashBackend::StFlash => {
+            let program = config
+                .server_path
+                .clone()
+                .unwrap_or_else(|| sibling(&settings.programmer_path, "st-util"));
+            let mut args = vec!["-p".to_string(), port_str];
+            if let Some(serial) = &request.probe {
+                args.push(format!("--serial={}", serial));
+            }
+            Ok((program, args))
+        }
+    }
+}
+
+fn spawn(program: &str, args: &[String], capture: bool) -> Result<Child, String> {
+    let mut command = Command::new(program);
+    command.args(args).kill_on_drop(true);
+    if capture {
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+    } else {
+        command
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    command.spawn().map_err(|e| tr!("flash.start_failed", program, e))
+}
+
+fn default_elf(file: &str) -> Option<PathBuf> {
+    let elf = Path::new(file).with_extension("elf");
+    elf.is_file().then_some(elf)
+}
+
+/// Starts a GDB server for the flashed board, runs the script against it
+/// and reports whether GDB exited with code 0 before the timeout.
+pub async fn run(request: &FlashRequest, config: &SmokeTestConfig) -> SmokeTestResult {
+    if request.transport == Transport::Dfu {
+        return SmokeTestResult::failed(request, tr!("smoke.dfu_unsupported"));
+    }
+    if !Path::new(&config.script).is_file() {
+        return SmokeTestResult::failed(request, tr!("smoke.script_missing", config.script));
+    }
+    let port = config.port.unwrap_or(DEFAULT_GDB_PORT);
+    let (server_program, server_args) = match server_command(request, config, port) {
+        Ok(command) => command,
+        Err(e) => return SmokeTestResult::failed(request, e),
+    };
+    // Killed when dropped at the end of this function
+    let _server = match spawn(&server_program, &server_args, false) {
+        Ok(child) => child,
+        Err(e) => return SmokeTestResult::failed(request, e),
+    };
+    // GDB does not retry the connection, so give the server time to attach
+    sleep(Duration::from_secs(2)).await;
+
+    let gdb = config.gdb_path.clone().unwrap_or_else(|| "arm-none-eabi-gdb".to_string());
+    let mut gdb_args = vec![
+        "-batch".to_string(),
+        "-nx".to_string(),
+        "-ex".to_string(),
+        format!("target extended-remote localhost:{}", port),
+        "-x".to_string(),
+        config.script.clone(),
+    ];
+    if let Some(elf) = config.elf.as_ref().map(PathBuf::from).or_else(|| default_elf(&request.file)) {
+        gdb_args.push(elf.to_string_lossy().into_owned());
+    }
+    let child = match spawn(&gdb, &gdb_args, true) {
+        Ok(child) => child,
+        Err(e) => return SmokeTestResult::failed(request, e),
+    };
+
+    let limit = Duration::from_secs(config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let mut result = SmokeTestResult::failed(request, String::new());
+    match timeout(limit, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let lines: Vec<String> = text.lines().map(str::to_string).collect();
+            result.output = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].to_vec();
+            result.exit_code = output.status.code();
+            result.passed = output.status.success();
+            result.error = None;
+        }
+        Ok(Err(e)) => result.error = Some(tr!("process.wait_failed", e)),
+        Err(_) => {
+            result.timed_out = true;
+            result.error = Some(tr!("smoke.timeout", limit.as_secs()));
+        }
+    }
+    result.finished_at = Local::now().to_rfc3339();
+    result
+}
+
+/// Adds the result to the run's manifest, both the archived copy and the
+/// one in the output directory.
+pub fn record(window: &Window, run_id: &str, result: &SmokeTestResult) -> Result<(), String> {
+    let mut manifest = RunManifest::load_archived(window, run_id)?;
+    manifest.smoke_tests.push(result.clone());
+    manifest.archive(window)?;
+    let output_dir = PathBuf::from(&manifest.output_dir);
+    if output_dir.is_dir() {
+        manifest.write(&output_dir)?;
+    }
+    Ok(())
+}
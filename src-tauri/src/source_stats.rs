@@ -0,0 +1,116 @@
+use crate::i18n::tr;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use stm32_builder_core::project::is_build_output;
+use tauri::command;
+
+/// Extensions counted as sources; `.S` is preprocessed assembly.
+const SOURCE_EXTENSIONS: &[&str] = &["c", "h", "s"];
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FileStats {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+impl FileStats {
+    fn add(&mut self, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Sources of one top-level folder of the project (`Core`, `Drivers`, ...);
+/// files in the project folder itself are listed under `.`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FolderStats {
+    pub folder: String,
+    pub c: FileStats,
+    pub h: FileStats,
+    pub asm: FileStats,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SourceStats {
+    pub folders: Vec<FolderStats>,
+    pub c: FileStats,
+    pub h: FileStats,
+    pub asm: FileStats,
+}
+
+/// `.c`, `.h` and `.s` files of the project, relative to it, in a stable
+/// order. Hidden folders and build output are left out, so the list only
+/// changes when the sources do.
+pub fn source_files(project_path: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![project_path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_dir() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && !is_build_output(&path) {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file()
+                && extension(&path).is_some()
+                && let Ok(relative) = path.strip_prefix(project_path)
+            {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    SOURCE_EXTENSIONS.contains(&extension.as_str()).then_some(extension)
+}
+
+pub fn source_stats(project_path: &Path) -> SourceStats {
+    let mut stats = SourceStats::default();
+    let mut folders: BTreeMap<String, FolderStats> = BTreeMap::new();
+    for file in source_files(project_path) {
+        let bytes = fs::metadata(project_path.join(&file)).map(|m| m.len()).unwrap_or(0);
+        let folder = match file.components().next() {
+            Some(Component::Normal(first)) if file.components().count() > 1 => first.to_string_lossy().into_owned(),
+            _ => ".".to_string(),
+        };
+        let entry = folders.entry(folder.clone()).or_insert_with(|| FolderStats { folder, ..Default::default() });
+        match extension(&file).as_deref() {
+            Some("c") => {
+                entry.c.add(bytes);
+                stats.c.add(bytes);
+            }
+            Some("h") => {
+                entry.h.add(bytes);
+                stats.h.add(bytes);
+            }
+            _ => {
+                entry.asm.add(bytes);
+                stats.asm.add(bytes);
+            }
+        }
+    }
+    stats.folders = folders.into_values().collect();
+    stats
+}
+
+/// Counts and sizes of the project's `.c`, `.h` and `.s` files per
+/// top-level folder.
+#[command]
+pub async fn get_source_stats(project_path: String) -> Result<SourceStats, String> {
+    let project_path = PathBuf::from(&project_path);
+    if !project_path.is_dir() {
+        return Err(tr!("path.not_a_directory", project_path.display()));
+    }
+    tokio::task::spawn_blocking(move || source_stats(&project_path))
+        .await
+        .map_err(|e| e.to_string())
+}
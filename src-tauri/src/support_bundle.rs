@@ -0,0 +1,129 @@
+use crate::i18n::tr;
+use crate::{log_index, manifest};
+use chrono::Local;
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use tauri::{command, Manager, Window};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const SCHEMA_FILE: &str = "build_settings.yaml";
+
+/// Collects everything needed to look into a run into one zip:
+/// logs, manifest, generated `build_config.h` headers, the settings schema
+/// and a description of the app and machine.
+///
+/// Returns the path of the written archive. Without `destination` it goes to
+/// `diagnostics/` under the app data directory.
+#[command]
+pub async fn export_diagnostics(
+    window: Window,
+    run_id: String,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let manifest_path = manifest::archived_path(&window, &run_id)?;
+    let manifest_json = fs::read_to_string(&manifest_path)
+        .map_err(|e| tr!("manifest.missing", run_id, e))?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest_json)
+        .map_err(|e| tr!("manifest.invalid", run_id, e))?;
+
+    let zip_path = match destination {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = window
+                .app_handle()
+                .path()
+                .app_data_dir()
+                .map_err(|e| tr!("app_data.unavailable", e))?
+                .join("diagnostics");
+            fs::create_dir_all(&dir)
+                .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+            dir.join(format!("diagnostics_{}.zip", run_id))
+        }
+    };
+
+    let file = File::create(&zip_path)
+        .map_err(|e| tr!("fs.create", zip_path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, data: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(data).map_err(Into::into))
+            .map_err(|e| tr!("bundle.add_failed", name, e))
+    };
+
+    add("manifest.json", manifest_json.as_bytes())?;
+
+    // Everything the run logged, including the raw CubeIDE output
+    let index = log_index::index_path(&window)?;
+    let run_log = log_index::run_entries(&index, &run_id)?
+        .iter()
+        .map(|e| match &e.combination {
+            Some(combo) => format!("[{}] [{}] [{}] {}", e.timestamp, e.level, combo, e.message),
+            None => format!("[{}] [{}] {}", e.timestamp, e.level, e.message),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    add("logs/run.log", run_log.as_bytes())?;
+
+    // Files from the output directory, as far as they still exist
+    if let Some(output_dir) = manifest["output_dir"].as_str().map(Path::new) {
+        if let Ok(data) = fs::read(output_dir.join("build_log.txt")) {
+            add("logs/build_log.txt", &data)?;
+        }
+        let combinations = manifest["combinations"].as_array().cloned().unwrap_or_default();
+        for combination in combinations.iter().filter_map(|c| c["combination"].as_str()) {
+            for (path, name) in combination_files(&output_dir.join(combination)) {
+                if let Ok(data) = fs::read(&path) {
+                    add(&format!("combinations/{}/{}", combination, name), &data)?;
+                }
+            }
+        }
+    }
+
+    if let Ok(data) = fs::read(SCHEMA_FILE) {
+        add(SCHEMA_FILE, &data)?;
+    }
+
+    let environment = json!({
+        "app_name": window.app_handle().package_info().name,
+        "app_version": window.app_handle().package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "os_version": System::long_os_version(),
+        "kernel_version": System::kernel_version(),
+        "total_memory": System::new_all().total_memory(),
+        "cpus": std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        "exported_at": Local::now().to_rfc3339(),
+    });
+    let environment = serde_json::to_string_pretty(&environment).map_err(|e| tr!("bundle.environment_failed", e))?;
+    add("environment.json", environment.as_bytes())?;
+
+    zip.finish()
+        .map_err(|e| tr!("bundle.finish_failed", zip_path.display(), e))?;
+    Ok(zip_path.display().to_string())
+}
+
+/// The current build log and generated header of one combination;
+/// rotated archives are left out.
+fn combination_files(dir: &Path) -> Vec<(PathBuf, String)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| (e.path(), e.file_name().to_string_lossy().into_owned()))
+        .filter(|(path, name)| {
+            let is_log = path.extension().map(|ext| ext == "txt").unwrap_or(false)
+                && !path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().contains('.'))
+                    .unwrap_or(false);
+            name == "build_config.h" || is_log
+        })
+        .collect()
+}
@@ -0,0 +1,98 @@
+use crate::analysis::GateAction;
+use crate::i18n::tr;
+use stm32_builder_core::diagnostics::{Diagnostic, Severity};
+use object::{Object, ObjectSymbol};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Float printf/scanf support, heap, exceptions and soft-float doubles
+const DEFAULT_SYMBOLS: [&str; 6] = ["_printf_float", "_scanf_float", "_malloc_r", "__cxa_throw", "__aeabi_dadd", "__aeabi_dcmpeq"];
+
+/// Symbols that must not be linked into the firmware.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SymbolGate {
+    /// Symbol names, or prefixes ending in `*`; a default set of common
+    /// bloat sources when empty.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// Forbidden symbols defined in the ELF, as findings of severity matching
+/// the gate's action.
+pub fn check(gate: &SymbolGate, elf: &Path) -> Result<Vec<Diagnostic>, String> {
+    let data = fs::read(elf).map_err(|e| tr!("fs.read", elf.display(), e))?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| tr!("elf.parse_failed", elf.display(), e))?;
+    let patterns: Vec<&str> = if gate.symbols.is_empty() {
+        DEFAULT_SYMBOLS.to_vec()
+    } else {
+        gate.symbols.iter().map(String::as_str).collect()
+    };
+
+    let mut names: Vec<&str> = file
+        .symbols()
+        .filter(|symbol| !symbol.is_undefined())
+        .filter_map(|symbol| symbol.name().ok())
+        .filter(|name| patterns.iter().any(|pattern| matches(pattern, name)))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let severity = match gate.action {
+        GateAction::Warn => Severity::Warning,
+        GateAction::Fail => Severity::Error,
+    };
+    Ok(names
+        .into_iter()
+        .map(|name| Diagnostic {
+            severity,
+            message: tr!("symbols.linked_in", name),
+            file: Some(elf.display().to_string()),
+            line: None,
+            column: None,
+            option: Some(format!("symbols:{}", name)),
+            fixits: Vec::new(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf_check::tests::{elf_with_symbols, EM_ARM};
+
+    fn check_symbols(gate: &SymbolGate, symbols: &[&str]) -> Vec<Diagnostic> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blinky.elf");
+        fs::write(&path, elf_with_symbols(EM_ARM, 0x0800_0001, 0x0800_0000, 0x0800_0000, symbols)).unwrap();
+        check(gate, &path).unwrap()
+    }
+
+    #[test]
+    fn forbidden_symbol_fails_the_gate() {
+        let gate = SymbolGate { symbols: Vec::new(), action: GateAction::Fail };
+        let findings = check_symbols(&gate, &["Reset_Handler", "main", "_printf_float"]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].option.as_deref(), Some("symbols:_printf_float"));
+    }
+
+    #[test]
+    fn prefixes_match_and_clean_images_pass() {
+        let gate = SymbolGate { symbols: vec!["HAL_UART_*".to_string()], action: GateAction::Warn };
+        assert!(check_symbols(&gate, &["Reset_Handler", "_printf_float"]).is_empty());
+        let findings = check_symbols(&gate, &["HAL_UART_Transmit", "HAL_UART_Init", "HAL_GPIO_Init"]);
+        let options: Vec<&str> = findings.iter().filter_map(|f| f.option.as_deref()).collect();
+        assert_eq!(options, vec!["symbols:HAL_UART_Init", "symbols:HAL_UART_Transmit"]);
+        assert!(findings.iter().all(|f| f.severity == Severity::Warning));
+    }
+}
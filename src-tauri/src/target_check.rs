@@ -0,0 +1,215 @@
+use crate::flashing::{
+    cube_connect_args, openocd_setup_args, run_streaming, FlashBackend, FlashRequest, FlashSettings, Transport,
+};
+use crate::i18n::tr;
+use crate::mcu_info::{find_ioc, parse_ioc};
+use crate::probes::{capture, st_info_path};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::{command, Window};
+
+lazy_static! {
+    // CubeProgrammer: "Device ID   : 0x413", st-info: "chipid:     0x0413"
+    static ref DEVICE_ID: Regex = Regex::new(r"(?i)(?:Device ID|chipid)\s*:\s*0x([0-9a-f]+)").unwrap();
+    // OpenOCD's flash driver: "device id = 0x10076413"
+    static ref OPENOCD_DEVICE_ID: Regex = Regex::new(r"(?i)device id = 0x([0-9a-f]+)").unwrap();
+    static ref DEVICE_NAME: Regex = Regex::new(r"(?i)(?:Device name|dev-type)\s*:\s*(.+?)\s*$").unwrap();
+    static ref CPROJECT_MCU: Regex = Regex::new(r#"option\.target_mcu[^>]*?value="([^"]+)""#).unwrap();
+}
+
+/// DBGMCU device ids and the part numbers they cover.
+const DEVICES: &[(u16, &[&str])] = &[
+    (0x440, &["STM32F030x8", "STM32F05"]),
+    (0x444, &["STM32F03"]),
+    (0x445, &["STM32F04", "STM32F070x6"]),
+    (0x448, &["STM32F07"]),
+    (0x442, &["STM32F09", "STM32F030xC"]),
+    (0x412, &["STM32F10"]),
+    (0x410, &["STM32F10"]),
+    (0x414, &["STM32F10"]),
+    (0x430, &["STM32F10"]),
+    (0x418, &["STM32F105", "STM32F107"]),
+    (0x411, &["STM32F2"]),
+    (0x422, &["STM32F302", "STM32F303", "STM32F358"]),
+    (0x432, &["STM32F37"]),
+    (0x438, &["STM32F303", "STM32F334", "STM32F328"]),
+    (0x439, &["STM32F301", "STM32F302", "STM32F318"]),
+    (0x446, &["STM32F302", "STM32F303", "STM32F398"]),
+    (0x413, &["STM32F405", "STM32F407", "STM32F415", "STM32F417"]),
+    (0x419, &["STM32F42", "STM32F43"]),
+    (0x423, &["STM32F401"]),
+    (0x433, &["STM32F401"]),
+    (0x431, &["STM32F411"]),
+    (0x458, &["STM32F410"]),
+    (0x441, &["STM32F412"]),
+    (0x421, &["STM32F446"]),
+    (0x434, &["STM32F469", "STM32F479"]),
+    (0x463, &["STM32F413", "STM32F423"]),
+    (0x452, &["STM32F72", "STM32F73"]),
+    (0x449, &["STM32F74", "STM32F75"]),
+    (0x451, &["STM32F76", "STM32F77"]),
+    (0x466, &["STM32G03", "STM32G04"]),
+    (0x456, &["STM32G05", "STM32G06"]),
+    (0x460, &["STM32G07", "STM32G08"]),
+    (0x467, &["STM32G0B", "STM32G0C"]),
+    (0x468, &["STM32G431", "STM32G441"]),
+    (0x469, &["STM32G47", "STM32G48"]),
+    (0x479, &["STM32G491", "STM32G4A1"]),
+    (0x450, &["STM32H74", "STM32H75"]),
+    (0x480, &["STM32H7A", "STM32H7B"]),
+    (0x483, &["STM32H72", "STM32H73"]),
+    (0x457, &["STM32L01", "STM32L02"]),
+    (0x425, &["STM32L03", "STM32L04"]),
+    (0x417, &["STM32L05", "STM32L06"]),
+    (0x447, &["STM32L07", "STM32L08"]),
+    (0x416, &["STM32L1"]),
+    (0x429, &["STM32L1"]),
+    (0x427, &["STM32L1"]),
+    (0x436, &["STM32L1"]),
+    (0x437, &["STM32L1"]),
+    (0x464, &["STM32L41", "STM32L42"]),
+    (0x435, &["STM32L43", "STM32L44"]),
+    (0x462, &["STM32L45", "STM32L46"]),
+    (0x415, &["STM32L47", "STM32L48"]),
+    (0x461, &["STM32L496", "STM32L4A6"]),
+    (0x470, &["STM32L4R", "STM32L4S"]),
+    (0x471, &["STM32L4P", "STM32L4Q"]),
+    (0x472, &["STM32L55", "STM32L56"]),
+    (0x482, &["STM32U575", "STM32U585"]),
+    (0x495, &["STM32WB55", "STM32WB35"]),
+    (0x497, &["STM32WLE", "STM32WL5"]),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetStatus {
+    /// The connected chip is one the project can be built for.
+    Match,
+    Mismatch,
+    /// Device id or project MCU could not be determined.
+    Unknown,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetCheck {
+    pub device_id: Option<String>,
+    pub device_name: Option<String>,
+    pub declared_mcu: Option<String>,
+    pub status: TargetStatus,
+}
+
+/// MCU the project is built for, from the `.cproject` target option or
+/// the CubeMX `.ioc` file.
+pub fn declared_mcu(project_path: &Path) -> Option<String> {
+    if let Ok(content) = fs::read_to_string(project_path.join(".cproject"))
+        && let Some(caps) = CPROJECT_MCU.captures(&content)
+    {
+        return Some(caps[1].to_string());
+    }
+    let content = fs::read_to_string(find_ioc(project_path)?).ok()?;
+    parse_ioc(&content).remove("Mcu.UserName")
+}
+
+fn parse_device_id(output: &[String]) -> Option<u16> {
+    output.iter().find_map(|line| {
+        let caps = DEVICE_ID.captures(line).or_else(|| OPENOCD_DEVICE_ID.captures(line))?;
+        // OpenOCD reports the whole IDCODE; the device id is its low 12 bits
+        u32::from_str_radix(&caps[1], 16).ok().map(|id| (id & 0xFFF) as u16)
+    })
+}
+
+fn parse_device_name(output: &[String]) -> Option<String> {
+    output
+        .iter()
+        .find_map(|line| DEVICE_NAME.captures(line).map(|caps| caps[1].to_string()))
+}
+
+/// Compares a device id with the project's MCU, e.g. `STM32F407VGTx`.
+pub fn compare(device_id: Option<u16>, declared_mcu: Option<&str>) -> TargetStatus {
+    let (Some(device_id), Some(mcu)) = (device_id, declared_mcu) else {
+        return TargetStatus::Unknown;
+    };
+    let mcu = mcu.to_uppercase();
+    match DEVICES.iter().find(|(id, _)| *id == device_id) {
+        Some((_, parts)) if parts.iter().any(|part| mcu.starts_with(&part.to_uppercase())) => TargetStatus::Match,
+        Some(_) => TargetStatus::Mismatch,
+        None => TargetStatus::Unknown,
+    }
+}
+
+/// Program and arguments that connect to the target without programming it.
+fn query_args(request: &FlashRequest) -> Result<(String, Vec<String>), String> {
+    Ok(match request.settings.backend {
+        FlashBackend::CubeProgrammer => (request.settings.program(), cube_connect_args(request)),
+        FlashBackend::OpenOcd => {
+            let mut args = openocd_setup_args(request)?;
+            for cmd in ["init", "flash probe 0", "exit"] {
+                args.extend(["-c".to_string(), cmd.to_string()]);
+            }
+            (request.settings.program(), args)
+        }
+        FlashBackend::StFlash => (st_info_path(&request.settings), vec!["--probe".to_string()]),
+    })
+}
+
+/// Connects to the target and returns the programmer's output.
+async fn query_target(window: &Window, request: &FlashRequest) -> Result<Vec<String>, String> {
+    let (program, args) = query_args(request)?;
+    let (_, output) = run_streaming(window, &program, &args, &request.file, request.probe.as_deref()).await?;
+    Ok(output)
+}
+
+/// Device id of the chip on the probe, `None` while no board is attached.
+/// Unlike `check`, the tool's output is not streamed to the window.
+pub async fn detect_device(request: &FlashRequest) -> Option<u16> {
+    let (program, args) = query_args(request).ok()?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = capture(&program, &args).await?;
+    let lines: Vec<String> = output.lines().map(str::to_string).collect();
+    parse_device_id(&lines)
+}
+
+/// Reads the connected chip's device id and checks it against the MCU of
+/// the project at `project_path`.
+pub async fn check(window: &Window, request: &FlashRequest, project_path: &Path) -> Result<TargetCheck, String> {
+    let output = query_target(window, request).await?;
+    let device_id = parse_device_id(&output);
+    let declared_mcu = declared_mcu(project_path);
+    Ok(TargetCheck {
+        device_id: device_id.map(|id| format!("0x{:03X}", id)),
+        device_name: parse_device_name(&output),
+        status: compare(device_id, declared_mcu.as_deref()),
+        declared_mcu,
+    })
+}
+
+/// Identifies the chip on the selected probe and cross-checks it with the project.
+#[command]
+pub async fn detect_target(
+    window: Window,
+    settings: FlashSettings,
+    probe: Option<String>,
+    project_path: String,
+) -> Result<TargetCheck, String> {
+    let request = FlashRequest {
+        settings,
+        file: String::new(),
+        combination: None,
+        run_id: None,
+        project_path: Some(project_path.clone()),
+        probe,
+        address: None,
+        full_erase: false,
+        verify: false,
+        reset: false,
+        confirm_rdp: None,
+        ignore_mcu_mismatch: false,
+        transport: Transport::Swd,
+    };
+    check(&window, &request, Path::new(&project_path))
+        .await
+        .map_err(|e| tr!("target.detect_failed", e))
+}
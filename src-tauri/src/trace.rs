@@ -0,0 +1,266 @@
+use crate::flashing::{cube_connect_args, openocd_setup_args, FlashBackend, FlashRequest, FlashSettings, Transport};
+use crate::i18n::tr;
+use crate::logging::emit_to_window;
+use crate::manifest::RunManifest;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{command, Window};
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_RTT_PORT: u16 = 19021;
+// Where OpenOCD searches for the "SEGGER RTT" control block by default
+const DEFAULT_RTT_SEARCH_ADDRESS: &str = "0x20000000";
+const DEFAULT_RTT_SEARCH_SIZE: &str = "0x20000";
+const RTT_CONNECT_ATTEMPTS: u32 = 20;
+
+lazy_static! {
+    static ref TRACES: Mutex<HashMap<String, JoinHandle<()>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceMode {
+    /// SWO/SWV through STM32CubeProgrammer.
+    Swo,
+    /// SEGGER RTT through OpenOCD's RTT server.
+    Rtt,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TraceRequest {
+    pub settings: FlashSettings,
+    pub mode: TraceMode,
+    pub probe: Option<String>,
+    /// Core clock in Hz; SWO only.
+    #[serde(rename = "cpuFrequency")]
+    pub cpu_frequency: Option<u32>,
+    /// RAM range searched for the RTT control block.
+    #[serde(rename = "rttAddress")]
+    pub rtt_address: Option<String>,
+    #[serde(rename = "rttSize")]
+    pub rtt_size: Option<String>,
+    #[serde(rename = "rttPort")]
+    pub rtt_port: Option<u16>,
+    /// Run and combination whose log folder receives the trace file.
+    #[serde(rename = "runId")]
+    pub run_id: Option<String>,
+    pub combination: Option<String>,
+}
+
+/// Payload of the `trace-line` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceLine {
+    pub trace_id: String,
+    pub line: String,
+}
+
+/// Payload of the `trace-stopped` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceStopped {
+    pub trace_id: String,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceSession {
+    pub trace_id: String,
+    /// File the trace is written to, if the request named a run.
+    pub file: Option<String>,
+}
+
+impl TraceRequest {
+    /// The connection arguments only need the probe and backend settings.
+    fn connection(&self) -> FlashRequest {
+        FlashRequest {
+            settings: self.settings.clone(),
+            file: String::new(),
+            combination: self.combination.clone(),
+            run_id: self.run_id.clone(),
+            project_path: None,
+            probe: self.probe.clone(),
+            address: None,
+            full_erase: false,
+            verify: false,
+            reset: false,
+            confirm_rdp: None,
+            ignore_mcu_mismatch: false,
+            transport: Transport::Swd,
+        }
+    }
+
+    fn program(&self) -> String {
+        let backend = match self.mode {
+            TraceMode::Swo => FlashBackend::CubeProgrammer,
+            TraceMode::Rtt => FlashBackend::OpenOcd,
+        };
+        if self.settings.backend == backend {
+            self.settings.program()
+        } else {
+            FlashSettings { backend, ..Default::default() }.program()
+        }
+    }
+
+    fn args(&self) -> Result<Vec<String>, String> {
+        match self.mode {
+            TraceMode::Swo => {
+                let frequency = self.cpu_frequency.ok_or_else(|| tr!("trace.frequency_missing"))?;
+                let mut args = cube_connect_args(&self.connection());
+                args.extend([
+                    "-startswv".to_string(),
+                    format!("freq={}", frequency / 1_000_000),
+                    "portnumber=0".to_string(),
+                ]);
+                Ok(args)
+            }
+            TraceMode::Rtt => {
+                let mut args = openocd_setup_args(&self.connection())?;
+                let address = self.rtt_address.as_deref().unwrap_or(DEFAULT_RTT_SEARCH_ADDRESS);
+                let size = self.rtt_size.as_deref().unwrap_or(DEFAULT_RTT_SEARCH_SIZE);
+                for cmd in [
+                    "init".to_string(),
+                    format!("rtt setup {} {} {{SEGGER RTT}}", address, size),
+                    "rtt start".to_string(),
+                    format!("rtt server start {} 0", self.rtt_port.unwrap_or(DEFAULT_RTT_PORT)),
+                ] {
+                    args.extend(["-c".to_string(), cmd]);
+                }
+                Ok(args)
+            }
+        }
+    }
+}
+
+fn trace_file(window: &Window, request: &TraceRequest) -> Result<Option<PathBuf>, String> {
+    let Some(run_id) = &request.run_id else {
+        return Ok(None);
+    };
+    let manifest = RunManifest::load_archived(window, run_id)?;
+    let mut dir = PathBuf::from(&manifest.output_dir);
+    if let Some(combination) = &request.combination {
+        dir.push(combination);
+    }
+    let name = match request.mode {
+        TraceMode::Swo => "trace_swo.log",
+        TraceMode::Rtt => "trace_rtt.log",
+    };
+    Ok(Some(dir.join(name)))
+}
+
+fn spawn_tool(program: &str, args: &[String], capture_stdout: bool) -> Result<Child, String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .kill_on_drop(true)
+        .stdout(if capture_stdout { std::process::Stdio::piped() } else { std::process::Stdio::null() })
+        .stderr(std::process::Stdio::null());
+    #[cfg(windows)]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    command.spawn().map_err(|e| tr!("flash.start_failed", program, e))
+}
+
+async fn connect_rtt(port: u16) -> Result<TcpStream, String> {
+    // OpenOCD needs a moment to attach before the server accepts connections
+    let mut last_error = None;
+    for _ in 0..RTT_CONNECT_ATTEMPTS {
+        match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+        sleep(Duration::from_millis(250)).await;
+    }
+    Err(tr!("trace.connect_failed", port, last_error.map(|e| e.to_string()).unwrap_or_default()))
+}
+
+async fn pump<R: AsyncBufRead + Unpin>(
+    window: &Window,
+    trace_id: &str,
+    reader: R,
+    file: Option<&Path>,
+) -> Result<(), String> {
+    let mut out = match file {
+        Some(path) => Some(
+            File::create(path)
+                .await
+                .map_err(|e| tr!("fs.create", path.display(), e))?,
+        ),
+        None => None,
+    };
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+        if let Some(out) = out.as_mut() {
+            out.write_all(format!("{}\n", line).as_bytes()).await.map_err(|e| e.to_string())?;
+        }
+        emit_to_window(window, "trace-line", &TraceLine { trace_id: trace_id.to_string(), line });
+    }
+    Ok(())
+}
+
+async fn run_trace(window: &Window, trace_id: &str, request: &TraceRequest, file: Option<&Path>) -> Result<(), String> {
+    let args = request.args()?;
+    let program = request.program();
+    match request.mode {
+        TraceMode::Swo => {
+            let mut child = spawn_tool(&program, &args, true)?;
+            let stdout = child.stdout.take().expect("Failed to capture stdout");
+            pump(window, trace_id, BufReader::new(stdout), file).await?;
+            let status = child.wait().await.map_err(|e| tr!("process.wait_failed", e))?;
+            match status.code() {
+                Some(0) | None => Ok(()),
+                Some(code) => Err(tr!("trace.tool_failed", code)),
+            }
+        }
+        TraceMode::Rtt => {
+            // Kept alive (and killed on drop) for as long as the stream is read
+            let _openocd = spawn_tool(&program, &args, false)?;
+            let stream = connect_rtt(request.rtt_port.unwrap_or(DEFAULT_RTT_PORT)).await?;
+            pump(window, trace_id, BufReader::new(stream), file).await
+        }
+    }
+}
+
+/// Starts capturing SWO or RTT output. Lines are emitted as `trace-line`
+/// events and, when the request names a run, written into that run's log
+/// folder. Runs until `stop_trace` or until the tool exits.
+#[command]
+pub async fn start_trace(window: Window, request: TraceRequest) -> Result<TraceSession, String> {
+    // Fail early on incomplete settings rather than in the background task
+    request.args()?;
+    let file = trace_file(&window, &request)?;
+    let trace_id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+
+    let session = TraceSession {
+        trace_id: trace_id.clone(),
+        file: file.as_ref().map(|f| f.to_string_lossy().into_owned()),
+    };
+    let task_id = trace_id.clone();
+    let handle = tokio::spawn(async move {
+        let error = run_trace(&window, &task_id, &request, file.as_deref()).await.err();
+        TRACES.lock().unwrap().remove(&task_id);
+        emit_to_window(&window, "trace-stopped", &TraceStopped { trace_id: task_id, error });
+    });
+    TRACES.lock().unwrap().insert(trace_id, handle);
+    Ok(session)
+}
+
+#[command]
+pub async fn stop_trace(window: Window, trace_id: String) -> Result<(), String> {
+    let handle = TRACES
+        .lock()
+        .unwrap()
+        .remove(&trace_id)
+        .ok_or_else(|| tr!("trace.not_running", trace_id))?;
+    // Aborting drops the child process, which kills it
+    handle.abort();
+    emit_to_window(&window, "trace-stopped", &TraceStopped { trace_id, error: None });
+    Ok(())
+}
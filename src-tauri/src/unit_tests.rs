@@ -0,0 +1,196 @@
+use crate::analysis::{run_tool, GateAction};
+use crate::compile_db::split_command;
+use crate::i18n::tr;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_COMMAND: &str = "ceedling test:all";
+// Written by Ceedling's `report_tests_log_factory` plugin with the junit format
+const DEFAULT_RESULTS: &str = "build/artifacts/test/junit_tests_report.xml";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStage {
+    /// Once, before the first combination is built.
+    #[default]
+    Pre,
+    /// Once, after every combination built successfully.
+    Post,
+}
+
+/// Unit tests (Ceedling, or anything writing JUnit XML) run with the build.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UnitTestSettings {
+    /// Run in the project directory; `ceedling test:all` by default.
+    pub command: Option<String>,
+    /// JUnit XML the command writes, relative to the project.
+    pub results: Option<String>,
+    #[serde(default)]
+    pub stage: TestStage,
+    #[serde(default)]
+    pub action: GateAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Error,
+    Skipped,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TestCase {
+    pub suite: String,
+    pub name: String,
+    pub status: TestStatus,
+    pub time_secs: Option<f64>,
+    /// Failure or error message.
+    pub message: Option<String>,
+}
+
+/// Outcome of the unit tests of a run, recorded in its manifest.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UnitTestReport {
+    pub stage: TestStage,
+    pub exit_code: i32,
+    pub tests: usize,
+    pub failures: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    pub cases: Vec<TestCase>,
+}
+
+impl UnitTestReport {
+    pub fn passed(&self) -> bool {
+        self.exit_code == 0 && self.failures == 0 && self.errors == 0
+    }
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+fn new_case(element: &BytesStart) -> TestCase {
+    TestCase {
+        suite: attribute(element, b"classname").unwrap_or_default(),
+        name: attribute(element, b"name").unwrap_or_default(),
+        status: TestStatus::Passed,
+        time_secs: attribute(element, b"time").and_then(|t| t.parse().ok()),
+        message: None,
+    }
+}
+
+/// Test cases of a JUnit XML report.
+pub fn parse_junit(xml: &str) -> Result<Vec<TestCase>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut cases = Vec::new();
+    let mut current: Option<TestCase> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"testcase" => current = Some(new_case(e)),
+            Ok(Event::Empty(ref e)) if e.name().as_ref() == b"testcase" => cases.push(new_case(e)),
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let status = match e.name().as_ref() {
+                    b"failure" => TestStatus::Failed,
+                    b"error" => TestStatus::Error,
+                    b"skipped" => TestStatus::Skipped,
+                    _ => continue,
+                };
+                if let Some(case) = current.as_mut() {
+                    case.status = status;
+                    case.message = attribute(e, b"message");
+                }
+            }
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"testcase" => cases.extend(current.take()),
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(tr!("tests.results_invalid", e)),
+            _ => (),
+        }
+    }
+    Ok(cases)
+}
+
+/// Runs the unit tests and reads their JUnit results. A failing command
+/// without results (e.g. a compile error in a test) is an error.
+pub async fn run(settings: &UnitTestSettings, project_path: &Path) -> Result<UnitTestReport, String> {
+    let mut words = split_command(settings.command.as_deref().unwrap_or(DEFAULT_COMMAND)).into_iter();
+    let program = words.next().ok_or_else(|| tr!("tests.command_empty"))?;
+    let args: Vec<String> = words.collect();
+    let results = project_path.join(settings.results.as_deref().unwrap_or(DEFAULT_RESULTS));
+    // Do not report the results of an earlier run
+    fs::remove_file(&results).ok();
+
+    let (exit_code, stdout, stderr) = run_tool(&program, &args, project_path).await?;
+    let Ok(xml) = fs::read_to_string(&results) else {
+        let last_line = stderr.iter().chain(stdout.iter()).rev().find(|l| !l.trim().is_empty()).cloned();
+        return Err(tr!("tests.no_results", results.display(), exit_code, last_line.unwrap_or_default()));
+    };
+    let cases = parse_junit(&xml)?;
+    let count = |status: TestStatus| cases.iter().filter(|c| c.status == status).count();
+    Ok(UnitTestReport {
+        stage: settings.stage,
+        exit_code,
+        tests: cases.len(),
+        failures: count(TestStatus::Failed),
+        errors: count(TestStatus::Error),
+        skipped: count(TestStatus::Skipped),
+        cases,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // As written by Ceedling's junit report
+    const REPORT: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<testsuites tests="3" failures="1">
+  <testsuite name="test_led" tests="3" failures="1" skipped="1" time="0.01">
+    <testcase name="test_led_on" classname="test_led" time="0.002"/>
+    <testcase name="test_led_off" classname="test_led" time="0.003">
+      <failure message="Expected 0 Was 1" type="Assertion">test/test_led.c:24</failure>
+    </testcase>
+    <testcase name="test_led_blink" classname="test_led" time="0">
+      <skipped message="Not implemented"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+
+    #[test]
+    fn reads_passed_failed_and_skipped_cases() {
+        let cases = parse_junit(REPORT).unwrap();
+        let summary: Vec<(&str, TestStatus, Option<&str>)> = cases
+            .iter()
+            .map(|c| (c.name.as_str(), c.status, c.message.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("test_led_on", TestStatus::Passed, None),
+                ("test_led_off", TestStatus::Failed, Some("Expected 0 Was 1")),
+                ("test_led_blink", TestStatus::Skipped, Some("Not implemented")),
+            ]
+        );
+        assert_eq!(cases[0].suite, "test_led");
+        assert_eq!(cases[1].time_secs, Some(0.003));
+    }
+
+    #[test]
+    fn malformed_report_is_an_error() {
+        let cut = REPORT.replace("</testcase>", "");
+        assert!(parse_junit(&cut).is_err());
+        assert!(parse_junit("<testsuite><testcase name=\"a></testsuite>").is_err());
+    }
+}
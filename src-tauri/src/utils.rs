@@ -1,35 +1,40 @@
-use chrono::Local;
 use quick_xml::reader::Reader;
 use quick_xml::events::Event;
 use std::fs;
-use std::path::Path;
-use tauri::{Error, command};
+use std::path::{Path, PathBuf};
+use tauri::command;
+use crate::i18n::tr;
+use crate::error::BuildError;
+use stm32_builder_core::cproject::{header_placement, read_cproject, ConfigurationDetails, CprojectConfiguration, HeaderPlacement};
+use stm32_builder_core::paths;
+use stm32_builder_core::project::{read_description, FoundProject, LinkedResource};
 use quick_xml::name::QName;
 // use tauri::Window;
 // use crate::utils::{log_with_timestamp, LogLevel};
 
-pub fn validate_project_file(project_path: &Path) -> Result<(), tauri::Error> {
+pub fn validate_project_file(project_path: &Path) -> Result<(), BuildError> {
     let project_file = project_path.join(".project");
     let content = fs::read_to_string(&project_file)
-        .map_err(|e| tauri::Error::from(anyhow::anyhow!("Error reading '{}': {}", project_file.display(), e)))?;
+        .map_err(|e| BuildError::path(&project_file, tr!("fs.read", project_file.display(), e)))?;
     if !content.contains("<projectDescription>") {
-        return Err(tauri::Error::from(anyhow::anyhow!("File '{}' is not a valid .project file", project_file.display())));
+        return Err(BuildError::config(tr!("project.invalid_file", project_file.display())));
     }
     Ok(())
 }
 
-pub fn validate_cproject_file(project_path: &Path) -> Result<(), tauri::Error> {
+pub fn validate_cproject_file(project_path: &Path) -> Result<(), BuildError> {
     let cproject_file = project_path.join(".cproject");
     let content = fs::read_to_string(&cproject_file)
-        .map_err(|e| tauri::Error::from(anyhow::anyhow!("Error reading '{}': {}", cproject_file.display(), e)))?;
+        .map_err(|e| BuildError::path(&cproject_file, tr!("fs.read", cproject_file.display(), e)))?;
     if !content.contains("<cproject") {
-        return Err(tauri::Error::from(anyhow::anyhow!("File '{}' is not a valid .cproject file", cproject_file.display())));
+        return Err(BuildError::config(tr!("cproject.invalid_file", cproject_file.display())));
     }
     Ok(())
 }
 
 // Log levels
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Debug,
     Warning,
@@ -43,58 +48,24 @@ pub fn validate_path(path: String) -> Result<(), String> {
     if path.exists() && path.is_dir() {
         Ok(())
     } else {
-        Err(format!("Path '{}' does not exist or is not a directory", path.display()))
+        Err(tr!("path.not_a_directory", path.display()))
     }
 }
-// Check if message should be logged based on level
-fn should_log(_level: &LogLevel) -> bool {
-    true // Log all levels for debugging
-}
-
-pub fn log_with_timestamp(msg: &str, level: LogLevel) -> String {
-    if should_log(&level) {
-        format!("[{}] [{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), format!("{:?}", level), msg)
-    } else {
-        String::new()
-    }
-}
-
 // pub fn quote_path(path: &str) -> String {
 //     format!("\"{}\"", path)
 // }
 
-pub fn get_project_name(project_path: &Path) -> Result<String, Error> {
-    let project_file = project_path.join(".project");
-    if !project_file.exists() {
-        return Err(Error::from(anyhow::anyhow!(".project file not found")));
-    }
-    let xml_content = fs::read_to_string(&project_file)
-        .map_err(|e| Error::from(anyhow::anyhow!(e.to_string())))?;
-    let mut reader = Reader::from_str(&xml_content);
-    reader.config_mut().trim_text(true);
-
-    loop {
-        match reader.read_event() {
-            Ok(Event::Start(ref e)) if e.name().as_ref() == b"name" => {
-                if let Ok(Event::Text(text)) = reader.read_event() {
-                    return Ok(text.unescape().map_err(|e| Error::from(anyhow::anyhow!(e.to_string())))?.into_owned());
-                }
-            }
-            Ok(Event::Eof) => break,
-            Err(e) => return Err(Error::from(anyhow::anyhow!(e.to_string()))),
-            _ => (),
-        }
-    }
-    Err(Error::from(anyhow::anyhow!("Project name not found in .project file")))
+pub fn get_project_name(project_path: &Path) -> Result<String, BuildError> {
+    stm32_builder_core::project::project_name(project_path).map_err(BuildError::config)
 }
 
-pub fn get_cproject_configurations(project_path: &Path) -> Result<Vec<String>, Error> {
+pub fn get_cproject_configurations(project_path: &Path) -> Result<Vec<String>, BuildError> {
     let cproject_file = project_path.join(".cproject");
     if !cproject_file.exists() {
-        return Err(Error::from(anyhow::anyhow!(".cproject file not found")));
+        return Err(BuildError::path(&cproject_file, tr!("cproject.missing")));
     }
     let xml_content = fs::read_to_string(&cproject_file)
-        .map_err(|e| Error::from(anyhow::anyhow!(e.to_string())))?;
+        .map_err(|e| BuildError::path(&cproject_file, tr!("fs.read", cproject_file.display(), e)))?;
     let mut reader = Reader::from_str(&xml_content);
     reader.config_mut().trim_text(true);
     let mut configs = Vec::new();
@@ -105,12 +76,11 @@ pub fn get_cproject_configurations(project_path: &Path) -> Result<Vec<String>, E
             Ok(Event::Start(ref e)) if e.name().as_ref() == b"configuration" => {
                 _in_configuration = true;
                 for attr in e.attributes() {
-                    if let Ok(attr) = attr {
-                        if attr.key.as_ref() == b"name" {
-                            if let Ok(value) = attr.unescape_value() {
-                                configs.push(value.into_owned());
-                            }
-                        }
+                    if let Ok(attr) = attr
+                        && attr.key.as_ref() == b"name"
+                        && let Ok(value) = attr.unescape_value()
+                    {
+                        configs.push(value.into_owned());
                     }
                 }
             }
@@ -118,27 +88,149 @@ pub fn get_cproject_configurations(project_path: &Path) -> Result<Vec<String>, E
                 _in_configuration = false;
             }
             Ok(Event::Eof) => break,
-            Err(e) => return Err(Error::from(anyhow::anyhow!(format!("Error parsing .cproject: {}", e)))),
+            Err(e) => return Err(BuildError::config(tr!("cproject.parse", e))),
             _ => (),
         }
     }
     Ok(configs)
 }
 
+/// The project folder in the form the builder uses, so paths derived from
+/// it match the ones of a build; `path` itself when it cannot be resolved.
+fn project_dir(path: &str) -> PathBuf {
+    paths::canonicalize(Path::new(path)).unwrap_or_else(|_| PathBuf::from(path))
+}
+
 #[command]
 pub async fn get_project_configurations(project_path: String) -> Result<Vec<String>, String> {
-    let project_path = Path::new(&project_path);
+    let project_path = &project_dir(&project_path);
     match get_cproject_configurations(project_path) {
         Ok(configs) => Ok(configs),
-        Err(e) => Err(format!("Failed to get project configurations: {}", e))
+        Err(e) => Err(tr!("project.configurations_failed", e))
     }
 }
 
 #[command]
 pub async fn get_project_name_from_path(project_path: String) -> Result<String, String> {
-    let project_path = Path::new(&project_path);
+    let project_path = &project_dir(&project_path);
     match get_project_name(project_path) {
         Ok(name) => Ok(name),
-        Err(e) => Err(format!("Failed to get project name: {}", e))
+        Err(e) => Err(tr!("project.name", e))
+    }
+}
+
+/// Files and folders of the project that live outside its folder, with
+/// where they resolve to on disk.
+#[command]
+pub async fn get_linked_resources(project_path: String) -> Result<Vec<LinkedResource>, String> {
+    match read_description(&project_dir(&project_path)) {
+        Ok(description) => Ok(description.linked_resources),
+        Err(e) => Err(tr!("project.linked_resources_failed", e))
     }
-}
\ No newline at end of file
+}
+
+/// Eclipse projects anywhere under `root_path`, e.g. a repository checkout,
+/// with their names.
+#[command]
+pub async fn find_projects(root_path: String) -> Result<Vec<FoundProject>, String> {
+    let root = Path::new(&root_path);
+    if !root.is_dir() {
+        return Err(tr!("path.not_a_directory", root.display()));
+    }
+    let root = paths::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    tokio::task::spawn_blocking(move || stm32_builder_core::project::find_projects(&root))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Cortex core of each STM32 line.
+const CORES: &[(&str, &str)] = &[
+    ("STM32F0", "cortex-m0"),
+    ("STM32C0", "cortex-m0plus"),
+    ("STM32G0", "cortex-m0plus"),
+    ("STM32L0", "cortex-m0plus"),
+    ("STM32F1", "cortex-m3"),
+    ("STM32F2", "cortex-m3"),
+    ("STM32L1", "cortex-m3"),
+    ("STM32F3", "cortex-m4"),
+    ("STM32F4", "cortex-m4"),
+    ("STM32G4", "cortex-m4"),
+    ("STM32L4", "cortex-m4"),
+    ("STM32WB", "cortex-m4"),
+    ("STM32WL", "cortex-m4"),
+    ("STM32F7", "cortex-m7"),
+    ("STM32H7", "cortex-m7"),
+    ("STM32H5", "cortex-m33"),
+    ("STM32L5", "cortex-m33"),
+    ("STM32U5", "cortex-m33"),
+];
+
+/// Target and code generation options of one configuration.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ToolchainInfo {
+    pub configuration: String,
+    /// e.g. `STM32F407VGTx`.
+    pub mcu: Option<String>,
+    /// `-mcpu`, derived from the MCU line; the main core of dual-core parts.
+    pub cpu: Option<String>,
+    /// `-mfpu`, e.g. `fpv4-sp-d16`; `None` when the project sets none.
+    pub fpu: Option<String>,
+    /// `-mfloat-abi`: `soft`, `softfp` or `hard`.
+    pub float_abi: Option<String>,
+    /// C compiler optimization flag, e.g. `-Os`; `None` when the project
+    /// keeps CubeIDE's default for the configuration.
+    pub optimization: Option<String>,
+}
+
+impl ToolchainInfo {
+    fn from_configuration(config: &CprojectConfiguration) -> Self {
+        let mcu = config.option(".option.target_mcu").and_then(|o| o.value.clone());
+        let cpu = mcu.as_deref().and_then(|mcu| {
+            let mcu = mcu.to_uppercase();
+            CORES.iter().find(|(line, _)| mcu.starts_with(line)).map(|(_, core)| core.to_string())
+        });
+        ToolchainInfo {
+            configuration: config.name.clone(),
+            cpu,
+            mcu,
+            fpu: config.enum_value(".option.fpu").filter(|fpu| fpu != "no" && fpu != "none"),
+            float_abi: config.enum_value(".option.floatabi"),
+            optimization: config
+                .enum_value("c.compiler.option.optimization.level")
+                .map(|level| format!("-O{}", level.trim_start_matches('o'))),
+        }
+    }
+}
+
+#[command]
+pub async fn get_project_toolchain(project_path: String) -> Result<Vec<ToolchainInfo>, String> {
+    let project_path = &project_dir(&project_path);
+    match read_cproject(project_path) {
+        Ok(configs) => Ok(configs.iter().map(ToolchainInfo::from_configuration).collect()),
+        Err(e) => Err(tr!("project.toolchain_failed", e))
+    }
+}
+
+#[command]
+pub async fn get_configuration_details(project_path: String) -> Result<Vec<ConfigurationDetails>, String> {
+    let project_path = &project_dir(&project_path);
+    let details = get_project_name(project_path).and_then(|name| {
+        let configs = read_cproject(project_path).map_err(BuildError::config)?;
+        Ok(configs.iter().map(|c| ConfigurationDetails::new(c, &name, project_path)).collect())
+    });
+    match details {
+        Ok(details) => Ok(details),
+        Err(e) => Err(tr!("project.configuration_details_failed", e))
+    }
+}
+
+#[command]
+pub async fn suggest_header_dir(project_path: String, config_name: Option<String>) -> Result<HeaderPlacement, String> {
+    let project_path = &project_dir(&project_path);
+    let placement = get_project_name(project_path)
+        .and_then(|name| header_placement(project_path, &name, config_name.as_deref().unwrap_or("Debug")).map_err(BuildError::config));
+    match placement {
+        Ok(placement) => Ok(placement),
+        Err(e) => Err(tr!("project.include_folders_failed", e))
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, Manager, Window};
+use crate::i18n::tr;
+
+const VERSIONS_FILE: &str = "versions.json";
+
+/// Firmware version of a project. `build` counts successful runs and is
+/// kept by the app rather than in the project, so it does not dirty the tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub build: u32,
+}
+
+impl FirmwareVersion {
+    /// `FW_VERSION_MAJOR/MINOR/PATCH/BUILD` for `build_config.h`.
+    pub fn defines(&self) -> String {
+        format!(
+            "#define FW_VERSION_MAJOR {}\n#define FW_VERSION_MINOR {}\n#define FW_VERSION_PATCH {}\n#define FW_VERSION_BUILD {}\n",
+            self.major, self.minor, self.patch, self.build
+        )
+    }
+
+    /// Version as used in artifact names, e.g. `v1.2.3-b45`.
+    pub fn label(&self) -> String {
+        format!("v{}.{}.{}-b{}", self.major, self.minor, self.patch, self.build)
+    }
+}
+
+fn versions_path(window: &Window) -> Result<PathBuf, String> {
+    let dir = window
+        .app_handle()
+        .path()
+        .app_data_dir()
+        .map_err(|e| tr!("app_data.unavailable", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| tr!("fs.create_dir", dir.display(), e))?;
+    Ok(dir.join(VERSIONS_FILE))
+}
+
+fn load_versions(window: &Window) -> Result<HashMap<String, FirmwareVersion>, String> {
+    let path = versions_path(window)?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| tr!("fs.read", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| tr!("versions.invalid", path.display(), e))
+}
+
+pub fn save_version(window: &Window, project_path: &str, version: FirmwareVersion) -> Result<(), String> {
+    let mut versions = load_versions(window)?;
+    versions.insert(project_path.to_string(), version);
+    let path = versions_path(window)?;
+    let json = serde_json::to_string_pretty(&versions)
+        .map_err(|e| tr!("versions.serialize_failed", e))?;
+    fs::write(&path, json).map_err(|e| tr!("fs.write", path.display(), e))
+}
+
+/// Version of the project's last successful run.
+pub fn current(window: &Window, project_path: &str) -> Result<FirmwareVersion, String> {
+    Ok(load_versions(window)?.get(project_path).copied().unwrap_or_default())
+}
+
+/// Version a new run builds: the current one with the next build number.
+/// It is only stored with `save_version` once the run succeeded.
+pub fn next_build(window: &Window, project_path: &str) -> Result<FirmwareVersion, String> {
+    let current = current(window, project_path)?;
+    Ok(FirmwareVersion { build: current.build + 1, ..current })
+}
+
+#[command]
+pub async fn get_firmware_version(window: Window, project_path: String) -> Result<FirmwareVersion, String> {
+    current(&window, &project_path)
+}
+
+/// Sets the project's version, e.g. to start a new minor release. The
+/// next run is built as `build + 1`.
+#[command]
+pub async fn set_firmware_version(window: Window, project_path: String, version: FirmwareVersion) -> Result<(), String> {
+    save_version(&window, &project_path, version)
+}
@@ -0,0 +1,49 @@
+use crate::preflight::find_tool;
+use std::path::{Path, PathBuf};
+use stm32_builder_core::builder::MakeBuild;
+use stm32_builder_core::runner::ProcessSpec;
+
+// CubeIDE plugins bundling the tools the generated makefiles call
+const MAKE_PLUGIN: &str = "externaltools.make";
+const TOOLCHAIN_PLUGIN: &str = "externaltools.gnu-tools-for-stm32";
+
+/// `make` and the GNU toolchain shipped with CubeIDE, for rebuilding the
+/// combinations after the first one without starting CubeIDE again.
+#[derive(Clone, Debug)]
+pub struct MakeTools {
+    make: PathBuf,
+    /// `PATH` with the toolchain and make folders first, as CubeIDE sets it.
+    path: String,
+}
+
+impl MakeTools {
+    /// Looks the tools up next to the CubeIDE executable, then on `PATH`.
+    pub fn find(cube_ide_exe: &Path) -> Option<MakeTools> {
+        let ide_dir = cube_ide_exe.parent()?;
+        let make = find_tool(ide_dir, MAKE_PLUGIN, "make")?;
+        let gcc = find_tool(ide_dir, TOOLCHAIN_PLUGIN, "arm-none-eabi-gcc")?;
+        let inherited = std::env::var_os("PATH").unwrap_or_default();
+        let dirs = [gcc.parent(), make.parent()]
+            .into_iter()
+            .flatten()
+            .map(Path::to_path_buf)
+            .chain(std::env::split_paths(&inherited));
+        let path = std::env::join_paths(dirs).ok()?.to_string_lossy().into_owned();
+        Some(MakeTools { make, path })
+    }
+
+    /// Process rebuilding the configuration in `build_dir`; `None` when no
+    /// headless build generated its makefiles yet.
+    pub fn spec(&self, build_dir: &Path, clean: bool, project_path: &Path) -> Option<ProcessSpec> {
+        if !build_dir.join("makefile").is_file() {
+            return None;
+        }
+        let jobs = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Some(ProcessSpec {
+            program: self.make.clone().into(),
+            args: MakeBuild { build_dir, clean, jobs }.args(),
+            cwd: project_path.to_path_buf(),
+            envs: vec![("PATH".to_string(), self.path.clone())],
+        })
+    }
+}
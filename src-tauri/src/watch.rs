@@ -0,0 +1,242 @@
+use crate::builder::run_project;
+use crate::i18n::tr;
+use crate::logging::{emit_to_window, Logger};
+use crate::models::BuildConfig;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use stm32_builder_core::builder::new_run_id;
+use stm32_builder_core::cproject::read_cproject;
+use stm32_builder_core::paths;
+use stm32_builder_core::selection::Selection;
+use tauri::{command, Manager, Window};
+use tokio::sync::mpsc;
+
+pub const WATCH_TRIGGERED: &str = "watch-triggered";
+
+// Editors save in several steps; changes this close together rebuild once
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+const DEFAULT_INCLUDE: &[&str] = &["**/*.c", "**/*.h"];
+// The header the builder writes
+const DEFAULT_EXCLUDE: &[&str] = &["**/build_config.h"];
+// Output folders of CubeIDE's default configurations, for a `.cproject`
+// that cannot be read
+const DEFAULT_OUTPUT_DIRS: &[&str] = &["Debug", "Release"];
+
+/// What a watch reacts to. Globs are matched against paths relative to
+/// the project folder.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WatchSettings {
+    /// Files that trigger a rebuild; `**/*.c` and `**/*.h` when not given.
+    pub include: Option<Vec<String>>,
+    /// Files never triggering a rebuild, on top of the generated
+    /// `build_config.h`, the output folder of every configuration and the
+    /// builder's output folder when it is inside the project.
+    pub exclude: Option<Vec<String>>,
+    /// Rebuild once no file changed for this long (500 ms by default).
+    #[serde(rename = "debounceMs")]
+    pub debounce_ms: Option<u64>,
+}
+
+/// Payload of the `watch-triggered` event, emitted before a watch rebuild.
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchTrigger {
+    pub run_id: String,
+    /// Changed files, relative to the project folder.
+    pub files: Vec<String>,
+}
+
+/// Include and exclude globs of a watch.
+struct WatchFilter {
+    root: PathBuf,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+fn glob_set<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| tr!("watch.invalid_glob", pattern, e))?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Globs of the folders builds write into: CDT's folder of each of the
+/// project's configurations and `build_dir`, where the runs go, if it is
+/// inside the project.
+fn output_excludes(root: &Path, build_dir: &Path) -> Vec<String> {
+    let mut dirs: Vec<String> = match read_cproject(root) {
+        Ok(configs) if !configs.is_empty() => configs
+            .into_iter()
+            .flat_map(|c| {
+                // `${workspace_loc:/blinky}/Debug` when it is not the configuration's name
+                let build_path = c.build_path.as_deref().and_then(|p| p.rsplit_once("}/")).map(|(_, dir)| dir.to_string());
+                std::iter::once(c.name).chain(build_path)
+            })
+            .collect(),
+        _ => DEFAULT_OUTPUT_DIRS.iter().map(|d| d.to_string()).collect(),
+    };
+    let build_dir = paths::canonicalize(build_dir).unwrap_or_else(|_| build_dir.to_path_buf());
+    if let Ok(relative) = build_dir.strip_prefix(root)
+        && !relative.as_os_str().is_empty()
+    {
+        dirs.push(relative.to_string_lossy().replace('\\', "/"));
+    }
+    dirs.iter().map(|dir| format!("{}/**", globset::escape(dir))).collect()
+}
+
+impl WatchFilter {
+    fn new(root: &Path, settings: &WatchSettings, output_dirs: &[String]) -> Result<Self, String> {
+        let include = match &settings.include {
+            Some(patterns) if !patterns.is_empty() => glob_set(patterns.iter().map(String::as_str))?,
+            _ => glob_set(DEFAULT_INCLUDE.iter().copied())?,
+        };
+        let exclude = glob_set(
+            DEFAULT_EXCLUDE
+                .iter()
+                .copied()
+                .chain(output_dirs.iter().map(String::as_str))
+                .chain(settings.exclude.iter().flatten().map(String::as_str)),
+        )?;
+        Ok(WatchFilter { root: root.to_path_buf(), include, exclude })
+    }
+
+    /// Path of a changed file relative to the project, if it is watched.
+    fn matches(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        (self.include.is_match(relative) && !self.exclude.is_match(relative)).then(|| relative.to_path_buf())
+    }
+}
+
+/// Watch mode of every window, registered with `app.manage()`. Dropping a
+/// watcher closes its channel, which ends the rebuild task once its
+/// current build is over.
+#[derive(Default)]
+pub struct WatchManager {
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+fn changed_files(filter: &WatchFilter, event: Event) -> Vec<PathBuf> {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+            event.paths.iter().filter_map(|p| filter.matches(p)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+async fn rebuild_on_change(
+    window: Window,
+    config: BuildConfig,
+    debounce: Duration,
+    mut changes: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+) {
+    let mut logger = Logger::new(&window);
+    // Changes made during a build wait in the channel, so they are rebuilt
+    // once that build is over
+    while let Some(mut files) = changes.recv().await {
+        loop {
+            match tokio::time::timeout(debounce, changes.recv()).await {
+                Ok(Some(more)) => files.extend(more),
+                // Stopped while waiting for the changes to settle
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        files.sort();
+        files.dedup();
+
+        let run_id = new_run_id();
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        logger.info(&tr!("watch.changed", files.join(", ")));
+        emit_to_window(&window, WATCH_TRIGGERED, WatchTrigger { run_id: run_id.clone(), files });
+        if let Err(e) = run_project(window.clone(), config.clone(), run_id).await {
+            logger.error(&tr!("watch.build_failed", e));
+        }
+    }
+}
+
+/// Watches the project's sources and builds `combination` with `config`
+/// whenever `settings` says a change matters, until `stop_watch`. A window
+/// watches one combination at a time; starting a new watch replaces it.
+#[command]
+pub async fn start_watch(
+    window: Window,
+    config: BuildConfig,
+    combination: Selection,
+    settings: Option<WatchSettings>,
+) -> Result<(), String> {
+    let project_path = PathBuf::from(&config.project_path);
+    if !project_path.is_dir() {
+        return Err(tr!("watch.no_project", project_path.display()));
+    }
+    let settings = settings.unwrap_or_default();
+    // Events carry absolute paths, which go through the same symlinks
+    let root = paths::canonicalize(&project_path).unwrap_or_else(|_| project_path.clone());
+    let output_dirs = output_excludes(&root, Path::new(&config.build_dir));
+    let filter = WatchFilter::new(&root, &settings, &output_dirs)?;
+    let debounce = Duration::from_millis(settings.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+    let config = BuildConfig { selected_combinations: Some(vec![combination]), ..config };
+
+    let (sender, changes) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            let files = changed_files(&filter, event);
+            if !files.is_empty() {
+                sender.send(files).ok();
+            }
+        }
+    })
+    .map_err(|e| tr!("watch.failed", project_path.display(), e))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| tr!("watch.failed", project_path.display(), e))?;
+
+    tokio::spawn(rebuild_on_change(window.clone(), config, debounce, changes));
+    window.state::<WatchManager>().watchers.lock().unwrap().insert(window.label().to_string(), watcher);
+    Logger::new(&window).info(&tr!("watch.started", project_path.display()));
+    Ok(())
+}
+
+/// Ends the watch of this window; a build it started runs to completion.
+#[command]
+pub async fn stop_watch(window: Window) -> Result<(), String> {
+    let watcher = window.state::<WatchManager>().watchers.lock().unwrap().remove(window.label());
+    match watcher {
+        Some(_) => Ok(()),
+        None => Err(tr!("watch.not_running")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_folders_of_every_configuration_and_the_run_folder_are_excluded() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = paths::canonicalize(dir.path()).unwrap();
+        std::fs::write(
+            root.join(".cproject"),
+            r#"<cproject><storageModule><cconfiguration><storageModule>
+            <configuration name="Debug"/>
+            <configuration name="Release F4"><folderInfo resourcePath="">
+            <builder buildPath="${workspace_loc:/blinky}/out"/></folderInfo></configuration>
+            </storageModule></cconfiguration></storageModule></cproject>"#,
+        )
+        .unwrap();
+        std::fs::create_dir(root.join("builds")).unwrap();
+
+        let output_dirs = output_excludes(&root, &root.join("builds"));
+        let filter = WatchFilter::new(&root, &WatchSettings::default(), &output_dirs).unwrap();
+        assert_eq!(filter.matches(&root.join("Core/Src/main.c")), Some(PathBuf::from("Core/Src/main.c")));
+        for generated in ["Debug/Core/Src/main.c", "Release F4/main.h", "out/main.c", "builds/run/brd_4/build_config.h", "Core/Inc/build_config.h"] {
+            assert_eq!(filter.matches(&root.join(generated)), None, "{}", generated);
+        }
+    }
+}
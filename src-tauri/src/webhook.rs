@@ -0,0 +1,130 @@
+use crate::i18n::tr;
+use crate::manifest::RunManifest;
+use crate::report::REPORT_FILE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Endpoint that receives a JSON summary of every finished run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// Only post runs that failed or were cancelled.
+    #[serde(rename = "onlyFailures", default)]
+    pub only_failures: bool,
+    /// Extra request headers, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "run.succeeded")]
+    Succeeded,
+    #[serde(rename = "run.failed")]
+    Failed,
+    #[serde(rename = "run.cancelled")]
+    Cancelled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookCombination {
+    pub combination: String,
+    pub success: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub failure_reason: Option<String>,
+    /// `file://` URL of the renamed `.bin`.
+    pub artifact: Option<String>,
+    pub artifact_size: Option<u64>,
+}
+
+/// Body posted to the webhooks.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub run_id: String,
+    pub project_path: String,
+    pub config_name: Option<String>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub total_errors: usize,
+    pub total_warnings: usize,
+    pub failure_reason: Option<String>,
+    /// `file://` URL of the run's HTML report.
+    pub report: Option<String>,
+    pub combinations: Vec<WebhookCombination>,
+}
+
+fn file_url(path: &Path) -> String {
+    let path = path.display().to_string().replace('\\', "/");
+    format!("file:///{}", path.trim_start_matches('/'))
+}
+
+impl WebhookPayload {
+    pub fn new(manifest: &RunManifest, failure_reason: Option<String>) -> Self {
+        let event = if manifest.cancelled {
+            WebhookEvent::Cancelled
+        } else if manifest.success {
+            WebhookEvent::Succeeded
+        } else {
+            WebhookEvent::Failed
+        };
+        let report = Path::new(&manifest.output_dir).join(REPORT_FILE);
+        WebhookPayload {
+            event,
+            run_id: manifest.run_id.clone(),
+            project_path: manifest.project_path.clone(),
+            config_name: manifest.config_name.clone(),
+            started_at: manifest.started_at.clone(),
+            finished_at: manifest.finished_at.clone(),
+            total_errors: manifest.total_errors,
+            total_warnings: manifest.total_warnings,
+            failure_reason,
+            report: report.exists().then(|| file_url(&report)),
+            combinations: manifest
+                .combinations
+                .iter()
+                .map(|c| WebhookCombination {
+                    combination: c.combination.clone(),
+                    success: c.success,
+                    errors: c.errors,
+                    warnings: c.warnings,
+                    failure_reason: c.failure_reason.clone(),
+                    artifact: c.artifact.as_deref().map(|a| file_url(Path::new(a))),
+                    artifact_size: c.artifact_size,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn post(hook: &Webhook, payload: &WebhookPayload) -> Result<(), String> {
+    let mut request = ureq::post(&hook.url).timeout(TIMEOUT);
+    for (name, value) in &hook.headers {
+        request = request.set(name, value);
+    }
+    request
+        .send_json(payload)
+        .map(|_| ())
+        .map_err(|e| tr!("webhook.failed", hook.url, e))
+}
+
+/// Posts the run's outcome to every webhook that wants it; returns the
+/// errors of those that could not be reached.
+pub async fn notify(hooks: &[Webhook], payload: WebhookPayload) -> Vec<String> {
+    let hooks: Vec<Webhook> = hooks
+        .iter()
+        .filter(|hook| !hook.only_failures || payload.event != WebhookEvent::Succeeded)
+        .cloned()
+        .collect();
+    if hooks.is_empty() {
+        return Vec::new();
+    }
+    tokio::task::spawn_blocking(move || hooks.iter().filter_map(|hook| post(hook, &payload).err()).collect())
+        .await
+        .unwrap_or_default()
+}
@@ -0,0 +1,200 @@
+use crate::i18n::tr;
+use crate::utils::get_project_name;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use stm32_builder_core::paths;
+use tauri::command;
+
+// Eclipse keeps one folder per imported project here
+const PROJECTS_DIR: &str = ".metadata/.plugins/org.eclipse.core.resources/.projects";
+
+/// State of a CubeIDE workspace before a headless build uses it.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct WorkspaceValidation {
+    pub path: String,
+    pub exists: bool,
+    /// `.metadata` is present, i.e. Eclipse has used the folder before. A
+    /// new workspace is fine: the build imports the project into it.
+    pub initialized: bool,
+    /// A running Eclipse holds `.metadata/.lock`; a headless build would
+    /// fail with "Workspace is in use".
+    pub locked: bool,
+    pub project_name: Option<String>,
+    /// `None` when no project was given or the workspace is new.
+    pub project_imported: Option<bool>,
+    /// Folder the workspace builds for the project, which may be another
+    /// checkout than the one selected.
+    pub project_location: Option<String>,
+    /// Nothing above stands in the way of a build.
+    pub ready: bool,
+}
+
+/// Whether another process holds the Eclipse workspace lock. The lock file
+/// outlives Eclipse, so only an actual lock on it counts.
+#[cfg(unix)]
+pub fn is_locked(workspace: &Path) -> bool {
+    use nix::fcntl::{fcntl, FcntlArg};
+    use nix::libc;
+    use std::os::fd::AsRawFd;
+
+    let Ok(file) = File::options().read(true).write(true).open(workspace.join(".metadata/.lock")) else {
+        return false;
+    };
+    // Java's FileLock is a POSIX record lock, invisible to flock()
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    match fcntl(file.as_raw_fd(), FcntlArg::F_GETLK(&mut lock)) {
+        Ok(_) => lock.l_type != libc::F_UNLCK as libc::c_short,
+        Err(_) => false,
+    }
+}
+
+#[cfg(windows)]
+pub fn is_locked(workspace: &Path) -> bool {
+    let Ok(file) = File::options().read(true).write(true).open(workspace.join(".metadata/.lock")) else {
+        // Cannot even be opened while another process holds it exclusively
+        return workspace.join(".metadata/.lock").exists();
+    };
+    // Dropping the file releases the lock again
+    matches!(file.try_lock(), Err(std::fs::TryLockError::WouldBlock))
+}
+
+/// Decodes the `%XX` escapes of a file URI.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads the `URI//file:/...` a project's `.location` file points to.
+fn read_location(location_file: &Path) -> Option<PathBuf> {
+    let content = fs::read(location_file).ok()?;
+    let start = content.windows(5).position(|w| w == b"URI//")?;
+    // Written with Java's writeUTF: a big-endian length, then the string
+    let len = u16::from_be_bytes([*content.get(start.checked_sub(2)?)?, content[start - 1]]) as usize;
+    let uri = std::str::from_utf8(content.get(start..start + len)?).ok()?;
+    let path = percent_decode(uri.strip_prefix("URI//file:")?);
+    // `file:/C:/...` on Windows, `file:/home/...` elsewhere
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => &path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Folder of a project imported into the workspace, `None` if it is not.
+pub fn imported_project_location(workspace: &Path, project_name: &str) -> Option<PathBuf> {
+    let metadata = workspace.join(PROJECTS_DIR).join(project_name);
+    if !metadata.is_dir() {
+        return None;
+    }
+    // Projects inside the workspace folder have no `.location`
+    read_location(&metadata.join(".location")).or_else(|| Some(workspace.join(project_name)))
+}
+
+fn same_folder(a: &Path, b: &Path) -> bool {
+    match (paths::canonicalize(a), paths::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Project imported into a workspace.
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkspaceProject {
+    pub name: String,
+    pub path: String,
+    /// The folder is still there with its `.project`; Eclipse keeps
+    /// projects whose checkout was deleted.
+    pub exists: bool,
+}
+
+/// Projects imported into the workspace, sorted by name.
+pub fn workspace_projects(workspace: &Path) -> Result<Vec<WorkspaceProject>, String> {
+    let projects_dir = workspace.join(PROJECTS_DIR);
+    if !projects_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&projects_dir).map_err(|e| tr!("fs.read", projects_dir.display(), e))?;
+    let mut projects: Vec<WorkspaceProject> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().into_string().ok())
+        // Eclipse's own hidden projects, e.g. `.org.eclipse.jdt.core.external.folders`
+        .filter(|name| !name.starts_with('.'))
+        .filter_map(|name| {
+            let location = imported_project_location(workspace, &name)?;
+            Some(WorkspaceProject {
+                exists: location.join(".project").is_file(),
+                path: location.display().to_string(),
+                name,
+            })
+        })
+        .collect();
+    projects.sort_by_key(|a| a.name.to_lowercase());
+    Ok(projects)
+}
+
+/// Projects imported into a CubeIDE workspace and the folders they are
+/// built from, read from the workspace metadata.
+#[command]
+pub async fn list_workspace_projects(workspace_path: String) -> Result<Vec<WorkspaceProject>, String> {
+    let workspace = Path::new(&workspace_path);
+    if !workspace.is_dir() {
+        return Err(tr!("path.not_a_directory", workspace.display()));
+    }
+    workspace_projects(workspace)
+}
+
+/// Checks a workspace before a headless build: whether it exists, is in
+/// use by a running CubeIDE and, given `project_path`, whether that
+/// project is imported from the same folder.
+#[command]
+pub async fn validate_workspace(path: String, project_path: Option<String>) -> Result<WorkspaceValidation, String> {
+    let workspace = Path::new(&path);
+    let mut validation = WorkspaceValidation {
+        path: path.clone(),
+        exists: workspace.is_dir(),
+        initialized: workspace.join(".metadata").is_dir(),
+        ..Default::default()
+    };
+    validation.locked = validation.initialized && is_locked(workspace);
+
+    let mut location_matches = true;
+    if let Some(project_path) = project_path.as_deref().map(Path::new) {
+        let name = get_project_name(project_path).map_err(|e| tr!("project.name", e))?;
+        if validation.initialized {
+            let location = imported_project_location(workspace, &name);
+            if let Some(location) = &location {
+                location_matches = same_folder(location, project_path);
+            }
+            validation.project_imported = Some(location.is_some());
+            validation.project_location = location.map(|l| l.display().to_string());
+        }
+        validation.project_name = Some(name);
+    }
+    validation.ready = validation.exists
+        && !validation.locked
+        && validation.project_imported != Some(false)
+        && location_matches;
+    Ok(validation)
+}
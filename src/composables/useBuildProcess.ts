@@ -1,9 +1,19 @@
 import { ref } from 'vue';
 import { listen } from '@tauri-apps/api/event';
 import type { BuildMessage } from '../types/build';
-import type { BuildConfig, BuildStatusType, BuildProcessReturn, LocalBuildConfig, Settings } from '../types/index';
+import type { BuildConfig, BuildError, BuildStatusType, BuildProcessReturn, CombinationResult, LocalBuildConfig, Settings } from '../types/index';
 import { executeBuild, cancelBuild } from '../services/buildService';
 
+// build_project rejects with a BuildError
+function describeError(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  const buildError = error as BuildError | null;
+  if (buildError && typeof buildError === 'object' && 'kind' in buildError) {
+    return 'message' in buildError ? buildError.message : buildError.kind;
+  }
+  return String(error);
+}
+
 export function useBuildProcess(): BuildProcessReturn {
   const settings = ref<Settings>({
     projectPath: null,
@@ -53,6 +63,17 @@ export function useBuildProcess(): BuildProcessReturn {
     buildStatus.value = 'building';
     buildMessages.value = [];
 
+    // Show each combination as soon as it is done instead of after the whole matrix
+    const unlistenCombination = await listen<CombinationResult>('combination-finished', (event) => {
+      const combination = event.payload;
+      buildMessages.value.push({
+        type: combination.success ? 'success' : 'error',
+        text: combination.success
+          ? `${combination.combination}: ${combination.artifact ?? ''}`
+          : `${combination.combination}: ${combination.failure_reason ?? ''}`,
+      });
+    });
+
     try {
       const config: BuildConfig = {
         projectPath: settings.value.projectPath!,
@@ -73,10 +94,7 @@ export function useBuildProcess(): BuildProcessReturn {
       
       const result = await executeBuild(config);
       // Если сборка была отменена через invoke (build_project вернул cancel)
-      if (
-        typeof result.result === 'string' &&
-        result.result.toLowerCase().includes('cancelled')
-      ) {
+      if (result.cancelled) {
         buildStatus.value = 'idle';
         buildMessages.value.push({
           type: 'success',
@@ -95,8 +113,10 @@ export function useBuildProcess(): BuildProcessReturn {
       buildStatus.value = 'error';
       buildMessages.value.push({
         type: 'error',
-        text: `Build error: ${error instanceof Error ? error.message : String(error)}`,
+        text: `Build error: ${describeError(error)}`,
       });
+    } finally {
+      unlistenCombination();
     }
   }
 
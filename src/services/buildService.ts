@@ -1,12 +1,220 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { BuildResult, BuildConfig } from '../types/index';
+import type { BuildResult, BuildConfig, PreflightReport, RunFilter, RunSummary, RunDetails, SizePoint, RecentKind, FlashRequest, FlashResult, FlashSettings, ProbeInfo, FlashBatchRequest, BoardResult, SerialPortInfo, TraceRequest, TraceSession, TargetCheck, ProductionRequest, ProductionSession, FirmwareVersion, ReleaseBundle, VerificationReport, ApiServerInfo, PluginManifest, WatchSettings, FoundProject, LinkedResource, McuInfo, ToolchainInfo, ConfigurationDetails, HeaderPlacement, WorkspaceValidation, WorkspaceProject, BinFooter, ProcessPriority, Middleware, SourceStats } from '../types/index';
 
 export async function executeBuild(config: BuildConfig): Promise<BuildResult> {
   return await invoke<BuildResult>('build_project', { config });
 }
 
-export async function cancelBuild(): Promise<void> {
-  await invoke('cancel_build');
+export async function cancelBuild(runId?: string): Promise<void> {
+  await invoke('cancel_build', { runId });
+}
+
+export async function setMaxConcurrentBuilds(n: number): Promise<void> {
+  await invoke('set_max_concurrent_builds', { n });
+}
+
+export async function setBuildPriority(priority: ProcessPriority): Promise<void> {
+  await invoke('set_build_priority', { priority });
+}
+
+export async function startWatch(config: BuildConfig, combination: Record<string, string>, settings?: WatchSettings): Promise<void> {
+  await invoke('start_watch', { config, combination, settings });
+}
+
+export async function stopWatch(): Promise<void> {
+  await invoke('stop_watch');
+}
+
+export async function setBackendLanguage(language: 'en' | 'ru'): Promise<void> {
+  await invoke('set_language', { language });
+}
+
+export async function runPreflight(config: BuildConfig): Promise<PreflightReport> {
+  return await invoke<PreflightReport>('preflight_check', { config });
+}
+
+export async function listRuns(filter?: RunFilter): Promise<RunSummary[]> {
+  return await invoke<RunSummary[]>('list_runs', { filter });
+}
+
+export async function getRun(runId: string): Promise<RunDetails> {
+  return await invoke<RunDetails>('get_run', { runId });
+}
+
+export async function deleteRun(runId: string): Promise<void> {
+  await invoke('delete_run', { runId });
+}
+
+export async function addRunTag(runId: string, tag: string): Promise<void> {
+  await invoke('add_run_tag', { runId, tag });
+}
+
+export async function removeRunTag(runId: string, tag: string): Promise<void> {
+  await invoke('remove_run_tag', { runId, tag });
+}
+
+export async function setRunNote(runId: string, note: string | null): Promise<void> {
+  await invoke('set_run_note', { runId, note });
+}
+
+export async function getSizeTrend(projectPath: string, combination: string, limit?: number): Promise<SizePoint[]> {
+  return await invoke<SizePoint[]>('get_size_trend', { projectPath, combination, limit });
+}
+
+export async function getRecentEntries(kind: RecentKind): Promise<string[]> {
+  return await invoke<string[]>('get_recent_entries', { kind });
+}
+
+export async function addRecentEntry(kind: RecentKind, value: string): Promise<void> {
+  await invoke('add_recent_entry', { kind, value });
+}
+
+export async function saveProjectConfig(config: BuildConfig): Promise<void> {
+  await invoke('save_project_config', { config });
+}
+
+export async function loadProjectConfig(projectPath: string): Promise<BuildConfig | null> {
+  return await invoke<BuildConfig | null>('load_project_config', { projectPath });
+}
+
+export async function getLastBuildConfig(): Promise<BuildConfig | null> {
+  return await invoke<BuildConfig | null>('get_last_build_config');
+}
+
+export async function exportRunReport(
+  runId: string,
+  format: 'csv' | 'json',
+  destination?: string
+): Promise<string> {
+  return await invoke<string>('export_run_report', { runId, format, destination });
+}
+
+export async function importCombinationSelection(path: string): Promise<Record<string, string>[]> {
+  return await invoke<Record<string, string>[]>('import_combination_selection', { path });
+}
+
+export async function flashFirmware(request: FlashRequest): Promise<FlashResult> {
+  return await invoke<FlashResult>('flash_firmware', { request });
+}
+
+export async function listProbes(settings?: FlashSettings): Promise<ProbeInfo[]> {
+  return await invoke<ProbeInfo[]>('list_probes', { settings });
+}
+
+export async function flashBatch(request: FlashBatchRequest): Promise<BoardResult[]> {
+  return await invoke<BoardResult[]>('flash_batch', { request });
+}
+
+export async function listSerialPorts(): Promise<SerialPortInfo[]> {
+  return await invoke<SerialPortInfo[]>('list_serial_ports');
+}
+
+export async function openSerialPort(port: string, baudRate: number): Promise<void> {
+  await invoke('open_serial_port', { port, baudRate });
+}
+
+export async function closeSerialPort(port: string): Promise<void> {
+  await invoke('close_serial_port', { port });
+}
+
+export async function startTrace(request: TraceRequest): Promise<TraceSession> {
+  return await invoke<TraceSession>('start_trace', { request });
+}
+
+export async function stopTrace(traceId: string): Promise<void> {
+  await invoke('stop_trace', { traceId });
+}
+
+export async function detectTarget(settings: FlashSettings, projectPath: string, probe?: string): Promise<TargetCheck> {
+  return await invoke<TargetCheck>('detect_target', { settings, probe, projectPath });
+}
+
+export async function startProduction(request: ProductionRequest): Promise<ProductionSession> {
+  return await invoke<ProductionSession>('start_production', { request });
+}
+
+export async function stopProduction(sessionId: string): Promise<void> {
+  return await invoke<void>('stop_production', { sessionId });
+}
+
+export async function getFirmwareVersion(projectPath: string): Promise<FirmwareVersion> {
+  return await invoke<FirmwareVersion>('get_firmware_version', { projectPath });
+}
+
+export async function setFirmwareVersion(projectPath: string, version: FirmwareVersion): Promise<void> {
+  return await invoke<void>('set_firmware_version', { projectPath, version });
+}
+
+export async function createReleaseBundle(runId: string, destination?: string, zip?: boolean): Promise<ReleaseBundle> {
+  return await invoke<ReleaseBundle>('create_release_bundle', { runId, destination, zip });
+}
+
+export async function exportCiWorkflow(config: BuildConfig, destination?: string, runsOn?: string[]): Promise<string> {
+  return await invoke<string>('export_ci_workflow', { config, destination, runsOn });
+}
+
+export async function verifyReproducibility(config: BuildConfig, selection: Record<string, string>): Promise<VerificationReport> {
+  return await invoke<VerificationReport>('verify_reproducibility', { config, selection });
+}
+
+export async function startApiServer(port?: number, token?: string): Promise<ApiServerInfo> {
+  return await invoke<ApiServerInfo>('start_api_server', { port, token });
+}
+
+export async function stopApiServer(): Promise<void> {
+  return await invoke<void>('stop_api_server');
+}
+
+export async function listPlugins(): Promise<PluginManifest[]> {
+  return await invoke<PluginManifest[]>('list_plugins');
+}
+
+export async function exportDiagnostics(runId: string, destination?: string): Promise<string> {
+  return await invoke<string>('export_diagnostics', { runId, destination });
+}
+
+export async function findProjects(rootPath: string): Promise<FoundProject[]> {
+  return await invoke<FoundProject[]>('find_projects', { rootPath });
+}
+
+export async function getLinkedResources(projectPath: string): Promise<LinkedResource[]> {
+  return await invoke<LinkedResource[]>('get_linked_resources', { projectPath });
+}
+
+export async function getProjectMcuInfo(projectPath: string): Promise<McuInfo> {
+  return await invoke<McuInfo>('get_project_mcu_info', { projectPath });
+}
+
+export async function getProjectToolchain(projectPath: string): Promise<ToolchainInfo[]> {
+  return await invoke<ToolchainInfo[]>('get_project_toolchain', { projectPath });
+}
+
+export async function getConfigurationDetails(projectPath: string): Promise<ConfigurationDetails[]> {
+  return await invoke<ConfigurationDetails[]>('get_configuration_details', { projectPath });
+}
+
+export async function suggestHeaderDir(projectPath: string, configName?: string): Promise<HeaderPlacement> {
+  return await invoke<HeaderPlacement>('suggest_header_dir', { projectPath, configName });
+}
+
+export async function validateWorkspace(path: string, projectPath?: string): Promise<WorkspaceValidation> {
+  return await invoke<WorkspaceValidation>('validate_workspace', { path, projectPath });
+}
+
+export async function listWorkspaceProjects(workspacePath: string): Promise<WorkspaceProject[]> {
+  return await invoke<WorkspaceProject[]>('list_workspace_projects', { workspacePath });
+}
+
+export async function detectMiddlewares(projectPath: string): Promise<Middleware[]> {
+  return await invoke<Middleware[]>('detect_middlewares', { projectPath });
+}
+
+export async function getSourceStats(projectPath: string): Promise<SourceStats> {
+  return await invoke<SourceStats>('get_source_stats', { projectPath });
+}
+
+export async function readBinFooter(path: string): Promise<BinFooter | null> {
+  return await invoke<BinFooter | null>('read_bin_footer', { path });
 }
 
 export async function loadBuildSettings(): Promise<any> {
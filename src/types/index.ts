@@ -11,6 +11,204 @@ export interface BuildConfig {
   cancelled: boolean;
   customConsoleArgs?: string;
   settings: Record<string, any>;
+  logRetention?: LogRetention;
+  maxWarnings?: number;
+  selectedCombinations?: Record<string, string>[];
+  flash?: FlashSettings;
+  embedGitInfo?: boolean;
+  dirtyTree?: DirtyTreePolicy;
+  versioning?: boolean;
+  reproducible?: boolean;
+  cleanExport?: boolean;
+  gitTag?: TagSettings;
+  cppcheck?: CppcheckSettings;
+  clangTidy?: ClangTidySettings;
+  analysisHooks?: AnalysisHook[];
+  forbiddenSymbols?: SymbolGate;
+  elfChecks?: ElfChecks;
+  renode?: RenodeSettings;
+  qemu?: QemuSettings;
+  unitTests?: UnitTestSettings;
+  sizeGate?: SizeGate;
+  unusedCodeReport?: boolean;
+  ramBreakdown?: boolean;
+  errorWarnings?: string[];
+  webhooks?: Webhook[];
+  notifications?: NotificationSettings;
+  script?: string;
+  dryRun?: boolean;
+  timeoutSecs?: number;
+  defineMode?: DefineMode;
+  binFooter?: boolean;
+  signing?: SigningSettings;
+  warmBuild?: boolean;
+}
+
+export interface CppcheckSettings {
+  path?: string;
+  checks?: string;
+  args?: string;
+}
+
+export interface ClangTidySettings {
+  path?: string;
+  checks?: string;
+  failOn?: string[];
+  args?: string;
+}
+
+export type HookFormat = 'gcc' | 'regex' | 'sarif';
+
+export interface AnalysisHook {
+  name: string;
+  command: string;
+  format?: HookFormat;
+  pattern?: string;
+  output?: string;
+}
+
+export type GateAction = 'warn' | 'fail';
+
+export interface SymbolGate {
+  symbols?: string[];
+  action?: GateAction;
+}
+
+export interface MemoryRegion {
+  name: string;
+  origin: number;
+  length: number;
+  writable?: boolean;
+}
+
+export interface ElfChecks {
+  regions?: MemoryRegion[];
+  entry?: string;
+  action?: GateAction;
+}
+
+export interface RenodeSettings {
+  path?: string;
+  script: string;
+  uart?: string;
+  pattern?: string;
+  timeoutSecs?: number;
+  action?: GateAction;
+}
+
+export interface QemuSettings {
+  path?: string;
+  machine?: string;
+  runMs?: number;
+  faultPattern?: string;
+  action?: GateAction;
+}
+
+export type TestStage = 'pre' | 'post';
+
+export interface UnitTestSettings {
+  command?: string;
+  results?: string;
+  stage?: TestStage;
+  action?: GateAction;
+}
+
+export type TestStatus = 'passed' | 'failed' | 'error' | 'skipped';
+
+export interface TestCase {
+  suite: string;
+  name: string;
+  status: TestStatus;
+  time_secs: number | null;
+  message: string | null;
+}
+
+export interface UnitTestReport {
+  stage: TestStage;
+  exit_code: number;
+  tests: number;
+  failures: number;
+  errors: number;
+  skipped: number;
+  cases: TestCase[];
+}
+
+export interface SizeGate {
+  maxGrowthPercent?: number;
+  maxGrowthBytes?: number;
+  action?: GateAction;
+}
+
+export interface VerificationReport {
+  combination: string | null;
+  run_ids: string[];
+  deterministic: boolean;
+  sizes: [number, number];
+  first_difference: number | null;
+}
+
+export interface Webhook {
+  url: string;
+  onlyFailures?: boolean;
+  headers?: Record<string, string>;
+}
+
+export interface NotificationSettings {
+  onSuccess?: boolean;
+  combinationBudgetSecs?: number;
+}
+
+export interface WatchSettings {
+  include?: string[];
+  exclude?: string[];
+  debounceMs?: number;
+}
+
+export interface WatchTrigger {
+  run_id: string;
+  files: string[];
+}
+
+export type PluginStage = 'pre_build' | 'post_build';
+
+export interface PluginManifest {
+  name: string;
+  description?: string | null;
+  command: string;
+  when: PluginStage;
+  outputs: string[];
+  action: GateAction;
+  file: string;
+}
+
+export interface ApiServerInfo {
+  address: string;
+  token: string;
+}
+
+export interface TagSettings {
+  prefix?: string;
+  push?: boolean;
+  remote?: string;
+}
+
+export type DirtyTreePolicy = 'allow' | 'warn' | 'refuse';
+
+export type DefineMode = 'header' | 'cproject';
+
+export interface SigningSettings {
+  key: string;
+  certificates: string[];
+  digest?: string;
+  passSource?: string;
+  openssl?: string;
+}
+
+export interface LogRetention {
+  maxFiles: number;
+  maxAgeDays?: number | null;
+  maxTotalBytes?: number | null;
+  compress: boolean;
 }
 
 export interface BuildSettingsConfig {
@@ -30,6 +228,7 @@ export interface BuildSettingsConfig {
     }>;
     min_selected?: number;
   }>;
+  error_warnings?: string[];
 }
 
 export interface Settings {
@@ -72,11 +271,634 @@ export interface LocalBuildConfig {
   cancelled?: boolean;
 }
 
+export type LogLevel = 'debug' | 'warning' | 'info' | 'error';
+
+export interface LogEntry {
+  run_id: string | null;
+  timestamp: string;
+  level: LogLevel;
+  message: string;
+  combination: string | null;
+  stage: string | null;
+}
+
+export interface Diagnostic {
+  severity: 'error' | 'warning' | 'note';
+  message: string;
+  file: string | null;
+  line: number | null;
+  column: number | null;
+  option: string | null;
+  fixits: FixIt[];
+}
+
+export interface FixIt {
+  file: string;
+  line: number;
+  column: number;
+  end_line: number;
+  end_column: number;
+  replacement: string;
+}
+
+export type FailureCode =
+  | 'project_not_found'
+  | 'workspace_in_use'
+  | 'toolchain_not_found'
+  | 'jvm_out_of_memory'
+  | 'jvm_start_failed';
+
+export interface FailureHint {
+  code: FailureCode;
+  hint: string;
+  evidence: string;
+}
+
+export interface CombinationResult {
+  combination: string;
+  success: boolean;
+  exit_code: number | null;
+  errors: number;
+  warnings: number;
+  warnings_exceeded: boolean;
+  failure_reason: string | null;
+  failure_hint: FailureHint | null;
+  diagnostics: Diagnostic[];
+  artifact: string | null;
+  artifact_size: number | null;
+  sizes: SectionSizes | null;
+  log_file: string | null;
+  simulation?: SimulationResult | null;
+  unused_code?: UnusedCodeReport | null;
+  ram_breakdown?: RamBreakdown | null;
+  signature?: string | null;
+}
+
+export interface RamUsage {
+  module: string;
+  data: number;
+  bss: number;
+}
+
+export interface RamBreakdown {
+  modules: RamUsage[];
+  data: number;
+  bss: number;
+}
+
+export interface DiscardedSection {
+  name: string;
+  size: number;
+  object: string;
+}
+
+export interface UnusedCodeReport {
+  functions: DiscardedSection[];
+  objects: DiscardedSection[];
+  function_bytes: number;
+  object_bytes: number;
+}
+
+export interface SimulationResult {
+  simulator: string;
+  passed: boolean;
+  timed_out: boolean;
+  duration_ms: number;
+  output: string[];
+  error: string | null;
+}
+
+export interface SectionSizes {
+  text: number;
+  data: number;
+  bss: number;
+  flash: number;
+  ram: number;
+}
+
+export type BuildError =
+  | { kind: 'config_error'; message: string }
+  | { kind: 'path_error'; path: string; message: string }
+  | { kind: 'workspace_locked'; workspace: string }
+  | { kind: 'toolchain_missing'; message: string }
+  | { kind: 'compile_failed'; combination: string; message: string; diagnostics: Diagnostic[] }
+  | { kind: 'cancelled' }
+  | { kind: 'timeout'; combination: string; seconds: number };
+
 export interface BuildResult {
+  run_id: string;
   result: string;
-  logs: string[];
-  stages: string[];
+  logs: LogEntry[];
+  stages: StageRecord[];
+  success: boolean;
+  cancelled: boolean;
+  combinations: CombinationResult[];
+  total_errors: number;
+  total_warnings: number;
+  failure_reason: string | null;
+  failure_hint: FailureHint | null;
+  error?: BuildError | null;
+  preflight: PreflightReport | null;
+  unit_tests?: UnitTestReport | null;
+  stage_timings: StageTiming[];
+}
+
+export type StageStatus = 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface StageRecord {
+  run_id: string | null;
+  id: string;
+  label: string;
+  combination: string | null;
+  status: StageStatus;
+  started_at: string;
+  finished_at: string | null;
+}
+
+export interface StageTiming {
+  stage: string;
+  combination: string | null;
+  duration_ms: number;
+}
+
+export type BuildStatusType = 'idle' | 'building' | 'success' | 'error' | 'cancelled';
+
+export interface BuildOutputLine {
+  run_id: string;
+  combination: string;
+  stream: 'stdout' | 'stderr';
+  line: string;
+}
+
+export interface CombinationProgress {
+  run_id: string;
+  combination: string;
+  index: number;
+  total: number;
+  phase: 'compiling' | 'linking';
+  file: string | null;
+  files_built: number;
+  files_total: number | null;
+  percent: number | null;
+}
+
+export interface ResourceSample {
+  run_id: string;
+  combination: string;
+  timestamp: string;
+  pid: number;
+  process_count: number;
+  cpu_percent: number;
+  memory_bytes: number;
+}
+
+export type CheckStatus = 'ok' | 'warning' | 'failed';
+
+export interface PreflightCheck {
+  id: string;
+  status: CheckStatus;
+  message: string;
+}
+
+export interface PreflightReport {
+  passed: boolean;
+  checks: PreflightCheck[];
+}
+
+export interface RunFilter {
+  projectPath?: string;
+  success?: boolean;
+  since?: string;
+  until?: string;
+  tag?: string;
+  text?: string;
+  limit?: number;
+  offset?: number;
+}
+
+export interface RunSummary {
+  run_id: string;
+  project_path: string;
+  config_name: string | null;
+  output_dir: string;
+  started_at: string;
+  finished_at: string;
+  duration_ms: number;
+  success: boolean;
+  cancelled: boolean;
+  total_errors: number;
+  total_warnings: number;
+  combination_count: number;
+  tags: string[];
+  note: string | null;
+}
+
+export interface CombinationRecord {
+  combination: string;
   success: boolean;
+  errors: number;
+  warnings: number;
+  failure_reason: string | null;
+  artifact: string | null;
+  artifact_size: number | null;
 }
 
-export type BuildStatusType = 'idle' | 'building' | 'success' | 'error' | 'cancelled';
\ No newline at end of file
+export interface RunDetails extends RunSummary {
+  combinations: CombinationRecord[];
+}
+
+export interface SizePoint {
+  run_id: string;
+  started_at: string;
+  config_name: string | null;
+  bin_size: number | null;
+  text: number;
+  data: number;
+  bss: number;
+  flash: number;
+  ram: number;
+}
+
+export type RecentKind = 'project' | 'workspace' | 'cube_ide' | 'output_dir';
+
+export type FlashBackend = 'cube_programmer' | 'open_ocd' | 'st_flash';
+
+export interface FlashSettings {
+  backend: FlashBackend;
+  programmerPath: string;
+  interface?: string;
+  target?: string;
+  scriptsDir?: string;
+  optionBytes?: OptionBytes;
+  combinationOptionBytes?: Record<string, OptionBytes>;
+  smokeTest?: SmokeTestConfig;
+  personalization?: PersonalizationConfig;
+  externalLoader?: string;
+  dfuUtilPath?: string;
+}
+
+export type Transport = 'swd' | 'dfu';
+
+export interface PersonalizationConfig {
+  placeholder: string;
+  value: 'uid' | 'serial' | 'random';
+  serialStart?: number;
+  uidAddress?: string;
+  logFile?: string;
+}
+
+export interface ProvisioningRecord {
+  timestamp: string;
+  uid: string;
+  combination: string | null;
+  probe: string | null;
+  source_file: string;
+  serial: number | null;
+  value: string;
+}
+
+export interface SmokeTestConfig {
+  script: string;
+  gdbPath?: string;
+  serverPath?: string;
+  elf?: string;
+  port?: number;
+  timeoutSecs?: number;
+}
+
+export interface SmokeTestResult {
+  combination: string | null;
+  probe: string | null;
+  passed: boolean;
+  exit_code: number | null;
+  timed_out: boolean;
+  finished_at: string;
+  output: string[];
+  error: string | null;
+}
+
+export type RdpLevel = 'level0' | 'level1' | 'level2';
+
+export interface OptionBytes {
+  rdp?: RdpLevel;
+  borLevel?: number;
+  iwdgSw?: boolean;
+  wwdgSw?: boolean;
+  other?: Record<string, string>;
+}
+
+export interface FlashRequest {
+  settings: FlashSettings;
+  file: string;
+  combination?: string;
+  runId?: string;
+  projectPath?: string;
+  probe?: string;
+  address?: string;
+  fullErase?: boolean;
+  verify?: boolean;
+  reset?: boolean;
+  confirmRdp?: RdpLevel;
+  ignoreMcuMismatch?: boolean;
+  transport?: Transport;
+}
+
+export interface FlashOutputLine {
+  file: string;
+  probe: string | null;
+  line: string;
+}
+
+export interface FlashProgress {
+  file: string;
+  probe: string | null;
+  percent: number;
+}
+
+export interface FlashResult {
+  success: boolean;
+  exit_code: number | null;
+  message: string;
+  duration_ms: number;
+  target: TargetCheck | null;
+  verify: VerifyReport | null;
+  smoke_test: SmokeTestResult | null;
+  provisioning: ProvisioningRecord | null;
+  option_bytes: OptionBytes | null;
+  logs: LogEntry[];
+}
+
+export interface VerifyMismatch {
+  address: number;
+  expected: number;
+  actual: number;
+}
+
+export interface VerifyReport {
+  method: 'readback' | 'programmer';
+  passed: boolean;
+  checked_bytes: number;
+  mismatched_bytes: number;
+  mismatches: VerifyMismatch[];
+  error: string | null;
+}
+
+export type ProbeKind = 'st_link' | 'j_link';
+
+export interface ProbeInfo {
+  kind: ProbeKind;
+  serial: string;
+  board_name: string | null;
+  firmware: string | null;
+}
+
+export interface FlashJob {
+  combination: string;
+  file: string;
+  probe?: string;
+}
+
+export interface FlashBatchRequest {
+  settings: FlashSettings;
+  runId?: string;
+  projectPath?: string;
+  jobs: FlashJob[];
+  address?: string;
+  fullErase?: boolean;
+  verify?: boolean;
+  reset?: boolean;
+  parallel?: boolean;
+  stopOnFailure?: boolean;
+  confirmRdp?: RdpLevel;
+  ignoreMcuMismatch?: boolean;
+  transport?: Transport;
+}
+
+export type BoardStatus = 'queued' | 'flashing' | 'done' | 'failed' | 'skipped';
+
+export interface BoardStatusEvent {
+  index: number;
+  combination: string;
+  probe: string | null;
+  status: BoardStatus;
+  message: string | null;
+}
+
+export interface BoardResult {
+  combination: string;
+  probe: string | null;
+  status: BoardStatus;
+  result: FlashResult | null;
+}
+
+export interface SerialPortInfo {
+  name: string;
+  kind: 'usb' | 'pci' | 'bluetooth' | 'unknown';
+  product: string | null;
+  manufacturer: string | null;
+  serial_number: string | null;
+  vid: number | null;
+  pid: number | null;
+}
+
+export interface SerialLine {
+  port: string;
+  timestamp: string;
+  line: string;
+}
+
+export interface SerialClosed {
+  port: string;
+  error: string | null;
+}
+
+export type TraceMode = 'swo' | 'rtt';
+
+export interface TraceRequest {
+  settings: FlashSettings;
+  mode: TraceMode;
+  probe?: string;
+  cpuFrequency?: number;
+  rttAddress?: string;
+  rttSize?: string;
+  rttPort?: number;
+  runId?: string;
+  combination?: string;
+}
+
+export interface TraceSession {
+  trace_id: string;
+  file: string | null;
+}
+
+export interface TraceLine {
+  trace_id: string;
+  line: string;
+}
+
+export interface TraceStopped {
+  trace_id: string;
+  error: string | null;
+}
+
+export type TargetStatus = 'match' | 'mismatch' | 'unknown';
+
+export interface TargetCheck {
+  device_id: string | null;
+  device_name: string | null;
+  declared_mcu: string | null;
+  status: TargetStatus;
+}
+
+export interface ProductionRequest {
+  settings: FlashSettings;
+  file: string;
+  combination?: string;
+  runId?: string;
+  projectPath?: string;
+  probe?: string;
+  address?: string;
+  fullErase?: boolean;
+  verify?: boolean;
+  reset?: boolean;
+  confirmRdp?: RdpLevel;
+  logFile?: string;
+  pollIntervalMs?: number;
+}
+
+export type ProductionState = 'waiting' | 'flashing' | 'passed' | 'failed' | 'remove_board' | 'stopped';
+
+export interface ProductionStatus {
+  session_id: string;
+  state: ProductionState;
+  board: number;
+  passed: number;
+  failed: number;
+  message: string | null;
+}
+
+export interface ProductionSession {
+  session_id: string;
+  log_file: string;
+}
+
+export interface FirmwareVersion {
+  major: number;
+  minor: number;
+  patch: number;
+  build: number;
+}
+
+export interface ReleaseBundle {
+  directory: string;
+  zip: string | null;
+  previous_run: string | null;
+  commits: number;
+}
+
+export interface FoundProject {
+  name: string;
+  path: string;
+}
+
+export interface LinkedResource {
+  name: string;
+  folder: boolean;
+  location: string;
+  resolved: string | null;
+}
+
+export interface McuInfo {
+  ioc_file: string;
+  device: string | null;
+  family: string | null;
+  package: string | null;
+  board: string | null;
+  firmware_package: string | null;
+  hal_version: string | null;
+  flash_kb: number | null;
+}
+
+export interface ToolchainInfo {
+  configuration: string;
+  mcu: string | null;
+  cpu: string | null;
+  fpu: string | null;
+  float_abi: 'soft' | 'softfp' | 'hard' | null;
+  optimization: string | null;
+}
+
+export interface ConfigurationDetails {
+  name: string;
+  artifact: string;
+  artifact_extension: string;
+  build_dir: string;
+  defines: string[];
+  include_paths: string[];
+}
+
+export interface HeaderPlacement {
+  dir: string;
+  include_dirs: string[];
+  default_unreachable: boolean;
+}
+
+export interface WorkspaceValidation {
+  path: string;
+  exists: boolean;
+  initialized: boolean;
+  locked: boolean;
+  project_name: string | null;
+  project_imported: boolean | null;
+  project_location: string | null;
+  ready: boolean;
+}
+
+export interface WorkspaceProject {
+  name: string;
+  path: string;
+  exists: boolean;
+}
+
+export type MiddlewareId = 'freertos' | 'lwip' | 'usb_device' | 'usb_host' | 'fatfs' | 'touchgfx';
+
+export interface Middleware {
+  id: MiddlewareId;
+  in_ioc: boolean;
+  folder: string | null;
+}
+
+export interface FileStats {
+  count: number;
+  bytes: number;
+}
+
+export interface FolderStats {
+  folder: string;
+  c: FileStats;
+  h: FileStats;
+  asm: FileStats;
+}
+
+export interface SourceStats {
+  folders: FolderStats[];
+  c: FileStats;
+  h: FileStats;
+  asm: FileStats;
+}
+
+export interface BinFooter {
+  project: string;
+  configuration: string;
+  combination: Record<string, string>;
+  git_hash: string | null;
+  git_dirty: boolean | null;
+  version: string | null;
+}
+
+export type PriorityLevel = 'idle' | 'low' | 'normal' | 'high';
+
+export interface ProcessPriority {
+  level?: PriorityLevel;
+  cpus?: number[];
+}